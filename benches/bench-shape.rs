@@ -101,6 +101,7 @@ fn shape_ttf<'a>(
             opt_gdef_table.as_ref(),
             script_tag,
             opt_lang_tag,
+            &[],
             GsubFeatureMask::default(),
             num_glyphs,
             &mut glyphs,
@@ -117,6 +118,7 @@ fn shape_ttf<'a>(
                     kerning,
                     script_tag,
                     opt_lang_tag,
+                    &[],
                     &mut infos,
                 )?;
             }
@@ -21,6 +21,9 @@ use crate::binary::write::{WriteBinary, WriteBinaryDep, WriteBuffer, WriteContex
 use crate::binary::{I16Be, I32Be, U16Be, U24Be, U32Be, U8};
 use crate::error::{ParseError, WriteError};
 
+pub mod cff2;
+pub mod charstring;
+
 // CFF Spec: An operator may be preceded by up to a maximum of 48 operands.
 const MAX_OPERANDS: usize = 48;
 
@@ -287,6 +290,11 @@ pub enum FDSelect<'a> {
         ranges: ReadArrayCow<'a, Range<u16, u8>>,
         sentinel: u16,
     },
+    // Format 4 is only defined for CFF2
+    Format4 {
+        ranges: ReadArrayCow<'a, Range<u32, u16>>,
+        sentinel: u32,
+    },
 }
 
 /// CFF DICT operator
@@ -296,6 +304,15 @@ enum Op {
     Operand(Operand),
 }
 
+/// The major version of the CFF data a DICT is read from.
+///
+/// CFF2 adds the `vsindex`, `blend` and `vstore` operators, which are reserved in CFF.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum CFFVersion {
+    Cff,
+    Cff2,
+}
+
 /// CFF operand to an operator
 #[derive(Debug, PartialEq, Clone)]
 pub enum Operand {
@@ -334,6 +351,9 @@ pub enum Operator {
     Subrs = 19,
     DefaultWidthX = 20,
     NominalWidthX = 21,
+    VSIndex = 22,
+    Blend = 23,
+    VStore = 24,
     Copyright = op2(0),
     IsFixedPitch = op2(1),
     ItalicAngle = op2(2),
@@ -519,8 +539,8 @@ impl<'a> CFF<'a> {
     ///
     /// Subsetting the String INDEX requires updating all String IDs (SID) in the font so
     /// that they point at their new position in the String INDEX. Subsetting the subroutines
-    /// requires parsing the CharStrings, which describe the glyph outlines. The CharStrings
-    /// format is non-trivial so this has been left for now.
+    /// requires parsing the CharStrings, which describe the glyph outlines. This has been left
+    /// for now, see [charstring::CharStringInfo] for the subroutines used by each glyph.
     pub fn subset(
         &self,
         glyph_ids: &[u16],
@@ -780,39 +800,43 @@ impl<'a> ReadBinary<'a> for Index<'a> {
 
     fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
         let count = usize::from(ctxt.read_u16be()?);
+        read_index_data(ctxt, count)
+    }
+}
 
-        if count > 0 {
-            let off_size = ctxt.read_u8()?;
-            if off_size < 1 || off_size > 4 {
-                return Err(ParseError::BadValue);
-            }
+/// Read the data of an INDEX following its `count`, which is 16-bit in CFF and 32-bit in CFF2.
+fn read_index_data<'a>(ctxt: &mut ReadCtxt<'a>, count: usize) -> Result<Index<'a>, ParseError> {
+    if count > 0 {
+        let off_size = ctxt.read_u8()?;
+        if off_size < 1 || off_size > 4 {
+            return Err(ParseError::BadValue);
+        }
 
-            let offset_array_size = (count + 1) * usize::from(off_size);
-            let offset_array = ctxt.read_slice(offset_array_size)?;
+        let offset_array_size = (count + 1) * usize::from(off_size);
+        let offset_array = ctxt.read_slice(offset_array_size)?;
 
-            let last_offset_index = lookup_offset_index(off_size, offset_array, count);
-            if last_offset_index < 1 {
-                return Err(ParseError::BadValue);
-            }
+        let last_offset_index = lookup_offset_index(off_size, offset_array, count);
+        if last_offset_index < 1 {
+            return Err(ParseError::BadValue);
+        }
 
-            let data_array_size = last_offset_index - 1;
-            let data_array = ctxt.read_slice(data_array_size)?;
+        let data_array_size = last_offset_index - 1;
+        let data_array = ctxt.read_slice(data_array_size)?;
 
-            Ok(Index {
-                count,
-                off_size,
-                offset_array,
-                data_array,
-            })
-        } else {
-            // count == 0
-            Ok(Index {
-                count,
-                off_size: 1,
-                offset_array: &[],
-                data_array: &[],
-            })
-        }
+        Ok(Index {
+            count,
+            off_size,
+            offset_array,
+            data_array,
+        })
+    } else {
+        // count == 0
+        Ok(Index {
+            count,
+            off_size: 1,
+            offset_array: &[],
+            data_array: &[],
+        })
     }
 }
 
@@ -851,30 +875,37 @@ where
     type HostType = Self;
 
     fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self::HostType, ParseError> {
-        let mut dict = Vec::new();
-        let mut operands = Vec::new();
-
-        while ctxt.bytes_available() {
-            match Op::read(ctxt)? {
-                Op::Operator(operator) => {
-                    integer_to_offset(operator, &mut operands);
-                    dict.push((operator, operands.clone()));
-                    operands.clear();
-                }
-                Op::Operand(operand) => {
-                    operands.push(operand);
-                    if operands.len() > MAX_OPERANDS {
-                        return Err(ParseError::LimitExceeded);
-                    }
+        read_dict(ctxt, CFFVersion::Cff)
+    }
+}
+
+fn read_dict<T>(ctxt: &mut ReadCtxt<'_>, version: CFFVersion) -> Result<Dict<T>, ParseError>
+where
+    T: DictDefault,
+{
+    let mut dict = Vec::new();
+    let mut operands = Vec::new();
+
+    while ctxt.bytes_available() {
+        match read_op(ctxt, version)? {
+            Op::Operator(operator) => {
+                integer_to_offset(operator, &mut operands);
+                dict.push((operator, operands.clone()));
+                operands.clear();
+            }
+            Op::Operand(operand) => {
+                operands.push(operand);
+                if operands.len() > MAX_OPERANDS {
+                    return Err(ParseError::LimitExceeded);
                 }
             }
         }
-
-        Ok(Dict {
-            dict,
-            default: PhantomData,
-        })
     }
+
+    Ok(Dict {
+        dict,
+        default: PhantomData,
+    })
 }
 
 fn offset_size(value: usize) -> Option<u8> {
@@ -899,7 +930,8 @@ fn integer_to_offset(operator: Operator, operands: &mut [Operand]) {
         | (Operator::CharStrings, [Operand::Integer(offset)])
         | (Operator::Subrs, [Operand::Integer(offset)])
         | (Operator::FDArray, [Operand::Integer(offset)])
-        | (Operator::FDSelect, [Operand::Integer(offset)]) => {
+        | (Operator::FDSelect, [Operand::Integer(offset)])
+        | (Operator::VStore, [Operand::Integer(offset)]) => {
             operands[0] = Operand::Offset(*offset);
         }
         (Operator::Private, [Operand::Integer(length), Operand::Integer(offset)]) => {
@@ -954,28 +986,35 @@ impl<'a> ReadBinary<'a> for Op {
     type HostType = Self;
 
     fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
-        let b0 = ctxt.read_u8()?;
-
-        match b0 {
-            0..=11 | 13..=21 => ok_operator(u16::from(b0).try_into().unwrap()), // NOTE(unwrap): Safe due to pattern
-            12 => ok_operator(u16::from(op2(ctxt.read_u8()?)).try_into()?),
-            28 => {
-                let num = ctxt.read_i16be()?;
-                Ok(Op::Operand(Operand::Integer(i32::from(num))))
-            }
-            29 => ok_int(ctxt.read_i32be()?),
-            30 => ok_real(ctxt.read_until_nibble(0xF)?),
-            32..=246 => ok_int(i32::from(b0) - 139),
-            247..=250 => {
-                let b1 = ctxt.read_u8()?;
-                ok_int((i32::from(b0) - 247) * 256 + i32::from(b1) + 108)
-            }
-            251..=254 => {
-                let b1 = ctxt.read_u8()?;
-                ok_int(-(i32::from(b0) - 251) * 256 - i32::from(b1) - 108)
-            }
-            22..=27 | 31 | 255 => Err(ParseError::BadValue), // reserved
+        read_op(ctxt, CFFVersion::Cff)
+    }
+}
+
+fn read_op(ctxt: &mut ReadCtxt<'_>, version: CFFVersion) -> Result<Op, ParseError> {
+    let b0 = ctxt.read_u8()?;
+
+    match b0 {
+        0..=11 | 13..=21 => ok_operator(u16::from(b0).try_into().unwrap()), // NOTE(unwrap): Safe due to pattern
+        22..=24 if version == CFFVersion::Cff2 => {
+            ok_operator(u16::from(b0).try_into().unwrap()) // NOTE(unwrap): Safe due to pattern
+        }
+        12 => ok_operator(u16::from(op2(ctxt.read_u8()?)).try_into()?),
+        28 => {
+            let num = ctxt.read_i16be()?;
+            Ok(Op::Operand(Operand::Integer(i32::from(num))))
+        }
+        29 => ok_int(ctxt.read_i32be()?),
+        30 => ok_real(ctxt.read_until_nibble(0xF)?),
+        32..=246 => ok_int(i32::from(b0) - 139),
+        247..=250 => {
+            let b1 = ctxt.read_u8()?;
+            ok_int((i32::from(b0) - 247) * 256 + i32::from(b1) + 108)
         }
+        251..=254 => {
+            let b1 = ctxt.read_u8()?;
+            ok_int(-(i32::from(b0) - 251) * 256 - i32::from(b1) - 108)
+        }
+        22..=27 | 31 | 255 => Err(ParseError::BadValue), // reserved
     }
 }
 
@@ -1107,6 +1146,24 @@ impl<'a> WriteBinary for Range<SID, u16> {
     }
 }
 
+impl<'a> ReadFrom<'a> for Range<u32, u16> {
+    type ReadType = (U32Be, U16Be);
+    fn from((first, n_left): (u32, u16)) -> Self {
+        Range { first, n_left }
+    }
+}
+
+impl WriteBinary for Range<u32, u16> {
+    type Output = ();
+
+    fn write<C: WriteContext>(ctxt: &mut C, range: Self) -> Result<(), WriteError> {
+        U32Be::write(ctxt, range.first)?;
+        U16Be::write(ctxt, range.n_left)?;
+
+        Ok(())
+    }
+}
+
 impl<'a, F, N> Range<F, N>
 where
     N: num::Unsigned + Copy,
@@ -1336,6 +1393,15 @@ impl<'a> ReadBinaryDep<'a> for FDSelect<'a> {
                     sentinel: sentinel,
                 })
             }
+            4 => {
+                let nranges = usize::try_from(ctxt.read::<U32Be>()?)?;
+                let ranges = ctxt.read_array(nranges)?;
+                let sentinel = ctxt.read::<U32Be>()?;
+                Ok(FDSelect::Format4 {
+                    ranges: ReadArrayCow::Borrowed(ranges),
+                    sentinel,
+                })
+            }
             _ => Err(ParseError::BadValue),
         }
     }
@@ -1358,6 +1424,12 @@ impl<'a> WriteBinary<&Self> for FDSelect<'a> {
                 ReadArrayCow::write(ctxt, ranges)?;
                 U16Be::write(ctxt, *sentinel)?;
             }
+            FDSelect::Format4 { ranges, sentinel } => {
+                U8::write(ctxt, 4)?; // format
+                U32Be::write(ctxt, u32::try_from(ranges.len())?)?;
+                ReadArrayCow::write(ctxt, ranges)?;
+                U32Be::write(ctxt, *sentinel)?;
+            }
         }
 
         Ok(())
@@ -1398,6 +1470,23 @@ impl<'a> PartialEq for FDSelect<'a> {
                         .zip(other_ranges.iter())
                         .all(|(left, right)| left == right)
             }
+            (
+                FDSelect::Format4 {
+                    ranges: self_ranges,
+                    sentinel: self_sentinel,
+                },
+                FDSelect::Format4 {
+                    ranges: other_ranges,
+                    sentinel: other_sentinel,
+                },
+            ) => {
+                self_ranges.len() == other_ranges.len()
+                    && self_sentinel == other_sentinel
+                    && self_ranges
+                        .iter()
+                        .zip(other_ranges.iter())
+                        .all(|(left, right)| left == right)
+            }
             _ => false,
         }
     }
@@ -1405,7 +1494,16 @@ impl<'a> PartialEq for FDSelect<'a> {
 
 impl<'a> FDSelect<'a> {
     /// Returns the index of the Font DICT for the supplied `glyph_id`
+    ///
+    /// Format 4 indices that don't fit in a `u8` yield `None`, use `font_dict_index_u16` to
+    /// look these up.
     pub fn font_dict_index(&self, glyph_id: u16) -> Option<u8> {
+        self.font_dict_index_u16(glyph_id)
+            .and_then(|index| u8::try_from(index).ok())
+    }
+
+    /// Returns the index of the Font DICT for the supplied `glyph_id`
+    pub fn font_dict_index_u16(&self, glyph_id: u16) -> Option<u16> {
         let index = usize::from(glyph_id);
         match self {
             FDSelect::Format0 {
@@ -1413,7 +1511,7 @@ impl<'a> FDSelect<'a> {
             } => glyph_font_dict_indices
                 .check_index(index)
                 .ok()
-                .map(|_| glyph_font_dict_indices.get_item(index)),
+                .map(|_| u16::from(glyph_font_dict_indices.get_item(index))),
             FDSelect::Format3 { ranges, sentinel } => {
                 #[rustfmt::skip]
                 let range_windows = ranges
@@ -1422,6 +1520,23 @@ impl<'a> FDSelect<'a> {
                     .chain(iter::once((*sentinel, None)))
                     .tuple_windows();
 
+                for ((first, fd_index), (last, _)) in range_windows {
+                    if glyph_id >= first && glyph_id < last {
+                        return fd_index.map(u16::from);
+                    }
+                }
+
+                None
+            }
+            FDSelect::Format4 { ranges, sentinel } => {
+                let glyph_id = u32::from(glyph_id);
+                #[rustfmt::skip]
+                let range_windows = ranges
+                    .iter()
+                    .map(|Range { first, n_left }| (first, Some(n_left)))
+                    .chain(iter::once((*sentinel, None)))
+                    .tuple_windows();
+
                 for ((first, fd_index), (last, _)) in range_windows {
                     if glyph_id >= first && glyph_id < last {
                         return fd_index;
@@ -1806,6 +1921,9 @@ impl TryFrom<u16> for Operator {
                 19 => Ok(Operator::Subrs),
                 20 => Ok(Operator::DefaultWidthX),
                 21 => Ok(Operator::NominalWidthX),
+                22 => Ok(Operator::VSIndex),
+                23 => Ok(Operator::Blend),
+                24 => Ok(Operator::VStore),
                 _ => Err(ParseError::BadValue),
             }
         }
@@ -2855,6 +2973,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_cff2_op() {
+        // blend is reserved in CFF
+        let mut ctxt = ReadScope::new(&[23]).ctxt();
+        assert!(read_op(&mut ctxt, CFFVersion::Cff).is_err());
+        let mut ctxt = ReadScope::new(&[23]).ctxt();
+        assert_eq!(
+            read_op(&mut ctxt, CFFVersion::Cff2).unwrap(),
+            Op::Operator(Operator::Blend)
+        );
+    }
+
     #[test]
     fn test_fail_op2() {
         let mut ctxt = ReadScope::new(&[12]).ctxt();
@@ -0,0 +1,399 @@
+//! CFF2 font handling.
+//!
+//! CFF2 is the PostScript outline format of OpenType variable fonts. It shares the INDEX and DICT
+//! structures of CFF but has no Name or String INDEX, holds a single font, and adds an Item
+//! Variation Store along with the `blend` and `vsindex` operators for varying values.
+//!
+//! Refer to the [CFF2 specification](https://docs.microsoft.com/en-us/typography/opentype/spec/cff2)
+//! for more information.
+
+use std::convert::TryFrom;
+
+use super::{
+    integer_to_offset, read_dict, read_index_data, read_op, CFFVersion, FDSelect, FontDict, Index,
+    Op, Operand, Operator, PrivateDict, TopDict,
+};
+use crate::binary::read::{ReadBinary, ReadCtxt, ReadScope};
+use crate::error::ParseError;
+use crate::tables::variable_fonts::item_variation_store::ItemVariationStore;
+use crate::tables::F2Dot14;
+
+// CFF2 Spec: The default maximum number of operands on the stack is 513.
+const MAX_OPERANDS: usize = 513;
+
+/// Top level representation of a CFF2 font, read from a CFF2 OpenType table.
+pub struct CFF2<'a> {
+    pub header: Header,
+    pub top_dict: TopDict,
+    pub global_subr_index: Index<'a>,
+    pub char_strings_index: Index<'a>,
+    /// The variation data referenced by `blend` operators, absent in fonts without variations.
+    pub vstore: Option<ItemVariationStore<'a>>,
+    pub font_dict_index: Index<'a>,
+    /// The Private DICT of each Font DICT, with `blend` operators resolved to default values.
+    pub private_dicts: Vec<PrivateDict>,
+    pub local_subr_indices: Vec<Option<Index<'a>>>,
+    /// Maps glyphs to Font DICTs. Only present when there is more than one Font DICT.
+    pub fd_select: Option<FDSelect<'a>>,
+}
+
+/// CFF2 Font Header
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    pub major: u8,
+    pub minor: u8,
+    pub header_size: u8,
+    pub top_dict_length: u16,
+}
+
+impl<'a> ReadBinary<'a> for CFF2<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        // Offsets in the Top DICT are relative to the start of the CFF2 data
+        let scope = ctxt.scope();
+
+        let header = ctxt.read::<Header>()?;
+        let top_dict: TopDict = read_dict(
+            &mut ctxt.read_scope(usize::from(header.top_dict_length))?.ctxt(),
+            CFFVersion::Cff2,
+        )?;
+        let global_subr_index = read_index(ctxt)?;
+
+        let offset = top_dict
+            .get_i32(Operator::CharStrings)
+            .unwrap_or(Err(ParseError::MissingValue))?;
+        let char_strings_index = read_index(&mut scope.offset(usize::try_from(offset)?).ctxt())?;
+
+        // The Item Variation Store is preceded by its length
+        let vstore = match top_dict.get_i32(Operator::VStore).transpose()? {
+            Some(offset) => {
+                let mut ctxt = scope.offset(usize::try_from(offset)?).ctxt();
+                let length = usize::from(ctxt.read_u16be()?);
+                Some(ctxt.read_scope(length)?.read::<ItemVariationStore<'a>>()?)
+            }
+            None => None,
+        };
+
+        let offset = top_dict
+            .get_i32(Operator::FDArray)
+            .ok_or(ParseError::MissingValue)??;
+        let font_dict_index = read_index(&mut scope.offset(usize::try_from(offset)?).ctxt())?;
+        if font_dict_index.count == 0 {
+            return Err(ParseError::MissingValue);
+        }
+
+        // FDSelect is only required if there is more than one Font DICT
+        let fd_select = match top_dict.get_i32(Operator::FDSelect).transpose()? {
+            Some(offset) => Some(
+                scope
+                    .offset(usize::try_from(offset)?)
+                    .read_dep::<FDSelect<'a>>(char_strings_index.count)?,
+            ),
+            None if font_dict_index.count > 1 => return Err(ParseError::MissingValue),
+            None => None,
+        };
+
+        let mut private_dicts = Vec::with_capacity(font_dict_index.count);
+        let mut local_subr_indices = Vec::with_capacity(font_dict_index.count);
+        for object in font_dict_index.iter() {
+            let font_dict = ReadScope::new(object).read::<FontDict>()?;
+            let (private_dict, private_dict_offset) =
+                read_private_dict(&scope, &font_dict, vstore.as_ref())?;
+            let local_subr_index =
+                read_local_subr_index(&scope, &private_dict, private_dict_offset)?;
+
+            private_dicts.push(private_dict);
+            local_subr_indices.push(local_subr_index);
+        }
+
+        Ok(CFF2 {
+            header,
+            top_dict,
+            global_subr_index,
+            char_strings_index,
+            vstore,
+            font_dict_index,
+            private_dicts,
+            local_subr_indices,
+            fd_select,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for Header {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let major = ctxt.read_u8()?;
+        ctxt.check(major == 2)?;
+        let minor = ctxt.read_u8()?;
+        let header_size = ctxt.read_u8()?;
+        let top_dict_length = ctxt.read_u16be()?;
+
+        if header_size < 5 {
+            return Err(ParseError::BadValue);
+        }
+
+        let _unknown = ctxt.read_slice(usize::from(header_size - 5))?;
+
+        Ok(Header {
+            major,
+            minor,
+            header_size,
+            top_dict_length,
+        })
+    }
+}
+
+impl<'a> CFF2<'a> {
+    /// Returns the index of the Font DICT for the supplied `glyph_id`
+    pub fn font_dict_index(&self, glyph_id: u16) -> Option<usize> {
+        match self.fd_select {
+            Some(ref fd_select) => fd_select.font_dict_index_u16(glyph_id).map(usize::from),
+            None => Some(0),
+        }
+    }
+
+    /// Returns the Font DICT at `index`
+    pub fn font_dict(&self, index: usize) -> Result<FontDict, ParseError> {
+        let data = self
+            .font_dict_index
+            .read_object(index)
+            .ok_or(ParseError::BadIndex)?;
+        ReadScope::new(data).read::<FontDict>()
+    }
+
+    /// Calculate the scalars of the regions used by `blend` operators at the normalized
+    /// variation coordinates `coords`.
+    ///
+    /// `vsindex` selects the item variation data that holds the regions. The blended value of a
+    /// `blend` operand is its default value plus the sum of each delta multiplied by the
+    /// corresponding scalar.
+    pub fn blend_scalars(&self, vsindex: u16, coords: &[F2Dot14]) -> Result<Vec<f32>, ParseError> {
+        let vstore = self.vstore.as_ref().ok_or(ParseError::MissingValue)?;
+        let item_variation_data = vstore
+            .item_variation_data
+            .get(usize::from(vsindex))
+            .ok_or(ParseError::BadIndex)?;
+        item_variation_data
+            .region_indexes
+            .iter()
+            .map(|region_index| {
+                vstore
+                    .variation_region_list
+                    .region_scalar(region_index, coords)
+            })
+            .collect()
+    }
+}
+
+/// Read a CFF2 INDEX, which differs from a CFF INDEX in having a 32-bit count.
+fn read_index<'a>(ctxt: &mut ReadCtxt<'a>) -> Result<Index<'a>, ParseError> {
+    let count = usize::try_from(ctxt.read_u32be()?)?;
+    read_index_data(ctxt, count)
+}
+
+/// Returns the number of regions that `blend` operators supply deltas for with `vsindex`.
+fn region_count(
+    opt_vstore: Option<&ItemVariationStore<'_>>,
+    vsindex: i32,
+) -> Result<usize, ParseError> {
+    let vstore = opt_vstore.ok_or(ParseError::MissingValue)?;
+    let item_variation_data = vstore
+        .item_variation_data
+        .get(usize::try_from(vsindex)?)
+        .ok_or(ParseError::BadIndex)?;
+    Ok(item_variation_data.region_indexes.len())
+}
+
+/// Read the Private DICT of `font_dict`, returning it and its offset within `scope`.
+///
+/// Unlike other DICTs, the Private DICT may contain `blend` operators. These are resolved to
+/// their default values. The `vsindex` operator is retained.
+fn read_private_dict(
+    scope: &ReadScope<'_>,
+    font_dict: &FontDict,
+    opt_vstore: Option<&ItemVariationStore<'_>>,
+) -> Result<(PrivateDict, usize), ParseError> {
+    let (private_dict_offset, private_dict_length) = match font_dict.get(Operator::Private) {
+        Some([Operand::Offset(length), Operand::Offset(offset)]) => {
+            (usize::try_from(*offset)?, usize::try_from(*length)?)
+        }
+        Some(_) => return Err(ParseError::BadValue),
+        None => return Err(ParseError::MissingValue),
+    };
+    let mut ctxt = scope
+        .offset_length(private_dict_offset, private_dict_length)?
+        .ctxt();
+
+    let mut dict = PrivateDict::new();
+    let mut operands = Vec::new();
+    let mut vsindex = 0;
+    while ctxt.bytes_available() {
+        match read_op(&mut ctxt, CFFVersion::Cff2)? {
+            Op::Operator(Operator::Blend) => {
+                // The operands are n default values, the deltas for each default value for each
+                // region, and n.
+                let n = match operands.pop() {
+                    Some(Operand::Integer(n)) => usize::try_from(n)?,
+                    _ => return Err(ParseError::BadValue),
+                };
+                let blend_len = n * (region_count(opt_vstore, vsindex)? + 1);
+                let start = operands
+                    .len()
+                    .checked_sub(blend_len)
+                    .ok_or(ParseError::BadValue)?;
+                operands.truncate(start + n);
+            }
+            Op::Operator(operator) => {
+                if operator == Operator::VSIndex {
+                    vsindex = match operands.as_slice() {
+                        [Operand::Integer(vsindex)] => *vsindex,
+                        _ => return Err(ParseError::BadValue),
+                    };
+                }
+                integer_to_offset(operator, &mut operands);
+                dict.inner_mut().push((operator, operands.clone()));
+                operands.clear();
+            }
+            Op::Operand(operand) => {
+                operands.push(operand);
+                if operands.len() > MAX_OPERANDS {
+                    return Err(ParseError::LimitExceeded);
+                }
+            }
+        }
+    }
+
+    Ok((dict, private_dict_offset))
+}
+
+fn read_local_subr_index<'a>(
+    scope: &ReadScope<'a>,
+    private_dict: &PrivateDict,
+    private_dict_offset: usize,
+) -> Result<Option<Index<'a>>, ParseError> {
+    // As in CFF, the local subrs offset is relative to the beginning of the Private DICT data.
+    private_dict
+        .get_i32(Operator::Subrs)
+        .transpose()?
+        .map(|offset| {
+            let offset = usize::try_from(offset)?;
+            read_index(&mut scope.offset(private_dict_offset + offset).ctxt())
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::binary::write::{WriteBinary, WriteBuffer, WriteContext};
+    use crate::binary::{U16Be, U32Be, U8};
+
+    // Integers are written with the 5 byte encoding so that the size of a DICT doesn't depend on
+    // the offsets it holds.
+    fn write_dict(buffer: &mut WriteBuffer, operands: &[i32], operator: Operator) {
+        for operand in operands {
+            U8::write(buffer, 29).unwrap();
+            U32Be::write(buffer, *operand as u32).unwrap();
+        }
+        Operator::write(buffer, operator).unwrap();
+    }
+
+    fn write_index(buffer: &mut WriteBuffer, objects: &[&[u8]]) {
+        U32Be::write(buffer, objects.len() as u32).unwrap();
+        if objects.is_empty() {
+            return;
+        }
+        U8::write(buffer, 2).unwrap(); // off_size
+        let mut offset = 1;
+        U16Be::write(buffer, offset).unwrap();
+        for object in objects {
+            offset += object.len() as u16;
+            U16Be::write(buffer, offset).unwrap();
+        }
+        for object in objects {
+            buffer.write_bytes(object).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_read_cff2() {
+        // Item Variation Store with one axis, two regions and one item variation data using
+        // both regions
+        let vstore: &[u8] = &[
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x0C, 0x00, 0x01, 0x00, 0x00, 0x00, 0x1C, //
+            0x00, 0x01, 0x00, 0x02, // axis count, region count
+            0x00, 0x00, 0x40, 0x00, 0x40, 0x00, // region 0: 0 to 1, peak 1
+            0xC0, 0x00, 0xC0, 0x00, 0x00, 0x00, // region 1: -1 to 0, peak -1
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01,
+        ];
+        let mut char_strings = WriteBuffer::new();
+        write_index(&mut char_strings, &[&[0x8B], &[0x8C]]);
+        // Private DICT: vsindex 0, then BlueValues of -10 and 500, each with deltas for the two
+        // regions
+        let mut private_dict = WriteBuffer::new();
+        write_dict(&mut private_dict, &[0], Operator::VSIndex);
+        write_dict(
+            &mut private_dict,
+            &[-10, 500, 1, 2, 3, 4, 2],
+            Operator::Blend,
+        );
+        write_dict(&mut private_dict, &[], Operator::BlueValues);
+
+        // Header and Top DICT, followed by an empty global subr INDEX
+        let top_dict_length = 19;
+        let char_strings_offset = 5 + top_dict_length + 4;
+        let vstore_offset = char_strings_offset + char_strings.len();
+        let fd_array_offset = vstore_offset + 2 + vstore.len();
+        // The FDArray INDEX holds one Font DICT of 11 bytes
+        let private_dict_offset = fd_array_offset + 4 + 1 + 4 + 11;
+
+        let mut buffer = WriteBuffer::new();
+        U8::write(&mut buffer, 2).unwrap();
+        U8::write(&mut buffer, 0).unwrap();
+        U8::write(&mut buffer, 5).unwrap();
+        U16Be::write(&mut buffer, top_dict_length as u16).unwrap();
+        write_dict(
+            &mut buffer,
+            &[char_strings_offset as i32],
+            Operator::CharStrings,
+        );
+        write_dict(&mut buffer, &[vstore_offset as i32], Operator::VStore);
+        write_dict(&mut buffer, &[fd_array_offset as i32], Operator::FDArray);
+        write_index(&mut buffer, &[]);
+        assert_eq!(buffer.len(), char_strings_offset);
+        buffer.write_bytes(char_strings.bytes()).unwrap();
+        U16Be::write(&mut buffer, vstore.len() as u16).unwrap();
+        buffer.write_bytes(vstore).unwrap();
+        let mut font_dict = WriteBuffer::new();
+        write_dict(
+            &mut font_dict,
+            &[private_dict.len() as i32, private_dict_offset as i32],
+            Operator::Private,
+        );
+        write_index(&mut buffer, &[font_dict.bytes()]);
+        assert_eq!(buffer.len(), private_dict_offset);
+        buffer.write_bytes(private_dict.bytes()).unwrap();
+
+        let data = buffer.into_inner();
+        let cff2 = ReadScope::new(&data).read::<CFF2<'_>>().unwrap();
+        assert_eq!(cff2.header.top_dict_length, 19);
+        assert_eq!(cff2.char_strings_index.count, 2);
+        assert_eq!(cff2.font_dict_index(1), Some(0));
+        assert_eq!(
+            cff2.private_dicts[0].get(Operator::BlueValues),
+            Some([Operand::Integer(-10), Operand::Integer(500)].as_ref())
+        );
+        assert_eq!(
+            cff2.private_dicts[0].get(Operator::VSIndex),
+            Some([Operand::Integer(0)].as_ref())
+        );
+        assert!(cff2.local_subr_indices[0].is_none());
+
+        let scalars = cff2.blend_scalars(0, &[F2Dot14::from_f32(0.5)]).unwrap();
+        assert_eq!(scalars, vec![0.5, 0.]);
+    }
+}
@@ -0,0 +1,990 @@
+//! Type 2 charstring interpretation.
+//!
+//! Refer to [Technical Note #5177](https://wwwimages2.adobe.com/content/dam/acom/en/devnet/font/pdfs/5177.Type2.pdf)
+//! for more information.
+
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::mem;
+
+use super::cff2::CFF2;
+use super::{CFFVariant, Font, Index, Operand, Operator, PrivateDict, Real, CFF};
+use crate::binary::read::ReadScope;
+use crate::error::ParseError;
+use crate::outline::OutlineSink;
+use crate::tables::F2Dot14;
+
+// Type 2 Charstring Spec: Appendix B, Type 2 Charstring Implementation Limits
+const MAX_STACK: usize = 48;
+const MAX_SUBR_DEPTH: usize = 10;
+const TRANSIENT_ARRAY_SIZE: usize = 32;
+
+// CFF2 Spec: the argument stack limit is raised to 513 for CFF2 charstrings
+const MAX_CFF2_STACK: usize = 513;
+
+// CFF Spec: Appendix B, Standard Encoding. Maps character codes to SIDs, used to locate the
+// components of `seac` accented glyphs.
+#[rustfmt::skip]
+const STANDARD_ENCODING: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+    17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32,
+    33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48,
+    49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64,
+    65, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80,
+    81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 96, 97, 98, 99, 100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110,
+    0, 111, 112, 113, 114, 0, 115, 116, 117, 118, 119, 120, 121, 122, 0, 123,
+    0, 124, 125, 126, 127, 128, 129, 130, 131, 0, 132, 133, 0, 134, 135, 136,
+    137, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 138, 0, 139, 0, 0, 0, 0, 140, 141, 142, 143, 0, 0, 0, 0,
+    0, 144, 0, 0, 0, 145, 0, 0, 146, 147, 148, 149, 0, 0, 0, 0,
+];
+
+/// Information gathered while interpreting a charstring.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CharStringInfo {
+    /// The advance width of the glyph.
+    ///
+    /// This is always `None` for CFF2 fonts, which don't encode widths in charstrings.
+    pub width: Option<f32>,
+    /// Indices (unbiased) of the global subroutines called by the charstring.
+    pub used_global_subrs: BTreeSet<usize>,
+    /// Indices (unbiased) of the local subroutines called by the charstring.
+    pub used_local_subrs: BTreeSet<usize>,
+}
+
+/// Arguments to the `seac`-like form of `endchar`.
+struct Seac {
+    adx: f32,
+    ady: f32,
+    base_char: u8,
+    accent_char: u8,
+}
+
+/// State required to resolve `blend` operators in CFF2 charstrings.
+struct Variations<'f, 'a> {
+    cff2: &'f CFF2<'a>,
+    coords: &'f [F2Dot14],
+    vsindex: u16,
+    scalars: Option<Vec<f32>>,
+}
+
+struct Interpreter<'f, 'a, S: OutlineSink> {
+    global_subrs: &'f Index<'a>,
+    local_subrs: Option<&'f Index<'a>>,
+    variations: Option<Variations<'f, 'a>>,
+    sink: &'f mut S,
+    stack: Vec<f32>,
+    transient: [f32; TRANSIENT_ARRAY_SIZE],
+    x: f32,
+    y: f32,
+    path_open: bool,
+    n_stems: usize,
+    width_parsed: bool,
+    width: Option<f32>,
+    seac: Option<Seac>,
+    done: bool,
+    used_global_subrs: BTreeSet<usize>,
+    used_local_subrs: BTreeSet<usize>,
+}
+
+impl<'a> CFF<'a> {
+    /// Interpret the charstring of `glyph_id` in the font at `font_index`, passing its outline to
+    /// `sink`.
+    ///
+    /// Accented glyphs built with the `seac`-like form of `endchar` are expanded into the
+    /// outlines of their base and accent glyphs.
+    pub fn visit_char_string<S: OutlineSink>(
+        &self,
+        font_index: usize,
+        glyph_id: u16,
+        sink: &mut S,
+    ) -> Result<CharStringInfo, ParseError> {
+        let font = self.fonts.get(font_index).ok_or(ParseError::BadIndex)?;
+        let char_string = font
+            .char_strings_index
+            .read_object(usize::from(glyph_id))
+            .ok_or(ParseError::BadIndex)?;
+        let (private_dict, local_subrs) = font.private_dict_and_local_subrs(glyph_id)?;
+
+        let mut interpreter = Interpreter::new(&self.global_subr_index, local_subrs, None, sink);
+        interpreter.run(char_string)?;
+        if let Some(seac) = interpreter.seac.take() {
+            let components = [
+                (seac.base_char, 0., 0.),
+                (seac.accent_char, seac.adx, seac.ady),
+            ];
+            for &(code, x, y) in components.iter() {
+                let component_id = font.seac_glyph_id(code).ok_or(ParseError::BadValue)?;
+                let char_string = font
+                    .char_strings_index
+                    .read_object(usize::from(component_id))
+                    .ok_or(ParseError::BadIndex)?;
+                interpreter.reset(x, y);
+                interpreter.run(char_string)?;
+                if interpreter.seac.is_some() {
+                    // Components of an accented glyph can't themselves be accented glyphs
+                    return Err(ParseError::BadValue);
+                }
+            }
+        }
+
+        let width = match interpreter.width {
+            Some(width) => private_dict_value(private_dict, Operator::NominalWidthX) + width,
+            None => private_dict_value(private_dict, Operator::DefaultWidthX),
+        };
+        Ok(CharStringInfo {
+            width: Some(width),
+            used_global_subrs: interpreter.used_global_subrs,
+            used_local_subrs: interpreter.used_local_subrs,
+        })
+    }
+}
+
+impl<'a> CFF2<'a> {
+    /// Interpret the charstring of `glyph_id` at the normalized variation coordinates `coords`,
+    /// passing its outline to `sink`.
+    pub fn visit_char_string<S: OutlineSink>(
+        &self,
+        glyph_id: u16,
+        coords: &[F2Dot14],
+        sink: &mut S,
+    ) -> Result<CharStringInfo, ParseError> {
+        let char_string = self
+            .char_strings_index
+            .read_object(usize::from(glyph_id))
+            .ok_or(ParseError::BadIndex)?;
+        let font_dict_index = self.font_dict_index(glyph_id).ok_or(ParseError::BadIndex)?;
+        let private_dict = self
+            .private_dicts
+            .get(font_dict_index)
+            .ok_or(ParseError::BadIndex)?;
+        let local_subrs = self
+            .local_subr_indices
+            .get(font_dict_index)
+            .and_then(Option::as_ref);
+        let vsindex = private_dict
+            .get_i32(Operator::VSIndex)
+            .transpose()?
+            .unwrap_or(0);
+        let variations = Variations {
+            cff2: self,
+            coords,
+            vsindex: u16::try_from(vsindex)?,
+            scalars: None,
+        };
+
+        let mut interpreter =
+            Interpreter::new(&self.global_subr_index, local_subrs, Some(variations), sink);
+        interpreter.run(char_string)?;
+        Ok(CharStringInfo {
+            width: None,
+            used_global_subrs: interpreter.used_global_subrs,
+            used_local_subrs: interpreter.used_local_subrs,
+        })
+    }
+}
+
+impl<'a> Font<'a> {
+    /// Returns the Private DICT and local subroutines that apply to `glyph_id`.
+    fn private_dict_and_local_subrs(
+        &self,
+        glyph_id: u16,
+    ) -> Result<(&PrivateDict, Option<&Index<'a>>), ParseError> {
+        match self.data {
+            CFFVariant::Type1(ref type1) => {
+                Ok((&type1.private_dict, type1.local_subr_index.as_ref()))
+            }
+            CFFVariant::CID(ref cid) => {
+                let font_dict_index = cid
+                    .fd_select
+                    .font_dict_index(glyph_id)
+                    .map(usize::from)
+                    .ok_or(ParseError::BadIndex)?;
+                let private_dict = cid
+                    .private_dicts
+                    .get(font_dict_index)
+                    .ok_or(ParseError::BadIndex)?;
+                let local_subrs = cid
+                    .local_subr_indices
+                    .get(font_dict_index)
+                    .and_then(Option::as_ref);
+                Ok((private_dict, local_subrs))
+            }
+        }
+    }
+
+    /// Returns the glyph id of the glyph with Standard Encoding character `code`.
+    fn seac_glyph_id(&self, code: u8) -> Option<u16> {
+        let sid = u16::from(STANDARD_ENCODING[usize::from(code)]);
+        if sid == 0 {
+            return None;
+        }
+        let n_glyphs = u16::try_from(self.char_strings_index.len()).ok()?;
+        (0..n_glyphs).find(|&glyph_id| self.charset.id_for_glyph(glyph_id) == Some(sid))
+    }
+}
+
+impl<'f, 'a, S: OutlineSink> Interpreter<'f, 'a, S> {
+    fn new(
+        global_subrs: &'f Index<'a>,
+        local_subrs: Option<&'f Index<'a>>,
+        variations: Option<Variations<'f, 'a>>,
+        sink: &'f mut S,
+    ) -> Self {
+        Interpreter {
+            global_subrs,
+            local_subrs,
+            variations,
+            sink,
+            stack: Vec::new(),
+            transient: [0.; TRANSIENT_ARRAY_SIZE],
+            x: 0.,
+            y: 0.,
+            path_open: false,
+            n_stems: 0,
+            width_parsed: false,
+            width: None,
+            seac: None,
+            done: false,
+            used_global_subrs: BTreeSet::new(),
+            used_local_subrs: BTreeSet::new(),
+        }
+    }
+
+    /// Reset the interpreter to begin a new charstring with its origin at `x`, `y`.
+    ///
+    /// The width and subroutine usage are retained.
+    fn reset(&mut self, x: f32, y: f32) {
+        self.stack.clear();
+        self.transient = [0.; TRANSIENT_ARRAY_SIZE];
+        self.x = x;
+        self.y = y;
+        self.path_open = false;
+        self.n_stems = 0;
+        self.width_parsed = true;
+        self.done = false;
+    }
+
+    /// Interpret a complete charstring, closing any open path at the end.
+    fn run(&mut self, char_string: &[u8]) -> Result<(), ParseError> {
+        self.execute(char_string, 0)?;
+        self.close_path();
+        Ok(())
+    }
+
+    fn is_cff2(&self) -> bool {
+        self.variations.is_some()
+    }
+
+    fn execute(&mut self, char_string: &[u8], depth: usize) -> Result<(), ParseError> {
+        if depth > MAX_SUBR_DEPTH {
+            return Err(ParseError::LimitExceeded);
+        }
+
+        let mut ctxt = ReadScope::new(char_string).ctxt();
+        while !self.done && ctxt.bytes_available() {
+            let b0 = ctxt.read_u8()?;
+            match b0 {
+                // hstem, vstem, hstemhm, vstemhm
+                1 | 3 | 18 | 23 => {
+                    self.stems();
+                }
+                // vmoveto
+                4 => {
+                    let start = self.take_width(self.stack.len() > 1);
+                    let dy = self.arg(start)?;
+                    self.stack.clear();
+                    let (x, y) = (self.x, self.y + dy);
+                    self.move_to(x, y);
+                }
+                // rlineto
+                5 => {
+                    let stack = self.take_stack();
+                    for pair in stack.chunks_exact(2) {
+                        let (x, y) = (self.x + pair[0], self.y + pair[1]);
+                        self.line_to(x, y);
+                    }
+                    self.restore_stack(stack);
+                }
+                // hlineto, vlineto
+                6 | 7 => {
+                    let stack = self.take_stack();
+                    let mut horizontal = b0 == 6;
+                    for &delta in &stack {
+                        let (x, y) = if horizontal {
+                            (self.x + delta, self.y)
+                        } else {
+                            (self.x, self.y + delta)
+                        };
+                        self.line_to(x, y);
+                        horizontal = !horizontal;
+                    }
+                    self.restore_stack(stack);
+                }
+                // rrcurveto
+                8 => {
+                    let stack = self.take_stack();
+                    for args in stack.chunks_exact(6) {
+                        self.rcurve_to(args);
+                    }
+                    self.restore_stack(stack);
+                }
+                // callsubr
+                10 => {
+                    let subrs = self.local_subrs.ok_or(ParseError::MissingValue)?;
+                    let index = subr_index(subrs, self.pop()?)?;
+                    self.used_local_subrs.insert(index);
+                    let subr = subrs.read_object(index).ok_or(ParseError::BadIndex)?;
+                    self.execute(subr, depth + 1)?;
+                }
+                // return
+                11 => return Ok(()),
+                // escape
+                12 => {
+                    let b1 = ctxt.read_u8()?;
+                    self.escape(b1)?;
+                }
+                // endchar
+                14 => {
+                    let len = self.stack.len();
+                    let start = self.take_width(len == 1 || len == 5);
+                    if !self.is_cff2() && self.stack.len() - start == 4 {
+                        let base_char = u8::try_from(self.arg(start + 2)? as i32)?;
+                        let accent_char = u8::try_from(self.arg(start + 3)? as i32)?;
+                        self.seac = Some(Seac {
+                            adx: self.arg(start)?,
+                            ady: self.arg(start + 1)?,
+                            base_char,
+                            accent_char,
+                        });
+                    }
+                    self.stack.clear();
+                    self.close_path();
+                    self.done = true;
+                }
+                // vsindex
+                15 if self.is_cff2() => {
+                    let vsindex = u16::try_from(self.pop()? as i32)?;
+                    self.stack.clear();
+                    if let Some(ref mut variations) = self.variations {
+                        variations.vsindex = vsindex;
+                        variations.scalars = None;
+                    }
+                }
+                // blend
+                16 if self.is_cff2() => {
+                    self.blend()?;
+                }
+                // hintmask, cntrmask
+                19 | 20 => {
+                    // Any operands are the arguments of an implied vstem
+                    self.stems();
+                    ctxt.read_slice((self.n_stems + 7) / 8)?;
+                }
+                // rmoveto
+                21 => {
+                    let start = self.take_width(self.stack.len() > 2);
+                    let (dx, dy) = (self.arg(start)?, self.arg(start + 1)?);
+                    self.stack.clear();
+                    let (x, y) = (self.x + dx, self.y + dy);
+                    self.move_to(x, y);
+                }
+                // hmoveto
+                22 => {
+                    let start = self.take_width(self.stack.len() > 1);
+                    let dx = self.arg(start)?;
+                    self.stack.clear();
+                    let (x, y) = (self.x + dx, self.y);
+                    self.move_to(x, y);
+                }
+                // rcurveline
+                24 => {
+                    let stack = self.take_stack();
+                    if stack.len() < 8 {
+                        return Err(ParseError::BadValue);
+                    }
+                    let (curves, line) = stack.split_at(stack.len() - 2);
+                    for args in curves.chunks_exact(6) {
+                        self.rcurve_to(args);
+                    }
+                    let (x, y) = (self.x + line[0], self.y + line[1]);
+                    self.line_to(x, y);
+                    self.restore_stack(stack);
+                }
+                // rlinecurve
+                25 => {
+                    let stack = self.take_stack();
+                    if stack.len() < 8 {
+                        return Err(ParseError::BadValue);
+                    }
+                    let (lines, curve) = stack.split_at(stack.len() - 6);
+                    for pair in lines.chunks_exact(2) {
+                        let (x, y) = (self.x + pair[0], self.y + pair[1]);
+                        self.line_to(x, y);
+                    }
+                    self.rcurve_to(curve);
+                    self.restore_stack(stack);
+                }
+                // vvcurveto, hhcurveto
+                26 | 27 => {
+                    let stack = self.take_stack();
+                    let (mut first, args) = if stack.len() % 4 == 1 {
+                        (stack[0], &stack[1..])
+                    } else {
+                        (0., &stack[..])
+                    };
+                    for args in args.chunks_exact(4) {
+                        if b0 == 26 {
+                            // dx1? dya dxb dyb dyc
+                            self.rcurve_to(&[first, args[0], args[1], args[2], 0., args[3]]);
+                        } else {
+                            // dy1? dxa dxb dyb dxc
+                            self.rcurve_to(&[args[0], first, args[1], args[2], args[3], 0.]);
+                        }
+                        first = 0.;
+                    }
+                    self.restore_stack(stack);
+                }
+                // shortint
+                28 => {
+                    let value = ctxt.read_i16be()?;
+                    self.push(f32::from(value))?;
+                }
+                // callgsubr
+                29 => {
+                    let subrs = self.global_subrs;
+                    let index = subr_index(subrs, self.pop()?)?;
+                    self.used_global_subrs.insert(index);
+                    let subr = subrs.read_object(index).ok_or(ParseError::BadIndex)?;
+                    self.execute(subr, depth + 1)?;
+                }
+                // vhcurveto, hvcurveto
+                30 | 31 => {
+                    let stack = self.take_stack();
+                    self.alternating_curves(&stack, b0 == 31);
+                    self.restore_stack(stack);
+                }
+                32..=246 => {
+                    self.push((i32::from(b0) - 139) as f32)?;
+                }
+                247..=250 => {
+                    let b1 = ctxt.read_u8()?;
+                    self.push(((i32::from(b0) - 247) * 256 + i32::from(b1) + 108) as f32)?;
+                }
+                251..=254 => {
+                    let b1 = ctxt.read_u8()?;
+                    self.push((-(i32::from(b0) - 251) * 256 - i32::from(b1) - 108) as f32)?;
+                }
+                // 16.16 fixed point
+                255 => {
+                    let value = ctxt.read_i32be()?;
+                    self.push(value as f32 / 65536.)?;
+                }
+                _ => return Err(ParseError::BadValue),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Execute the two byte operator `12 b1`.
+    fn escape(&mut self, b1: u8) -> Result<(), ParseError> {
+        match b1 {
+            // dotsection (deprecated)
+            0 => self.stack.clear(),
+            // and
+            3 => {
+                let (a, b) = self.pop2()?;
+                self.push(bool_value(a != 0. && b != 0.))?;
+            }
+            // or
+            4 => {
+                let (a, b) = self.pop2()?;
+                self.push(bool_value(a != 0. || b != 0.))?;
+            }
+            // not
+            5 => {
+                let a = self.pop()?;
+                self.push(bool_value(a == 0.))?;
+            }
+            // abs
+            9 => {
+                let a = self.pop()?;
+                self.push(a.abs())?;
+            }
+            // add
+            10 => {
+                let (a, b) = self.pop2()?;
+                self.push(a + b)?;
+            }
+            // sub
+            11 => {
+                let (a, b) = self.pop2()?;
+                self.push(a - b)?;
+            }
+            // div
+            12 => {
+                let (a, b) = self.pop2()?;
+                if b == 0. {
+                    return Err(ParseError::BadValue);
+                }
+                self.push(a / b)?;
+            }
+            // neg
+            14 => {
+                let a = self.pop()?;
+                self.push(-a)?;
+            }
+            // eq
+            15 => {
+                let (a, b) = self.pop2()?;
+                self.push(bool_value(a == b))?;
+            }
+            // drop
+            18 => {
+                self.pop()?;
+            }
+            // put
+            20 => {
+                let (value, index) = self.pop2()?;
+                let slot = transient_index(index)?;
+                self.transient[slot] = value;
+            }
+            // get
+            21 => {
+                let slot = transient_index(self.pop()?)?;
+                self.push(self.transient[slot])?;
+            }
+            // ifelse
+            22 => {
+                let (v1, v2) = self.pop2()?;
+                let (s1, s2) = self.pop2()?;
+                self.push(if v1 <= v2 { s1 } else { s2 })?;
+            }
+            // mul
+            24 => {
+                let (a, b) = self.pop2()?;
+                self.push(a * b)?;
+            }
+            // sqrt
+            26 => {
+                let a = self.pop()?;
+                if a < 0. {
+                    return Err(ParseError::BadValue);
+                }
+                self.push(a.sqrt())?;
+            }
+            // dup
+            27 => {
+                let a = self.pop()?;
+                self.push(a)?;
+                self.push(a)?;
+            }
+            // exch
+            28 => {
+                let (a, b) = self.pop2()?;
+                self.push(b)?;
+                self.push(a)?;
+            }
+            // index
+            29 => {
+                let i = self.pop()? as i32;
+                let len = self.stack.len();
+                let offset = usize::try_from(i.max(0)).map_err(|_| ParseError::BadIndex)?;
+                if offset >= len {
+                    return Err(ParseError::BadIndex);
+                }
+                self.push(self.stack[len - 1 - offset])?;
+            }
+            // roll
+            30 => {
+                let (n, j) = self.pop2()?;
+                let n = usize::try_from(n as i32).map_err(|_| ParseError::BadValue)?;
+                let len = self.stack.len();
+                if n > len {
+                    return Err(ParseError::BadIndex);
+                }
+                if n > 0 {
+                    // A positive `j` rolls towards the top of the stack
+                    let shift = (j as i64).rem_euclid(n as i64) as usize;
+                    self.stack[len - n..].rotate_right(shift);
+                }
+            }
+            // hflex
+            34 => {
+                let args = self.take_args(7)?;
+                let y0 = self.y;
+                // dx1 dx2 dy2 dx3 dx4 dx5 dx6
+                self.rcurve_to(&[args[0], 0., args[1], args[2], args[3], 0.]);
+                let dy = y0 - self.y;
+                self.rcurve_to(&[args[4], 0., args[5], dy, args[6], 0.]);
+            }
+            // flex
+            35 => {
+                let args = self.take_args(13)?;
+                self.rcurve_to(&args[0..6]);
+                self.rcurve_to(&args[6..12]);
+            }
+            // hflex1
+            36 => {
+                let args = self.take_args(9)?;
+                let y0 = self.y;
+                // dx1 dy1 dx2 dy2 dx3 dx4 dx5 dy5 dx6
+                self.rcurve_to(&[args[0], args[1], args[2], args[3], args[4], 0.]);
+                let y5 = self.y + args[7];
+                self.rcurve_to(&[args[5], 0., args[6], args[7], args[8], y0 - y5]);
+            }
+            // flex1
+            37 => {
+                let args = self.take_args(11)?;
+                let (x0, y0) = (self.x, self.y);
+                let dx: f32 = args[0..10].iter().step_by(2).sum();
+                let dy: f32 = args[1..10].iter().step_by(2).sum();
+                self.rcurve_to(&args[0..6]);
+                let (x4, y4) = (self.x + args[6], self.y + args[7]);
+                let (x5, y5) = (x4 + args[8], y4 + args[9]);
+                let (x6, y6) = if dx.abs() > dy.abs() {
+                    (x5 + args[10], y0)
+                } else {
+                    (x0, y5 + args[10])
+                };
+                self.curve_to(x4, y4, x5, y5, x6, y6);
+            }
+            // random is not supported since its result would make outlines non-deterministic
+            23 => return Err(ParseError::NotImplemented),
+            _ => return Err(ParseError::BadValue),
+        }
+
+        Ok(())
+    }
+
+    /// Handle the hint operators, which contribute to the stem count used by `hintmask`.
+    fn stems(&mut self) {
+        let start = self.take_width(self.stack.len() % 2 == 1);
+        self.n_stems += (self.stack.len() - start) / 2;
+        self.stack.clear();
+    }
+
+    /// Record the width if `has_width` is set and this is the first stack clearing operator.
+    ///
+    /// Returns the index of the first argument to the operator.
+    fn take_width(&mut self, has_width: bool) -> usize {
+        let start = if !self.width_parsed && !self.is_cff2() && has_width {
+            self.width = self.stack.first().copied();
+            1
+        } else {
+            0
+        };
+        self.width_parsed = true;
+        start
+    }
+
+    /// Resolve a `blend` operator, replacing its operands with the blended values.
+    fn blend(&mut self) -> Result<(), ParseError> {
+        let n = usize::try_from(self.pop()? as i32)?;
+        let variations = self.variations.as_mut().ok_or(ParseError::MissingValue)?;
+        if variations.scalars.is_none() {
+            let scalars = variations
+                .cff2
+                .blend_scalars(variations.vsindex, variations.coords)?;
+            variations.scalars = Some(scalars);
+        }
+        let scalars = variations.scalars.as_ref().unwrap();
+
+        let k = scalars.len();
+        let n_operands = n * (k + 1);
+        if n_operands > self.stack.len() {
+            return Err(ParseError::BadValue);
+        }
+        let base = self.stack.len() - n_operands;
+        for i in 0..n {
+            let deltas = &self.stack[base + n + i * k..base + n + (i + 1) * k];
+            let delta: f32 = deltas
+                .iter()
+                .zip(scalars.iter())
+                .map(|(delta, scalar)| delta * scalar)
+                .sum();
+            self.stack[base + i] += delta;
+        }
+        self.stack.truncate(base + n);
+        Ok(())
+    }
+
+    /// Emit the curves of `hvcurveto` or `vhcurveto`, which alternate between starting
+    /// horizontally and vertically.
+    fn alternating_curves(&mut self, args: &[f32], mut horizontal: bool) {
+        let mut i = 0;
+        while i + 4 <= args.len() {
+            // The last curve may have an additional argument for its final point
+            let last = if args.len() - i == 5 { args[i + 4] } else { 0. };
+            if horizontal {
+                // dx1 dx2 dy2 dy3 dxf?
+                self.rcurve_to(&[args[i], 0., args[i + 1], args[i + 2], last, args[i + 3]]);
+            } else {
+                // dy1 dx2 dy2 dx3 dyf?
+                self.rcurve_to(&[0., args[i], args[i + 1], args[i + 2], args[i + 3], last]);
+            }
+            horizontal = !horizontal;
+            i += 4;
+        }
+    }
+
+    /// Emit a curve with control points relative to the previous point.
+    fn rcurve_to(&mut self, args: &[f32]) {
+        let (x1, y1) = (self.x + args[0], self.y + args[1]);
+        let (x2, y2) = (x1 + args[2], y1 + args[3]);
+        let (x, y) = (x2 + args[4], y2 + args[5]);
+        self.curve_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.close_path();
+        self.x = x;
+        self.y = y;
+        self.sink.move_to(x, y);
+        self.path_open = true;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+        self.sink.line_to(x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+        self.sink.curve_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close_path(&mut self) {
+        if self.path_open {
+            self.sink.close();
+            self.path_open = false;
+        }
+    }
+
+    fn push(&mut self, value: f32) -> Result<(), ParseError> {
+        let max = if self.is_cff2() {
+            MAX_CFF2_STACK
+        } else {
+            MAX_STACK
+        };
+        if self.stack.len() >= max {
+            return Err(ParseError::LimitExceeded);
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<f32, ParseError> {
+        self.stack.pop().ok_or(ParseError::BadValue)
+    }
+
+    /// Pop two values, returning them in the order they were pushed.
+    fn pop2(&mut self) -> Result<(f32, f32), ParseError> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        Ok((a, b))
+    }
+
+    fn arg(&self, index: usize) -> Result<f32, ParseError> {
+        self.stack.get(index).copied().ok_or(ParseError::BadValue)
+    }
+
+    /// Take exactly `count` arguments from the stack, clearing it.
+    fn take_args(&mut self, count: usize) -> Result<Vec<f32>, ParseError> {
+        if self.stack.len() != count {
+            return Err(ParseError::BadValue);
+        }
+        Ok(self.take_stack())
+    }
+
+    /// Take the stack to process its arguments, leaving it empty.
+    fn take_stack(&mut self) -> Vec<f32> {
+        let mut stack = Vec::new();
+        mem::swap(&mut self.stack, &mut stack);
+        stack
+    }
+
+    /// Put back a stack that was taken to process its arguments, clearing it.
+    fn restore_stack(&mut self, mut stack: Vec<f32>) {
+        stack.clear();
+        self.stack = stack;
+    }
+}
+
+/// Returns the subroutine number biased by the number of subroutines in `subrs`.
+fn subr_index(subrs: &Index<'_>, operand: f32) -> Result<usize, ParseError> {
+    let bias = if subrs.count < 1240 {
+        107
+    } else if subrs.count < 33900 {
+        1131
+    } else {
+        32768
+    };
+    usize::try_from(operand as i32 + bias).map_err(|_| ParseError::BadIndex)
+}
+
+fn transient_index(value: f32) -> Result<usize, ParseError> {
+    match usize::try_from(value as i32) {
+        Ok(index) if index < TRANSIENT_ARRAY_SIZE => Ok(index),
+        _ => Err(ParseError::BadIndex),
+    }
+}
+
+fn bool_value(value: bool) -> f32 {
+    if value {
+        1.
+    } else {
+        0.
+    }
+}
+
+/// Returns the numeric value of `operator` in `private_dict`, or zero if it's not a number.
+fn private_dict_value(private_dict: &PrivateDict, operator: Operator) -> f32 {
+    match private_dict.get_with_default(operator) {
+        Some([Operand::Integer(value)]) => *value as f32,
+        Some([Operand::Real(real)]) => real_value(real).unwrap_or(0.),
+        _ => 0.,
+    }
+}
+
+/// Decode the packed BCD nibbles of `real`.
+fn real_value(real: &Real) -> Option<f32> {
+    // Each nibble is at most two characters
+    let mut string = String::with_capacity(real.0.len() * 4);
+    'bytes: for &byte in real.0.iter() {
+        for &nibble in &[byte >> 4, byte & 0xF] {
+            match nibble {
+                0..=9 => string.push(char::from(b'0' + nibble)),
+                0xA => string.push('.'),
+                0xB => string.push('E'),
+                0xC => string.push_str("E-"),
+                0xE => string.push('-'),
+                0xF => break 'bytes,
+                _ => return None,
+            }
+        }
+    }
+    string.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Command {
+        MoveTo(f32, f32),
+        LineTo(f32, f32),
+        CurveTo(f32, f32, f32, f32, f32, f32),
+        Close,
+    }
+
+    impl OutlineSink for Vec<Command> {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.push(Command::MoveTo(x, y));
+        }
+
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.push(Command::LineTo(x, y));
+        }
+
+        fn quad_to(&mut self, _x1: f32, _y1: f32, _x: f32, _y: f32) {
+            unreachable!()
+        }
+
+        fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+            self.push(Command::CurveTo(x1, y1, x2, y2, x, y));
+        }
+
+        fn close(&mut self) {
+            self.push(Command::Close);
+        }
+    }
+
+    const EMPTY_INDEX: [u8; 2] = [0x00, 0x00];
+
+    // Encode a small integer operand
+    fn int(value: i8) -> u8 {
+        (i16::from(value) + 139) as u8
+    }
+
+    #[test]
+    fn test_paths() {
+        let global_subrs = ReadScope::new(&EMPTY_INDEX).read::<Index<'_>>().unwrap();
+        #[rustfmt::skip]
+        let char_string = [
+            int(10), int(10), int(20), 21, // width, rmoveto
+            int(30), int(40), 5, // rlineto
+            int(10), int(20), int(30), int(40), 31, // hvcurveto
+            14, // endchar
+        ];
+        let mut commands: Vec<Command> = Vec::new();
+        let mut interpreter = Interpreter::new(&global_subrs, None, None, &mut commands);
+        interpreter.run(&char_string).unwrap();
+        assert_eq!(interpreter.width, Some(10.));
+        assert_eq!(
+            commands,
+            vec![
+                Command::MoveTo(10., 20.),
+                Command::LineTo(40., 60.),
+                Command::CurveTo(50., 60., 70., 90., 70., 130.),
+                Command::Close,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subrs_and_hintmask() {
+        let global_subrs = ReadScope::new(&EMPTY_INDEX).read::<Index<'_>>().unwrap();
+        // One subroutine: 10 0 rlineto return
+        let local_subr_data = [0x00, 0x01, 0x01, 0x01, 0x05, int(10), int(0), 5, 11];
+        let local_subrs = ReadScope::new(&local_subr_data)
+            .read::<Index<'_>>()
+            .unwrap();
+        #[rustfmt::skip]
+        let char_string = [
+            int(0), int(10), int(20), int(10), 18, // hstemhm
+            int(30), int(10), 19, 0xE0, // hintmask with an implied vstem
+            int(0), int(0), 21, // rmoveto
+            int(-107), 10, // callsubr 0
+            14, // endchar
+        ];
+        let mut commands: Vec<Command> = Vec::new();
+        let mut interpreter =
+            Interpreter::new(&global_subrs, Some(&local_subrs), None, &mut commands);
+        interpreter.run(&char_string).unwrap();
+        assert_eq!(interpreter.width, None);
+        assert_eq!(interpreter.n_stems, 3);
+        assert_eq!(
+            interpreter.used_local_subrs.iter().collect::<Vec<_>>(),
+            vec![&0]
+        );
+        assert_eq!(
+            commands,
+            vec![
+                Command::MoveTo(0., 0.),
+                Command::LineTo(10., 0.),
+                Command::Close,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_real_value() {
+        // Examples from the CFF specification, table 5
+        let real = Real(vec![0xE2, 0xA2, 0x5F]);
+        assert_eq!(real_value(&real), Some(-2.25));
+        let real = Real(vec![0x0A, 0x14, 0x05, 0x41, 0xC3, 0xFF]);
+        assert_eq!(real_value(&real), Some(0.140541E-3));
+        let real = Real(vec![0x1D, 0xFF]);
+        assert_eq!(real_value(&real), None);
+    }
+}
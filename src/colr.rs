@@ -0,0 +1,751 @@
+#![deny(missing_docs)]
+
+//! `COLR` version 1 paint graph walking.
+//!
+//! The paint graph of a version 1 color glyph is walked from its root paint, passing the
+//! transforms, clips, fills and compositing layers it describes to a `ColorPainter`. Colors are
+//! resolved from a `CPAL` palette.
+//!
+//! — <https://docs.microsoft.com/en-us/typography/opentype/spec/colr>
+
+use std::collections::BTreeSet;
+use std::f32::consts::PI;
+
+use crate::error::ParseError;
+use crate::tables::colr::{
+    Affine2x3, ClipBox, ColorLine, ColrTable, CompositeMode, Extend, Paint, PaintRef,
+    FOREGROUND_PALETTE_INDEX,
+};
+use crate::tables::cpal::{ColorRecord, CpalTable};
+
+/// The maximum depth of nested paints.
+const MAX_PAINT_DEPTH: usize = 64;
+
+/// The maximum number of paints visited for a single glyph. Paints can be shared by many layers,
+/// so without this limit the work done for a glyph would grow exponentially with the depth of
+/// the paint graph.
+const MAX_PAINT_VISITS: usize = 65536;
+
+/// Receives the operations of a version 1 color glyph.
+///
+/// Every `push_*` call is balanced by the corresponding `pop_*` call. Coordinates are in font
+/// units and are subject to all pushed transforms.
+pub trait ColorPainter {
+    /// Apply `transform` to subsequent operations, in addition to those already pushed.
+    fn push_transform(&mut self, transform: Affine2x3);
+
+    /// Remove the most recently pushed transform.
+    fn pop_transform(&mut self);
+
+    /// Clip subsequent fills to the outline of `glyph_id`.
+    fn push_clip_glyph(&mut self, glyph_id: u16);
+
+    /// Clip subsequent fills to `clip_box`.
+    fn push_clip_box(&mut self, clip_box: ClipBox);
+
+    /// Remove the most recently pushed clip.
+    fn pop_clip(&mut self);
+
+    /// Fill the current clip with `brush`.
+    fn fill(&mut self, brush: Brush);
+
+    /// Begin a new, transparent, layer that is combined with the content beneath it using
+    /// `mode` when it's popped.
+    fn push_layer(&mut self, mode: CompositeMode);
+
+    /// Combine the most recently pushed layer with the content beneath it.
+    fn pop_layer(&mut self);
+}
+
+/// A color resolved from a `CPAL` palette.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PaintColor {
+    /// The palette color. `None` indicates the text foreground color should be used.
+    pub color: Option<ColorRecord>,
+    /// Alpha multiplier for the color.
+    pub alpha: f32,
+}
+
+/// A color stop of a gradient with its color resolved.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GradientStop {
+    /// The position of the stop on the color line.
+    pub offset: f32,
+    /// The color of the stop.
+    pub color: PaintColor,
+}
+
+/// How to fill the current clip.
+///
+/// Gradient stops are sorted by offset.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Brush {
+    /// A solid color.
+    Solid(PaintColor),
+    /// A linear gradient from `p0` towards `p1`, with its color lines parallel to the line
+    /// from `p0` to `p2`.
+    LinearGradient {
+        /// The start point of the gradient.
+        p0: (f32, f32),
+        /// The end point of the gradient.
+        p1: (f32, f32),
+        /// The rotation point of the gradient.
+        p2: (f32, f32),
+        /// The color stops of the gradient.
+        stops: Vec<GradientStop>,
+        /// How the gradient is extended outside of the range of its color stops.
+        extend: Extend,
+    },
+    /// A radial gradient between two circles.
+    RadialGradient {
+        /// The center of the start circle.
+        c0: (f32, f32),
+        /// The radius of the start circle.
+        r0: f32,
+        /// The center of the end circle.
+        c1: (f32, f32),
+        /// The radius of the end circle.
+        r1: f32,
+        /// The color stops of the gradient.
+        stops: Vec<GradientStop>,
+        /// How the gradient is extended outside of the range of its color stops.
+        extend: Extend,
+    },
+    /// A sweep gradient around `center`.
+    SweepGradient {
+        /// The center of the gradient.
+        center: (f32, f32),
+        /// The start angle of the gradient in degrees, counter-clockwise from the x axis.
+        start_angle: f32,
+        /// The end angle of the gradient in degrees, counter-clockwise from the x axis.
+        end_angle: f32,
+        /// The color stops of the gradient.
+        stops: Vec<GradientStop>,
+        /// How the gradient is extended outside of the range of its color stops.
+        extend: Extend,
+    },
+}
+
+/// Paint the version 1 color glyph of `glyph_id` to `painter`, with colors from the palette at
+/// `palette_index` in `cpal`.
+///
+/// Returns `false` if `glyph_id` doesn't have a version 1 color glyph. The glyph may still have
+/// a version 0 color glyph, see `ColrTable::color_layers`.
+///
+/// A paint graph that contains a cycle, is nested too deeply or visits too many paints results in
+/// an error. Operations may have been passed to `painter` before the error was encountered.
+pub fn paint_glyph<P: ColorPainter>(
+    colr: &ColrTable<'_>,
+    cpal: &CpalTable<'_>,
+    palette_index: u16,
+    glyph_id: u16,
+    painter: &mut P,
+) -> Result<bool, ParseError> {
+    let mut walker = Walker {
+        colr,
+        cpal,
+        palette_index,
+        painter,
+        stack: Vec::new(),
+        visits: 0,
+    };
+    match colr.base_glyph_paint(glyph_id) {
+        Some(paint_ref) => {
+            walker.paint_color_glyph(glyph_id, paint_ref)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Returns the glyphs referenced by the paint graph of the version 1 color glyph of `glyph_id`.
+///
+/// This includes the glyphs that provide clip outlines and the base glyphs of reused color
+/// glyphs. When subsetting, these glyphs need to be retained along with `glyph_id`.
+pub fn referenced_glyphs(colr: &ColrTable<'_>, glyph_id: u16) -> Result<BTreeSet<u16>, ParseError> {
+    let mut glyphs = BTreeSet::new();
+    let mut visited = BTreeSet::new();
+    let mut queue: Vec<PaintRef> = colr.base_glyph_paint(glyph_id).into_iter().collect();
+    // The graph may share paints, so each is visited at most once. This also means cycles are
+    // harmless here.
+    while let Some(paint_ref) = queue.pop() {
+        if !visited.insert(paint_ref) {
+            continue;
+        }
+        match colr.paint(paint_ref)? {
+            Paint::ColrLayers {
+                num_layers,
+                first_layer_index,
+            } => {
+                for index in layer_indices(first_layer_index, num_layers) {
+                    queue.push(colr.layer_paint(index)?);
+                }
+            }
+            Paint::Solid { .. }
+            | Paint::LinearGradient { .. }
+            | Paint::RadialGradient { .. }
+            | Paint::SweepGradient { .. } => {}
+            Paint::Glyph { paint, glyph_id } => {
+                glyphs.insert(glyph_id);
+                queue.push(paint);
+            }
+            Paint::ColrGlyph { glyph_id } => {
+                glyphs.insert(glyph_id);
+                queue.push(
+                    colr.base_glyph_paint(glyph_id)
+                        .ok_or(ParseError::BadIndex)?,
+                );
+            }
+            Paint::Transform { paint, .. }
+            | Paint::Translate { paint, .. }
+            | Paint::Scale { paint, .. }
+            | Paint::Rotate { paint, .. }
+            | Paint::Skew { paint, .. } => queue.push(paint),
+            Paint::Composite {
+                source, backdrop, ..
+            } => {
+                queue.push(backdrop);
+                queue.push(source);
+            }
+        }
+    }
+    Ok(glyphs)
+}
+
+struct Walker<'a, 'b, 'c, P: ColorPainter> {
+    colr: &'a ColrTable<'b>,
+    cpal: &'a CpalTable<'c>,
+    palette_index: u16,
+    painter: &'a mut P,
+    /// The paints on the path from the root to the paint being visited.
+    stack: Vec<PaintRef>,
+    /// The number of paints visited so far.
+    visits: usize,
+}
+
+impl<'a, 'b, 'c, P: ColorPainter> Walker<'a, 'b, 'c, P> {
+    /// Paint the color glyph of `glyph_id`, which has the root paint `paint_ref`, within its
+    /// clip box.
+    fn paint_color_glyph(&mut self, glyph_id: u16, paint_ref: PaintRef) -> Result<(), ParseError> {
+        match self.colr.clip_box(glyph_id)? {
+            Some(clip_box) => {
+                self.painter.push_clip_box(clip_box);
+                self.visit(paint_ref)?;
+                self.painter.pop_clip();
+            }
+            None => self.visit(paint_ref)?,
+        }
+        Ok(())
+    }
+
+    fn visit(&mut self, paint_ref: PaintRef) -> Result<(), ParseError> {
+        if self.stack.contains(&paint_ref) {
+            return Err(ParseError::BadValue);
+        }
+        if self.stack.len() >= MAX_PAINT_DEPTH || self.visits >= MAX_PAINT_VISITS {
+            return Err(ParseError::LimitExceeded);
+        }
+        self.visits += 1;
+        self.stack.push(paint_ref);
+        self.visit_paint(self.colr.paint(paint_ref)?)?;
+        self.stack.pop();
+        Ok(())
+    }
+
+    fn visit_paint(&mut self, paint: Paint) -> Result<(), ParseError> {
+        match paint {
+            Paint::ColrLayers {
+                num_layers,
+                first_layer_index,
+            } => {
+                for index in layer_indices(first_layer_index, num_layers) {
+                    let layer = self.colr.layer_paint(index)?;
+                    self.visit(layer)?;
+                }
+            }
+            Paint::Solid {
+                palette_index,
+                alpha,
+                ..
+            } => {
+                let color = self.color(palette_index, alpha)?;
+                self.painter.fill(Brush::Solid(color));
+            }
+            Paint::LinearGradient {
+                color_line,
+                p0,
+                p1,
+                p2,
+                ..
+            } => {
+                let brush = Brush::LinearGradient {
+                    p0: point(p0),
+                    p1: point(p1),
+                    p2: point(p2),
+                    stops: self.stops(&color_line)?,
+                    extend: color_line.extend,
+                };
+                self.painter.fill(brush);
+            }
+            Paint::RadialGradient {
+                color_line,
+                c0,
+                r0,
+                c1,
+                r1,
+                ..
+            } => {
+                let brush = Brush::RadialGradient {
+                    c0: point(c0),
+                    r0: f32::from(r0),
+                    c1: point(c1),
+                    r1: f32::from(r1),
+                    stops: self.stops(&color_line)?,
+                    extend: color_line.extend,
+                };
+                self.painter.fill(brush);
+            }
+            Paint::SweepGradient {
+                color_line,
+                center,
+                start_angle,
+                end_angle,
+                ..
+            } => {
+                let brush = Brush::SweepGradient {
+                    center: point(center),
+                    start_angle: start_angle * 180.,
+                    end_angle: end_angle * 180.,
+                    stops: self.stops(&color_line)?,
+                    extend: color_line.extend,
+                };
+                self.painter.fill(brush);
+            }
+            Paint::Glyph { paint, glyph_id } => {
+                self.painter.push_clip_glyph(glyph_id);
+                self.visit(paint)?;
+                self.painter.pop_clip();
+            }
+            Paint::ColrGlyph { glyph_id } => {
+                let paint_ref = self
+                    .colr
+                    .base_glyph_paint(glyph_id)
+                    .ok_or(ParseError::BadIndex)?;
+                self.paint_color_glyph(glyph_id, paint_ref)?;
+            }
+            Paint::Transform {
+                paint, transform, ..
+            } => self.visit_transformed(paint, transform)?,
+            Paint::Translate { paint, dx, dy, .. } => {
+                let transform = Affine2x3::translate(f32::from(dx), f32::from(dy));
+                self.visit_transformed(paint, transform)?;
+            }
+            Paint::Scale {
+                paint,
+                scale_x,
+                scale_y,
+                center,
+                ..
+            } => {
+                let transform = around(Affine2x3::scale(scale_x, scale_y), center);
+                self.visit_transformed(paint, transform)?;
+            }
+            Paint::Rotate {
+                paint,
+                angle,
+                center,
+                ..
+            } => {
+                let transform = around(Affine2x3::rotate(angle * PI), center);
+                self.visit_transformed(paint, transform)?;
+            }
+            Paint::Skew {
+                paint,
+                x_skew_angle,
+                y_skew_angle,
+                center,
+                ..
+            } => {
+                let skew = Affine2x3::skew(x_skew_angle * PI, y_skew_angle * PI);
+                self.visit_transformed(paint, around(skew, center))?;
+            }
+            Paint::Composite {
+                source,
+                mode,
+                backdrop,
+            } => {
+                self.painter.push_layer(CompositeMode::SrcOver);
+                self.visit(backdrop)?;
+                self.painter.push_layer(mode);
+                self.visit(source)?;
+                self.painter.pop_layer();
+                self.painter.pop_layer();
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_transformed(
+        &mut self,
+        paint_ref: PaintRef,
+        transform: Affine2x3,
+    ) -> Result<(), ParseError> {
+        self.painter.push_transform(transform);
+        self.visit(paint_ref)?;
+        self.painter.pop_transform();
+        Ok(())
+    }
+
+    fn color(&self, palette_index: u16, alpha: f32) -> Result<PaintColor, ParseError> {
+        let color = if palette_index == FOREGROUND_PALETTE_INDEX {
+            None
+        } else {
+            Some(self.cpal.color(self.palette_index, palette_index)?)
+        };
+        Ok(PaintColor { color, alpha })
+    }
+
+    fn stops(&self, color_line: &ColorLine) -> Result<Vec<GradientStop>, ParseError> {
+        let mut stops = color_line
+            .stops
+            .iter()
+            .map(|stop| {
+                Ok(GradientStop {
+                    offset: stop.stop_offset,
+                    color: self.color(stop.palette_index, stop.alpha)?,
+                })
+            })
+            .collect::<Result<Vec<_>, ParseError>>()?;
+        // Stable, so stops at the same offset keep their order
+        stops.sort_by(|a, b| {
+            a.offset
+                .partial_cmp(&b.offset)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(stops)
+    }
+}
+
+fn layer_indices(first_layer_index: u32, num_layers: u8) -> impl Iterator<Item = u32> {
+    (0..u32::from(num_layers)).filter_map(move |i| first_layer_index.checked_add(i))
+}
+
+fn point((x, y): (i16, i16)) -> (f32, f32) {
+    (f32::from(x), f32::from(y))
+}
+
+fn around(transform: Affine2x3, center: Option<(i16, i16)>) -> Affine2x3 {
+    match center {
+        Some(center) => transform.around_center(point(center)),
+        None => transform,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::tests::{push_u16, push_u32};
+
+    #[derive(Debug, PartialEq)]
+    enum Op {
+        PushTransform(Affine2x3),
+        PopTransform,
+        PushClipGlyph(u16),
+        PushClipBox(ClipBox),
+        PopClip,
+        Fill(Brush),
+        PushLayer(CompositeMode),
+        PopLayer,
+    }
+
+    impl ColorPainter for Vec<Op> {
+        fn push_transform(&mut self, transform: Affine2x3) {
+            self.push(Op::PushTransform(transform));
+        }
+
+        fn pop_transform(&mut self) {
+            self.push(Op::PopTransform);
+        }
+
+        fn push_clip_glyph(&mut self, glyph_id: u16) {
+            self.push(Op::PushClipGlyph(glyph_id));
+        }
+
+        fn push_clip_box(&mut self, clip_box: ClipBox) {
+            self.push(Op::PushClipBox(clip_box));
+        }
+
+        fn pop_clip(&mut self) {
+            self.push(Op::PopClip);
+        }
+
+        fn fill(&mut self, brush: Brush) {
+            self.push(Op::Fill(brush));
+        }
+
+        fn push_layer(&mut self, mode: CompositeMode) {
+            self.push(Op::PushLayer(mode));
+        }
+
+        fn pop_layer(&mut self) {
+            self.push(Op::PopLayer);
+        }
+    }
+
+    #[rustfmt::skip]
+    const CPAL_DATA: [u8; 22] = [
+        0x00, 0x00, // version
+        0x00, 0x02, // num_palette_entries
+        0x00, 0x01, // num_palettes
+        0x00, 0x02, // num_color_records
+        0x00, 0x00, 0x00, 0x0E, // color_records_offset
+        0x00, 0x00, // color_record_indices
+        0x00, 0x00, 0xFF, 0xFF, // red
+        0xFF, 0x00, 0x00, 0xFF, // blue
+    ];
+
+    const RED: ColorRecord = ColorRecord {
+        blue: 0,
+        green: 0,
+        red: 0xFF,
+        alpha: 0xFF,
+    };
+
+    const BLUE: ColorRecord = ColorRecord {
+        blue: 0xFF,
+        green: 0,
+        red: 0,
+        alpha: 0xFF,
+    };
+
+    /// A version 1 table with the header, base glyph list, layer list and clip list, followed
+    /// by `paints`. Glyph 5's root paint is at offset 0 of `paints`, glyph 6's at
+    /// `glyph_6_paint`. Layer offsets are relative to the start of `paints`.
+    fn colr_v1(glyph_6_paint: u32, layers: &[u32], paints: &[u8]) -> Vec<u8> {
+        const HEADER_LEN: u32 = 34;
+        const BASE_GLYPH_LIST_LEN: u32 = 16;
+        const CLIP_LIST_LEN: u32 = 21;
+        let layer_list_len = 4 + 4 * layers.len() as u32;
+        let layer_list_offset = HEADER_LEN + BASE_GLYPH_LIST_LEN;
+        let clip_list_offset = layer_list_offset + layer_list_len;
+        let paints_offset = clip_list_offset + CLIP_LIST_LEN;
+
+        let mut data = Vec::new();
+        // version, numBaseGlyphRecords, baseGlyphRecordsOffset, layerRecordsOffset,
+        // numLayerRecords
+        push_u16(&mut data, &[1, 0]);
+        push_u32(&mut data, &[0, 0]);
+        push_u16(&mut data, &[0]);
+        // baseGlyphListOffset, layerListOffset, clipListOffset, varIndexMapOffset,
+        // itemVariationStoreOffset
+        push_u32(
+            &mut data,
+            &[HEADER_LEN, layer_list_offset, clip_list_offset, 0, 0],
+        );
+        // Base glyph list
+        push_u32(&mut data, &[2]);
+        push_u16(&mut data, &[5]);
+        push_u32(&mut data, &[paints_offset - HEADER_LEN]);
+        push_u16(&mut data, &[6]);
+        push_u32(&mut data, &[paints_offset - HEADER_LEN + glyph_6_paint]);
+        // Layer list
+        push_u32(&mut data, &[layers.len() as u32]);
+        for layer in layers {
+            push_u32(&mut data, &[paints_offset - layer_list_offset + layer]);
+        }
+        // Clip list: a clip box for glyph 5
+        #[rustfmt::skip]
+        data.extend_from_slice(&[
+            1, // format
+            0, 0, 0, 1, // num_clips
+            0, 5, 0, 5, 0, 0, 12, // clip record
+            1, 0, 0, 0, 0, 0x03, 0xE8, 0x03, 0xE8, // clip box
+        ]);
+        data.extend_from_slice(paints);
+        data
+    }
+
+    #[test]
+    fn test_paint_glyph() {
+        #[rustfmt::skip]
+        let paints = [
+            // 0: PaintColrLayers
+            1, 2, 0, 0, 0, 0,
+            // 6: PaintGlyph, glyph 10, filled with paint at 21
+            10, 0, 0, 15, 0, 10,
+            // 12: PaintTranslate paint at 27 by (100, -50)
+            14, 0, 0, 15, 0, 100, 0xFF, 0xCE,
+            // 20: padding
+            0,
+            // 21: PaintSolid, red, alpha 1.0
+            2, 0, 0, 0x40, 0x00,
+            // 26: padding
+            0,
+            // 27: PaintGlyph, glyph 11, filled with paint at 33
+            10, 0, 0, 6, 0, 11,
+            // 33: PaintLinearGradient, color line at 49
+            4, 0, 0, 16, 0, 0, 0, 0, 0, 100, 0, 0, 0, 0, 0, 100,
+            // 49: color line: pad, two stops, out of order
+            0, 0, 2,
+            0x40, 0x00, 0x00, 0x00, 0x40, 0x00,
+            0x00, 0x00, 0x00, 0x01, 0x20, 0x00,
+        ];
+        let data = colr_v1(0, &[6, 12], &paints);
+        let colr = ReadScope::new(&data).read::<ColrTable<'_>>().unwrap();
+        let cpal = ReadScope::new(&CPAL_DATA).read::<CpalTable<'_>>().unwrap();
+
+        let mut ops = Vec::new();
+        assert_eq!(paint_glyph(&colr, &cpal, 0, 5, &mut ops), Ok(true));
+        assert_eq!(
+            ops,
+            vec![
+                Op::PushClipBox(ClipBox {
+                    x_min: 0,
+                    y_min: 0,
+                    x_max: 1000,
+                    y_max: 1000,
+                    var_index_base: None
+                }),
+                Op::PushClipGlyph(10),
+                Op::Fill(Brush::Solid(PaintColor {
+                    color: Some(RED),
+                    alpha: 1.
+                })),
+                Op::PopClip,
+                Op::PushTransform(Affine2x3::translate(100., -50.)),
+                Op::PushClipGlyph(11),
+                Op::Fill(Brush::LinearGradient {
+                    p0: (0., 0.),
+                    p1: (100., 0.),
+                    p2: (0., 100.),
+                    stops: vec![
+                        GradientStop {
+                            offset: 0.,
+                            color: PaintColor {
+                                color: Some(BLUE),
+                                alpha: 0.5
+                            }
+                        },
+                        GradientStop {
+                            offset: 1.,
+                            color: PaintColor {
+                                color: Some(RED),
+                                alpha: 1.
+                            }
+                        },
+                    ],
+                    extend: Extend::Pad,
+                }),
+                Op::PopClip,
+                Op::PopTransform,
+                Op::PopClip,
+            ]
+        );
+        assert_eq!(
+            referenced_glyphs(&colr, 5)
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![10, 11]
+        );
+
+        let mut ops: Vec<Op> = Vec::new();
+        assert_eq!(paint_glyph(&colr, &cpal, 0, 7, &mut ops), Ok(false));
+        assert!(ops.is_empty());
+    }
+
+    #[test]
+    fn test_paint_composite_and_colr_glyph() {
+        #[rustfmt::skip]
+        let paints = [
+            // 0: PaintComposite, source at 8, multiply, backdrop at 11
+            32, 0, 0, 8, 23, 0, 0, 11,
+            // 8: PaintColrGlyph, glyph 5
+            11, 0, 5,
+            // 11: PaintSolid, foreground, alpha 0.5
+            2, 0xFF, 0xFF, 0x20, 0x00,
+            // 16: PaintRotateAroundCenter, paint at 26 by 90° around (10, 10)
+            26, 0, 0, 10, 0x20, 0x00, 0, 10, 0, 10,
+            // 26: PaintSolid, foreground, alpha 0.5
+            2, 0xFF, 0xFF, 0x20, 0x00,
+        ];
+        let data = colr_v1(16, &[], &paints);
+        let colr = ReadScope::new(&data).read::<ColrTable<'_>>().unwrap();
+        let cpal = ReadScope::new(&CPAL_DATA).read::<CpalTable<'_>>().unwrap();
+
+        // Glyph 6 is a rotated foreground fill
+        let mut ops = Vec::new();
+        assert_eq!(paint_glyph(&colr, &cpal, 0, 6, &mut ops), Ok(true));
+        assert_eq!(ops.len(), 3);
+        match &ops[0] {
+            Op::PushTransform(transform) => {
+                let (x, y) = transform.transform_point(20., 10.);
+                assert!((x - 10.).abs() < 1e-3 && (y - 20.).abs() < 1e-3);
+            }
+            op => panic!("unexpected op {:?}", op),
+        }
+
+        // Glyph 5 uses itself as the source of its composite, which is a cycle
+        let mut ops = Vec::new();
+        assert_eq!(
+            paint_glyph(&colr, &cpal, 0, 5, &mut ops),
+            Err(ParseError::BadValue)
+        );
+        assert_eq!(
+            &ops[..3],
+            &[
+                Op::PushClipBox(ClipBox {
+                    x_min: 0,
+                    y_min: 0,
+                    x_max: 1000,
+                    y_max: 1000,
+                    var_index_base: None
+                }),
+                Op::PushLayer(CompositeMode::SrcOver),
+                Op::Fill(Brush::Solid(PaintColor {
+                    color: None,
+                    alpha: 0.5
+                })),
+            ]
+        );
+        assert_eq!(
+            referenced_glyphs(&colr, 5)
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![5]
+        );
+    }
+
+    #[test]
+    fn test_paint_shared_layers() {
+        // Five levels of PaintColrLayers, each with 16 layers that all share the paint of the next
+        // level, ending in a PaintSolid. Painting the root visits more than a million paints.
+        let mut paints = Vec::new();
+        let mut layers = Vec::new();
+        for level in 0..5u32 {
+            paints.extend_from_slice(&[1, 16]);
+            push_u32(&mut paints, &[level * 16]);
+            layers.extend((0..16).map(|_| (level + 1) * 6));
+        }
+        paints.extend_from_slice(&[2, 0, 0, 0x40, 0x00]);
+        // Glyph 6 starts from the third level, so visits 4369 paints
+        let data = colr_v1(12, &layers, &paints);
+        let colr = ReadScope::new(&data).read::<ColrTable<'_>>().unwrap();
+        let cpal = ReadScope::new(&CPAL_DATA).read::<CpalTable<'_>>().unwrap();
+
+        let mut ops = Vec::new();
+        assert_eq!(
+            paint_glyph(&colr, &cpal, 0, 5, &mut ops),
+            Err(ParseError::LimitExceeded)
+        );
+
+        let mut ops = Vec::new();
+        assert_eq!(paint_glyph(&colr, &cpal, 0, 6, &mut ops), Ok(true));
+        assert_eq!(ops.len(), 16 * 16 * 16);
+
+        // Shared paints are only visited once when collecting glyphs
+        assert_eq!(referenced_glyphs(&colr, 5), Ok(BTreeSet::new()));
+    }
+}
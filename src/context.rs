@@ -4,7 +4,7 @@ use std::marker::PhantomData;
 use std::rc::Rc;
 
 use crate::gdef;
-use crate::layout::{ClassDef, Coverage, GDEFTable};
+use crate::layout::{ClassDef, Coverage, GDEFTable, LookupCacheItem};
 
 #[derive(Copy, Clone)]
 pub struct LookupFlag(pub u16);
@@ -14,6 +14,7 @@ pub enum IgnoreMarks {
     NoIgnoreMarks,
     IgnoreAllMarks,
     IgnoreMarksExcept(u8),
+    IgnoreMarksExceptSet(u16),
 }
 
 #[derive(Copy, Clone)]
@@ -87,6 +88,10 @@ impl LookupFlag {
         (self.0 & 0x0004) != 0
     }
 
+    pub fn get_use_mark_filtering_set(self) -> bool {
+        (self.0 & 0x0010) != 0
+    }
+
     pub fn get_ignore_marks(self) -> IgnoreMarks {
         if (self.0 & 0x8) != 0 {
             IgnoreMarks::IgnoreAllMarks
@@ -123,6 +128,26 @@ impl MatchType {
         }
     }
 
+    /// Create a `MatchType` from the flag and mark filtering set of a lookup.
+    ///
+    /// The mark filtering set takes precedence over the mark attachment type, but marks are
+    /// ignored altogether when the lookup flag says so.
+    pub fn from_lookup<T>(lookup: &LookupCacheItem<T>) -> MatchType {
+        let lookup_flag = lookup.lookup_flag;
+        let ignore_marks = match (lookup_flag.get_ignore_marks(), lookup.mark_filtering_set) {
+            (IgnoreMarks::IgnoreAllMarks, _) => IgnoreMarks::IgnoreAllMarks,
+            (_, Some(mark_filtering_set)) if lookup_flag.get_use_mark_filtering_set() => {
+                IgnoreMarks::IgnoreMarksExceptSet(mark_filtering_set)
+            }
+            (ignore_marks, _) => ignore_marks,
+        };
+        MatchType {
+            ignore_bases: lookup_flag.get_ignore_bases(),
+            ignore_ligatures: lookup_flag.get_ignore_ligatures(),
+            ignore_marks,
+        }
+    }
+
     pub fn match_glyph<G: Glyph>(&self, opt_gdef_table: Option<&GDEFTable>, glyph: &G) -> bool {
         if !self.ignore_bases
             && !self.ignore_ligatures
@@ -146,6 +171,14 @@ impl MatchType {
                     gdef::mark_attach_class(opt_gdef_table, glyph.get_glyph_index());
                 (glyph_class != 3) || (mark_attach_class == u16::from(keep_class))
             }
+            IgnoreMarks::IgnoreMarksExceptSet(mark_filtering_set) => {
+                (glyph_class != 3)
+                    || gdef::mark_glyph_set_contains(
+                        opt_gdef_table,
+                        mark_filtering_set,
+                        glyph.get_glyph_index(),
+                    )
+            }
         }
     }
 
@@ -3,18 +3,39 @@ use std::convert::{self, TryFrom};
 use std::rc::Rc;
 
 use rustc_hash::FxHashMap;
+use tinyvec::tiny_vec;
 
 use crate::binary::read::ReadScope;
 use crate::bitmap::cbdt::{self, CBDTTable, CBLCTable};
 use crate::bitmap::sbix::Sbix as SbixTable;
 use crate::bitmap::{BitDepth, BitmapGlyph};
+use crate::cff::cff2::CFF2;
+use crate::cff::CFF;
+use crate::colr::{self, ColorPainter};
 use crate::error::ParseError;
+use crate::gdef;
 use crate::glyph_info::GlyphNames;
-use crate::layout::{new_layout_cache, GDEFTable, LayoutCache, LayoutTable, GPOS, GSUB};
-use crate::tables::cmap::{Cmap, CmapSubtable, EncodingId, EncodingRecord, PlatformId};
+use crate::gsub::{GlyphOrigin, RawGlyph};
+use crate::layout::{
+    new_layout_cache, CaretValue, GDEFTable, LayoutCache, LayoutTable, GPOS, GSUB,
+};
+use crate::outline::{ControlBox, OutlineSink};
+use crate::tables::base::{self, BaseTable};
+use crate::tables::cmap::{
+    Cmap, CmapSubtable, EncodingId, EncodingRecord, PlatformId, VariationMapping,
+};
+use crate::tables::colr::{ColorLayer, ColrTable};
+use crate::tables::cpal::CpalTable;
+use crate::tables::glyf::GlyfTable;
+use crate::tables::loca::LocaTable;
+use crate::tables::math::MathTable;
 use crate::tables::os2::Os2;
 use crate::tables::svg::SvgTable;
-use crate::tables::{FontTableProvider, HeadTable, HheaTable, MaxpTable};
+use crate::tables::variable_fonts::hvar::HvarTable;
+use crate::tables::variable_fonts::mvar::MvarTable;
+use crate::tables::vorg::VorgTable;
+use crate::tables::{F2Dot14, FontTableProvider, HeadTable, HheaTable, MaxpTable, VmtxTable};
+use crate::unicode::VariationSelector;
 use crate::{glyph_info, tag};
 
 #[derive(Copy, Clone)]
@@ -29,6 +50,7 @@ pub enum Encoding {
 pub enum OutlineFormat {
     Glyf,
     Cff,
+    Cff2,
     None,
 }
 
@@ -45,8 +67,20 @@ pub struct FontDataImpl<T: FontTableProvider> {
     pub hhea_table: HheaTable,
     vmtx_table: LazyLoad<Box<[u8]>>,
     vhea_table: LazyLoad<Rc<HheaTable>>,
+    vorg_table: LazyLoad<Rc<[u8]>>,
+    hvar_table: LazyLoad<Rc<[u8]>>,
+    vvar_table: LazyLoad<Rc<[u8]>>,
+    mvar_table: LazyLoad<Rc<[u8]>>,
+    kern_table: LazyLoad<Rc<[u8]>>,
+    morx_table: LazyLoad<Rc<[u8]>>,
+    kerx_table: LazyLoad<Rc<[u8]>>,
+    ankr_table: LazyLoad<Rc<[u8]>>,
+    colr_table: LazyLoad<Rc<[u8]>>,
+    cpal_table: LazyLoad<Rc<[u8]>>,
+    outlines: LazyLoad<Rc<Outlines>>,
     cmap_subtable_offset: usize,
     pub cmap_subtable_encoding: Encoding,
+    cmap_uvs_subtable_offset: Option<usize>,
     gdef_cache: LazyLoad<Rc<GDEFTable>>,
     gsub_cache: LazyLoad<LayoutCache<GSUB>>,
     gpos_cache: LazyLoad<LayoutCache<GPOS>>,
@@ -63,6 +97,13 @@ pub enum Images {
     Svg(tables::Svg),
 }
 
+/// The parsed outline table, according to `outline_format`.
+enum Outlines {
+    Glyf(tables::Glyf),
+    Cff(tables::Cff),
+    Cff2(tables::Cff2),
+}
+
 rental! {
     mod tables {
         use super::*;
@@ -90,6 +131,24 @@ rental! {
             data: Box<[u8]>,
             table: SvgTable<'data>
         }
+
+        #[rental]
+        pub struct Glyf {
+            data: Box<[u8]>,
+            table: GlyfTable<'data>
+        }
+
+        #[rental]
+        pub struct Cff {
+            data: Box<[u8]>,
+            table: CFF<'data>
+        }
+
+        #[rental]
+        pub struct Cff2 {
+            data: Box<[u8]>,
+            table: CFF2<'data>
+        }
     }
 }
 
@@ -99,6 +158,11 @@ impl<T: FontTableProvider> FontDataImpl<T> {
 
         match charmap_info(&cmap_table)? {
             Some((cmap_subtable_encoding, cmap_subtable_offset)) => {
+                let cmap_uvs_subtable_offset = ReadScope::new(&cmap_table)
+                    .read::<Cmap<'_>>()?
+                    .find_subtable(PlatformId::UNICODE, EncodingId::UNICODE_VARIATION_SEQUENCES)
+                    .map(|encoding_record| usize::try_from(encoding_record.offset))
+                    .transpose()?;
                 let maxp_table =
                     ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
                 let hmtx_table = read_and_box_table(provider.as_ref(), tag::HMTX)?;
@@ -115,6 +179,8 @@ impl<T: FontTableProvider> FontDataImpl<T> {
                         OutlineFormat::Glyf
                     } else if provider.has_table(tag::CFF) {
                         OutlineFormat::Cff
+                    } else if provider.has_table(tag::CFF2) {
+                        OutlineFormat::Cff2
                     } else {
                         OutlineFormat::None
                     };
@@ -127,8 +193,20 @@ impl<T: FontTableProvider> FontDataImpl<T> {
                     hhea_table,
                     vmtx_table: LazyLoad::NotLoaded,
                     vhea_table: LazyLoad::NotLoaded,
+                    vorg_table: LazyLoad::NotLoaded,
+                    hvar_table: LazyLoad::NotLoaded,
+                    vvar_table: LazyLoad::NotLoaded,
+                    mvar_table: LazyLoad::NotLoaded,
+                    kern_table: LazyLoad::NotLoaded,
+                    morx_table: LazyLoad::NotLoaded,
+                    kerx_table: LazyLoad::NotLoaded,
+                    ankr_table: LazyLoad::NotLoaded,
+                    colr_table: LazyLoad::NotLoaded,
+                    cpal_table: LazyLoad::NotLoaded,
+                    outlines: LazyLoad::NotLoaded,
                     cmap_subtable_offset: usize::try_from(cmap_subtable_offset)?,
                     cmap_subtable_encoding,
+                    cmap_uvs_subtable_offset,
                     gdef_cache: LazyLoad::NotLoaded,
                     gsub_cache: LazyLoad::NotLoaded,
                     gpos_cache: LazyLoad::NotLoaded,
@@ -155,6 +233,95 @@ impl<T: FontTableProvider> FontDataImpl<T> {
         }
     }
 
+    /// Look up the glyph index of `char_code` followed by the variation selector `selector`.
+    ///
+    /// Falls back to the glyph for `char_code` alone if the font does not support the variation
+    /// sequence.
+    pub fn lookup_variant_glyph_index(&self, char_code: u32, selector: u32) -> u32 {
+        match self.map_variant(char_code, selector) {
+            Ok(Some(VariationMapping::Glyph(glyph_index))) => u32::from(glyph_index),
+            _ => self.lookup_glyph_index(char_code),
+        }
+    }
+
+    /// Map the characters of `text` to glyphs, ready for applying `gsub`.
+    ///
+    /// A variation selector that follows a character is recorded in the `variation` field of
+    /// that character's glyph instead of being mapped to a glyph of its own, and the glyph is
+    /// then chosen with [apply_variation_selectors](FontDataImpl::apply_variation_selectors).
+    /// Characters that are not in the font are mapped to glyph 0.
+    pub fn map_glyphs(&self, text: &str) -> Vec<RawGlyph<()>> {
+        let cmap_subtable = ReadScope::new(self.cmap_subtable_data())
+            .read::<CmapSubtable<'_>>()
+            .ok();
+        let mut glyphs: Vec<RawGlyph<()>> = Vec::with_capacity(text.len());
+        for ch in text.chars() {
+            if let Ok(selector) = VariationSelector::try_from(ch) {
+                match glyphs.last_mut() {
+                    Some(glyph) if glyph.variation.is_none() => {
+                        glyph.variation = Some(selector);
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            let glyph_index = cmap_subtable
+                .as_ref()
+                .and_then(|subtable| subtable.map_glyph(ch as u32).ok())
+                .and_then(convert::identity)
+                .unwrap_or(0);
+            glyphs.push(RawGlyph {
+                unicodes: tiny_vec![[char; 1] => ch],
+                glyph_index,
+                liga_component_pos: 0,
+                glyph_origin: GlyphOrigin::Char(ch),
+                small_caps: false,
+                multi_subst_dup: false,
+                is_vert_alt: false,
+                fake_bold: false,
+                fake_italic: false,
+                variation: None,
+                extra_data: (),
+            });
+        }
+        self.apply_variation_selectors(&mut glyphs);
+        glyphs
+    }
+
+    /// Update the glyph index of glyphs that carry a variation selector.
+    ///
+    /// This uses the format 14 `cmap` sub-table, if present, so that sequences such as CJK
+    /// ideographic variants and emoji/text presentation selectors pick the glyph the font
+    /// designates for them. It is called by [map_glyphs](FontDataImpl::map_glyphs) and should be
+    /// called before applying `gsub` to glyphs that were mapped by other means.
+    pub fn apply_variation_selectors<D>(&self, glyphs: &mut [RawGlyph<D>]) {
+        for glyph in glyphs.iter_mut() {
+            if let (GlyphOrigin::Char(ch), Some(variation)) = (glyph.glyph_origin, glyph.variation)
+            {
+                let selector = u32::from(char::from(variation));
+                if let Ok(Some(VariationMapping::Glyph(glyph_index))) =
+                    self.map_variant(ch as u32, selector)
+                {
+                    glyph.glyph_index = glyph_index;
+                }
+            }
+        }
+    }
+
+    fn map_variant(
+        &self,
+        char_code: u32,
+        selector: u32,
+    ) -> Result<Option<VariationMapping>, ParseError> {
+        match self.cmap_uvs_subtable_offset {
+            Some(offset) => ReadScope::new(&self.cmap_table)
+                .offset(offset)
+                .read::<CmapSubtable<'_>>()?
+                .map_variant(char_code, selector),
+            None => Ok(None),
+        }
+    }
+
     pub fn glyph_names<'a>(&self, ids: &[u16]) -> Vec<Cow<'a, str>> {
         let post = read_and_box_optional_table(self.font_table_provider.as_ref(), tag::POST)
             .ok()
@@ -293,29 +460,196 @@ impl<T: FontTableProvider> FontDataImpl<T> {
     pub fn supports_emoji(&mut self) -> bool {
         match self.embedded_images() {
             Ok(Some(_)) => true,
-            _ => false,
+            _ => {
+                self.font_table_provider.has_table(tag::COLR)
+                    && self.font_table_provider.has_table(tag::CPAL)
+            }
         }
     }
 
-    pub fn horizontal_advance(&mut self, glyph: u16) -> Option<u16> {
-        glyph_info::advance(&self.maxp_table, &self.hhea_table, &self.hmtx_table, glyph).ok()
+    /// Returns the layers of the `COLR` version 0 color glyph of `glyph_id`, bottom-most first,
+    /// with colors from the `CPAL` palette at `palette_index`.
+    ///
+    /// Returns `None` if the font lacks `COLR` and `CPAL` tables or `glyph_id` doesn't have a
+    /// color glyph. Palette 0 is the default palette.
+    pub fn color_layers(
+        &mut self,
+        glyph_id: u16,
+        palette_index: u16,
+    ) -> Result<Option<Vec<ColorLayer>>, ParseError> {
+        let colr_data = match self.colr_table_data()? {
+            Some(colr_data) => colr_data,
+            None => return Ok(None),
+        };
+        let cpal_data = match self.cpal_table_data()? {
+            Some(cpal_data) => cpal_data,
+            None => return Ok(None),
+        };
+        let colr = ReadScope::new(&colr_data).read::<ColrTable<'_>>()?;
+        let cpal = ReadScope::new(&cpal_data).read::<CpalTable<'_>>()?;
+        colr.color_layers(glyph_id, &cpal, palette_index)
+    }
+
+    /// Paint the `COLR` version 1 color glyph of `glyph_id` to `painter`, with colors from the
+    /// palette at `palette_index`.
+    ///
+    /// Returns `false` if the font lacks `COLR` and `CPAL` tables or `glyph_id` doesn't have a
+    /// version 1 color glyph, in which case `color_layers` may still return version 0 layers.
+    pub fn paint_color_glyph<P: ColorPainter>(
+        &mut self,
+        glyph_id: u16,
+        palette_index: u16,
+        painter: &mut P,
+    ) -> Result<bool, ParseError> {
+        let colr_data = match self.colr_table_data()? {
+            Some(colr_data) => colr_data,
+            None => return Ok(false),
+        };
+        let cpal_data = match self.cpal_table_data()? {
+            Some(cpal_data) => cpal_data,
+            None => return Ok(false),
+        };
+        let colr = ReadScope::new(&colr_data).read::<ColrTable<'_>>()?;
+        let cpal = ReadScope::new(&cpal_data).read::<CpalTable<'_>>()?;
+        colr::paint_glyph(&colr, &cpal, palette_index, glyph_id, painter)
+    }
+
+    /// Retrieve the horizontal advance of `glyph` at the normalized variation coordinates
+    /// `coords`.
+    ///
+    /// `HVAR` deltas are applied when `coords` is non-empty. Pass an empty slice for the
+    /// default instance.
+    pub fn horizontal_advance(&mut self, glyph: u16, coords: &[F2Dot14]) -> Option<u16> {
+        let hvar_data = if coords.is_empty() {
+            None
+        } else {
+            let provider = self.font_table_provider.as_ref();
+            self.hvar_table
+                .get_or_load(|| load_optional_shared_table(provider, tag::HVAR))
+                .ok()?
+        };
+        let hvar = hvar_data
+            .as_ref()
+            .map(|data| ReadScope::new(data).read_dep::<HvarTable<'_>>(tag::HVAR))
+            .transpose()
+            .ok()?;
+
+        glyph_info::variable_advance(
+            &self.maxp_table,
+            &self.hhea_table,
+            &self.hmtx_table,
+            hvar.as_ref(),
+            glyph,
+            coords,
+        )
+        .ok()
     }
 
-    pub fn vertical_advance(&mut self, glyph: u16) -> Option<u16> {
+    /// Retrieve the vertical advance of `glyph` at the normalized variation coordinates
+    /// `coords`.
+    ///
+    /// `VVAR` deltas are applied when `coords` is non-empty. Pass an empty slice for the
+    /// default instance.
+    pub fn vertical_advance(&mut self, glyph: u16, coords: &[F2Dot14]) -> Option<u16> {
         let provider = self.font_table_provider.as_ref();
         let vmtx = self
             .vmtx_table
             .get_or_load(|| read_and_box_optional_table(provider, tag::VMTX))
             .ok()?;
+        let vvar_data = if coords.is_empty() {
+            None
+        } else {
+            self.vvar_table
+                .get_or_load(|| load_optional_shared_table(provider, tag::VVAR))
+                .ok()?
+        };
         let vhea = self.vhea_table().ok()?;
 
         if let (Some(vhea), Some(vmtx_table)) = (vhea, vmtx) {
-            Some(glyph_info::advance(&self.maxp_table, &vhea, &vmtx_table, glyph).unwrap())
+            let vvar = vvar_data
+                .as_ref()
+                .map(|data| ReadScope::new(data).read_dep::<HvarTable<'_>>(tag::VVAR))
+                .transpose()
+                .ok()?;
+            glyph_info::variable_advance(
+                &self.maxp_table,
+                &vhea,
+                &vmtx_table,
+                vvar.as_ref(),
+                glyph,
+                coords,
+            )
+            .ok()
         } else {
             None
         }
     }
 
+    /// Retrieve the top side bearing of `glyph` from the `vmtx` table.
+    ///
+    /// Returns `Ok(None)` if the font does not have `vhea` and `vmtx` tables.
+    pub fn top_side_bearing(&mut self, glyph: u16) -> Result<Option<i16>, ParseError> {
+        let vhea = match self.vhea_table()? {
+            Some(vhea) => vhea,
+            None => return Ok(None),
+        };
+        let provider = self.font_table_provider.as_ref();
+        let vmtx_data = match self
+            .vmtx_table
+            .get_or_load(|| read_and_box_optional_table(provider, tag::VMTX))?
+        {
+            Some(vmtx_data) => vmtx_data,
+            None => return Ok(None),
+        };
+        let vmtx = ReadScope::new(&vmtx_data).read_dep::<VmtxTable<'_>>((
+            usize::from(self.maxp_table.num_glyphs),
+            usize::from(vhea.num_h_metrics),
+        ))?;
+        vmtx.side_bearing(glyph, vhea.num_h_metrics).map(Some)
+    }
+
+    /// Retrieve the y coordinate of the vertical origin of `glyph`, in font units.
+    ///
+    /// The origin is read from the `VORG` table when present, which is only expected in CFF
+    /// fonts. Otherwise it is the top of the glyph's bounding box plus its top side bearing from
+    /// `vmtx`. Fonts without `vmtx` use the ascender: `sTypoAscender` from `OS/2` if available,
+    /// otherwise the `hhea` ascender.
+    pub fn vertical_origin(&mut self, glyph: u16) -> Result<i16, ParseError> {
+        let provider = self.font_table_provider.as_ref();
+        let vorg_data = self
+            .vorg_table
+            .get_or_load(|| load_optional_shared_table(provider, tag::VORG))?;
+        if let Some(vorg_data) = vorg_data {
+            let vorg = ReadScope::new(&vorg_data).read::<VorgTable<'_>>()?;
+            return Ok(vorg.vert_origin_y(glyph));
+        }
+
+        if let Some(top_side_bearing) = self.top_side_bearing(glyph)? {
+            let mut control_box = ControlBox::default();
+            match self.visit_outline(glyph, &mut control_box) {
+                Ok(()) => {
+                    // Empty glyphs have a bounding box of all zeros
+                    let y_max = control_box
+                        .bounds
+                        .map_or(0, |(_, _, _, y_max)| y_max.ceil() as i32);
+                    let origin = y_max + i32::from(top_side_bearing);
+                    return Ok(i16::try_from(origin)?);
+                }
+                // The font has no outlines
+                Err(ParseError::MissingValue) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        match self.os2_table()? {
+            Some(Os2 {
+                version0: Some(version0),
+                ..
+            }) => Ok(version0.s_typo_ascender),
+            _ => Ok(self.hhea_table.ascender),
+        }
+    }
+
     pub fn head_table(&self) -> Result<Option<HeadTable>, ParseError> {
         self.font_table_provider
             .table_data(tag::HEAD)?
@@ -330,6 +664,167 @@ impl<T: FontTableProvider> FontDataImpl<T> {
             .transpose()
     }
 
+    pub fn base_table(&self) -> Result<Option<BaseTable>, ParseError> {
+        self.font_table_provider
+            .table_data(tag::BASE)?
+            .map(|data| ReadScope::new(&data).read::<BaseTable>())
+            .transpose()
+    }
+
+    pub fn math_table(&self) -> Result<Option<MathTable>, ParseError> {
+        self.font_table_provider
+            .table_data(tag::MATH)?
+            .map(|data| ReadScope::new(&data).read::<MathTable>())
+            .transpose()
+    }
+
+    /// Returns the coordinate of the horizontal baseline `baseline_tag` of `script_tag`, in font
+    /// units.
+    ///
+    /// The `BASE` table is used when it has a coordinate for the baseline, falling back to the
+    /// `DFLT` script if it has no data for `script_tag`. Otherwise a coordinate is synthesized from
+    /// the `OS/2` table, see `base::synthesize_baseline`.
+    pub fn baseline(&self, script_tag: u32, baseline_tag: u32) -> Result<Option<i16>, ParseError> {
+        if let Some(base) = self.base_table()? {
+            if let Some(coordinate) = base.baseline(script_tag, baseline_tag) {
+                return Ok(Some(coordinate));
+            }
+        }
+        let os2 = match self.os2_table()? {
+            Some(os2) => os2,
+            None => return Ok(None),
+        };
+        let head = self.head_table()?.ok_or(ParseError::MissingValue)?;
+        Ok(base::synthesize_baseline(
+            &os2,
+            head.units_per_em,
+            baseline_tag,
+        ))
+    }
+
+    /// Read the `hhea` table with `MVAR` deltas at the normalized variation coordinates `coords`
+    /// applied.
+    pub fn varied_hhea_table(&mut self, coords: &[F2Dot14]) -> Result<HheaTable, ParseError> {
+        let mut hhea = ReadScope::new(&self.font_table_provider.read_table_data(tag::HHEA)?)
+            .read::<HheaTable>()?;
+        if let Some(mvar_data) = self.mvar_table_data()? {
+            let mvar = ReadScope::new(&mvar_data).read::<MvarTable<'_>>()?;
+            mvar.apply_to_hhea(&mut hhea, coords)?;
+        }
+        Ok(hhea)
+    }
+
+    /// Read the `OS/2` table with `MVAR` deltas at the normalized variation coordinates `coords`
+    /// applied.
+    pub fn varied_os2_table(&mut self, coords: &[F2Dot14]) -> Result<Option<Os2>, ParseError> {
+        let mut os2 = match self.os2_table()? {
+            Some(os2) => os2,
+            None => return Ok(None),
+        };
+        if let Some(mvar_data) = self.mvar_table_data()? {
+            let mvar = ReadScope::new(&mvar_data).read::<MvarTable<'_>>()?;
+            mvar.apply_to_os2(&mut os2, coords)?;
+        }
+        Ok(Some(os2))
+    }
+
+    fn mvar_table_data(&mut self) -> Result<Option<Rc<[u8]>>, ParseError> {
+        let provider = self.font_table_provider.as_ref();
+        self.mvar_table
+            .get_or_load(|| load_optional_shared_table(provider, tag::MVAR))
+    }
+
+    /// Returns the data of the legacy `kern` table, if present.
+    ///
+    /// The data can be read as a `tables::kern::KernTable` and applied with
+    /// `gpos::kern_table_apply` for fonts that lack `GPOS` kerning.
+    pub fn kern_table_data(&mut self) -> Result<Option<Rc<[u8]>>, ParseError> {
+        let provider = self.font_table_provider.as_ref();
+        self.kern_table
+            .get_or_load(|| load_optional_shared_table(provider, tag::KERN))
+    }
+
+    /// Returns the data of the `morx` table, if present.
+    ///
+    /// The data can be read as a `tables::morx::MorxTable` and applied with `morx::morx_apply`
+    /// for fonts that lack a `GSUB` table.
+    pub fn morx_table_data(&mut self) -> Result<Option<Rc<[u8]>>, ParseError> {
+        let provider = self.font_table_provider.as_ref();
+        self.morx_table
+            .get_or_load(|| load_optional_shared_table(provider, tag::MORX))
+    }
+
+    /// Returns the data of the `kerx` table, if present.
+    ///
+    /// The data can be read as a `tables::kerx::KerxTable` and applied with `kerx::kerx_apply`
+    /// for fonts that lack a `GPOS` table.
+    pub fn kerx_table_data(&mut self) -> Result<Option<Rc<[u8]>>, ParseError> {
+        let provider = self.font_table_provider.as_ref();
+        self.kerx_table
+            .get_or_load(|| load_optional_shared_table(provider, tag::KERX))
+    }
+
+    /// Returns the data of the `ankr` table, if present.
+    ///
+    /// The data can be read as a `tables::ankr::AnkrTable`, which holds the anchor points used
+    /// by `kerx` attachment subtables.
+    pub fn ankr_table_data(&mut self) -> Result<Option<Rc<[u8]>>, ParseError> {
+        let provider = self.font_table_provider.as_ref();
+        self.ankr_table
+            .get_or_load(|| load_optional_shared_table(provider, tag::ANKR))
+    }
+
+    /// Returns the data of the `COLR` table, if present.
+    ///
+    /// The data can be read as a `tables::colr::ColrTable`.
+    pub fn colr_table_data(&mut self) -> Result<Option<Rc<[u8]>>, ParseError> {
+        let provider = self.font_table_provider.as_ref();
+        self.colr_table
+            .get_or_load(|| load_optional_shared_table(provider, tag::COLR))
+    }
+
+    /// Returns the data of the `CPAL` table, if present.
+    ///
+    /// The data can be read as a `tables::cpal::CpalTable`.
+    pub fn cpal_table_data(&mut self) -> Result<Option<Rc<[u8]>>, ParseError> {
+        let provider = self.font_table_provider.as_ref();
+        self.cpal_table
+            .get_or_load(|| load_optional_shared_table(provider, tag::CPAL))
+    }
+
+    /// Visit the outline of `glyph_id`, passing its contours to `sink`.
+    ///
+    /// `glyf` outlines have their composite glyphs resolved and are emitted as quadratic curves.
+    /// `CFF ` and `CFF2` outlines are emitted as cubic curves, `CFF2` outlines are those of the
+    /// default instance.
+    pub fn visit_outline<S: OutlineSink>(
+        &mut self,
+        glyph_id: u16,
+        sink: &mut S,
+    ) -> Result<(), ParseError> {
+        match *self.outlines()?.ok_or(ParseError::MissingValue)? {
+            Outlines::Glyf(ref glyf) => {
+                glyf.rent(|glyf: &GlyfTable<'_>| glyf.visit_outline(glyph_id, sink))
+            }
+            Outlines::Cff(ref cff) => {
+                cff.rent(|cff: &CFF<'_>| cff.visit_char_string(0, glyph_id, sink).map(|_info| ()))
+            }
+            Outlines::Cff2(ref cff2) => cff2.rent(|cff2: &CFF2<'_>| {
+                cff2.visit_char_string(glyph_id, &[], sink).map(|_info| ())
+            }),
+        }
+    }
+
+    fn outlines(&mut self) -> Result<Option<Rc<Outlines>>, ParseError> {
+        let provider = self.font_table_provider.as_ref();
+        let outline_format = self.outline_format;
+        let num_glyphs = usize::from(self.maxp_table.num_glyphs);
+        self.outlines.get_or_load(|| {
+            let outlines = load_outlines(provider, outline_format, num_glyphs)?;
+            Ok(outlines.map(Rc::new))
+        })
+    }
+
     pub fn gdef_table(&mut self) -> Result<Option<Rc<GDEFTable>>, ParseError> {
         let provider = &self.font_table_provider;
         self.gdef_cache.get_or_load(|| {
@@ -342,6 +837,15 @@ impl<T: FontTableProvider> FontDataImpl<T> {
         })
     }
 
+    /// Returns the carets of the ligature `glyph` from the `GDEF` ligature caret list.
+    ///
+    /// The result is empty if the font does not define carets for `glyph`. See
+    /// `gdef::ligature_carets` for how the carets relate to the ligature components.
+    pub fn ligature_carets(&mut self, glyph: u16) -> Result<Vec<CaretValue>, ParseError> {
+        let gdef_table = self.gdef_table()?;
+        Ok(gdef::ligature_carets(gdef_table.as_ref().map(Rc::as_ref), glyph).to_vec())
+    }
+
     pub fn gsub_cache(&mut self) -> Result<Option<LayoutCache<GSUB>>, ParseError> {
         let provider = &self.font_table_provider;
         self.gsub_cache.get_or_load(|| {
@@ -427,6 +931,15 @@ fn read_and_box_optional_table(
         .map(|table| Box::from(table.into_owned())))
 }
 
+fn load_optional_shared_table(
+    provider: &impl FontTableProvider,
+    tag: u32,
+) -> Result<Option<Rc<[u8]>>, ParseError> {
+    Ok(provider
+        .table_data(tag)?
+        .map(|table| Rc::from(table.into_owned())))
+}
+
 fn load_cblc_cbdt(
     provider: &impl FontTableProvider,
 ) -> Result<(tables::CBLC, tables::CBDT), ParseError> {
@@ -458,6 +971,44 @@ fn load_svg(provider: &impl FontTableProvider) -> Result<tables::Svg, ParseError
     tables::Svg::try_new_or_drop(svg_data, |data| ReadScope::new(data).read::<SvgTable<'_>>())
 }
 
+fn load_outlines(
+    provider: &impl FontTableProvider,
+    outline_format: OutlineFormat,
+    num_glyphs: usize,
+) -> Result<Option<Outlines>, ParseError> {
+    let outlines = match outline_format {
+        OutlineFormat::Glyf => {
+            let head_data = provider.read_table_data(tag::HEAD)?;
+            let head = ReadScope::new(&head_data).read::<HeadTable>()?;
+            let loca_data = provider.read_table_data(tag::LOCA)?;
+            let glyf_data = read_and_box_table(provider, tag::GLYF)?;
+            let loca = ReadScope::new(&loca_data)
+                .read_dep::<LocaTable<'_>>((num_glyphs, head.index_to_loc_format))?;
+            let glyf = tables::Glyf::try_new_or_drop(glyf_data, |data| {
+                GlyfTable::read_records(ReadScope::new(data), &loca)
+            })?;
+            Outlines::Glyf(glyf)
+        }
+        OutlineFormat::Cff => {
+            let cff_data = read_and_box_table(provider, tag::CFF)?;
+            let cff = tables::Cff::try_new_or_drop(cff_data, |data| {
+                ReadScope::new(data).read::<CFF<'_>>()
+            })?;
+            Outlines::Cff(cff)
+        }
+        OutlineFormat::Cff2 => {
+            let cff2_data = read_and_box_table(provider, tag::CFF2)?;
+            let cff2 = tables::Cff2::try_new_or_drop(cff2_data, |data| {
+                ReadScope::new(data).read::<CFF2<'_>>()
+            })?;
+            Outlines::Cff2(cff2)
+        }
+        OutlineFormat::None => return Ok(None),
+    };
+
+    Ok(Some(outlines))
+}
+
 fn charmap_info(cmap_buf: &[u8]) -> Result<Option<(Encoding, u32)>, ParseError> {
     let cmap = ReadScope::new(cmap_buf).read::<Cmap<'_>>()?;
     Ok(find_good_cmap_subtable(&cmap)
@@ -500,8 +1051,11 @@ pub fn find_good_cmap_subtable(cmap: &Cmap<'_>) -> Option<(Encoding, EncodingRec
         return Some((Encoding::Unicode, encoding_record));
     }
 
-    // Any UNICODE table
-    if let Some(encoding_record) = cmap.find_subtable_for_platform(PlatformId::UNICODE) {
+    // Any UNICODE table, other than Unicode Variation Sequences (format 14)
+    if let Some(encoding_record) = cmap.encoding_records().find(|record| {
+        record.platform_id == PlatformId::UNICODE.0
+            && record.encoding_id != EncodingId::UNICODE_VARIATION_SEQUENCES.0
+    }) {
         return Some((Encoding::Unicode, encoding_record));
     }
 
@@ -566,6 +1120,78 @@ mod tests {
     use crate::tables::OpenTypeFile;
     use crate::tests::read_fixture;
 
+    #[test]
+    fn test_map_glyphs_variation_selectors() {
+        let font_buffer = read_fixture("tests/aots/cmap14_font1.otf");
+        let opentype_file = ReadScope::new(&font_buffer)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let font_table_provider = opentype_file
+            .font_provider(0)
+            .expect("error reading font file");
+        let font_data_impl = FontDataImpl::new(Box::new(font_table_provider))
+            .expect("error reading font data")
+            .expect("missing required font tables");
+
+        // U+4E10 U+E0100 maps to a glyph of its own, U+4E03 U+E0100 uses the default glyph
+        let glyphs = font_data_impl.map_glyphs("\u{4E10}\u{E0100}\u{4E10}\u{4E03}\u{E0100}");
+        let glyph_indices = glyphs
+            .iter()
+            .map(|glyph| glyph.glyph_index)
+            .collect::<Vec<_>>();
+        assert_eq!(glyph_indices, vec![25, 0, 13]);
+        assert_eq!(glyphs[0].variation.map(char::from), Some('\u{E0100}'));
+        assert!(glyphs[1].variation.is_none());
+    }
+
+    #[test]
+    fn test_vertical_metrics() {
+        let font_buffer = read_fixture("tests/fonts/noto/NotoSansJP-Regular.otf");
+        let opentype_file = ReadScope::new(&font_buffer)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let font_table_provider = opentype_file
+            .font_provider(0)
+            .expect("error reading font file");
+        let mut font_data_impl = FontDataImpl::new(Box::new(font_table_provider))
+            .expect("error reading font data")
+            .expect("missing required font tables");
+
+        assert_eq!(font_data_impl.vertical_advance(100, &[]), Some(1000));
+        assert_eq!(font_data_impl.top_side_bearing(100), Ok(Some(167)));
+        assert_eq!(font_data_impl.top_side_bearing(8323), Ok(Some(0)));
+        // From VORG
+        assert_eq!(font_data_impl.vertical_origin(100).unwrap(), 880);
+        assert_eq!(font_data_impl.vertical_origin(6896).unwrap(), 860);
+
+        // The default vertical origin is consistent with the bounding box and top side bearing
+        let mut control_box = ControlBox::default();
+        font_data_impl.visit_outline(100, &mut control_box).unwrap();
+        let (_, _, _, y_max) = control_box.bounds.unwrap();
+        assert_eq!(y_max + 167., 880.);
+    }
+
+    #[test]
+    fn test_vertical_origin_fallback() {
+        let font_buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let opentype_file = ReadScope::new(&font_buffer)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let font_table_provider = opentype_file
+            .font_provider(0)
+            .expect("error reading font file");
+        let mut font_data_impl = FontDataImpl::new(Box::new(font_table_provider))
+            .expect("error reading font data")
+            .expect("missing required font tables");
+
+        let os2 = font_data_impl.os2_table().unwrap().unwrap();
+        assert_eq!(font_data_impl.top_side_bearing(1), Ok(None));
+        assert_eq!(
+            font_data_impl.vertical_origin(1).unwrap(),
+            os2.version0.unwrap().s_typo_ascender
+        );
+    }
+
     #[test]
     fn test_glyph_names() {
         let font_buffer = read_fixture("tests/fonts/opentype/TwitterColorEmoji-SVGinOT.ttf");
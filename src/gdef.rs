@@ -2,7 +2,7 @@
 //!
 //! <https://docs.microsoft.com/en-us/typography/opentype/spec/gdef>
 
-use crate::layout::GDEFTable;
+use crate::layout::{CaretValue, GDEFTable};
 
 pub fn gdef_is_mark(opt_gdef_table: Option<&GDEFTable>, glyph_index: u16) -> bool {
     glyph_class(opt_gdef_table, glyph_index) == 3
@@ -27,3 +27,49 @@ pub fn mark_attach_class(opt_gdef_table: Option<&GDEFTable>, glyph: u16) -> u16
         None => 0,
     }
 }
+
+/// Returns `true` if `glyph` is in the GDEF mark glyph set at `mark_glyph_set_index`.
+pub fn mark_glyph_set_contains(
+    opt_gdef_table: Option<&GDEFTable>,
+    mark_glyph_set_index: u16,
+    glyph: u16,
+) -> bool {
+    opt_gdef_table
+        .and_then(|gdef_table| gdef_table.opt_mark_glyph_sets.as_ref())
+        .and_then(|mark_glyph_sets| mark_glyph_sets.get(usize::from(mark_glyph_set_index)))
+        .and_then(|coverage| coverage.glyph_coverage_value(glyph))
+        .is_some()
+}
+
+/// Returns the carets of the ligature `glyph` from the GDEF ligature caret list.
+///
+/// The slice is empty if the font does not define carets for `glyph`. A ligature of `n`
+/// components has `n - 1` carets, the caret at index `i` separates components `i` and `i + 1`.
+pub fn ligature_carets(opt_gdef_table: Option<&GDEFTable>, glyph: u16) -> &[CaretValue] {
+    opt_gdef_table
+        .and_then(|gdef_table| gdef_table.opt_lig_caret_list.as_ref())
+        .and_then(|lig_caret_list| lig_caret_list.ligature_carets(glyph))
+        .unwrap_or(&[])
+}
+
+/// Returns the caret at the end of the ligature component at `liga_component_pos`.
+///
+/// `liga_component_pos` is numbered as `RawGlyph::liga_component_pos`, so for a mark attached
+/// to a ligature this is the caret following the component the mark belongs to. Returns `None`
+/// for the last component, or if the font does not define carets for `glyph`.
+pub fn ligature_component_caret(
+    opt_gdef_table: Option<&GDEFTable>,
+    glyph: u16,
+    liga_component_pos: u16,
+) -> Option<&CaretValue> {
+    ligature_carets(opt_gdef_table, glyph).get(usize::from(liga_component_pos))
+}
+
+/// Returns the contour point indices of the attachment points of `glyph` from the GDEF
+/// attachment point list.
+pub fn attach_points(opt_gdef_table: Option<&GDEFTable>, glyph: u16) -> &[u16] {
+    opt_gdef_table
+        .and_then(|gdef_table| gdef_table.opt_attach_list.as_ref())
+        .and_then(|attach_list| attach_list.attach_points(glyph))
+        .unwrap_or(&[])
+}
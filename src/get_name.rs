@@ -1,11 +1,19 @@
 //! Utilities for obtaining a name from a fonts `name` table.
 
-use crate::binary::read::ReadScope;
+use crate::binary::read::{ReadArrayIter, ReadScope};
 use crate::error::ParseError;
-use crate::tables::NameTable;
-use encoding_rs::{DecoderResult, MACINTOSH, UTF_16BE};
+use crate::tables::variable_fonts::stat::StatTable;
+use crate::tables::{NameRecord, NameTable};
+use encoding_rs::{
+    DecoderResult, BIG5, EUC_KR, GBK, MACINTOSH, SHIFT_JIS, UTF_16BE, X_MAC_CYRILLIC,
+};
+use std::borrow::Cow;
 use std::ffi::CString;
 
+const PLATFORM_UNICODE: u16 = 0;
+const PLATFORM_MACINTOSH: u16 = 1;
+const PLATFORM_WINDOWS: u16 = 3;
+
 pub fn fontcode_get_name(
     name_table_data: &[u8],
     name_id: u16,
@@ -38,6 +46,313 @@ pub fn fontcode_get_name(
     Ok(result)
 }
 
+/// Build the subfamily name of the font or named instance at `location` from the `STAT` table.
+///
+/// `location` holds user coordinates by axis tag. The names chosen by
+/// `StatTable::subfamily_name_ids` are looked up with `fontcode_get_name` and joined with
+/// spaces, e.g. "Bold Italic". Returns `None` if any of the names are missing from the `name`
+/// table.
+pub fn fontcode_get_subfamily_name(
+    name_table_data: &[u8],
+    stat_table: &StatTable,
+    location: &[(u32, f32)],
+) -> Result<Option<CString>, ParseError> {
+    let mut subfamily_name = Vec::new();
+    for name_id in stat_table.subfamily_name_ids(location) {
+        match fontcode_get_name(name_table_data, name_id)? {
+            Some(name) => {
+                if !subfamily_name.is_empty() {
+                    subfamily_name.push(b' ');
+                }
+                subfamily_name.extend_from_slice(name.as_bytes());
+            }
+            None => return Ok(None),
+        }
+    }
+    Ok(CString::new(subfamily_name).ok())
+}
+
+/// A `name` table record decoded to a `String`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Name {
+    pub platform_id: u16,
+    pub encoding_id: u16,
+    pub language_id: u16,
+    pub name_id: u16,
+    /// The BCP 47 language tag of the record, if its language is known.
+    pub language_tag: Option<String>,
+    pub string: String,
+}
+
+/// Iterator over the decoded records of a `name` table.
+///
+/// Created by `names`.
+pub struct Names<'a, 'b> {
+    name_table: &'b NameTable<'a>,
+    records: ReadArrayIter<'a, NameRecord>,
+}
+
+/// Returns an iterator over all records of `name_table` decoded to `String`.
+///
+/// Strings with the Unicode and Windows Unicode encodings are decoded from UTF-16BE. Macintosh
+/// Roman, Japanese, Chinese, Korean and Cyrillic strings are decoded with `encoding_rs`. Records
+/// with other encodings, or with malformed strings or strings outside the string storage, are
+/// skipped.
+pub fn names<'a, 'b>(name_table: &'b NameTable<'a>) -> Names<'a, 'b> {
+    Names {
+        name_table,
+        records: name_table.name_records.iter(),
+    }
+}
+
+impl<'a, 'b> Iterator for Names<'a, 'b> {
+    type Item = Name;
+
+    fn next(&mut self) -> Option<Name> {
+        for record in &mut self.records {
+            let data = match self
+                .name_table
+                .string_storage
+                .offset_length(usize::from(record.offset), usize::from(record.length))
+            {
+                Ok(scope) => scope.data(),
+                Err(_) => continue,
+            };
+            if let Some(string) = decode_string(record.platform_id, record.encoding_id, data) {
+                return Some(Name {
+                    platform_id: record.platform_id,
+                    encoding_id: record.encoding_id,
+                    language_id: record.language_id,
+                    name_id: record.name_id,
+                    language_tag: language_tag(
+                        self.name_table,
+                        record.platform_id,
+                        record.language_id,
+                    ),
+                    string,
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Returns the BCP 47 language tag for a record with `platform_id` and `language_id`.
+///
+/// Language IDs from `0x8000` refer to the language-tag records of version 1 tables. Returns
+/// `None` if the language is unknown, or for Unicode platform records without a language-tag
+/// record.
+pub fn language_tag(
+    name_table: &NameTable<'_>,
+    platform_id: u16,
+    language_id: u16,
+) -> Option<String> {
+    if language_id >= 0x8000 {
+        let records = name_table.opt_langtag_records.as_ref()?;
+        let index = usize::from(language_id - 0x8000);
+        if index >= records.len() {
+            return None;
+        }
+        let record = records.get_item(index);
+        let data = name_table
+            .string_storage
+            .offset_length(usize::from(record.offset), usize::from(record.length))
+            .ok()?
+            .data();
+        return decode_string(PLATFORM_UNICODE, 0, data);
+    }
+    let languages: &[(u16, &str)] = match platform_id {
+        PLATFORM_MACINTOSH => &MAC_LANGUAGES,
+        PLATFORM_WINDOWS => &WINDOWS_LANGUAGES,
+        _ => return None,
+    };
+    languages
+        .binary_search_by_key(&language_id, |&(id, _)| id)
+        .ok()
+        .map(|index| languages[index].1.to_string())
+}
+
+/// Returns the string for `name_id` in the first of `languages` that the `name` table has it
+/// in.
+///
+/// `languages` are BCP 47 language tags in order of preference. A language matches records
+/// with the same tag or a more specific one, so "en" matches "en-US". If a language has no
+/// matches, subtags are removed from its end until one does, so "zh-Hant-TW" also tries
+/// "zh-Hant" and "zh". English is tried after `languages`, then any record with `name_id`.
+/// Windows records are preferred over Unicode records, which are preferred over Macintosh
+/// records.
+pub fn name_for_language(
+    name_table: &NameTable<'_>,
+    name_id: u16,
+    languages: &[&str],
+) -> Option<String> {
+    let mut candidates = names(name_table)
+        .filter(|name| name.name_id == name_id)
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|name| platform_rank(name.platform_id));
+
+    for &language in languages.iter().chain(&["en"]) {
+        let mut range = language;
+        loop {
+            let found = candidates.iter().find(|name| match name.language_tag {
+                Some(ref tag) => language_matches(tag, range),
+                None => false,
+            });
+            if let Some(name) = found {
+                return Some(name.string.clone());
+            }
+            match range.rfind('-') {
+                Some(index) => range = &range[..index],
+                None => break,
+            }
+        }
+    }
+    candidates.into_iter().next().map(|name| name.string)
+}
+
+/// Returns the Windows language ID for the BCP 47 language tag `tag`.
+///
+/// Tags that don't match a Windows language exactly, ignoring case, fall back to the default
+/// Windows language with the same primary language subtag and no script subtag, preferring
+/// one with the same region. So "de" maps to German (Germany) and "es" to Spanish (Spain).
+/// Tags with a script subtag only fall back to a language with the same script and region,
+/// so "zh-Hans" and "sr-Cyrl" are not found.
+pub(crate) fn windows_language_id(tag: &str) -> Option<u16> {
+    let exact = WINDOWS_LANGUAGES
+        .iter()
+        .find(|(_, language)| language.eq_ignore_ascii_case(tag));
+    if let Some(&(id, _)) = exact {
+        return Some(id);
+    }
+
+    let tag = Subtags::parse(tag);
+    let candidates = WINDOWS_LANGUAGES
+        .iter()
+        .map(|&(id, language)| (id, Subtags::parse(language)))
+        .filter(|(_, language)| {
+            language.language.eq_ignore_ascii_case(tag.language)
+                && subtag_eq(language.script, tag.script)
+                && (tag.script.is_none() || subtag_eq(language.region, tag.region))
+        })
+        .collect::<Vec<_>>();
+    let same_region = candidates
+        .iter()
+        .filter(|(_, language)| tag.region.is_some() && subtag_eq(language.region, tag.region))
+        .cloned()
+        .collect::<Vec<_>>();
+    let candidates = if same_region.is_empty() {
+        candidates
+    } else {
+        same_region
+    };
+    // The lowest ID is the default language, which may have further subtags, such as the
+    // traditional sort order of Spanish. Prefer the same language and region without them.
+    let &(default_id, ref default) = candidates.first()?;
+    let plain = candidates
+        .iter()
+        .find(|(_, language)| !language.has_more && subtag_eq(language.region, default.region));
+    Some(plain.map_or(default_id, |&(id, _)| id))
+}
+
+/// Returns the Macintosh language ID for the BCP 47 language tag `tag`.
+///
+/// Tags that don't match a Macintosh language exactly, ignoring case, fall back to their
+/// primary language subtag, so "en-US" maps to English.
+pub(crate) fn mac_language_id(tag: &str) -> Option<u16> {
+    let find = |tag: &str| {
+        MAC_LANGUAGES
+            .iter()
+            .find(|(_, language)| language.eq_ignore_ascii_case(tag))
+            .map(|&(id, _)| id)
+    };
+    find(tag).or_else(|| find(tag.split('-').next()?))
+}
+
+/// The language, script and region subtags of a BCP 47 language tag.
+#[derive(Clone)]
+struct Subtags<'a> {
+    language: &'a str,
+    script: Option<&'a str>,
+    region: Option<&'a str>,
+    /// Whether the tag has subtags after the region, such as variants or extensions.
+    has_more: bool,
+}
+
+impl<'a> Subtags<'a> {
+    fn parse(tag: &'a str) -> Self {
+        let is_script = |subtag: &str| {
+            subtag.len() == 4 && subtag.bytes().all(|byte| byte.is_ascii_alphabetic())
+        };
+        let is_region = |subtag: &str| match subtag.len() {
+            2 => subtag.bytes().all(|byte| byte.is_ascii_alphabetic()),
+            3 => subtag.bytes().all(|byte| byte.is_ascii_digit()),
+            _ => false,
+        };
+        let mut subtags = tag.split('-').peekable();
+        let language = subtags.next().unwrap_or("");
+        let script = match subtags.peek() {
+            Some(subtag) if is_script(subtag) => subtags.next(),
+            _ => None,
+        };
+        let region = match subtags.peek() {
+            Some(subtag) if is_region(subtag) => subtags.next(),
+            _ => None,
+        };
+        Subtags {
+            language,
+            script,
+            region,
+            has_more: subtags.next().is_some(),
+        }
+    }
+}
+
+fn subtag_eq(a: Option<&str>, b: Option<&str>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Returns `true` if `tag` is `range` or starts with `range` followed by a subtag.
+fn language_matches(tag: &str, range: &str) -> bool {
+    match tag.get(..range.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(range) => {
+            tag.len() == range.len() || tag.as_bytes()[range.len()] == b'-'
+        }
+        _ => false,
+    }
+}
+
+fn platform_rank(platform_id: u16) -> u8 {
+    match platform_id {
+        PLATFORM_WINDOWS => 0,
+        PLATFORM_UNICODE => 1,
+        PLATFORM_MACINTOSH => 2,
+        _ => 3,
+    }
+}
+
+/// Decode a string with the encoding for `platform_id` and `encoding_id`.
+fn decode_string(platform_id: u16, encoding_id: u16, data: &[u8]) -> Option<String> {
+    let encoding = match (platform_id, encoding_id) {
+        (PLATFORM_UNICODE, _) => UTF_16BE,
+        // Symbol, Unicode BMP and Unicode full repertoire
+        (PLATFORM_WINDOWS, 0) | (PLATFORM_WINDOWS, 1) | (PLATFORM_WINDOWS, 10) => UTF_16BE,
+        (PLATFORM_MACINTOSH, 0) => MACINTOSH,
+        (PLATFORM_MACINTOSH, 1) => SHIFT_JIS,
+        (PLATFORM_MACINTOSH, 2) => BIG5,
+        (PLATFORM_MACINTOSH, 3) => EUC_KR,
+        (PLATFORM_MACINTOSH, 7) => X_MAC_CYRILLIC,
+        (PLATFORM_MACINTOSH, 25) => GBK,
+        _ => return None,
+    };
+    encoding
+        .decode_without_bom_handling_and_without_replacement(data)
+        .map(Cow::into_owned)
+}
+
 enum NameEncoding {
     Utf16Be,
     AppleRoman,
@@ -101,3 +416,176 @@ fn decode_name(encoding: NameEncoding, data: &[u8]) -> Option<CString> {
         None
     }
 }
+
+/// Macintosh language IDs and their BCP 47 language tags, sorted by ID.
+#[rustfmt::skip]
+const MAC_LANGUAGES: [(u16, &str); 118] = [
+    (0, "en"), (1, "fr"), (2, "de"), (3, "it"), (4, "nl"), (5, "sv"), (6, "es"), (7, "da"),
+    (8, "pt"), (9, "nb"), (10, "he"), (11, "ja"), (12, "ar"), (13, "fi"), (14, "el"), (15, "is"),
+    (16, "mt"), (17, "tr"), (18, "hr"), (19, "zh-Hant"), (20, "ur"), (21, "hi"), (22, "th"),
+    (23, "ko"), (24, "lt"), (25, "pl"), (26, "hu"), (27, "et"), (28, "lv"), (29, "se"), (30, "fo"),
+    (31, "fa"), (32, "ru"), (33, "zh-Hans"), (34, "nl-BE"), (35, "ga"), (36, "sq"), (37, "ro"),
+    (38, "cs"), (39, "sk"), (40, "sl"), (41, "yi"), (42, "sr"), (43, "mk"), (44, "bg"), (45, "uk"),
+    (46, "be"), (47, "uz"), (48, "kk"), (49, "az-Cyrl"), (50, "az-Arab"), (51, "hy"), (52, "ka"),
+    (53, "ro-MD"), (54, "ky"), (55, "tg"), (56, "tk"), (57, "mn-Mong"), (58, "mn"), (59, "ps"),
+    (60, "ku"), (61, "ks"), (62, "sd"), (63, "bo"), (64, "ne"), (65, "sa"), (66, "mr"), (67, "bn"),
+    (68, "as"), (69, "gu"), (70, "pa"), (71, "or"), (72, "ml"), (73, "kn"), (74, "ta"), (75, "te"),
+    (76, "si"), (77, "my"), (78, "km"), (79, "lo"), (80, "vi"), (81, "id"), (82, "tl"), (83, "ms"),
+    (84, "ms-Arab"), (85, "am"), (86, "ti"), (87, "om"), (88, "so"), (89, "sw"), (90, "rw"),
+    (91, "rn"), (92, "ny"), (93, "mg"), (94, "eo"), (128, "cy"), (129, "eu"), (130, "ca"),
+    (131, "la"), (132, "qu"), (133, "gn"), (134, "ay"), (135, "tt"), (136, "ug"), (137, "dz"),
+    (138, "jv"), (139, "su"), (140, "gl"), (141, "af"), (142, "br"), (143, "iu"), (144, "gd"),
+    (145, "gv"), (146, "ga"), (147, "to"), (148, "el-polyton"), (149, "kl"), (150, "az"),
+];
+
+/// Windows language IDs and their BCP 47 language tags, sorted by ID.
+#[rustfmt::skip]
+const WINDOWS_LANGUAGES: [(u16, &str); 205] = [
+    (0x0401, "ar-SA"), (0x0402, "bg-BG"), (0x0403, "ca-ES"), (0x0404, "zh-TW"), (0x0405, "cs-CZ"),
+    (0x0406, "da-DK"), (0x0407, "de-DE"), (0x0408, "el-GR"), (0x0409, "en-US"),
+    (0x040A, "es-ES-u-co-trad"), (0x040B, "fi-FI"), (0x040C, "fr-FR"), (0x040D, "he-IL"),
+    (0x040E, "hu-HU"), (0x040F, "is-IS"), (0x0410, "it-IT"), (0x0411, "ja-JP"), (0x0412, "ko-KR"),
+    (0x0413, "nl-NL"), (0x0414, "nb-NO"), (0x0415, "pl-PL"), (0x0416, "pt-BR"), (0x0417, "rm-CH"),
+    (0x0418, "ro-RO"), (0x0419, "ru-RU"), (0x041A, "hr-HR"), (0x041B, "sk-SK"), (0x041C, "sq-AL"),
+    (0x041D, "sv-SE"), (0x041E, "th-TH"), (0x041F, "tr-TR"), (0x0420, "ur-PK"), (0x0421, "id-ID"),
+    (0x0422, "uk-UA"), (0x0423, "be-BY"), (0x0424, "sl-SI"), (0x0425, "et-EE"), (0x0426, "lv-LV"),
+    (0x0427, "lt-LT"), (0x0428, "tg-Cyrl-TJ"), (0x042A, "vi-VN"), (0x042B, "hy-AM"),
+    (0x042C, "az-Latn-AZ"), (0x042D, "eu-ES"), (0x042E, "hsb-DE"), (0x042F, "mk-MK"),
+    (0x0432, "tn-ZA"), (0x0434, "xh-ZA"), (0x0435, "zu-ZA"), (0x0436, "af-ZA"), (0x0437, "ka-GE"),
+    (0x0438, "fo-FO"), (0x0439, "hi-IN"), (0x043A, "mt-MT"), (0x043B, "se-NO"), (0x043E, "ms-MY"),
+    (0x043F, "kk-KZ"), (0x0440, "ky-KG"), (0x0441, "sw-KE"), (0x0442, "tk-TM"),
+    (0x0443, "uz-Latn-UZ"), (0x0444, "tt-RU"), (0x0445, "bn-IN"), (0x0446, "pa-IN"),
+    (0x0447, "gu-IN"), (0x0448, "or-IN"), (0x0449, "ta-IN"), (0x044A, "te-IN"), (0x044B, "kn-IN"),
+    (0x044C, "ml-IN"), (0x044D, "as-IN"), (0x044E, "mr-IN"), (0x044F, "sa-IN"), (0x0450, "mn-MN"),
+    (0x0451, "bo-CN"), (0x0452, "cy-GB"), (0x0453, "km-KH"), (0x0454, "lo-LA"), (0x0456, "gl-ES"),
+    (0x0457, "kok-IN"), (0x045A, "syr-SY"), (0x045B, "si-LK"), (0x045D, "iu-Cans-CA"),
+    (0x045E, "am-ET"), (0x0461, "ne-NP"), (0x0462, "fy-NL"), (0x0463, "ps-AF"), (0x0464, "fil-PH"),
+    (0x0465, "dv-MV"), (0x0468, "ha-Latn-NG"), (0x046A, "yo-NG"), (0x046B, "quz-BO"),
+    (0x046C, "nso-ZA"), (0x046D, "ba-RU"), (0x046E, "lb-LU"), (0x046F, "kl-GL"), (0x0470, "ig-NG"),
+    (0x0478, "ii-CN"), (0x047A, "arn-CL"), (0x047C, "moh-CA"), (0x047E, "br-FR"), (0x0480, "ug-CN"),
+    (0x0481, "mi-NZ"), (0x0482, "oc-FR"), (0x0483, "co-FR"), (0x0484, "gsw-FR"), (0x0485, "sah-RU"),
+    (0x0486, "quc-Latn-GT"), (0x0487, "rw-RW"), (0x0488, "wo-SN"), (0x048C, "prs-AF"),
+    (0x0801, "ar-IQ"), (0x0804, "zh-CN"), (0x0807, "de-CH"), (0x0809, "en-GB"), (0x080A, "es-MX"),
+    (0x080C, "fr-BE"), (0x0810, "it-CH"), (0x0813, "nl-BE"), (0x0814, "nn-NO"), (0x0816, "pt-PT"),
+    (0x081A, "sr-Latn-RS"), (0x081D, "sv-FI"), (0x082C, "az-Cyrl-AZ"), (0x082E, "dsb-DE"),
+    (0x083B, "se-SE"), (0x083C, "ga-IE"), (0x083E, "ms-BN"), (0x0843, "uz-Cyrl-UZ"),
+    (0x0845, "bn-BD"), (0x0850, "mn-Mong-CN"), (0x085D, "iu-Latn-CA"), (0x085F, "tzm-Latn-DZ"),
+    (0x086B, "quz-EC"), (0x0C01, "ar-EG"), (0x0C04, "zh-HK"), (0x0C07, "de-AT"), (0x0C09, "en-AU"),
+    (0x0C0A, "es-ES"), (0x0C0C, "fr-CA"), (0x0C1A, "sr-Cyrl-RS"), (0x0C3B, "se-FI"),
+    (0x0C6B, "quz-PE"), (0x1001, "ar-LY"), (0x1004, "zh-SG"), (0x1007, "de-LU"), (0x1009, "en-CA"),
+    (0x100A, "es-GT"), (0x100C, "fr-CH"), (0x101A, "hr-BA"), (0x103B, "smj-NO"), (0x1401, "ar-DZ"),
+    (0x1404, "zh-MO"), (0x1407, "de-LI"), (0x1409, "en-NZ"), (0x140A, "es-CR"), (0x140C, "fr-LU"),
+    (0x141A, "bs-Latn-BA"), (0x143B, "smj-SE"), (0x1801, "ar-MA"), (0x1809, "en-IE"),
+    (0x180A, "es-PA"), (0x180C, "fr-MC"), (0x181A, "sr-Latn-BA"), (0x183B, "sma-NO"),
+    (0x1C01, "ar-TN"), (0x1C09, "en-ZA"), (0x1C0A, "es-DO"), (0x1C1A, "sr-Cyrl-BA"),
+    (0x1C3B, "sma-SE"), (0x2001, "ar-OM"), (0x2009, "en-JM"), (0x200A, "es-VE"),
+    (0x201A, "bs-Cyrl-BA"), (0x203B, "sms-FI"), (0x2401, "ar-YE"), (0x2409, "en-029"),
+    (0x240A, "es-CO"), (0x243B, "smn-FI"), (0x2801, "ar-SY"), (0x2809, "en-BZ"), (0x280A, "es-PE"),
+    (0x2C01, "ar-JO"), (0x2C09, "en-TT"), (0x2C0A, "es-AR"), (0x3001, "ar-LB"), (0x3009, "en-ZW"),
+    (0x300A, "es-EC"), (0x3401, "ar-KW"), (0x3409, "en-PH"), (0x340A, "es-CL"), (0x3801, "ar-AE"),
+    (0x380A, "es-UY"), (0x3C01, "ar-BH"), (0x3C0A, "es-PY"), (0x4001, "ar-QA"), (0x4009, "en-IN"),
+    (0x400A, "es-BO"), (0x4409, "en-MY"), (0x440A, "es-SV"), (0x4809, "en-SG"), (0x480A, "es-HN"),
+    (0x4C0A, "es-NI"), (0x500A, "es-PR"), (0x540A, "es-US"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::push_u16;
+
+    // A version 1 name table with subfamily names in several languages and encodings.
+    fn name_table_data() -> Vec<u8> {
+        let records: &[(u16, u16, u16, &[u8])] = &[
+            // Windows, Unicode BMP, en-US: "Bold"
+            (3, 1, 0x0409, &[0, b'B', 0, b'o', 0, b'l', 0, b'd']),
+            // Windows, Unicode BMP, fr-FR: "Gras"
+            (3, 1, 0x040C, &[0, b'G', 0, b'r', 0, b'a', 0, b's']),
+            // Windows, Unicode BMP, language-tag record 0: "Fett"
+            (3, 1, 0x8000, &[0, b'F', 0, b'e', 0, b't', 0, b't']),
+            // Macintosh, Japanese, Japanese: "太字"
+            (1, 1, 11, &[0x91, 0xBE, 0x8E, 0x9A]),
+            // Macintosh, Cyrillic, Russian: "Жирный"
+            (1, 7, 32, &[0x86, 0xE8, 0xF0, 0xED, 0xFB, 0xE9]),
+            // Macintosh, Arabic, unsupported
+            (1, 4, 12, &[0xC7]),
+        ];
+        let lang_tag = [0, b'd', 0, b'e'];
+
+        let storage_offset = 6 + records.len() * 12 + 2 + 4;
+        let mut data = Vec::new();
+        // version, count, storageOffset
+        push_u16(&mut data, &[1, records.len() as u16, storage_offset as u16]);
+        let mut storage = Vec::new();
+        for &(platform_id, encoding_id, language_id, string) in records {
+            push_u16(
+                &mut data,
+                &[
+                    platform_id,
+                    encoding_id,
+                    language_id,
+                    2,
+                    string.len() as u16,
+                    storage.len() as u16,
+                ],
+            );
+            storage.extend_from_slice(string);
+        }
+        // langTagCount and the language-tag record
+        push_u16(&mut data, &[1, lang_tag.len() as u16, storage.len() as u16]);
+        storage.extend_from_slice(&lang_tag);
+        data.extend_from_slice(&storage);
+        data
+    }
+
+    #[test]
+    fn test_names() {
+        let data = name_table_data();
+        let name_table = ReadScope::new(&data).read::<NameTable<'_>>().unwrap();
+        let names = names(&name_table)
+            .map(|name| (name.language_tag.unwrap(), name.string))
+            .collect::<Vec<_>>();
+        let expected = [
+            ("en-US", "Bold"),
+            ("fr-FR", "Gras"),
+            ("de", "Fett"),
+            ("ja", "太字"),
+            ("ru", "Жирный"),
+        ];
+        assert_eq!(names.len(), expected.len());
+        for ((tag, string), &(expected_tag, expected_string)) in names.iter().zip(&expected) {
+            assert_eq!(tag, expected_tag);
+            assert_eq!(string, expected_string);
+        }
+    }
+
+    #[test]
+    fn test_name_for_language() {
+        let data = name_table_data();
+        let name_table = ReadScope::new(&data).read::<NameTable<'_>>().unwrap();
+        let name = |languages: &[&str]| name_for_language(&name_table, 2, languages).unwrap();
+        assert_eq!(name(&["fr-CA"]), "Gras");
+        assert_eq!(name(&["de-AT", "fr"]), "Fett");
+        assert_eq!(name(&["JA"]), "太字");
+        assert_eq!(name(&["ru-RU"]), "Жирный");
+        assert_eq!(name(&["zh-Hant-TW"]), "Bold");
+        assert_eq!(name(&[]), "Bold");
+        assert_eq!(name_for_language(&name_table, 1, &["en"]), None);
+    }
+
+    #[test]
+    fn test_language_ids() {
+        assert_eq!(windows_language_id("en-GB"), Some(0x0809));
+        assert_eq!(windows_language_id("EN"), Some(0x0409));
+        assert_eq!(windows_language_id("de"), Some(0x0407));
+        assert_eq!(windows_language_id("de-CH-1996"), Some(0x0807));
+        assert_eq!(windows_language_id("es"), Some(0x0C0A));
+        assert_eq!(windows_language_id("zh-Hans"), None);
+        assert_eq!(windows_language_id("zh-Hans-CN"), None);
+        assert_eq!(windows_language_id("sr-Cyrl"), None);
+        assert_eq!(windows_language_id("sr-cyrl-rs-x-test"), Some(0x0C1A));
+        assert_eq!(windows_language_id("az"), None);
+        assert_eq!(windows_language_id("tlh"), None);
+        assert_eq!(mac_language_id("en-US"), Some(0));
+        assert_eq!(mac_language_id("tlh"), None);
+    }
+}
@@ -11,7 +11,8 @@ use crate::font_data_impl::Encoding;
 use crate::macroman::macroman_to_char;
 use crate::post::PostTable;
 use crate::tables::cmap::CmapSubtable;
-use crate::tables::{HheaTable, HmtxTable, MaxpTable};
+use crate::tables::variable_fonts::hvar::HvarTable;
+use crate::tables::{F2Dot14, HheaTable, HmtxTable, MaxpTable};
 
 /// Retrieve glyph advance.
 ///
@@ -39,6 +40,28 @@ pub fn advance(
     }
 }
 
+/// Retrieve glyph advance at the normalized variation coordinates `coords`.
+///
+/// The advance read from `hmtx_data` is adjusted by the deltas in `hvar`, which should be the
+/// `HVAR` table when `hhea` is supplied and the `VVAR` table when `vhea` is supplied.
+pub fn variable_advance(
+    maxp: &MaxpTable,
+    hhea: &HheaTable,
+    hmtx_data: &[u8],
+    hvar: Option<&HvarTable<'_>>,
+    glyph: u16,
+    coords: &[F2Dot14],
+) -> Result<u16, ParseError> {
+    let advance = advance(maxp, hhea, hmtx_data, glyph)?;
+    match hvar {
+        Some(hvar) if glyph < maxp.num_glyphs => {
+            let adjustment = hvar.advance_adjustment(glyph, coords)?;
+            Ok((f32::from(advance) + adjustment).round() as u16)
+        }
+        _ => Ok(advance),
+    }
+}
+
 rental! {
     mod rentable {
         use super::*;
@@ -17,10 +17,37 @@ use crate::layout::{
 };
 use crate::scripts;
 use crate::scripts::ScriptType;
+use crate::tables::kern::KernTable;
+use crate::tables::F2Dot14;
 use crate::tag;
 
+const POS_RECURSION_LIMIT: usize = 2;
+
 type PosContext<'a> = ContextLookupHelper<'a, GPOS>;
 
+/// The tables used to apply a contextual lookup and the lookups nested within it.
+#[derive(Copy, Clone)]
+struct PosApply<'a> {
+    gpos_cache: &'a LayoutCache<GPOS>,
+    lookup_list: &'a LookupList<GPOS>,
+    opt_gdef_table: Option<&'a GDEFTable>,
+    /// The number of further contextual lookups that may be nested.
+    recursion_limit: usize,
+}
+
+impl<'a> PosApply<'a> {
+    /// Returns the context for applying a contextual lookup nested within this one.
+    fn nested(self) -> Result<Self, ParseError> {
+        match self.recursion_limit.checked_sub(1) {
+            Some(recursion_limit) => Ok(PosApply {
+                recursion_limit,
+                ..self
+            }),
+            None => Err(ParseError::LimitExceeded),
+        }
+    }
+}
+
 pub fn gpos_apply_lookup(
     gpos_cache: &LayoutCache<GPOS>,
     gpos_table: &LayoutTable<GPOS>,
@@ -30,7 +57,7 @@ pub fn gpos_apply_lookup(
 ) -> Result<(), ParseError> {
     if let Some(ref lookup_list) = gpos_table.opt_lookup_list {
         let lookup = lookup_list.lookup_cache_gpos(gpos_cache, lookup_index)?;
-        let match_type = MatchType::from_lookup_flag(lookup.lookup_flag);
+        let match_type = MatchType::from_lookup(&lookup);
         match lookup.lookup_subtables {
             PosLookup::SinglePos(ref subtables) => {
                 forall_glyphs_match(match_type, opt_gdef_table, infos, |i, infos| {
@@ -67,29 +94,25 @@ pub fn gpos_apply_lookup(
                 })
             }
             PosLookup::ContextPos(ref subtables) => {
-                forall_glyphs_match(match_type, opt_gdef_table, infos, |i, infos| {
-                    contextpos(
-                        gpos_cache,
-                        &lookup_list,
-                        opt_gdef_table,
-                        match_type,
-                        &subtables,
-                        i,
-                        infos,
-                    )
+                let apply = PosApply {
+                    gpos_cache,
+                    lookup_list,
+                    opt_gdef_table,
+                    recursion_limit: POS_RECURSION_LIMIT,
+                };
+                forall_glyph_contexts_match(match_type, opt_gdef_table, infos, |i, infos| {
+                    contextpos(apply, match_type, &subtables, i, infos)
                 })
             }
             PosLookup::ChainContextPos(ref subtables) => {
-                forall_glyphs_match(match_type, opt_gdef_table, infos, |i, infos| {
-                    chaincontextpos(
-                        gpos_cache,
-                        &lookup_list,
-                        opt_gdef_table,
-                        match_type,
-                        &subtables,
-                        i,
-                        infos,
-                    )
+                let apply = PosApply {
+                    gpos_cache,
+                    lookup_list,
+                    opt_gdef_table,
+                    recursion_limit: POS_RECURSION_LIMIT,
+                };
+                forall_glyph_contexts_match(match_type, opt_gdef_table, infos, |i, infos| {
+                    chaincontextpos(apply, match_type, &subtables, i, infos)
                 })
             }
         }
@@ -233,7 +256,7 @@ pub enum MarkPlacement {
 }
 
 impl Placement {
-    fn combine_distance(&mut self, x2: i32, y2: i32) {
+    pub(crate) fn combine_distance(&mut self, x2: i32, y2: i32) {
         *self = match *self {
             Placement::None => Placement::Distance(x2, y2),
             Placement::Distance(x1, y1) => Placement::Distance(x1 + x2, y1 + y2),
@@ -243,7 +266,7 @@ impl Placement {
         }
     }
 
-    fn combine_anchor(&mut self, an1: Anchor, an2: Anchor) {
+    pub(crate) fn combine_anchor(&mut self, an1: Anchor, an2: Anchor) {
         *self = match *self {
             Placement::None => Placement::Anchor(an1, an2),
             Placement::Distance(_, _) => {
@@ -330,6 +353,28 @@ fn forall_glyphs_match(
     Ok(())
 }
 
+// Calls `f` for each glyph that matches `match_type`. `f` returns the length of the input
+// sequence when a contextual lookup was applied, in which case matching resumes after it.
+fn forall_glyph_contexts_match(
+    match_type: MatchType,
+    opt_gdef_table: Option<&GDEFTable>,
+    infos: &mut [Info],
+    f: impl Fn(usize, &mut [Info]) -> Result<Option<usize>, ParseError>,
+) -> Result<(), ParseError> {
+    let mut i = 0;
+    while i < infos.len() {
+        if match_type.match_glyph(opt_gdef_table, &infos[i]) {
+            match f(i, infos)? {
+                Some(input_length) => i += input_length,
+                None => i += 1,
+            }
+        } else {
+            i += 1;
+        }
+    }
+    Ok(())
+}
+
 fn forall_glyph_pairs_match(
     match_type: MatchType,
     opt_gdef_table: Option<&GDEFTable>,
@@ -501,94 +546,84 @@ fn markmarkpos(
     }
 }
 
-fn contextpos<'a>(
-    gpos_cache: &LayoutCache<GPOS>,
-    lookup_list: &LookupList<GPOS>,
-    opt_gdef_table: Option<&GDEFTable>,
+fn contextpos(
+    apply: PosApply<'_>,
     match_type: MatchType,
     subtables: &[ContextLookup<GPOS>],
     i: usize,
     infos: &mut [Info],
-) -> Result<(), ParseError> {
+) -> Result<Option<usize>, ParseError> {
     let glyph_index = infos[i].glyph.glyph_index;
+    let opt_gdef_table = apply.opt_gdef_table;
     match gpos_lookup_contextpos(opt_gdef_table, match_type, subtables, glyph_index, i, infos)? {
-        Some(pos) => apply_pos_context(
-            gpos_cache,
-            lookup_list,
-            opt_gdef_table,
-            match_type,
-            &pos,
-            i,
-            infos,
-        ),
-        None => Ok(()),
+        Some(pos) => apply_pos_context(apply, match_type, &pos, i, infos),
+        None => Ok(None),
     }
 }
 
-fn chaincontextpos<'a>(
-    gpos_cache: &LayoutCache<GPOS>,
-    lookup_list: &LookupList<GPOS>,
-    opt_gdef_table: Option<&GDEFTable>,
+fn chaincontextpos(
+    apply: PosApply<'_>,
     match_type: MatchType,
     subtables: &[ChainContextLookup<GPOS>],
     i: usize,
     infos: &mut [Info],
-) -> Result<(), ParseError> {
+) -> Result<Option<usize>, ParseError> {
     let glyph_index = infos[i].glyph.glyph_index;
+    let opt_gdef_table = apply.opt_gdef_table;
     match gpos_lookup_chaincontextpos(opt_gdef_table, match_type, subtables, glyph_index, i, infos)?
     {
-        Some(pos) => apply_pos_context(
-            gpos_cache,
-            lookup_list,
-            opt_gdef_table,
-            match_type,
-            &pos,
-            i,
-            infos,
-        ),
-        None => Ok(()),
+        Some(pos) => apply_pos_context(apply, match_type, &pos, i, infos),
+        None => Ok(None),
     }
 }
 
-fn apply_pos_context<'a>(
-    gpos_cache: &LayoutCache<GPOS>,
-    lookup_list: &LookupList<GPOS>,
-    opt_gdef_table: Option<&GDEFTable>,
-    _match_type: MatchType,
+/// Apply the nested lookups of a matched context, returning the length of the input sequence.
+fn apply_pos_context(
+    apply: PosApply<'_>,
+    match_type: MatchType,
     pos: &PosContext<'_>,
     i: usize,
     infos: &mut [Info],
-) -> Result<(), ParseError> {
+) -> Result<Option<usize>, ParseError> {
+    let len = match match_type.find_nth(
+        apply.opt_gdef_table,
+        infos,
+        i,
+        pos.match_context.input_table.len(),
+    ) {
+        Some(last) => last - i + 1,
+        None => return Ok(None),
+    };
     for (pos_index, pos_lookup_index) in pos.lookup_array {
         apply_pos(
-            gpos_cache,
-            lookup_list,
-            opt_gdef_table,
+            apply,
+            match_type,
             usize::from(*pos_index),
             usize::from(*pos_lookup_index),
             infos,
             i,
         )?;
     }
-    Ok(())
+    Ok(Some(len))
 }
 
-fn apply_pos<'a>(
-    gpos_cache: &LayoutCache<GPOS>,
-    lookup_list: &LookupList<GPOS>,
-    opt_gdef_table: Option<&GDEFTable>,
+fn apply_pos(
+    apply: PosApply<'_>,
+    parent_match_type: MatchType,
     pos_index: usize,
     lookup_index: usize,
     infos: &mut [Info],
     index: usize,
 ) -> Result<(), ParseError> {
-    let lookup = lookup_list.lookup_cache_gpos(gpos_cache, lookup_index)?;
-    let match_type = MatchType::from_lookup_flag(lookup.lookup_flag);
-    let i1;
-    match match_type.find_nth(opt_gdef_table, infos, index, pos_index) {
-        Some(index1) => i1 = index1,
+    let opt_gdef_table = apply.opt_gdef_table;
+    let lookup = apply
+        .lookup_list
+        .lookup_cache_gpos(apply.gpos_cache, lookup_index)?;
+    let match_type = MatchType::from_lookup(&lookup);
+    let i1 = match parent_match_type.find_nth(opt_gdef_table, infos, index, pos_index) {
+        Some(index1) => index1,
         None => return Ok(()),
-    }
+    };
     match lookup.lookup_subtables {
         PosLookup::SinglePos(ref subtables) => singlepos(&subtables, &mut infos[i1]),
         PosLookup::PairPos(ref subtables) => {
@@ -631,8 +666,14 @@ fn apply_pos<'a>(
                 Ok(())
             }
         }
-        PosLookup::ContextPos(ref _subtables) => Ok(()),
-        PosLookup::ChainContextPos(ref _subtables) => Ok(()),
+        PosLookup::ContextPos(ref subtables) => {
+            contextpos(apply.nested()?, match_type, subtables, i1, infos)?;
+            Ok(())
+        }
+        PosLookup::ChainContextPos(ref subtables) => {
+            chaincontextpos(apply.nested()?, match_type, subtables, i1, infos)?;
+            Ok(())
+        }
     }
 }
 
@@ -642,6 +683,7 @@ pub fn gpos_apply(
     kerning: bool,
     script_tag: u32,
     opt_lang_tag: Option<u32>,
+    coords: &[F2Dot14],
     infos: &mut [Info],
 ) -> Result<(), ParseError> {
     let gpos_table = &gpos_cache.layout_table;
@@ -653,6 +695,7 @@ pub fn gpos_apply(
             opt_gdef_table,
             script_tag,
             opt_lang_tag,
+            coords,
             infos,
         );
     }
@@ -667,6 +710,7 @@ pub fn gpos_apply(
                     &gpos_table,
                     opt_gdef_table,
                     &langsys,
+                    coords,
                     &[tag::CURS, tag::KERN, tag::MARK, tag::MKMK],
                     infos,
                 ),
@@ -677,6 +721,7 @@ pub fn gpos_apply(
                             &gpos_table,
                             opt_gdef_table,
                             &langsys,
+                            coords,
                             &[tag::DIST, tag::KERN, tag::MARK, tag::MKMK],
                             infos,
                         )
@@ -686,6 +731,7 @@ pub fn gpos_apply(
                             &gpos_table,
                             opt_gdef_table,
                             &langsys,
+                            coords,
                             &[tag::DIST, tag::MARK, tag::MKMK],
                             infos,
                         )
@@ -697,16 +743,87 @@ pub fn gpos_apply(
     }
 }
 
+/// Apply kerning from a legacy `kern` table to `infos`.
+///
+/// This is a fallback for fonts without `GPOS` kerning. Nothing is applied if `kerning` is
+/// `false`, or if `opt_gpos_cache` holds a `GPOS` table with a `kern` feature for the script and
+/// language at the normalized variation coordinates `coords`. Marks are skipped, so that the
+/// glyphs either side of them are kerned.
+pub fn kern_table_apply(
+    opt_gpos_cache: Option<&LayoutCache<GPOS>>,
+    kern_table: &KernTable<'_>,
+    kerning: bool,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    coords: &[F2Dot14],
+    infos: &mut [Info],
+) -> Result<(), ParseError> {
+    if !kerning {
+        return Ok(());
+    }
+    if let Some(gpos_cache) = opt_gpos_cache {
+        if gpos_has_kern_feature(&gpos_cache.layout_table, script_tag, opt_lang_tag, coords)? {
+            return Ok(());
+        }
+    }
+
+    let mut opt_prev: Option<usize> = None;
+    for i in 0..infos.len() {
+        if infos[i].is_mark {
+            continue;
+        }
+        if let Some(prev) = opt_prev {
+            if let Some(value) = kern_table
+                .horizontal_kerning(infos[prev].glyph.glyph_index, infos[i].glyph.glyph_index)?
+            {
+                infos[prev].kerning = infos[prev].kerning.saturating_add(value);
+            }
+        }
+        opt_prev = Some(i);
+    }
+    Ok(())
+}
+
+fn gpos_has_kern_feature(
+    gpos_table: &LayoutTable<GPOS>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    coords: &[F2Dot14],
+) -> Result<bool, ParseError> {
+    match gpos_table.find_script_or_default(script_tag)? {
+        Some(script) => match script.find_langsys_or_default(opt_lang_tag)? {
+            Some(langsys) => {
+                let opt_feature_variation = gpos_table.find_feature_variation(coords);
+                Ok(gpos_table
+                    .find_langsys_feature_variation(langsys, tag::KERN, opt_feature_variation)?
+                    .is_some())
+            }
+            None => Ok(false),
+        },
+        None => Ok(false),
+    }
+}
+
+/// Apply the lookups of the features in `feature_tags`.
+///
+/// Features are substituted according to the feature variations that apply at the normalized
+/// variation coordinates `coords`.
 pub fn gpos_apply0(
     gpos_cache: &LayoutCache<GPOS>,
     gpos_table: &LayoutTable<GPOS>,
     opt_gdef_table: Option<&GDEFTable>,
     langsys: &LangSys,
+    coords: &[F2Dot14],
     feature_tags: &[u32],
     infos: &mut [Info],
 ) -> Result<(), ParseError> {
+    let opt_feature_variation = gpos_table.find_feature_variation(coords);
     for feature_tag in feature_tags {
-        if let Some(feature_table) = gpos_table.find_langsys_feature(&langsys, *feature_tag)? {
+        if let Some(feature_table) = gpos_table.find_langsys_feature_variation(
+            &langsys,
+            *feature_tag,
+            opt_feature_variation,
+        )? {
             for lookup_index in &feature_table.lookup_indices {
                 gpos_apply_lookup(
                     gpos_cache,
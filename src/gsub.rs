@@ -24,6 +24,7 @@ use crate::layout::{
 };
 use crate::scripts;
 use crate::scripts::ScriptType;
+use crate::tables::F2Dot14;
 use crate::tag;
 use crate::unicode::VariationSelector;
 
@@ -134,14 +135,17 @@ impl<T> Glyph for RawGlyph<T> {
 
 pub fn gsub_feature_would_apply<T: GlyphData>(
     gsub_cache: &LayoutCache<GSUB>,
-    gsub_table: &LayoutTable<GSUB>,
     opt_gdef_table: Option<&GDEFTable>,
     langsys: &LangSys,
+    opt_feature_variation: Option<usize>,
     feature_tag: u32,
     glyphs: &[RawGlyph<T>],
     i: usize,
 ) -> Result<bool, ParseError> {
-    if let Some(feature_table) = gsub_table.find_langsys_feature(langsys, feature_tag)? {
+    let gsub_table = &gsub_cache.layout_table;
+    if let Some(feature_table) =
+        gsub_table.find_langsys_feature_variation(langsys, feature_tag, opt_feature_variation)?
+    {
         if let Some(ref lookup_list) = gsub_table.opt_lookup_list {
             for lookup_index in &feature_table.lookup_indices {
                 let lookup_index = usize::from(*lookup_index);
@@ -161,7 +165,7 @@ pub fn gsub_lookup_would_apply<T: GlyphData>(
     glyphs: &[RawGlyph<T>],
     i: usize,
 ) -> Result<bool, ParseError> {
-    let match_type = MatchType::from_lookup_flag(lookup.lookup_flag);
+    let match_type = MatchType::from_lookup(lookup);
     if i < glyphs.len() && match_type.match_glyph(opt_gdef_table, &glyphs[i]) {
         return match lookup.lookup_subtables {
             SubstLookup::SingleSubst(ref subtables) => {
@@ -238,7 +242,7 @@ pub fn gsub_apply_lookup<T: GlyphData>(
 ) -> Result<usize, ParseError> {
     if let Some(ref lookup_list) = gsub_table.opt_lookup_list {
         let lookup = lookup_list.lookup_cache_gsub(gsub_cache, lookup_index)?;
-        let match_type = MatchType::from_lookup_flag(lookup.lookup_flag);
+        let match_type = MatchType::from_lookup(&lookup);
         match lookup.lookup_subtables {
             SubstLookup::SingleSubst(ref subtables) => {
                 for i in start..(start + length) {
@@ -694,7 +698,7 @@ fn apply_subst<'a, T: GlyphData>(
     index: usize,
 ) -> Result<Option<isize>, ParseError> {
     let lookup = lookup_list.lookup_cache_gsub(gsub_cache, lookup_index)?;
-    let match_type = MatchType::from_lookup_flag(lookup.lookup_flag);
+    let match_type = MatchType::from_lookup(&lookup);
     let i = match parent_match_type.find_nth(opt_gdef_table, glyphs, index, subst_index) {
         Some(index1) => index1,
         None => return Ok(None), // FIXME error?
@@ -768,13 +772,16 @@ fn apply_subst<'a, T: GlyphData>(
 fn build_lookups_custom(
     gsub_table: &LayoutTable<GSUB>,
     langsys: &LangSys,
+    opt_feature_variation: Option<usize>,
     feature_tags: &[FeatureInfo],
 ) -> Result<BTreeMap<usize, u32>, ParseError> {
     let mut lookups = BTreeMap::new();
     for feature_info in feature_tags {
-        if let Some(feature_table) =
-            gsub_table.find_langsys_feature(langsys, feature_info.feature_tag)?
-        {
+        if let Some(feature_table) = gsub_table.find_langsys_feature_variation(
+            langsys,
+            feature_info.feature_tag,
+            opt_feature_variation,
+        )? {
             for lookup_index in &feature_table.lookup_indices {
                 lookups.insert(usize::from(*lookup_index), feature_info.feature_tag);
             }
@@ -783,14 +790,19 @@ fn build_lookups_custom(
     Ok(lookups)
 }
 
-pub fn build_lookups(
-    gsub_table: &LayoutTable<GSUB>,
+pub fn build_lookups<T>(
+    layout_table: &LayoutTable<T>,
     langsys: &LangSys,
+    opt_feature_variation: Option<usize>,
     feature_tags: &[u32],
 ) -> Result<Vec<(usize, u32)>, ParseError> {
     let mut lookups = BTreeMap::new();
     for feature_tag in feature_tags {
-        if let Some(feature_table) = gsub_table.find_langsys_feature(langsys, *feature_tag)? {
+        if let Some(feature_table) = layout_table.find_langsys_feature_variation(
+            langsys,
+            *feature_tag,
+            opt_feature_variation,
+        )? {
             for lookup_index in &feature_table.lookup_indices {
                 lookups.insert(usize::from(*lookup_index), *feature_tag);
             }
@@ -805,18 +817,27 @@ pub fn build_lookups(
 fn build_lookups_default(
     gsub_table: &LayoutTable<GSUB>,
     langsys: &LangSys,
+    opt_feature_variation: Option<usize>,
     feature_masks: GsubFeatureMask,
 ) -> Result<Vec<(usize, u32)>, ParseError> {
     let mut lookups = BTreeMap::new();
     for (feature_mask, feature_tag) in FEATURE_MASKS {
         if feature_masks.contains(*feature_mask) {
-            if let Some(feature_table) = gsub_table.find_langsys_feature(langsys, *feature_tag)? {
+            if let Some(feature_table) = gsub_table.find_langsys_feature_variation(
+                langsys,
+                *feature_tag,
+                opt_feature_variation,
+            )? {
                 for lookup_index in &feature_table.lookup_indices {
                     lookups.insert(usize::from(*lookup_index), *feature_tag);
                 }
             } else if *feature_tag == tag::VRT2 {
                 let vert_tag = tag::VERT;
-                if let Some(feature_table) = gsub_table.find_langsys_feature(langsys, vert_tag)? {
+                if let Some(feature_table) = gsub_table.find_langsys_feature_variation(
+                    langsys,
+                    vert_tag,
+                    opt_feature_variation,
+                )? {
                     for lookup_index in &feature_table.lookup_indices {
                         lookups.insert(usize::from(*lookup_index), vert_tag);
                     }
@@ -891,6 +912,7 @@ pub fn gsub_apply_custom<T: GlyphData + Debug>(
     opt_gdef_table: Option<&GDEFTable>,
     script_tag: u32,
     opt_lang_tag: Option<u32>,
+    coords: &[F2Dot14],
     features_list: &[FeatureInfo],
     num_glyphs: u16,
     glyphs: &mut Vec<RawGlyph<T>>,
@@ -898,7 +920,9 @@ pub fn gsub_apply_custom<T: GlyphData + Debug>(
     let gsub_table = &gsub_cache.layout_table;
     if let Some(script) = gsub_table.find_script_or_default(script_tag)? {
         if let Some(langsys) = script.find_langsys_or_default(opt_lang_tag)? {
-            let lookups = build_lookups_custom(gsub_table, langsys, features_list)?;
+            let opt_feature_variation = gsub_table.find_feature_variation(coords);
+            let lookups =
+                build_lookups_custom(gsub_table, langsys, opt_feature_variation, features_list)?;
 
             // note: iter() returns sorted by key
             for (lookup_index, feature_tag) in lookups {
@@ -984,6 +1008,7 @@ bitflags! {
         const TNUM = 1 << 16;
         const VRT2_OR_VERT = 1 << 17;
         const ZERO = 1 << 18;
+        const RVRN = 1 << 19;
     }
 }
 
@@ -1003,6 +1028,7 @@ const FEATURE_MASKS: &[(GsubFeatureMask, u32)] = &[
     (GsubFeatureMask::ORDN, tag::ORDN),
     (GsubFeatureMask::PNUM, tag::PNUM),
     (GsubFeatureMask::RLIG, tag::RLIG),
+    (GsubFeatureMask::RVRN, tag::RVRN),
     (GsubFeatureMask::SMCP, tag::SMCP),
     (GsubFeatureMask::TNUM, tag::TNUM),
     (GsubFeatureMask::VRT2_OR_VERT, tag::VRT2),
@@ -1027,6 +1053,7 @@ impl GsubFeatureMask {
             tag::ORDN => GsubFeatureMask::ORDN,
             tag::PNUM => GsubFeatureMask::PNUM,
             tag::RLIG => GsubFeatureMask::RLIG,
+            tag::RVRN => GsubFeatureMask::RVRN,
             tag::SMCP => GsubFeatureMask::SMCP,
             tag::TNUM => GsubFeatureMask::TNUM,
             tag::VERT => GsubFeatureMask::VRT2_OR_VERT,
@@ -1045,6 +1072,7 @@ impl Default for GsubFeatureMask {
             | GsubFeatureMask::LIGA
             | GsubFeatureMask::LOCL
             | GsubFeatureMask::CALT
+            | GsubFeatureMask::RVRN
     }
 }
 
@@ -1058,23 +1086,36 @@ pub fn features_supported(
     Ok(supported_features.contains(feature_mask))
 }
 
+/// Find the index into `gsub_cache.cached_lookups` of the lookups for the given script, language
+/// and features.
+///
+/// Features are substituted according to the feature variations that apply at the normalized
+/// variation coordinates `coords`.
 pub fn get_lookups_cache_index(
     gsub_cache: &LayoutCache<GSUB>,
     script_tag: u32,
     opt_lang_tag: Option<u32>,
+    coords: &[F2Dot14],
     feature_mask: GsubFeatureMask,
 ) -> Result<usize, ParseError> {
+    let gsub_table = &gsub_cache.layout_table;
+    let opt_feature_variation = gsub_table.find_feature_variation(coords);
     let index = match gsub_cache.lookups_index.borrow_mut().entry((
         script_tag,
         lang_tag_key(opt_lang_tag),
         feature_mask.bits(),
+        opt_feature_variation,
     )) {
         Entry::Occupied(entry) => *entry.get(),
         Entry::Vacant(entry) => {
-            let gsub_table = &gsub_cache.layout_table;
             if let Some(script) = gsub_table.find_script_or_default(script_tag)? {
                 if let Some(langsys) = script.find_langsys_or_default(opt_lang_tag)? {
-                    let lookups = build_lookups_default(gsub_table, langsys, feature_mask)?;
+                    let lookups = build_lookups_default(
+                        gsub_table,
+                        langsys,
+                        opt_feature_variation,
+                        feature_mask,
+                    )?;
                     let index = gsub_cache.cached_lookups.borrow().len();
                     gsub_cache.cached_lookups.borrow_mut().push(lookups);
                     *entry.insert(index)
@@ -1095,45 +1136,75 @@ pub fn gsub_apply_default<'data>(
     opt_gdef_table: Option<&GDEFTable>,
     script_tag: u32,
     opt_lang_tag: Option<u32>,
+    coords: &[F2Dot14],
     mut feature_mask: GsubFeatureMask,
     num_glyphs: u16,
     glyphs: &mut Vec<RawGlyph<()>>,
 ) -> Result<(), ShapingError> {
     let gsub_table = &gsub_cache.layout_table;
+    let supported_features = get_supported_features(gsub_cache, script_tag, opt_lang_tag)?;
+
+    // `rvrn` is applied before any other feature so that glyphs are substituted for the variation
+    // instance first.
+    if feature_mask.contains(GsubFeatureMask::RVRN)
+        && supported_features.contains(GsubFeatureMask::RVRN)
+    {
+        let index = get_lookups_cache_index(
+            gsub_cache,
+            script_tag,
+            opt_lang_tag,
+            coords,
+            GsubFeatureMask::RVRN,
+        )?;
+        let lookups = &gsub_cache.cached_lookups.borrow()[index];
+        gsub_apply_lookups(gsub_cache, gsub_table, opt_gdef_table, lookups, glyphs)?;
+    }
+    feature_mask.remove(GsubFeatureMask::RVRN);
+
     match ScriptType::from(script_tag) {
         ScriptType::Arabic => scripts::arabic::gsub_apply_arabic(
             gsub_cache,
-            gsub_table,
             opt_gdef_table,
             script_tag,
             opt_lang_tag,
+            coords,
             glyphs,
         )?,
         ScriptType::Indic => scripts::indic::gsub_apply_indic(
             make_dotted_circle,
             gsub_cache,
-            gsub_table,
             opt_gdef_table,
             script_tag,
             opt_lang_tag,
+            coords,
             glyphs,
         )?,
         ScriptType::Syriac => scripts::syriac::gsub_apply_syriac(
             gsub_cache,
-            gsub_table,
             opt_gdef_table,
             script_tag,
             opt_lang_tag,
+            coords,
             glyphs,
         )?,
         ScriptType::Default => {
-            feature_mask &= get_supported_features(gsub_cache, script_tag, opt_lang_tag)?;
+            feature_mask &= supported_features;
             if feature_mask.contains(GsubFeatureMask::FRAC) {
-                let index_frac =
-                    get_lookups_cache_index(gsub_cache, script_tag, opt_lang_tag, feature_mask)?;
+                let index_frac = get_lookups_cache_index(
+                    gsub_cache,
+                    script_tag,
+                    opt_lang_tag,
+                    coords,
+                    feature_mask,
+                )?;
                 feature_mask.remove(GsubFeatureMask::FRAC);
-                let index =
-                    get_lookups_cache_index(gsub_cache, script_tag, opt_lang_tag, feature_mask)?;
+                let index = get_lookups_cache_index(
+                    gsub_cache,
+                    script_tag,
+                    opt_lang_tag,
+                    coords,
+                    feature_mask,
+                )?;
                 let lookups = &gsub_cache.cached_lookups.borrow()[index];
                 let lookups_frac = &gsub_cache.cached_lookups.borrow()[index_frac];
                 gsub_apply_lookups_frac(
@@ -1145,8 +1216,13 @@ pub fn gsub_apply_default<'data>(
                     glyphs,
                 )?;
             } else {
-                let index =
-                    get_lookups_cache_index(gsub_cache, script_tag, opt_lang_tag, feature_mask)?;
+                let index = get_lookups_cache_index(
+                    gsub_cache,
+                    script_tag,
+                    opt_lang_tag,
+                    coords,
+                    feature_mask,
+                )?;
                 let lookups = &gsub_cache.cached_lookups.borrow()[index];
                 gsub_apply_lookups(gsub_cache, gsub_table, opt_gdef_table, lookups, glyphs)?;
             }
@@ -1278,3 +1354,79 @@ fn find_fraction(glyphs: &[RawGlyph<()>]) -> Option<(usize, usize, usize)> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::layout::new_layout_cache;
+    use crate::tests::make_direct_glyphs;
+
+    // A GSUB table with an `rvrn` feature that uses lookup 0 (1 -> 2) by default and lookup 1
+    // (1 -> 3) when axis 0 is in 0.5..=1.0.
+    const RVRN_GSUB_DATA: [u8; 142] = [
+        0x00, 0x01, 0x00, 0x01, // version 1.1
+        0x00, 0x0E, // script list offset
+        0x00, 0x22, // feature list offset
+        0x00, 0x60, // lookup list offset
+        0x00, 0x00, 0x00, 0x30, // feature variations offset
+        // ScriptList
+        0x00, 0x01, 0x44, 0x46, 0x4C, 0x54, 0x00, 0x08, // DFLT
+        0x00, 0x04, 0x00, 0x00, // Script
+        0x00, 0x00, 0xFF, 0xFF, 0x00, 0x01, 0x00, 0x00, // LangSys
+        // FeatureList
+        0x00, 0x01, 0x72, 0x76, 0x72, 0x6E, 0x00, 0x08, // rvrn
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, // Feature, lookup 0
+        // FeatureVariations
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, //
+        0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x1E, // FeatureVariationRecord
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x06, // ConditionSet
+        0x00, 0x01, 0x00, 0x00, 0x20, 0x00, 0x40, 0x00, // axis 0 in 0.5..=1.0
+        // FeatureTableSubstitution
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, //
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x01, // Feature, lookup 1
+        // LookupList
+        0x00, 0x02, 0x00, 0x06, 0x00, 0x1A, //
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, // Lookup 0
+        0x00, 0x01, 0x00, 0x06, 0x00, 0x01, // SingleSubstFormat1, delta 1
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x01, // Coverage
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, // Lookup 1
+        0x00, 0x01, 0x00, 0x06, 0x00, 0x02, // SingleSubstFormat1, delta 2
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x01, // Coverage
+    ];
+
+    #[test]
+    fn test_gsub_apply_rvrn() {
+        let gsub = ReadScope::new(&RVRN_GSUB_DATA)
+            .read::<LayoutTable<GSUB>>()
+            .unwrap();
+        let gsub_cache = new_layout_cache(gsub);
+        let apply = |coords: &[F2Dot14], feature_mask: GsubFeatureMask| {
+            let mut glyphs = make_direct_glyphs(&[1]);
+            gsub_apply_default(
+                &Vec::new,
+                &gsub_cache,
+                None,
+                tag::LATN,
+                None,
+                coords,
+                feature_mask,
+                4,
+                &mut glyphs,
+            )
+            .unwrap();
+            glyphs[0].glyph_index
+        };
+
+        assert_eq!(apply(&[], GsubFeatureMask::default()), 2);
+        assert_eq!(
+            apply(&[F2Dot14::from_f32(0.25)], GsubFeatureMask::default()),
+            2
+        );
+        assert_eq!(
+            apply(&[F2Dot14::from_f32(0.75)], GsubFeatureMask::default()),
+            3
+        );
+        assert_eq!(apply(&[F2Dot14::from_f32(0.75)], GsubFeatureMask::LIGA), 1);
+    }
+}
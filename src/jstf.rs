@@ -0,0 +1,556 @@
+//! Justification using the `JSTF` table.
+//!
+//! Justification priorities enable or disable `GSUB` and `GPOS` lookups to shrink or extend a
+//! line that has already been shaped. Priorities are tried in order until the line reaches the
+//! target width. If a line is still too short after the last priority, extender glyphs such as
+//! the Arabic kashida are inserted between joined letters.
+//!
+//! — <https://docs.microsoft.com/en-us/typography/opentype/spec/jstf>
+
+use tinyvec::TinyVec;
+use unicode_joining_type::{get_joining_type, JoiningType};
+
+use crate::error::ParseError;
+use crate::gpos::{gpos_apply_lookup, Info};
+use crate::gsub::{gsub_apply_lookup, GlyphOrigin, RawGlyph};
+use crate::layout::{GDEFTable, LayoutCache, GPOS, GSUB};
+use crate::tables::jstf::{JstfLangSys, JstfPriority};
+use crate::tag;
+
+/// The maximum number of extender glyphs inserted in a line.
+const MAX_EXTENDERS: usize = 256;
+
+/// The font tables and shaping of a line to justify with `jstf_apply`.
+pub struct JstfOptions<'a> {
+    /// The `GSUB` table of the font, if any.
+    pub opt_gsub_cache: Option<&'a LayoutCache<GSUB>>,
+    /// The `GPOS` table of the font, if any.
+    pub opt_gpos_cache: Option<&'a LayoutCache<GPOS>>,
+    /// The `GDEF` table of the font, if any.
+    pub opt_gdef_table: Option<&'a GDEFTable>,
+    /// The `GSUB` lookups the line was shaped with, as returned by `gsub::build_lookups`.
+    pub gsub_lookups: &'a [(usize, u32)],
+    /// The `GPOS` lookups the line was positioned with, as returned by `gsub::build_lookups`.
+    pub gpos_lookups: &'a [(usize, u32)],
+    /// Glyphs that may be inserted to extend the line, from `JstfScript`.
+    pub extender_glyphs: &'a [u16],
+    /// The width to justify the line to.
+    pub target_width: i32,
+}
+
+/// The outcome of applying `JSTF` priorities to a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Justification {
+    /// The width of the line after justification, as returned by the `measure` function.
+    pub width: i32,
+    /// The number of priorities that were applied.
+    pub priorities_applied: usize,
+    /// The number of extender glyphs that were inserted.
+    pub extenders_inserted: usize,
+}
+
+/// Justify the shaped and positioned glyphs in `infos` to `options.target_width` with the
+/// priorities of `jstf_lang_sys`.
+///
+/// The line is shrunk if it's wider than the target width, otherwise it is extended. `glyphs`
+/// are the glyphs of the line before substitution and `measure` returns the width of a line.
+///
+/// Each priority adds to the lookups enabled and disabled by the priorities before it. The line
+/// is then shaped again and measured: `GSUB` lookups are applied to the glyphs in `infos`, or to
+/// `glyphs` with `options.gsub_lookups` if any have been disabled, and the result is positioned
+/// with `options.gpos_lookups`. Enabled lookups are applied after the lookups the line was
+/// shaped with, and are skipped if the corresponding cache is `None`. Note that shaping `glyphs`
+/// again does not repeat script specific processing, such as choosing Arabic joining forms.
+///
+/// If the `GPOS` lookups of a priority adjust the line further than the lookups of its `JstfMax`
+/// table, the `JstfMax` lookups are used instead.
+///
+/// If the line is still too short after the last priority, the first of
+/// `options.extender_glyphs` is inserted between joined letters, one at a time, until the line
+/// reaches the target width or 256 extenders have been inserted.
+pub fn jstf_apply(
+    options: &JstfOptions<'_>,
+    jstf_lang_sys: &JstfLangSys,
+    glyphs: &[RawGlyph<()>],
+    measure: impl Fn(&[Info]) -> i32,
+    infos: &mut Vec<Info>,
+) -> Result<Justification, ParseError> {
+    let mut justification = Justification {
+        width: measure(infos),
+        priorities_applied: 0,
+        extenders_inserted: 0,
+    };
+    let target_width = options.target_width;
+    if justification.width == target_width {
+        return Ok(justification);
+    }
+    let shrink = justification.width > target_width;
+    let reached = |width: i32| {
+        if shrink {
+            width <= target_width
+        } else {
+            width >= target_width
+        }
+    };
+
+    let substituted = infos
+        .iter()
+        .map(|info| info.glyph.clone())
+        .collect::<Vec<_>>();
+    let mut line = substituted.clone();
+    let mut line_mods = LineMods::default();
+    for priority in &jstf_lang_sys.jstf_priorities {
+        let mods = PriorityMods::new(priority, shrink);
+        let mut next_mods = line_mods.clone();
+        next_mods.enabled_gsub.extend_from_slice(mods.enable_gsub);
+        next_mods.disabled_gsub.extend_from_slice(mods.disable_gsub);
+        next_mods.disabled_gpos.extend_from_slice(mods.disable_gpos);
+        let opt_max_mods = mods.jstf_max.map(|jstf_max| {
+            let mut max_mods = next_mods.clone();
+            max_mods.enable_all_gpos(jstf_max);
+            max_mods
+        });
+        if let Some(gpos_cache) = options.opt_gpos_cache {
+            next_mods.enabled_gpos.extend(
+                mods.enable_gpos
+                    .iter()
+                    .map(|&lookup_index| (gpos_cache, usize::from(lookup_index))),
+            );
+        }
+
+        let next_line = substitute(options, &next_mods, glyphs, &substituted)?;
+        let mut next_infos = position(options, &next_mods, next_line.clone())?;
+        let mut width = measure(&next_infos);
+        if let Some(max_mods) = opt_max_mods {
+            let max_infos = position(options, &max_mods, next_line.clone())?;
+            let max_width = measure(&max_infos);
+            if (shrink && width < max_width) || (!shrink && width > max_width) {
+                next_mods = max_mods;
+                next_infos = max_infos;
+                width = max_width;
+            }
+        }
+
+        line = next_line;
+        line_mods = next_mods;
+        *infos = next_infos;
+        justification.width = width;
+        justification.priorities_applied += 1;
+        if reached(width) {
+            return Ok(justification);
+        }
+    }
+
+    if let (false, Some(&extender)) = (shrink, options.extender_glyphs.first()) {
+        let positions = extender_positions(&line);
+        let mut counts = vec![0; positions.len()];
+        let mut next = 0;
+        while !positions.is_empty()
+            && !reached(justification.width)
+            && justification.extenders_inserted < MAX_EXTENDERS
+        {
+            counts[next] += 1;
+            let extended = insert_extenders(&line, &positions, &counts, extender);
+            let extended_infos = position(options, &line_mods, extended)?;
+            let width = measure(&extended_infos);
+            if width <= justification.width {
+                // The extender does not make the line any wider
+                break;
+            }
+            *infos = extended_infos;
+            justification.width = width;
+            justification.extenders_inserted += 1;
+            next = (next + 1) % positions.len();
+        }
+    }
+
+    Ok(justification)
+}
+
+/// The lookups of a priority that apply in one direction.
+struct PriorityMods<'a> {
+    enable_gsub: &'a [u16],
+    disable_gsub: &'a [u16],
+    enable_gpos: &'a [u16],
+    disable_gpos: &'a [u16],
+    jstf_max: Option<&'a LayoutCache<GPOS>>,
+}
+
+impl<'a> PriorityMods<'a> {
+    fn new(priority: &'a JstfPriority, shrink: bool) -> Self {
+        if shrink {
+            PriorityMods {
+                enable_gsub: &priority.shrinkage_enable_gsub,
+                disable_gsub: &priority.shrinkage_disable_gsub,
+                enable_gpos: &priority.shrinkage_enable_gpos,
+                disable_gpos: &priority.shrinkage_disable_gpos,
+                jstf_max: priority.shrinkage_jstf_max.as_ref(),
+            }
+        } else {
+            PriorityMods {
+                enable_gsub: &priority.extension_enable_gsub,
+                disable_gsub: &priority.extension_disable_gsub,
+                enable_gpos: &priority.extension_enable_gpos,
+                disable_gpos: &priority.extension_disable_gpos,
+                jstf_max: priority.extension_jstf_max.as_ref(),
+            }
+        }
+    }
+}
+
+/// The lookups enabled and disabled by the priorities applied to a line.
+#[derive(Clone, Default)]
+struct LineMods<'a> {
+    enabled_gsub: Vec<u16>,
+    disabled_gsub: Vec<u16>,
+    /// Enabled `GPOS` lookups, with the cache that holds them.
+    enabled_gpos: Vec<(&'a LayoutCache<GPOS>, usize)>,
+    disabled_gpos: Vec<u16>,
+}
+
+impl<'a> LineMods<'a> {
+    fn enable_all_gpos(&mut self, gpos_cache: &'a LayoutCache<GPOS>) {
+        let lookup_count = gpos_cache
+            .layout_table
+            .opt_lookup_list
+            .as_ref()
+            .map_or(0, |lookup_list| lookup_list.lookup_count());
+        self.enabled_gpos
+            .extend((0..lookup_count).map(|lookup_index| (gpos_cache, lookup_index)));
+    }
+}
+
+fn is_disabled(disabled: &[u16], lookup_index: usize) -> bool {
+    disabled
+        .iter()
+        .any(|&disabled| usize::from(disabled) == lookup_index)
+}
+
+/// Apply the `GSUB` lookups of `mods`, to `substituted` or, if `GSUB` lookups have been
+/// disabled, to `glyphs`.
+fn substitute(
+    options: &JstfOptions<'_>,
+    mods: &LineMods<'_>,
+    glyphs: &[RawGlyph<()>],
+    substituted: &[RawGlyph<()>],
+) -> Result<Vec<RawGlyph<()>>, ParseError> {
+    let gsub_cache = match options.opt_gsub_cache {
+        Some(gsub_cache) => gsub_cache,
+        None => return Ok(substituted.to_vec()),
+    };
+    let mut line = if mods.disabled_gsub.is_empty() {
+        substituted.to_vec()
+    } else {
+        let mut line = glyphs.to_vec();
+        for &(lookup_index, feature_tag) in options.gsub_lookups {
+            if !is_disabled(&mods.disabled_gsub, lookup_index) {
+                substitute_lookup(options, gsub_cache, lookup_index, feature_tag, &mut line)?;
+            }
+        }
+        line
+    };
+    for &lookup_index in &mods.enabled_gsub {
+        substitute_lookup(
+            options,
+            gsub_cache,
+            usize::from(lookup_index),
+            tag::JSTF,
+            &mut line,
+        )?;
+    }
+    Ok(line)
+}
+
+fn substitute_lookup(
+    options: &JstfOptions<'_>,
+    gsub_cache: &LayoutCache<GSUB>,
+    lookup_index: usize,
+    feature_tag: u32,
+    glyphs: &mut Vec<RawGlyph<()>>,
+) -> Result<(), ParseError> {
+    let length = glyphs.len();
+    gsub_apply_lookup(
+        gsub_cache,
+        &gsub_cache.layout_table,
+        options.opt_gdef_table,
+        lookup_index,
+        feature_tag,
+        None,
+        glyphs,
+        0,
+        length,
+        |_| true,
+    )?;
+    Ok(())
+}
+
+/// Position `glyphs` with the `GPOS` lookups of `mods`.
+fn position(
+    options: &JstfOptions<'_>,
+    mods: &LineMods<'_>,
+    glyphs: Vec<RawGlyph<()>>,
+) -> Result<Vec<Info>, ParseError> {
+    let mut infos = Info::init_from_glyphs(options.opt_gdef_table, glyphs)?;
+    if let Some(gpos_cache) = options.opt_gpos_cache {
+        for &(lookup_index, _feature_tag) in options.gpos_lookups {
+            if !is_disabled(&mods.disabled_gpos, lookup_index) {
+                gpos_apply_lookup(
+                    gpos_cache,
+                    &gpos_cache.layout_table,
+                    options.opt_gdef_table,
+                    lookup_index,
+                    &mut infos,
+                )?;
+            }
+        }
+    }
+    for &(gpos_cache, lookup_index) in &mods.enabled_gpos {
+        gpos_apply_lookup(
+            gpos_cache,
+            &gpos_cache.layout_table,
+            options.opt_gdef_table,
+            lookup_index,
+            &mut infos,
+        )?;
+    }
+    Ok(infos)
+}
+
+/// Returns the indices in `glyphs` where an extender can be inserted, which are before the
+/// letters that join the letter before them.
+///
+/// Transparent glyphs, such as marks, are skipped so that an extender is inserted after the
+/// marks of the preceding letter.
+fn extender_positions(glyphs: &[RawGlyph<()>]) -> Vec<usize> {
+    let mut positions = Vec::new();
+    let mut prev_joins = false;
+    for (i, glyph) in glyphs.iter().enumerate() {
+        let (first, last) = match (glyph.unicodes.first(), glyph.unicodes.last()) {
+            (Some(&first), Some(&last)) => (get_joining_type(first), get_joining_type(last)),
+            _ => {
+                prev_joins = false;
+                continue;
+            }
+        };
+        if first == JoiningType::Transparent {
+            continue;
+        }
+        if prev_joins && is_right_joining(first) {
+            positions.push(i);
+        }
+        prev_joins = is_left_joining(last);
+    }
+    positions
+}
+
+fn is_left_joining(joining_type: JoiningType) -> bool {
+    joining_type == JoiningType::LeftJoining
+        || joining_type == JoiningType::DualJoining
+        || joining_type == JoiningType::JoinCausing
+}
+
+fn is_right_joining(joining_type: JoiningType) -> bool {
+    joining_type == JoiningType::RightJoining
+        || joining_type == JoiningType::DualJoining
+        || joining_type == JoiningType::JoinCausing
+}
+
+/// Returns `glyphs` with `counts[i]` copies of `extender` inserted at each of `positions`.
+fn insert_extenders(
+    glyphs: &[RawGlyph<()>],
+    positions: &[usize],
+    counts: &[usize],
+    extender: u16,
+) -> Vec<RawGlyph<()>> {
+    let mut extended = Vec::with_capacity(glyphs.len() + counts.iter().sum::<usize>());
+    for (i, glyph) in glyphs.iter().enumerate() {
+        if let Ok(index) = positions.binary_search(&i) {
+            for _ in 0..counts[index] {
+                extended.push(extender_glyph(extender));
+            }
+        }
+        extended.push(glyph.clone());
+    }
+    extended
+}
+
+fn extender_glyph(glyph_index: u16) -> RawGlyph<()> {
+    RawGlyph {
+        unicodes: TinyVec::new(),
+        glyph_index,
+        liga_component_pos: 0,
+        glyph_origin: GlyphOrigin::Direct,
+        small_caps: false,
+        multi_subst_dup: false,
+        is_vert_alt: false,
+        fake_bold: false,
+        fake_italic: false,
+        variation: None,
+        extra_data: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::layout::{new_layout_cache, LayoutTable, LayoutTableType};
+    use crate::tests::{make_direct_glyphs, push_u16};
+    use tinyvec::tiny_vec;
+
+    /// Make a layout table with no scripts or features, and a lookup for each of `lookups`,
+    /// given by its type and the data of its only subtable.
+    fn make_layout_cache<T: LayoutTableType>(lookups: &[(u16, &[u16])]) -> LayoutCache<T> {
+        let mut data = Vec::new();
+        push_u16(&mut data, &[1, 0, 0, 0, 10]);
+        // LookupList
+        push_u16(&mut data, &[lookups.len() as u16]);
+        let mut offset = 2 + 2 * lookups.len();
+        for (_, subtable) in lookups {
+            push_u16(&mut data, &[offset as u16]);
+            offset += 8 + 2 * subtable.len();
+        }
+        for (lookup_type, subtable) in lookups {
+            push_u16(&mut data, &[*lookup_type, 0, 1, 8]);
+            push_u16(&mut data, subtable);
+        }
+        let layout_table = ReadScope::new(&data).read::<LayoutTable<T>>().unwrap();
+        new_layout_cache(layout_table)
+    }
+
+    /// A `SingleSubstFormat1` subtable that adds `delta` to `glyph`.
+    fn single_subst(glyph: u16, delta: u16) -> [u16; 6] {
+        [1, 6, delta, 1, 1, glyph]
+    }
+
+    /// A `SinglePosFormat1` subtable that adds `x_advance` to the glyphs `first..=last`.
+    fn single_pos(first: u16, last: u16, x_advance: i16) -> [u16; 9] {
+        [1, 8, 4, x_advance as u16, 2, 1, first, last, 0]
+    }
+
+    /// Glyph 3 is an alef, which only joins the letter before it, the others are behs.
+    fn make_glyphs(glyph_indices: &[u16]) -> Vec<RawGlyph<()>> {
+        let mut glyphs = make_direct_glyphs(glyph_indices);
+        for glyph in &mut glyphs {
+            let ch = if glyph.glyph_index == 3 {
+                '\u{0627}'
+            } else {
+                '\u{0628}'
+            };
+            glyph.unicodes = tiny_vec![[char; 1] => ch];
+        }
+        glyphs
+    }
+
+    fn measure(infos: &[Info]) -> i32 {
+        infos
+            .iter()
+            .map(|info| match info.glyph.glyph_index {
+                13 => 60,
+                _ => 100,
+            })
+            .sum::<i32>()
+            + infos
+                .iter()
+                .map(|info| i32::from(info.kerning))
+                .sum::<i32>()
+    }
+
+    fn glyph_indices(infos: &[Info]) -> Vec<u16> {
+        infos.iter().map(|info| info.glyph.glyph_index).collect()
+    }
+
+    #[test]
+    fn test_jstf_apply() {
+        let gsub_cache =
+            make_layout_cache::<GSUB>(&[(1, &single_subst(1, 1)), (1, &single_subst(3, 10))]);
+        let gpos_cache = make_layout_cache::<GPOS>(&[
+            (1, &single_pos(2, 2, 50)),
+            (1, &single_pos(1, 13, -20)),
+            (1, &single_pos(1, 6, 100)),
+        ]);
+        let jstf_max = make_layout_cache::<GPOS>(&[(1, &single_pos(1, 6, 30))]);
+        let jstf_lang_sys = JstfLangSys {
+            jstf_priorities: vec![
+                JstfPriority {
+                    shrinkage_disable_gpos: vec![0],
+                    extension_enable_gpos: vec![2],
+                    extension_jstf_max: Some(jstf_max),
+                    ..JstfPriority::default()
+                },
+                JstfPriority {
+                    shrinkage_enable_gsub: vec![1],
+                    extension_disable_gsub: vec![0],
+                    ..JstfPriority::default()
+                },
+                JstfPriority {
+                    shrinkage_enable_gpos: vec![1],
+                    ..JstfPriority::default()
+                },
+            ],
+        };
+        let glyphs = make_glyphs(&[1, 3, 3]);
+        let mut options = JstfOptions {
+            opt_gsub_cache: Some(&gsub_cache),
+            opt_gpos_cache: Some(&gpos_cache),
+            opt_gdef_table: None,
+            gsub_lookups: &[(0, tag::LIGA)],
+            gpos_lookups: &[(0, tag::KERN)],
+            extender_glyphs: &[],
+            target_width: 350,
+        };
+        let justify = |options: &JstfOptions<'_>| {
+            let mut infos = Info::init_from_glyphs(None, make_glyphs(&[2, 3, 3])).unwrap();
+            gpos_apply_lookup(&gpos_cache, &gpos_cache.layout_table, None, 0, &mut infos).unwrap();
+            let justification =
+                jstf_apply(options, &jstf_lang_sys, &glyphs, measure, &mut infos).unwrap();
+            (justification, infos)
+        };
+        let justification = |width, priorities_applied, extenders_inserted| Justification {
+            width,
+            priorities_applied,
+            extenders_inserted,
+        };
+
+        let (result, infos) = justify(&options);
+        assert_eq!(result, justification(350, 0, 0));
+        assert_eq!(infos[0].kerning, 50);
+
+        // Disabling the kerning lookup is enough
+        options.target_width = 300;
+        let (result, infos) = justify(&options);
+        assert_eq!(result, justification(300, 1, 0));
+        assert_eq!(infos[0].kerning, 0);
+
+        options.target_width = 250;
+        let (result, infos) = justify(&options);
+        assert_eq!(result, justification(220, 2, 0));
+        assert_eq!(glyph_indices(&infos), vec![2, 13, 13]);
+
+        options.target_width = 100;
+        let (result, infos) = justify(&options);
+        assert_eq!(result, justification(160, 3, 0));
+        assert_eq!(glyph_indices(&infos), vec![2, 13, 13]);
+        assert_eq!(infos[1].kerning, -20);
+
+        // The first priority is limited by its JstfMax lookups, and the second one shapes the
+        // line again without the first GSUB lookup
+        options.target_width = 700;
+        let (result, infos) = justify(&options);
+        assert_eq!(result, justification(390, 3, 0));
+        assert_eq!(glyph_indices(&infos), vec![1, 3, 3]);
+        assert_eq!(infos[0].kerning, 30);
+
+        // Extenders are inserted between the beh and the alef
+        options.extender_glyphs = &[7];
+        let (result, infos) = justify(&options);
+        assert_eq!(result, justification(790, 3, 4));
+        assert_eq!(glyph_indices(&infos), vec![1, 7, 7, 7, 7, 3, 3]);
+
+        // The number of extenders is limited
+        options.target_width = 100_000;
+        let (result, infos) = justify(&options);
+        assert_eq!(result.extenders_inserted, MAX_EXTENDERS);
+        assert_eq!(infos.len(), 3 + MAX_EXTENDERS);
+    }
+}
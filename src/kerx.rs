@@ -0,0 +1,377 @@
+//! Apple Advanced Typography extended kerning (`kerx`) implementation.
+//!
+//! `kerx` is used in place of `GPOS` by fonts that only carry AAT layout tables. Kerning values
+//! are applied to the `kerning` of `gpos::Info` and attachments to its `placement` or
+//! `mark_placement`.
+//!
+//! — <https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6kerx.html>
+
+use crate::error::ParseError;
+use crate::gpos::{Info, MarkPlacement};
+use crate::layout::Anchor;
+use crate::tables::aat::{ExtendedStateTable, CLASS_END_OF_TEXT, STATE_START_OF_TEXT};
+use crate::tables::ankr::AnkrTable;
+use crate::tables::kerx::{
+    AttachmentAction, KerxData, KerxEntry, KerxFormat1, KerxFormat4, KerxSubtable, KerxTable,
+};
+
+/// The depth of the glyph stack of format 1 subtables.
+const KERNING_STACK_DEPTH: usize = 8;
+
+// Flags common to all state table entries
+const DONT_ADVANCE: u16 = 0x4000;
+
+// Format 1 entry flags
+const PUSH: u16 = 0x8000;
+const RESET: u16 = 0x2000;
+
+// Format 4 entry flags
+const SET_MARK: u16 = 0x8000;
+
+const NO_ACTION: u16 = 0xFFFF;
+
+/// Apply the `kerx` table to the glyphs in `infos`.
+///
+/// Kerning subtables are only applied when `kerning` is `true`, attachment subtables always are.
+/// Anchor point attachments require `opt_ankr_table`. Attachments to outline control points are
+/// not supported and ignored, as are subtables that only apply to vertical text.
+///
+/// The `kerx` table is intended for fonts without a `GPOS` table and should not be applied in
+/// addition to `GPOS`.
+pub fn kerx_apply(
+    kerx_table: &KerxTable<'_>,
+    opt_ankr_table: Option<&AnkrTable<'_>>,
+    kerning: bool,
+    infos: &mut [Info],
+) -> Result<(), ParseError> {
+    for subtable in &kerx_table.subtables {
+        if subtable.coverage & KerxSubtable::VERTICAL != 0 {
+            continue;
+        }
+        let cross_stream = subtable.coverage & KerxSubtable::CROSS_STREAM != 0;
+        match subtable.data {
+            KerxData::Format0(_) | KerxData::Format2(_) | KerxData::Format6(_) => {
+                if kerning {
+                    pair_kerning(subtable, cross_stream, infos)?;
+                }
+            }
+            KerxData::Format1(ref format1) => {
+                if kerning {
+                    state_kerning(format1, subtable.tuple_count, cross_stream, infos)?;
+                }
+            }
+            KerxData::Format4(ref format4) => attachment(format4, opt_ankr_table, infos)?,
+            KerxData::Unsupported(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Apply a kerning value to the glyph at `index`, moving it and all following glyphs.
+fn apply_kerning(infos: &mut [Info], index: usize, value: i16, cross_stream: bool) {
+    if cross_stream {
+        infos[index].placement.combine_distance(0, i32::from(value));
+    } else if index > 0 {
+        infos[index - 1].kerning = infos[index - 1].kerning.saturating_add(value);
+    }
+}
+
+fn pair_kerning(
+    subtable: &KerxSubtable<'_>,
+    cross_stream: bool,
+    infos: &mut [Info],
+) -> Result<(), ParseError> {
+    let mut opt_prev: Option<usize> = None;
+    for i in 0..infos.len() {
+        if is_mark(&infos[i]) {
+            continue;
+        }
+        if let Some(prev) = opt_prev {
+            if let Some(value) =
+                subtable.kerning(infos[prev].glyph.glyph_index, infos[i].glyph.glyph_index)?
+            {
+                if cross_stream {
+                    apply_kerning(infos, i, value, true);
+                } else {
+                    infos[prev].kerning = infos[prev].kerning.saturating_add(value);
+                }
+            }
+        }
+        opt_prev = Some(i);
+    }
+    Ok(())
+}
+
+/// Run a state table over `infos`.
+///
+/// `transition` returns the new state and whether the current glyph should be processed again.
+fn drive<F>(
+    state_table: &ExtendedStateTable<'_>,
+    infos: &mut [Info],
+    mut transition: F,
+) -> Result<(), ParseError>
+where
+    F: FnMut(&mut [Info], usize, u16, u16) -> Result<(u16, bool), ParseError>,
+{
+    // Guard against state tables that never advance
+    let mut max_ops = (infos.len() * 64).max(16384);
+    let mut state = STATE_START_OF_TEXT;
+    let mut i = 0;
+    loop {
+        let class = match infos.get(i) {
+            Some(info) => state_table.class(info.glyph.glyph_index)?,
+            None => CLASS_END_OF_TEXT,
+        };
+        let (new_state, dont_advance) = transition(infos, i, state, class)?;
+        state = new_state;
+        if i >= infos.len() || max_ops == 0 {
+            break;
+        }
+        max_ops -= 1;
+        if !dont_advance {
+            i += 1;
+        }
+    }
+    Ok(())
+}
+
+fn state_kerning(
+    format1: &KerxFormat1<'_>,
+    tuple_count: u32,
+    cross_stream: bool,
+    infos: &mut [Info],
+) -> Result<(), ParseError> {
+    // Only the default value of each tuple is used
+    let stride = tuple_count.max(1) as usize;
+    let mut stack = [0; KERNING_STACK_DEPTH];
+    let mut depth = 0;
+    drive(&format1.state_table, infos, |infos, i, state, class| {
+        let entry = format1.state_table.entry::<KerxEntry>(state, class)?;
+        if entry.flags & RESET != 0 {
+            depth = 0;
+        }
+        if entry.flags & PUSH != 0 {
+            if depth < KERNING_STACK_DEPTH {
+                stack[depth] = i;
+                depth += 1;
+            } else {
+                depth = 0;
+            }
+        }
+        if entry.value_index != NO_ACTION {
+            // Each value pops a glyph from the stack, the last value is marked by being odd
+            let mut value_index = usize::from(entry.value_index);
+            while depth > 0 {
+                depth -= 1;
+                let index = stack[depth];
+                let value = format1.value(value_index)?;
+                value_index += stride;
+                if index >= infos.len() {
+                    continue;
+                }
+                apply_kerning(infos, index, value & !1, cross_stream);
+                if value & 1 != 0 {
+                    break;
+                }
+            }
+        }
+        Ok((entry.new_state, entry.flags & DONT_ADVANCE != 0))
+    })
+}
+
+fn attachment(
+    format4: &KerxFormat4<'_>,
+    opt_ankr_table: Option<&AnkrTable<'_>>,
+    infos: &mut [Info],
+) -> Result<(), ParseError> {
+    let mut opt_mark: Option<usize> = None;
+    drive(&format4.state_table, infos, |infos, i, state, class| {
+        let entry = format4.state_table.entry::<KerxEntry>(state, class)?;
+        if let Some(mark) = opt_mark {
+            if entry.value_index != NO_ACTION && mark < i && i < infos.len() {
+                let opt_anchors = match format4.action(entry.value_index)? {
+                    AttachmentAction::AnchorPoints(mark_point, current_point) => {
+                        match opt_ankr_table {
+                            Some(ankr_table) => {
+                                let mark_glyph = infos[mark].glyph.glyph_index;
+                                let current_glyph = infos[i].glyph.glyph_index;
+                                match (
+                                    ankr_table.anchor(mark_glyph, mark_point)?,
+                                    ankr_table.anchor(current_glyph, current_point)?,
+                                ) {
+                                    (Some(mark_anchor), Some(current_anchor)) => {
+                                        Some((mark_anchor, current_anchor))
+                                    }
+                                    _ => None,
+                                }
+                            }
+                            None => None,
+                        }
+                    }
+                    AttachmentAction::Coordinates(mark_anchor, current_anchor) => {
+                        Some((mark_anchor, current_anchor))
+                    }
+                    AttachmentAction::ControlPoints(_, _) => None,
+                };
+                if let Some((mark_anchor, current_anchor)) = opt_anchors {
+                    attach(infos, mark, i, mark_anchor, current_anchor);
+                }
+            }
+        }
+        if entry.flags & SET_MARK != 0 {
+            opt_mark = Some(i);
+        }
+        Ok((entry.new_state, entry.flags & DONT_ADVANCE != 0))
+    })
+}
+
+/// Attach the glyph at `current` to the glyph at `mark`, aligning their anchors.
+///
+/// A base glyph attached to the base glyph before it is a cursive attachment, which is recorded
+/// in the `placement` of the preceding glyph as for `GPOS` cursive attachment. Any other
+/// attachment is treated as a mark attachment.
+fn attach(
+    infos: &mut [Info],
+    mark: usize,
+    current: usize,
+    mark_anchor: Anchor,
+    current_anchor: Anchor,
+) {
+    if mark + 1 == current && !is_mark(&infos[mark]) && !is_mark(&infos[current]) {
+        infos[mark]
+            .placement
+            .combine_anchor(current_anchor, mark_anchor);
+    } else {
+        infos[current].mark_placement =
+            MarkPlacement::MarkAnchor(mark, mark_anchor, current_anchor);
+    }
+}
+
+/// Returns `true` if the glyph is a mark, or has been attached to another glyph as a mark.
+fn is_mark(info: &Info) -> bool {
+    match info.mark_placement {
+        MarkPlacement::None => info.is_mark,
+        MarkPlacement::MarkAnchor(..) | MarkPlacement::MarkOverprint(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::gpos::Placement;
+    use crate::tests::{make_direct_glyphs, push_u16, push_u32};
+
+    fn make_infos(glyph_indices: &[u16]) -> Vec<Info> {
+        Info::init_from_glyphs(None, make_direct_glyphs(glyph_indices)).unwrap()
+    }
+
+    /// Wrap a state table subtable in a `kerx` table. The state table has one state and five
+    /// classes, where glyph 10 has class 4.
+    ///
+    /// `entries` are the entries for each class and are followed by `values` at offset 0x48 of
+    /// the state table header.
+    fn make_kerx(
+        format: u32,
+        flags: u32,
+        entries: &[(u16, u16, u16); 5],
+        values: &[u8],
+    ) -> Vec<u8> {
+        let mut subtable = Vec::new();
+        // State table header: class count, class table, state array and entry table offsets
+        push_u32(&mut subtable, &[5, 0x14, 0x20, 0x2A, flags]);
+        // Class table: format 8, glyph 10 has class 4
+        push_u16(&mut subtable, &[8, 10, 1, 4, 0, 0]);
+        // State array: one state, using the entry with the same index as the class
+        push_u16(&mut subtable, &[0, 1, 2, 3, 4]);
+        for &(new_state, flags, value_index) in entries {
+            push_u16(&mut subtable, &[new_state, flags, value_index]);
+        }
+        subtable.extend_from_slice(values);
+
+        let mut data = Vec::new();
+        push_u16(&mut data, &[2, 0]);
+        push_u32(&mut data, &[1]);
+        // Subtable header: length, coverage, tuple count
+        push_u32(&mut data, &[12 + subtable.len() as u32, format, 0]);
+        data.extend_from_slice(&subtable);
+        data
+    }
+
+    #[test]
+    fn test_state_kerning() {
+        // Push each glyph 10 and, at the end of text, kern the last two glyphs pushed. The
+        // second value is odd to end the list of values.
+        let entries = [
+            (0, 0, 0),
+            (0, 0, NO_ACTION),
+            (0, 0, NO_ACTION),
+            (0, 0, NO_ACTION),
+            (0, PUSH, NO_ACTION),
+        ];
+        let mut values = Vec::new();
+        push_u16(&mut values, &[-20i16 as u16, -29i16 as u16]);
+        let data = make_kerx(1, 0x48, &entries, &values);
+        let kerx = ReadScope::new(&data).read::<KerxTable<'_>>().unwrap();
+
+        let mut infos = make_infos(&[10, 10, 10]);
+        kerx_apply(&kerx, None, true, &mut infos).unwrap();
+        let kerning = infos.iter().map(|info| info.kerning).collect::<Vec<_>>();
+        assert_eq!(kerning, vec![-30, -20, 0]);
+
+        let mut infos = make_infos(&[10, 10, 10]);
+        kerx_apply(&kerx, None, false, &mut infos).unwrap();
+        assert!(infos.iter().all(|info| info.kerning == 0));
+    }
+
+    #[test]
+    fn test_attachment() {
+        // Mark each glyph 10 and attach it to the glyph marked before it
+        let entries = [
+            (0, 0, NO_ACTION),
+            (0, 0, NO_ACTION),
+            (0, 0, NO_ACTION),
+            (0, 0, NO_ACTION),
+            (0, SET_MARK, 0),
+        ];
+        let mut coordinates = Vec::new();
+        push_u16(&mut coordinates, &[100, 200, 10, 20]);
+        let flags = (u32::from(KerxFormat4::COORDINATES) << 30) | 0x48;
+        let data = make_kerx(4, flags, &entries, &coordinates);
+        let kerx = ReadScope::new(&data).read::<KerxTable<'_>>().unwrap();
+
+        let mut infos = make_infos(&[10, 10, 10]);
+        kerx_apply(&kerx, None, false, &mut infos).unwrap();
+        for info in &infos[..2] {
+            match info.placement {
+                Placement::Anchor(current, mark) => {
+                    assert_eq!((current.x, current.y), (10, 20));
+                    assert_eq!((mark.x, mark.y), (100, 200));
+                }
+                _ => panic!("expected cursive attachment"),
+            }
+        }
+        match infos[2].placement {
+            Placement::None => {}
+            _ => panic!("unexpected attachment"),
+        }
+
+        // Marks are attached as marks
+        let mut infos = make_infos(&[10, 10, 10]);
+        infos[1].is_mark = true;
+        kerx_apply(&kerx, None, false, &mut infos).unwrap();
+        match infos[1].mark_placement {
+            MarkPlacement::MarkAnchor(0, mark, current) => {
+                assert_eq!((mark.x, mark.y), (100, 200));
+                assert_eq!((current.x, current.y), (10, 20));
+            }
+            _ => panic!("expected mark attachment"),
+        }
+        // So are glyphs attached to a mark, without becoming marks themselves
+        match infos[2].mark_placement {
+            MarkPlacement::MarkAnchor(1, _, _) => {}
+            _ => panic!("expected mark attachment"),
+        }
+        assert!(!infos[2].is_mark);
+    }
+}
@@ -7,12 +7,14 @@ use crate::binary::read::{
     CheckIndex, ReadArray, ReadBinary, ReadBinaryDep, ReadCache, ReadCtxt, ReadFixedSizeDep,
     ReadFrom, ReadScope, ReadScopeOwned,
 };
-use crate::binary::U16Be;
+use crate::binary::{U16Be, U32Be};
 use crate::size;
+use crate::tables::F2Dot14;
 use crate::tag;
 use log::warn;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::marker::PhantomData;
 use std::rc::Rc;
 use std::u16;
@@ -22,10 +24,50 @@ pub enum GPOS {}
 
 pub struct GDEFTable {
     pub opt_glyph_classdef: Option<ClassDef>,
-    // pub opt_attach_list: Option<ReadScope<'a>>,
-    // pub opt_lig_caret_list: Option<ReadScope<'a>>,
+    pub opt_attach_list: Option<AttachList>,
+    pub opt_lig_caret_list: Option<LigCaretList>,
     pub opt_mark_attach_classdef: Option<ClassDef>,
-    // TODO read additional GDEF 1.2 and 1.3 fields
+    pub opt_mark_glyph_sets: Option<Vec<Coverage>>,
+    // TODO read additional GDEF 1.3 fields
+}
+
+/// Attachment point indices for glyphs, from the GDEF `AttachList`.
+pub struct AttachList {
+    coverage: Coverage,
+    attach_points: Vec<Vec<u16>>,
+}
+
+/// Ligature caret positions for glyphs, from the GDEF `LigCaretList`.
+pub struct LigCaretList {
+    coverage: Coverage,
+    lig_glyphs: Vec<Vec<CaretValue>>,
+}
+
+/// The position of a caret between two ligature components.
+#[derive(Clone)]
+pub enum CaretValue {
+    /// Format 1: a coordinate in design units.
+    Coordinate(i16),
+    /// Format 2: the index of a contour point on the ligature glyph outline.
+    ContourPoint(u16),
+    /// Format 3: a coordinate in design units, adjusted by a device or variation index table.
+    DeviceCoordinate(i16, Device),
+}
+
+/// A Device or VariationIndex table.
+#[derive(Clone)]
+pub enum Device {
+    /// Pixel adjustments for each ppem size from `start_size` to `end_size` inclusive.
+    Hinting {
+        start_size: u16,
+        end_size: u16,
+        deltas: Vec<i8>,
+    },
+    /// A reference to a delta-set in the GDEF item variation store.
+    VariationIndex {
+        delta_set_outer_index: u16,
+        delta_set_inner_index: u16,
+    },
 }
 
 // GSUB and GPOS tables have the same top-level structure
@@ -33,6 +75,7 @@ pub struct LayoutTable<T> {
     pub opt_script_list: Option<ScriptList>,
     pub opt_feature_list: Option<FeatureList>,
     pub opt_lookup_list: Option<LookupList<T>>,
+    pub opt_feature_variations: Option<FeatureVariations>,
 }
 
 pub struct ScriptList {
@@ -74,6 +117,33 @@ pub struct FeatureTable {
     pub lookup_indices: Vec<u16>,
 }
 
+/// Feature variations, present in version 1.1 of GSUB and GPOS
+///
+/// Substitutes alternate feature tables when the variation coordinates match a condition set.
+pub struct FeatureVariations {
+    feature_variation_records: Vec<FeatureVariationRecord>,
+}
+
+pub struct FeatureVariationRecord {
+    conditions: Vec<Condition>,
+    substitutions: Vec<FeatureTableSubstitutionRecord>,
+}
+
+pub enum Condition {
+    AxisRange {
+        axis_index: u16,
+        filter_range_min_value: F2Dot14,
+        filter_range_max_value: F2Dot14,
+    },
+    /// A condition with an unrecognised format, which never matches
+    Unknown(u16),
+}
+
+pub struct FeatureTableSubstitutionRecord {
+    pub feature_index: u16,
+    alternate_feature_table: FeatureTable,
+}
+
 pub struct LookupList<T> {
     scope_owned: ReadScopeOwned,
     lookup_offsets: Vec<u16>,
@@ -85,6 +155,7 @@ pub struct Lookup<'a, T: LayoutTableType> {
     lookup_type: LookupType<T>,
     pub lookup_flag: u16,
     subtable_offsets: ReadArray<'a, U16Be>,
+    pub mark_filtering_set: Option<u16>,
     phantom: PhantomData<T>,
 }
 
@@ -172,10 +243,10 @@ impl<'a> ReadBinary<'a> for GDEFTable {
 
         let major_version = ctxt.read_u16be()?;
         ctxt.check(major_version == 1)?;
-        let _minor_version = ctxt.read_u16be()?;
+        let minor_version = ctxt.read_u16be()?;
         let glyph_classdef_offset = usize::from(ctxt.read_u16be()?);
-        let _attach_list_offset = usize::from(ctxt.read_u16be()?);
-        let _lig_caret_list_offset = usize::from(ctxt.read_u16be()?);
+        let attach_list_offset = usize::from(ctxt.read_u16be()?);
+        let lig_caret_list_offset = usize::from(ctxt.read_u16be()?);
         // MarkAttachClassDef was added to GDEF in OpenType 1.2 but they did not change the GDEF
         // version. This means that it's not possible to know from the version alone whether the
         // field should be read. Some implementations use GSUB/GPOS to determine if it should be
@@ -185,6 +256,11 @@ impl<'a> ReadBinary<'a> for GDEFTable {
         //
         // See: https://github.com/yeslogic/prince/issues/297 for more detail.
         let mark_attach_classdef_offset = usize::from(ctxt.read_u16be()?);
+        let mark_glyph_sets_def_offset = if minor_version >= 2 {
+            usize::from(ctxt.read_u16be()?)
+        } else {
+            0
+        };
 
         let gdef_header_size = 6 * size::U16;
 
@@ -196,27 +272,23 @@ impl<'a> ReadBinary<'a> for GDEFTable {
             Some(table.offset(glyph_classdef_offset).read::<ClassDef>()?)
         };
 
-        /*
-                let opt_attach_list = if attach_list_offset >= table.data().len() {
-                    return Err(ParseError::BadOffset);
-                } else if attach_list_offset == 0 {
-                    None
-                } else if attach_list_offset < gdef_header_size {
-                    return Err(ParseError::BadOffset);
-                } else {
-                    Some(table.offset(attach_list_offset))
-                };
+        // The attachment point and ligature caret lists are not used for layout, so if they are
+        // malformed they are ignored instead of failing to read the whole table.
+        let opt_attach_list = if attach_list_offset < gdef_header_size {
+            None
+        } else {
+            table.offset(attach_list_offset).read::<AttachList>().ok()
+        };
+
+        let opt_lig_caret_list = if lig_caret_list_offset < gdef_header_size {
+            None
+        } else {
+            table
+                .offset(lig_caret_list_offset)
+                .read::<LigCaretList>()
+                .ok()
+        };
 
-                let opt_lig_caret_list = if lig_caret_list_offset >= table.data().len() {
-                    return Err(ParseError::BadOffset);
-                } else if lig_caret_list_offset == 0 {
-                    None
-                } else if lig_caret_list_offset < gdef_header_size {
-                    return Err(ParseError::BadOffset);
-                } else {
-                    Some(table.offset(lig_caret_list_offset))
-                };
-        */
         let opt_mark_attach_classdef = if mark_attach_classdef_offset == 0 {
             None
         } else if mark_attach_classdef_offset < gdef_header_size {
@@ -229,29 +301,230 @@ impl<'a> ReadBinary<'a> for GDEFTable {
             )
         };
 
+        let opt_mark_glyph_sets = if mark_glyph_sets_def_offset == 0 {
+            None
+        } else {
+            let mark_glyph_sets_def = table.offset(mark_glyph_sets_def_offset);
+            let mut ctxt = mark_glyph_sets_def.ctxt();
+            let format = ctxt.read_u16be()?;
+            ctxt.check_version(format == 1)?;
+            let mark_glyph_set_count = usize::from(ctxt.read_u16be()?);
+            let mark_glyph_sets = ctxt
+                .read_array::<U32Be>(mark_glyph_set_count)?
+                .iter()
+                .map(|offset| {
+                    mark_glyph_sets_def
+                        .offset(usize::try_from(offset)?)
+                        .read::<Coverage>()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Some(mark_glyph_sets)
+        };
+
         Ok(GDEFTable {
             opt_glyph_classdef,
-            // opt_attach_list,
-            // opt_lig_caret_list,
+            opt_attach_list,
+            opt_lig_caret_list,
             opt_mark_attach_classdef,
+            opt_mark_glyph_sets,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for AttachList {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let coverage_offset = usize::from(ctxt.read_u16be()?);
+        let coverage = scope.offset(coverage_offset).read::<Coverage>()?;
+        let glyph_count = usize::from(ctxt.read_u16be()?);
+        let read_attach_point = |attach_point_offset| -> Result<Vec<u16>, ParseError> {
+            let mut ctxt = scope.offset(usize::from(attach_point_offset)).ctxt();
+            let point_count = usize::from(ctxt.read_u16be()?);
+            Ok(ctxt.read_array::<U16Be>(point_count)?.to_vec())
+        };
+        // A malformed entry only loses the attachment points of its own glyph
+        let attach_points = ctxt
+            .read_array::<U16Be>(glyph_count)?
+            .iter()
+            .map(|attach_point_offset| read_attach_point(attach_point_offset).unwrap_or_default())
+            .collect();
+        Ok(AttachList {
+            coverage,
+            attach_points,
+        })
+    }
+}
+
+impl AttachList {
+    /// Returns the contour point indices of the attachment points of `glyph`, if any.
+    pub fn attach_points(&self, glyph: u16) -> Option<&[u16]> {
+        self.coverage
+            .glyph_coverage_value(glyph)
+            .and_then(|index| self.attach_points.get(usize::from(index)))
+            .map(Vec::as_slice)
+    }
+}
+
+impl<'a> ReadBinary<'a> for LigCaretList {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let coverage_offset = usize::from(ctxt.read_u16be()?);
+        let coverage = scope.offset(coverage_offset).read::<Coverage>()?;
+        let lig_glyph_count = usize::from(ctxt.read_u16be()?);
+        let read_lig_glyph = |lig_glyph_offset| -> Result<Vec<CaretValue>, ParseError> {
+            let lig_glyph = scope.offset(usize::from(lig_glyph_offset));
+            let mut ctxt = lig_glyph.ctxt();
+            let caret_count = usize::from(ctxt.read_u16be()?);
+            ctxt.read_array::<U16Be>(caret_count)?
+                .iter()
+                .map(|caret_value_offset| {
+                    lig_glyph
+                        .offset(usize::from(caret_value_offset))
+                        .read::<CaretValue>()
+                })
+                .collect()
+        };
+        // A malformed entry only loses the carets of its own ligature. Carets can't be skipped
+        // individually as their position in the list determines the components they separate.
+        let lig_glyphs = ctxt
+            .read_array::<U16Be>(lig_glyph_count)?
+            .iter()
+            .map(|lig_glyph_offset| read_lig_glyph(lig_glyph_offset).unwrap_or_default())
+            .collect();
+        Ok(LigCaretList {
+            coverage,
+            lig_glyphs,
+        })
+    }
+}
+
+impl LigCaretList {
+    /// Returns the carets of the ligature `glyph`, in increasing order of position.
+    ///
+    /// A ligature of `n` components has `n - 1` carets, the caret at index `i` separates
+    /// components `i` and `i + 1`.
+    pub fn ligature_carets(&self, glyph: u16) -> Option<&[CaretValue]> {
+        self.coverage
+            .glyph_coverage_value(glyph)
+            .and_then(|index| self.lig_glyphs.get(usize::from(index)))
+            .map(Vec::as_slice)
+    }
+}
+
+impl<'a> ReadBinary<'a> for CaretValue {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        match ctxt.read_u16be()? {
+            1 => Ok(CaretValue::Coordinate(ctxt.read_i16be()?)),
+            2 => Ok(CaretValue::ContourPoint(ctxt.read_u16be()?)),
+            3 => {
+                let coordinate = ctxt.read_i16be()?;
+                let device_offset = usize::from(ctxt.read_u16be()?);
+                let device = scope.offset(device_offset).read::<Device>()?;
+                Ok(CaretValue::DeviceCoordinate(coordinate, device))
+            }
+            _ => Err(ParseError::BadVersion),
+        }
+    }
+}
+
+impl CaretValue {
+    /// Returns the coordinate of the caret in design units, without any device adjustment.
+    ///
+    /// Returns `None` for contour point carets, which need the glyph outline to be resolved.
+    pub fn coordinate(&self) -> Option<i16> {
+        match *self {
+            CaretValue::Coordinate(coordinate) | CaretValue::DeviceCoordinate(coordinate, _) => {
+                Some(coordinate)
+            }
+            CaretValue::ContourPoint(_) => None,
+        }
+    }
+}
+
+impl<'a> ReadBinary<'a> for Device {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let start_size = ctxt.read_u16be()?;
+        let end_size = ctxt.read_u16be()?;
+        let delta_format = ctxt.read_u16be()?;
+        let bits_per_delta = match delta_format {
+            1 => 2,
+            2 => 4,
+            3 => 8,
+            0x8000 => {
+                return Ok(Device::VariationIndex {
+                    delta_set_outer_index: start_size,
+                    delta_set_inner_index: end_size,
+                })
+            }
+            _ => return Err(ParseError::BadVersion),
+        };
+        ctxt.check(start_size <= end_size)?;
+        let delta_count = usize::from(end_size - start_size) + 1;
+        let deltas_per_word = 16 / bits_per_delta;
+        let word_count = (delta_count - 1) / deltas_per_word + 1;
+        let words = ctxt.read_array::<U16Be>(word_count)?;
+        let deltas = (0..delta_count)
+            .map(|i| {
+                let word = words.get_item(i / deltas_per_word);
+                // Deltas are packed from the most significant bits, and sign extended by
+                // shifting the value to the top of an i16 and back down.
+                let shift = (i % deltas_per_word) * bits_per_delta;
+                ((word << shift) as i16 >> (16 - bits_per_delta)) as i8
+            })
+            .collect();
+        Ok(Device::Hinting {
+            start_size,
+            end_size,
+            deltas,
         })
     }
 }
 
+impl Device {
+    /// Returns the pixel adjustment for the size `ppem`.
+    ///
+    /// Variation index tables never provide a pixel adjustment.
+    pub fn hinting_delta(&self, ppem: u16) -> i8 {
+        match *self {
+            Device::Hinting {
+                start_size,
+                end_size,
+                ref deltas,
+            } if ppem >= start_size && ppem <= end_size => deltas[usize::from(ppem - start_size)],
+            _ => 0,
+        }
+    }
+}
+
 impl<'a, T> ReadBinary<'a> for LayoutTable<T> {
     type HostType = Self;
 
     fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
         let table = ctxt.scope();
 
-        let version = ctxt.read_i32be()?;
+        let major_version = ctxt.read_u16be()?;
+        let minor_version = ctxt.read_u16be()?;
         let script_list_offset = usize::from(ctxt.read_u16be()?);
         let feature_list_offset = usize::from(ctxt.read_u16be()?);
         let lookup_list_offset = usize::from(ctxt.read_u16be()?);
 
-        if version != 0x10000 {
+        if major_version != 1 || minor_version > 1 {
             return Err(ParseError::BadVersion);
         }
+        let feature_variations_offset = if minor_version == 1 {
+            usize::try_from(ctxt.read_u32be()?)?
+        } else {
+            0
+        };
 
         let opt_script_list = if script_list_offset >= table.data().len() {
             return Err(ParseError::BadOffset);
@@ -277,10 +550,23 @@ impl<'a, T> ReadBinary<'a> for LayoutTable<T> {
             Some(table.offset(lookup_list_offset).read::<LookupList<T>>()?)
         };
 
+        let opt_feature_variations = if feature_variations_offset >= table.data().len() {
+            return Err(ParseError::BadOffset);
+        } else if feature_variations_offset == 0 {
+            None
+        } else {
+            Some(
+                table
+                    .offset(feature_variations_offset)
+                    .read::<FeatureVariations>()?,
+            )
+        };
+
         Ok(LayoutTable {
             opt_script_list,
             opt_feature_list,
             opt_lookup_list,
+            opt_feature_variations,
         })
     }
 }
@@ -400,6 +686,164 @@ impl<'a> ReadBinary<'a> for FeatureTable {
     }
 }
 
+impl<'a> ReadBinary<'a> for FeatureVariations {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let major_version = ctxt.read_u16be()?;
+        let _minor_version = ctxt.read_u16be()?;
+        ctxt.check_version(major_version == 1)?;
+        let feature_variation_record_count = usize::try_from(ctxt.read_u32be()?)?;
+        let feature_variation_records = ctxt
+            .read_array_dep::<FeatureVariationRecord>(feature_variation_record_count, scope)?
+            .read_to_vec()?;
+        Ok(FeatureVariations {
+            feature_variation_records,
+        })
+    }
+}
+
+impl<'a> ReadBinaryDep<'a> for FeatureVariationRecord {
+    type Args = ReadScope<'a>;
+    type HostType = FeatureVariationRecord;
+
+    fn read_dep(ctxt: &mut ReadCtxt<'a>, scope: Self::Args) -> Result<Self, ParseError> {
+        let condition_set_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let feature_table_substitution_offset = usize::try_from(ctxt.read_u32be()?)?;
+
+        // A record without a condition set matches all variation coordinates
+        let conditions = if condition_set_offset != 0 {
+            let condition_set = scope.offset(condition_set_offset);
+            let mut ctxt = condition_set.ctxt();
+            let condition_count = usize::from(ctxt.read_u16be()?);
+            ctxt.read_array::<U32Be>(condition_count)?
+                .iter()
+                .map(|offset| {
+                    condition_set
+                        .offset(usize::try_from(offset)?)
+                        .read::<Condition>()
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            Vec::new()
+        };
+
+        let substitutions = if feature_table_substitution_offset != 0 {
+            let substitution = scope.offset(feature_table_substitution_offset);
+            let mut ctxt = substitution.ctxt();
+            let major_version = ctxt.read_u16be()?;
+            let _minor_version = ctxt.read_u16be()?;
+            ctxt.check_version(major_version == 1)?;
+            let substitution_count = usize::from(ctxt.read_u16be()?);
+            ctxt.read_array_dep::<FeatureTableSubstitutionRecord>(substitution_count, substitution)?
+                .read_to_vec()?
+        } else {
+            Vec::new()
+        };
+
+        Ok(FeatureVariationRecord {
+            conditions,
+            substitutions,
+        })
+    }
+}
+
+impl<'a> ReadFixedSizeDep<'a> for FeatureVariationRecord {
+    fn size(_scope: Self::Args) -> usize {
+        2 * size::U32
+    }
+}
+
+impl<'a> ReadBinary<'a> for Condition {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let format = ctxt.read_u16be()?;
+        match format {
+            1 => {
+                let axis_index = ctxt.read_u16be()?;
+                let filter_range_min_value = ctxt.read::<F2Dot14>()?;
+                let filter_range_max_value = ctxt.read::<F2Dot14>()?;
+                Ok(Condition::AxisRange {
+                    axis_index,
+                    filter_range_min_value,
+                    filter_range_max_value,
+                })
+            }
+            _ => Ok(Condition::Unknown(format)),
+        }
+    }
+}
+
+impl<'a> ReadBinaryDep<'a> for FeatureTableSubstitutionRecord {
+    type Args = ReadScope<'a>;
+    type HostType = FeatureTableSubstitutionRecord;
+
+    fn read_dep(ctxt: &mut ReadCtxt<'a>, scope: Self::Args) -> Result<Self, ParseError> {
+        let feature_index = ctxt.read_u16be()?;
+        let alternate_feature_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let alternate_feature_table = scope
+            .offset(alternate_feature_offset)
+            .read::<FeatureTable>()?;
+        Ok(FeatureTableSubstitutionRecord {
+            feature_index,
+            alternate_feature_table,
+        })
+    }
+}
+
+impl<'a> ReadFixedSizeDep<'a> for FeatureTableSubstitutionRecord {
+    fn size(_scope: Self::Args) -> usize {
+        size::U16 + size::U32
+    }
+}
+
+impl FeatureVariations {
+    /// Find the index of the first feature variation record whose conditions match the normalized
+    /// variation coordinates `coords`.
+    pub fn find_matching_record(&self, coords: &[F2Dot14]) -> Option<usize> {
+        self.feature_variation_records
+            .iter()
+            .position(|record| record.conditions_match(coords))
+    }
+}
+
+impl FeatureVariationRecord {
+    fn conditions_match(&self, coords: &[F2Dot14]) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.matches(coords))
+    }
+
+    fn find_substitute(&self, feature_index: u16) -> Option<&FeatureTable> {
+        self.substitutions
+            .iter()
+            .find(|record| record.feature_index == feature_index)
+            .map(|record| &record.alternate_feature_table)
+    }
+}
+
+impl Condition {
+    fn matches(&self, coords: &[F2Dot14]) -> bool {
+        match *self {
+            Condition::AxisRange {
+                axis_index,
+                filter_range_min_value,
+                filter_range_max_value,
+            } => {
+                let coord = coords
+                    .get(usize::from(axis_index))
+                    .map(|&coord| f32::from(coord))
+                    .unwrap_or(0.);
+                f32::from(filter_range_min_value) <= coord
+                    && coord <= f32::from(filter_range_max_value)
+            }
+            Condition::Unknown(_) => false,
+        }
+    }
+}
+
 impl<'a, T> ReadBinary<'a> for LookupList<T> {
     type HostType = Self;
 
@@ -466,18 +910,49 @@ impl<T> LayoutTable<T> {
         langsys: &LangSys,
         feature_tag: u32,
     ) -> Result<Option<&FeatureTable>, ParseError> {
+        self.find_langsys_feature_variation(langsys, feature_tag, None)
+    }
+
+    /// Find the feature table for `feature_tag`, substituting the alternate feature table from
+    /// the feature variation record at `opt_feature_variation`, if any.
+    pub fn find_langsys_feature_variation(
+        &self,
+        langsys: &LangSys,
+        feature_tag: u32,
+        opt_feature_variation: Option<usize>,
+    ) -> Result<Option<&FeatureTable>, ParseError> {
+        let opt_record = match (opt_feature_variation, &self.opt_feature_variations) {
+            (Some(index), Some(feature_variations)) => {
+                feature_variations
+                    .feature_variation_records
+                    .check_index(index)?;
+                Some(&feature_variations.feature_variation_records[index])
+            }
+            (Some(_), None) => return Err(ParseError::BadIndex),
+            (None, _) => None,
+        };
         if let Some(ref feature_list) = self.opt_feature_list {
             for feature_index in &langsys.feature_indices {
                 let feature_record =
                     feature_list.nth_feature_record(usize::from(*feature_index))?;
                 if feature_record.feature_tag == feature_tag {
-                    return Ok(Some(&feature_record.feature_table));
+                    let substitute =
+                        opt_record.and_then(|record| record.find_substitute(*feature_index));
+                    return Ok(Some(substitute.unwrap_or(&feature_record.feature_table)));
                 }
             }
         }
         Ok(None)
     }
 
+    /// Find the index of the feature variation record that applies at the normalized variation
+    /// coordinates `coords`.
+    pub fn find_feature_variation(&self, coords: &[F2Dot14]) -> Option<usize> {
+        self.opt_feature_variations
+            .as_ref()
+            .and_then(|feature_variations| feature_variations.find_matching_record(coords))
+    }
+
     pub fn feature_by_index(&self, feature_index: u16) -> Result<&FeatureRecord, ParseError> {
         if let Some(ref feature_list) = self.opt_feature_list {
             let feature_record = feature_list.nth_feature_record(usize::from(feature_index))?;
@@ -551,6 +1026,10 @@ impl<'a> ReadFixedSizeDep<'a> for LangSysRecord {
 }
 
 impl<T: LayoutTableType> LookupList<T> {
+    pub fn lookup_count(&self) -> usize {
+        self.lookup_offsets.len()
+    }
+
     pub fn lookup(&self, lookup_index: usize) -> Result<Lookup<'_, T>, ParseError> {
         self.lookup_offsets.check_index(lookup_index)?;
         let lookup_table_offset = self.lookup_offsets[lookup_index];
@@ -613,6 +1092,7 @@ impl LookupList<GSUB> {
         };
         Ok(LookupCacheItem {
             lookup_flag,
+            mark_filtering_set: lookup.mark_filtering_set,
             lookup_subtables,
         })
     }
@@ -671,6 +1151,7 @@ impl LookupList<GPOS> {
         };
         Ok(LookupCacheItem {
             lookup_flag,
+            mark_filtering_set: lookup.mark_filtering_set,
             lookup_subtables,
         })
     }
@@ -756,11 +1237,17 @@ impl<'a, T: LayoutTableType> ReadBinary<'a> for Lookup<'a, T> {
         let lookup_flag = ctxt.read_u16be()?;
         let subtable_count = usize::from(ctxt.read_u16be()?);
         let subtable_offsets = ctxt.read_array::<U16Be>(subtable_count)?;
+        let mark_filtering_set = if LookupFlag(lookup_flag).get_use_mark_filtering_set() {
+            Some(ctxt.read_u16be()?)
+        } else {
+            None
+        };
         Ok(Lookup {
             scope,
             lookup_type,
             lookup_flag,
             subtable_offsets,
+            mark_filtering_set,
             phantom: PhantomData,
         })
     }
@@ -2937,9 +3424,13 @@ pub type LookupCache<T> = Vec<Option<Rc<LookupCacheItem<T>>>>;
 
 pub struct LookupCacheItem<T> {
     pub lookup_flag: LookupFlag,
+    pub mark_filtering_set: Option<u16>,
     pub lookup_subtables: T,
 }
 
+/// (script_tag, lang_tag, GsubFeatureMask, feature variation index)
+pub type LookupsIndexKey = (u32, u32, u32, Option<usize>);
+
 pub struct LayoutCacheData<T: LayoutTableType> {
     pub layout_table: LayoutTable<T>,
     coverages: RefCell<ReadCache<Coverage>>,
@@ -2950,8 +3441,8 @@ pub struct LayoutCacheData<T: LayoutTableType> {
     /// opt_lang_tag = None is represented as `DFLT`
     pub supported_features: RefCell<HashMap<(u32, u32), u32>>,
 
-    /// maps (script_tag, lang_tag, GsubFeatureMask) to cached_lookups index
-    pub lookups_index: RefCell<HashMap<(u32, u32, u32), usize>>,
+    /// maps LookupsIndexKey to cached_lookups index
+    pub lookups_index: RefCell<HashMap<LookupsIndexKey, usize>>,
 
     pub cached_lookups: RefCell<Vec<Vec<(usize, u32)>>>,
 }
@@ -2977,6 +3468,8 @@ pub fn new_layout_cache<T: LayoutTableType>(layout_table: LayoutTable<T>) -> Lay
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::context::{Glyph, MatchType};
+    use crate::gdef;
 
     fn make_gdef_header(glyph_classdef_offset: u16) -> Vec<u8> {
         let mut data = vec![
@@ -3019,4 +3512,173 @@ mod tests {
             Err(err) => panic!("expeceted ParseError::BadEof got {:?}", err),
         }
     }
+
+    #[test]
+    fn test_feature_variations() {
+        let data = [
+            0x00, 0x01, 0x00, 0x01, // version 1.1
+            0x00, 0x0E, // script list offset
+            0x00, 0x22, // feature list offset
+            0x00, 0x00, // lookup list offset
+            0x00, 0x00, 0x00, 0x30, // feature variations offset
+            // ScriptList
+            0x00, 0x01, 0x44, 0x46, 0x4C, 0x54, 0x00, 0x08, // DFLT
+            0x00, 0x04, 0x00, 0x00, // Script
+            0x00, 0x00, 0xFF, 0xFF, 0x00, 0x01, 0x00, 0x00, // LangSys
+            // FeatureList
+            0x00, 0x01, 0x72, 0x76, 0x72, 0x6E, 0x00, 0x08, // rvrn
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00, // Feature, lookup 0
+            // FeatureVariations
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, //
+            0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x1E, // FeatureVariationRecord
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x06, // ConditionSet
+            0x00, 0x01, 0x00, 0x00, 0x20, 0x00, 0x40, 0x00, // axis 0 in 0.5..=1.0
+            // FeatureTableSubstitution
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, //
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x01, // Feature, lookup 1
+        ];
+        let gsub = ReadScope::new(&data).read::<LayoutTable<GSUB>>().unwrap();
+        let langsys = gsub
+            .find_script(tag::DFLT)
+            .unwrap()
+            .unwrap()
+            .find_langsys_or_default(None)
+            .unwrap()
+            .unwrap();
+        let lookup_indices = |coord: f32| {
+            let opt_feature_variation = gsub.find_feature_variation(&[F2Dot14::from_f32(coord)]);
+            gsub.find_langsys_feature_variation(langsys, tag::RVRN, opt_feature_variation)
+                .unwrap()
+                .unwrap()
+                .lookup_indices
+                .clone()
+        };
+
+        assert_eq!(gsub.find_feature_variation(&[]), None);
+        assert_eq!(lookup_indices(0.0), vec![0]);
+        assert_eq!(lookup_indices(0.75), vec![1]);
+        assert_eq!(lookup_indices(1.0), vec![1]);
+    }
+
+    #[test]
+    fn test_mark_filtering_set() {
+        struct TestGlyph(u16);
+
+        impl Glyph for TestGlyph {
+            fn get_glyph_index(&self) -> u16 {
+                self.0
+            }
+        }
+
+        let data = [
+            0x00, 0x01, 0x00, 0x02, // version 1.2
+            0x00, 0x0E, // glyph classdef offset
+            0x00, 0x00, // attach list offset
+            0x00, 0x00, // lig caret list offset
+            0x00, 0x00, // mark attach classdef offset
+            0x00, 0x1A, // mark glyph sets def offset
+            // ClassDef, glyph 1 is a base, glyphs 2 and 3 are marks
+            0x00, 0x01, 0x00, 0x01, 0x00, 0x03, 0x00, 0x01, 0x00, 0x03, 0x00, 0x03, //
+            // MarkGlyphSetsDef
+            0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x08, //
+            0x00, 0x01, 0x00, 0x01, 0x00, 0x02, // Coverage, glyph 2
+        ];
+        let gdef = ReadScope::new(&data).read::<GDEFTable>().unwrap();
+        assert_eq!(gdef.opt_mark_glyph_sets.as_ref().map(Vec::len), Some(1));
+
+        let lookup = LookupCacheItem {
+            lookup_flag: LookupFlag(0x0010),
+            mark_filtering_set: Some(0),
+            lookup_subtables: (),
+        };
+        let match_type = MatchType::from_lookup(&lookup);
+        assert!(match_type.match_glyph(Some(&gdef), &TestGlyph(1)));
+        assert!(match_type.match_glyph(Some(&gdef), &TestGlyph(2)));
+        assert!(!match_type.match_glyph(Some(&gdef), &TestGlyph(3)));
+
+        // IgnoreMarks takes precedence over the mark filtering set
+        let lookup = LookupCacheItem {
+            lookup_flag: LookupFlag(0x0018),
+            mark_filtering_set: Some(0),
+            lookup_subtables: (),
+        };
+        let match_type = MatchType::from_lookup(&lookup);
+        assert!(!match_type.match_glyph(Some(&gdef), &TestGlyph(2)));
+    }
+
+    #[test]
+    fn test_attach_list_and_lig_caret_list() {
+        let data = [
+            0x00, 0x01, 0x00, 0x00, // version 1.0
+            0x00, 0x00, // glyph classdef offset
+            0x00, 0x0C, // attach list offset
+            0x00, 0x1E, // lig caret list offset
+            0x00, 0x00, // mark attach classdef offset
+            // AttachList
+            0x00, 0x0C, 0x00, 0x01, 0x00, 0x06, //
+            0x00, 0x02, 0x00, 0x03, 0x00, 0x07, // AttachPoint
+            0x00, 0x01, 0x00, 0x01, 0x00, 0x05, // Coverage, glyph 5
+            // LigCaretList
+            0x00, 0x24, 0x00, 0x01, 0x00, 0x06, //
+            0x00, 0x03, 0x00, 0x08, 0x00, 0x0C, 0x00, 0x10, // LigGlyph
+            0x00, 0x01, 0x01, 0x2C, // CaretValue format 1
+            0x00, 0x02, 0x00, 0x04, // CaretValue format 2
+            0x00, 0x03, 0x02, 0x58, 0x00, 0x06, // CaretValue format 3
+            0x00, 0x09, 0x00, 0x0B, 0x00, 0x01, 0x78, 0x00, // Device
+            0x00, 0x01, 0x00, 0x01, 0x00, 0x0A, // Coverage, glyph 10
+        ];
+        let gdef = ReadScope::new(&data).read::<GDEFTable>().unwrap();
+
+        assert_eq!(gdef::attach_points(Some(&gdef), 5), &[3, 7]);
+        assert!(gdef::attach_points(Some(&gdef), 10).is_empty());
+
+        let carets = gdef::ligature_carets(Some(&gdef), 10);
+        assert_eq!(carets.len(), 3);
+        assert_eq!(carets[0].coordinate(), Some(300));
+        match carets[1] {
+            CaretValue::ContourPoint(point_index) => assert_eq!(point_index, 4),
+            _ => panic!("expected contour point caret"),
+        }
+        match carets[2] {
+            CaretValue::DeviceCoordinate(coordinate, ref device) => {
+                assert_eq!(coordinate, 600);
+                assert_eq!(device.hinting_delta(8), 0);
+                assert_eq!(device.hinting_delta(9), 1);
+                assert_eq!(device.hinting_delta(10), -1);
+                assert_eq!(device.hinting_delta(11), -2);
+            }
+            _ => panic!("expected device caret"),
+        }
+        assert_eq!(
+            gdef::ligature_component_caret(Some(&gdef), 10, 0).and_then(CaretValue::coordinate),
+            Some(300)
+        );
+        assert!(gdef::ligature_component_caret(Some(&gdef), 10, 3).is_none());
+        assert!(gdef::ligature_carets(Some(&gdef), 5).is_empty());
+    }
+
+    #[test]
+    fn test_lig_caret_list_bad_caret_value() {
+        let data = [
+            0x00, 0x01, 0x00, 0x00, // version 1.0
+            0x00, 0x00, // glyph classdef offset
+            0x00, 0x0C, // attach list offset
+            0x00, 0x12, // lig caret list offset
+            0x00, 0x00, // mark attach classdef offset
+            // AttachList, with a coverage offset beyond the end of the table
+            0x00, 0xFF, 0x00, 0x01, 0x00, 0x06, //
+            // LigCaretList
+            0x00, 0x18, 0x00, 0x02, 0x00, 0x08, 0x00, 0x10, //
+            0x00, 0x01, 0x00, 0x04, 0x00, 0x01, 0x00, 0x64, // LigGlyph, CaretValue format 1
+            0x00, 0x01, 0x00, 0x04, 0x00, 0x04, 0x00, 0x64, // LigGlyph, CaretValue format 4
+            0x00, 0x01, 0x00, 0x02, 0x00, 0x0A, 0x00, 0x0B, // Coverage, glyphs 10 and 11
+        ];
+        let gdef = ReadScope::new(&data).read::<GDEFTable>().unwrap();
+
+        assert!(gdef.opt_attach_list.is_none());
+        let carets = gdef::ligature_carets(Some(&gdef), 10);
+        assert_eq!(carets.len(), 1);
+        assert_eq!(carets[0].coordinate(), Some(100));
+        assert!(gdef::ligature_carets(Some(&gdef), 11).is_empty());
+    }
 }
@@ -43,7 +43,6 @@
 //! We don't currently support:
 //!
 //! * Shaping Hebrew, Tibetan, and Mongolian.
-//! * Apple's [morx table](https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6morx.html).
 //! * Emoji.
 //! * Unicode normalisation.
 //!
@@ -91,6 +90,7 @@ pub mod binary;
 pub mod bitmap;
 pub mod cff;
 pub mod checksum;
+pub mod colr;
 pub mod context;
 pub mod error;
 pub mod font_data_impl;
@@ -100,8 +100,12 @@ pub mod get_name;
 pub mod glyph_info;
 pub mod gpos;
 pub mod gsub;
+pub mod jstf;
+pub mod kerx;
 pub mod layout;
 pub mod macroman;
+pub mod morx;
+pub mod outline;
 pub mod post;
 pub mod scripts;
 pub mod size;
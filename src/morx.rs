@@ -0,0 +1,621 @@
+//! Apple Advanced Typography glyph metamorphosis (`morx`) implementation.
+//!
+//! `morx` is used in place of `GSUB` by fonts that only carry AAT layout tables. Features are
+//! selected by mapping the requested OpenType features to AAT feature type and selector pairs.
+//!
+//! — <https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6morx.html>
+
+use std::convert::TryFrom;
+
+use tinyvec::TinyVec;
+
+use crate::error::ParseError;
+use crate::gsub::{GlyphData, GlyphOrigin, GsubFeatureMask, RawGlyph};
+use crate::tables::aat::{
+    ExtendedStateTable, LookupTable, CLASS_END_OF_TEXT, DELETED_GLYPH, STATE_START_OF_TEXT,
+};
+use crate::tables::morx::{
+    ContextualEntry, ContextualSubtable, InsertionEntry, InsertionSubtable, LigatureEntry,
+    LigatureSubtable, MorxTable, RearrangementEntry, Subtable, SubtableType,
+};
+
+/// The maximum number of components tracked when forming ligatures.
+const MAX_LIGATURE_COMPONENTS: usize = 64;
+
+// Flags common to all state table entries
+const DONT_ADVANCE: u16 = 0x4000;
+
+// Rearrangement entry flags
+const MARK_FIRST: u16 = 0x8000;
+const MARK_LAST: u16 = 0x2000;
+const VERB: u16 = 0x000F;
+
+// Contextual and insertion entry flags
+const SET_MARK: u16 = 0x8000;
+
+// Ligature entry flags and actions
+const SET_COMPONENT: u16 = 0x8000;
+const PERFORM_ACTION: u16 = 0x2000;
+const LIG_ACTION_LAST: u32 = 0x8000_0000;
+const LIG_ACTION_STORE: u32 = 0x4000_0000;
+const LIG_ACTION_OFFSET: u32 = 0x3FFF_FFFF;
+
+// Insertion entry flags
+const CURRENT_INSERT_BEFORE: u16 = 0x0800;
+const MARKED_INSERT_BEFORE: u16 = 0x0400;
+const CURRENT_INSERT_COUNT: u16 = 0x03E0;
+const MARKED_INSERT_COUNT: u16 = 0x001F;
+
+/// Mapping of OpenType features to AAT feature types and the selectors that turn them on and
+/// off.
+///
+/// <https://developer.apple.com/fonts/TrueType-Reference-Manual/RM09/AppendixF.html>
+const FEATURE_MAPPINGS: &[(GsubFeatureMask, u16, u16, u16)] = &[
+    // Fractions: vertical fractions on, no fractions
+    (GsubFeatureMask::AFRC, 11, 1, 0),
+    // Upper case: small caps on, default upper case
+    (GsubFeatureMask::C2SC, 38, 1, 0),
+    // Contextual alternates: on, off
+    (GsubFeatureMask::CALT, 36, 0, 1),
+    // Ligatures: contextual ligatures on, off
+    (GsubFeatureMask::CLIG, 1, 18, 19),
+    // Ligatures: rare ligatures on, off
+    (GsubFeatureMask::DLIG, 1, 4, 5),
+    // Fractions: diagonal fractions on, no fractions
+    (GsubFeatureMask::FRAC, 11, 2, 0),
+    // Ligatures: historical ligatures on, off
+    (GsubFeatureMask::HLIG, 1, 20, 21),
+    // Ligatures: common ligatures on, off
+    (GsubFeatureMask::LIGA, 1, 2, 3),
+    // Number case: upper case numbers, no change
+    (GsubFeatureMask::LNUM, 21, 1, 2),
+    // Number case: lower case numbers, no change
+    (GsubFeatureMask::ONUM, 21, 0, 2),
+    // Vertical position: ordinals, normal position
+    (GsubFeatureMask::ORDN, 10, 3, 0),
+    // Number spacing: proportional numbers, no change
+    (GsubFeatureMask::PNUM, 6, 1, 4),
+    // Ligatures: required ligatures on, off
+    (GsubFeatureMask::RLIG, 1, 0, 1),
+    // Lower case: small caps, default lower case
+    (GsubFeatureMask::SMCP, 37, 1, 0),
+    // Number spacing: monospaced numbers, no change
+    (GsubFeatureMask::TNUM, 6, 0, 4),
+    // Vertical substitution: on, off
+    (GsubFeatureMask::VRT2_OR_VERT, 4, 0, 1),
+    // Typographic extras: slashed zero on, off
+    (GsubFeatureMask::ZERO, 14, 4, 5),
+];
+
+/// Map the OpenType features in `feature_mask` to AAT feature type and selector pairs.
+///
+/// Features that are enabled by default in OpenType (see `GsubFeatureMask::default`) but absent
+/// from `feature_mask` are mapped to the selector that turns them off.
+pub fn aat_features(feature_mask: GsubFeatureMask) -> Vec<(u16, u16)> {
+    let default_mask = GsubFeatureMask::default();
+    FEATURE_MAPPINGS
+        .iter()
+        .filter_map(|&(mask, feature_type, on_selector, off_selector)| {
+            if feature_mask.contains(mask) {
+                Some((feature_type, on_selector))
+            } else if default_mask.contains(mask) {
+                Some((feature_type, off_selector))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Apply the `morx` table to `glyphs`, for fonts that lack a `GSUB` table.
+///
+/// Subtables are enabled according to the features in `feature_mask`. Glyphs are assumed to be
+/// in logical order and laid out horizontally, so subtables that only apply to vertical text are
+/// skipped.
+pub fn morx_apply<T: GlyphData>(
+    morx_table: &MorxTable<'_>,
+    feature_mask: GsubFeatureMask,
+    glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ParseError> {
+    if glyphs.is_empty() {
+        return Ok(());
+    }
+    let features = aat_features(feature_mask);
+    for chain in &morx_table.chains {
+        let flags = chain.subtable_flags(&features);
+        for subtable in &chain.subtables {
+            if subtable.sub_feature_flags & flags == 0 {
+                continue;
+            }
+            if subtable.coverage & Subtable::VERTICAL != 0
+                && subtable.coverage & Subtable::ALL_DIRECTIONS == 0
+            {
+                continue;
+            }
+            let descending = subtable.coverage & Subtable::DESCENDING != 0;
+            if descending {
+                glyphs.reverse();
+            }
+            apply_subtable(&subtable.subtable, glyphs)?;
+            if descending {
+                glyphs.reverse();
+            }
+        }
+    }
+    glyphs.retain(|glyph| glyph.glyph_index != DELETED_GLYPH);
+    Ok(())
+}
+
+fn apply_subtable<T: GlyphData>(
+    subtable: &SubtableType<'_>,
+    glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ParseError> {
+    match subtable {
+        SubtableType::Rearrangement(state_table) => rearrangement(state_table, glyphs),
+        SubtableType::Contextual(contextual) => contextual_substitution(contextual, glyphs),
+        SubtableType::Ligature(ligature) => ligature_substitution(ligature, glyphs),
+        SubtableType::Noncontextual(lookup_table) => {
+            noncontextual_substitution(lookup_table, glyphs)
+        }
+        SubtableType::Insertion(insertion) => glyph_insertion(insertion, glyphs),
+        SubtableType::Unsupported(_) => Ok(()),
+    }
+}
+
+/// Run a state machine over `glyphs`, calling `transition` with the index of the current glyph,
+/// the current state and the class of the current glyph.
+///
+/// `transition` returns the new state and whether the current glyph should be processed again.
+/// It may insert glyphs and move the current index.
+fn drive<T, F>(
+    state_table: &ExtendedStateTable<'_>,
+    glyphs: &mut Vec<RawGlyph<T>>,
+    mut transition: F,
+) -> Result<(), ParseError>
+where
+    F: FnMut(&mut Vec<RawGlyph<T>>, &mut usize, u16, u16) -> Result<(u16, bool), ParseError>,
+{
+    // Guard against state tables that never advance
+    let mut max_ops = (glyphs.len() * 64).max(16384);
+    let mut state = STATE_START_OF_TEXT;
+    let mut i = 0;
+    loop {
+        let class = match glyphs.get(i) {
+            Some(glyph) => state_table.class(glyph.glyph_index)?,
+            None => CLASS_END_OF_TEXT,
+        };
+        let (new_state, dont_advance) = transition(glyphs, &mut i, state, class)?;
+        state = new_state;
+        if i >= glyphs.len() || max_ops == 0 {
+            break;
+        }
+        max_ops -= 1;
+        if !dont_advance {
+            i += 1;
+        }
+    }
+    Ok(())
+}
+
+fn rearrangement<T: GlyphData>(
+    state_table: &ExtendedStateTable<'_>,
+    glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ParseError> {
+    let mut start = 0;
+    let mut end = 0;
+    drive(state_table, glyphs, |glyphs, i, state, class| {
+        let entry = state_table.entry::<RearrangementEntry>(state, class)?;
+        if entry.flags & MARK_FIRST != 0 {
+            start = *i;
+        }
+        if entry.flags & MARK_LAST != 0 {
+            end = (*i + 1).min(glyphs.len());
+        }
+        let verb = entry.flags & VERB;
+        if verb != 0 && start < end {
+            rearrange(&mut glyphs[start..end], verb);
+        }
+        Ok((entry.new_state, entry.flags & DONT_ADVANCE != 0))
+    })
+}
+
+/// Rearrange `glyphs` according to `verb`.
+///
+/// Each verb moves up to two glyphs from the start (A, B) and up to two glyphs from the end (C,
+/// D) of the marked range to the opposite end, possibly reversing them.
+fn rearrange<T>(glyphs: &mut [T], verb: u16) {
+    // For each verb, the high nibble is the number of glyphs moved from the start and the low
+    // nibble the number from the end. A count of 3 indicates two glyphs that are reversed.
+    const MAP: [u8; 16] = [
+        0x00, // no change
+        0x10, // Ax => xA
+        0x01, // xD => Dx
+        0x11, // AxD => DxA
+        0x20, // ABx => xAB
+        0x30, // ABx => xBA
+        0x02, // xCD => CDx
+        0x03, // xCD => DCx
+        0x12, // AxCD => CDxA
+        0x13, // AxCD => DCxA
+        0x21, // ABxD => DxAB
+        0x31, // ABxD => DxBA
+        0x22, // ABxCD => CDxAB
+        0x32, // ABxCD => CDxBA
+        0x23, // ABxCD => DCxAB
+        0x33, // ABxCD => DCxBA
+    ];
+    let m = MAP[usize::from(verb & VERB)];
+    let l = usize::from(m >> 4).min(2);
+    let r = usize::from(m & 0x0F).min(2);
+    let reverse_l = m >> 4 == 3;
+    let reverse_r = m & 0x0F == 3;
+    let len = glyphs.len();
+    if len < l + r {
+        return;
+    }
+    // Move the start glyphs to the end, then the end glyphs in front of the middle ones
+    glyphs.rotate_left(l);
+    glyphs[..len - l].rotate_right(r);
+    if reverse_r {
+        glyphs[..r].reverse();
+    }
+    if reverse_l {
+        glyphs[len - l..].reverse();
+    }
+}
+
+fn contextual_substitution<T: GlyphData>(
+    subtable: &ContextualSubtable<'_>,
+    glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ParseError> {
+    let mut mark = 0;
+    let mut mark_set = false;
+    drive(&subtable.state_table, glyphs, |glyphs, i, state, class| {
+        let entry = subtable
+            .state_table
+            .entry::<ContextualEntry>(state, class)?;
+        // Neither substitution is applied at the end of the text if the mark was not set
+        if *i < glyphs.len() || mark_set {
+            if entry.mark_index != 0xFFFF && mark_set && mark < glyphs.len() {
+                substitute(subtable, entry.mark_index, &mut glyphs[mark])?;
+            }
+            if entry.current_index != 0xFFFF {
+                let current = (*i).min(glyphs.len() - 1);
+                substitute(subtable, entry.current_index, &mut glyphs[current])?;
+            }
+        }
+        if entry.flags & SET_MARK != 0 {
+            mark_set = true;
+            mark = *i;
+        }
+        Ok((entry.new_state, entry.flags & DONT_ADVANCE != 0))
+    })
+}
+
+fn substitute<T>(
+    subtable: &ContextualSubtable<'_>,
+    index: u16,
+    glyph: &mut RawGlyph<T>,
+) -> Result<(), ParseError> {
+    if let Some(glyph_index) = subtable.substitute(index, glyph.glyph_index)? {
+        glyph.glyph_index = glyph_index;
+        glyph.glyph_origin = GlyphOrigin::Direct;
+    }
+    Ok(())
+}
+
+fn ligature_substitution<T: GlyphData>(
+    subtable: &LigatureSubtable<'_>,
+    glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ParseError> {
+    let mut components: Vec<usize> = Vec::new();
+    drive(&subtable.state_table, glyphs, |glyphs, i, state, class| {
+        let entry = subtable.state_table.entry::<LigatureEntry>(state, class)?;
+        if entry.flags & SET_COMPONENT != 0 {
+            // Never mark the same glyph twice, which happens when DontAdvance is used
+            if components.last() == Some(i) {
+                components.pop();
+            }
+            if components.len() == MAX_LIGATURE_COMPONENTS {
+                components.remove(0);
+            }
+            components.push(*i);
+        }
+        if entry.flags & PERFORM_ACTION != 0 && !components.is_empty() && *i < glyphs.len() {
+            perform_ligature_actions(subtable, entry, &mut components, glyphs)?;
+        }
+        Ok((entry.new_state, entry.flags & DONT_ADVANCE != 0))
+    })
+}
+
+fn perform_ligature_actions<T: GlyphData>(
+    subtable: &LigatureSubtable<'_>,
+    entry: LigatureEntry,
+    components: &mut Vec<usize>,
+    glyphs: &mut [RawGlyph<T>],
+) -> Result<(), ParseError> {
+    let mut action_index = usize::from(entry.lig_action_index);
+    let mut cursor = components.len();
+    let mut ligature_index = 0usize;
+    loop {
+        if cursor == 0 {
+            // Stack underflow
+            components.clear();
+            break;
+        }
+        cursor -= 1;
+        let position = components[cursor];
+        let action = subtable.lig_action(action_index)?;
+
+        // The offset is a signed 30-bit value
+        let mut offset = action & LIG_ACTION_OFFSET;
+        if offset & 0x2000_0000 != 0 {
+            offset |= 0xC000_0000;
+        }
+        let component_index = i64::from(glyphs[position].glyph_index) + i64::from(offset as i32);
+        let component_index = usize::try_from(component_index).map_err(|_| ParseError::BadIndex)?;
+        ligature_index += usize::from(subtable.component(component_index)?);
+
+        if action & (LIG_ACTION_STORE | LIG_ACTION_LAST) != 0 {
+            let ligature_glyph = subtable.ligature(ligature_index)?;
+            // Merge the subsequent components into the ligature and delete them
+            for &component in &components[cursor + 1..] {
+                let mut unicodes = glyphs[component].unicodes.clone();
+                let extra_data = glyphs[component].extra_data.clone();
+                glyphs[position].unicodes.append(&mut unicodes);
+                glyphs[position].extra_data =
+                    GlyphData::merge(glyphs[position].extra_data.clone(), extra_data);
+                glyphs[component].glyph_index = DELETED_GLYPH;
+                glyphs[component].unicodes = TinyVec::new();
+            }
+            components.truncate(cursor + 1);
+            glyphs[position].glyph_index = ligature_glyph;
+            glyphs[position].glyph_origin = GlyphOrigin::Direct;
+        }
+
+        if action & LIG_ACTION_LAST != 0 {
+            break;
+        }
+        action_index += 1;
+    }
+    Ok(())
+}
+
+fn noncontextual_substitution<T>(
+    lookup_table: &LookupTable<'_>,
+    glyphs: &mut [RawGlyph<T>],
+) -> Result<(), ParseError> {
+    for glyph in glyphs.iter_mut() {
+        if glyph.glyph_index == DELETED_GLYPH {
+            continue;
+        }
+        if let Some(glyph_index) = lookup_table.lookup(glyph.glyph_index)? {
+            glyph.glyph_index = glyph_index;
+            glyph.glyph_origin = GlyphOrigin::Direct;
+        }
+    }
+    Ok(())
+}
+
+fn glyph_insertion<T: GlyphData>(
+    subtable: &InsertionSubtable<'_>,
+    glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ParseError> {
+    let mut mark = 0;
+    drive(&subtable.state_table, glyphs, |glyphs, i, state, class| {
+        let entry = subtable.state_table.entry::<InsertionEntry>(state, class)?;
+        let mark_loc = *i;
+        if entry.marked_insert_index != 0xFFFF {
+            let count = usize::from(entry.flags & MARKED_INSERT_COUNT);
+            let position = if entry.flags & MARKED_INSERT_BEFORE != 0 {
+                mark
+            } else {
+                mark + 1
+            };
+            insert_glyphs(
+                subtable,
+                entry.marked_insert_index,
+                count,
+                position.min(glyphs.len()),
+                glyphs,
+            )?;
+            *i += count;
+        }
+        if entry.flags & SET_MARK != 0 {
+            mark = mark_loc;
+        }
+        if entry.current_insert_index != 0xFFFF {
+            let count = usize::from((entry.flags & CURRENT_INSERT_COUNT) >> 5);
+            let position = if entry.flags & CURRENT_INSERT_BEFORE != 0 {
+                *i
+            } else {
+                *i + 1
+            };
+            insert_glyphs(
+                subtable,
+                entry.current_insert_index,
+                count,
+                position.min(glyphs.len()),
+                glyphs,
+            )?;
+            // When not advancing the inserted glyphs are processed next, otherwise they are
+            // skipped over
+            if entry.flags & DONT_ADVANCE == 0 {
+                *i += count;
+            }
+        }
+        Ok((entry.new_state, entry.flags & DONT_ADVANCE != 0))
+    })
+}
+
+fn insert_glyphs<T: GlyphData>(
+    subtable: &InsertionSubtable<'_>,
+    insertion_index: u16,
+    count: usize,
+    position: usize,
+    glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ParseError> {
+    // Inserted glyphs take their properties from the neighbouring glyph
+    let template = &glyphs[position.min(glyphs.len() - 1)];
+    let inserted = (0..count)
+        .map(|n| {
+            let glyph_index = subtable.insertion_glyph(usize::from(insertion_index) + n)?;
+            Ok(RawGlyph {
+                unicodes: TinyVec::new(),
+                glyph_index,
+                liga_component_pos: 0,
+                glyph_origin: GlyphOrigin::Direct,
+                small_caps: template.small_caps,
+                multi_subst_dup: false,
+                is_vert_alt: template.is_vert_alt,
+                fake_bold: template.fake_bold,
+                fake_italic: template.fake_italic,
+                variation: None,
+                extra_data: template.extra_data.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>, ParseError>>()?;
+    glyphs.splice(position..position, inserted);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::tests::{make_direct_glyphs, push_u16, push_u32};
+
+    fn glyph_indices(glyphs: &[RawGlyph<()>]) -> Vec<u16> {
+        glyphs.iter().map(|glyph| glyph.glyph_index).collect()
+    }
+
+    /// Wrap a subtable in a `morx` table with one chain enabling it by default.
+    fn make_morx(subtable_type: u32, subtable: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        push_u16(&mut data, &[2, 0]);
+        push_u32(&mut data, &[1]);
+        // Chain header: default flags, length, feature and subtable counts
+        push_u32(&mut data, &[1, 16 + 12 + subtable.len() as u32, 0, 1]);
+        // Subtable header: length, coverage, feature flags
+        push_u32(&mut data, &[12 + subtable.len() as u32, subtable_type, 1]);
+        data.extend_from_slice(subtable);
+        data
+    }
+
+    #[test]
+    fn test_rearrange() {
+        let mut glyphs = vec!['A', 'B', 'x', 'C', 'D'];
+        rearrange(&mut glyphs, 15);
+        assert_eq!(glyphs, vec!['D', 'C', 'x', 'B', 'A']);
+
+        let mut glyphs = vec!['A', 'x', 'y', 'D'];
+        rearrange(&mut glyphs, 3);
+        assert_eq!(glyphs, vec!['D', 'x', 'y', 'A']);
+
+        let mut glyphs = vec!['A', 'B', 'x'];
+        rearrange(&mut glyphs, 5);
+        assert_eq!(glyphs, vec!['x', 'B', 'A']);
+
+        let mut glyphs = vec!['x', 'C', 'D'];
+        rearrange(&mut glyphs, 6);
+        assert_eq!(glyphs, vec!['C', 'D', 'x']);
+    }
+
+    #[test]
+    fn test_aat_features() {
+        let features = aat_features(GsubFeatureMask::default() | GsubFeatureMask::SMCP);
+        assert!(features.contains(&(1, 2)));
+        assert!(features.contains(&(37, 1)));
+        // Off by default and not requested
+        assert!(!features.contains(&(1, 5)));
+        assert!(!features.contains(&(1, 4)));
+
+        let features = aat_features(GsubFeatureMask::empty());
+        assert!(features.contains(&(1, 3)));
+    }
+
+    #[test]
+    fn test_noncontextual_chain() {
+        #[rustfmt::skip]
+        let data = [
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, // version 2, one chain
+            // Chain, default flags 1, one feature, one subtable
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3C,
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+            // Feature: ligatures (1), common ligatures off (3) clears flag 1
+            0x00, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFE,
+            // Subtable header: noncontextual, feature flags 1
+            0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01,
+            // Lookup table format 6: 3 => 7, 4 => 8
+            0x00, 0x06, 0x00, 0x04, 0x00, 0x02, 0x00, 0x08, 0x00, 0x01, 0x00, 0x00,
+            0x00, 0x03, 0x00, 0x07, 0x00, 0x04, 0x00, 0x08,
+        ];
+        let morx = ReadScope::new(&data).read::<MorxTable<'_>>().unwrap();
+        assert_eq!(morx.chains.len(), 1);
+
+        let mut glyphs = make_direct_glyphs(&[3, 5, 4]);
+        morx_apply(&morx, GsubFeatureMask::default(), &mut glyphs).unwrap();
+        assert_eq!(glyph_indices(&glyphs), vec![7, 5, 8]);
+
+        // Turning off liga disables the subtable
+        let mut glyphs = make_direct_glyphs(&[3, 5, 4]);
+        morx_apply(&morx, GsubFeatureMask::empty(), &mut glyphs).unwrap();
+        assert_eq!(glyph_indices(&glyphs), vec![3, 5, 4]);
+    }
+
+    #[test]
+    fn test_ligature() {
+        // A ligature subtable forming glyph 10 from glyphs 1 and 2
+        let mut subtable = Vec::new();
+        // STXHeader: 6 classes, class table, state array and entry table offsets
+        push_u32(&mut subtable, &[6, 28, 38, 62]);
+        // Ligature action, component and ligature offsets
+        push_u32(&mut subtable, &[80, 88, 94]);
+        // Class table, format 8: glyph 1 is class 4, glyph 2 is class 5
+        push_u16(&mut subtable, &[8, 1, 2, 4, 5]);
+        // State array
+        push_u16(&mut subtable, &[0, 0, 0, 0, 1, 0]);
+        push_u16(&mut subtable, &[0, 0, 0, 0, 1, 2]);
+        // Entry table: reset; set component and go to state 1; set component and perform
+        // action
+        push_u16(&mut subtable, &[0, 0, 0, 1, SET_COMPONENT, 0]);
+        push_u16(&mut subtable, &[0, SET_COMPONENT | PERFORM_ACTION, 0]);
+        // Ligature actions, one per component
+        push_u32(&mut subtable, &[0, LIG_ACTION_LAST]);
+        // Components, indexed by glyph id
+        push_u16(&mut subtable, &[0, 0, 1]);
+        // Ligatures, indexed by the sum of the components
+        push_u16(&mut subtable, &[0, 10]);
+
+        let data = make_morx(2, &subtable);
+        let morx = ReadScope::new(&data).read::<MorxTable<'_>>().unwrap();
+        let mut glyphs = make_direct_glyphs(&[5, 1, 2, 6, 1]);
+        morx_apply(&morx, GsubFeatureMask::default(), &mut glyphs).unwrap();
+        assert_eq!(glyph_indices(&glyphs), vec![5, 10, 6, 1]);
+    }
+
+    #[test]
+    fn test_insertion() {
+        // An insertion subtable inserting glyph 20 after each glyph 1
+        let mut subtable = Vec::new();
+        // STXHeader: 5 classes, class table, state array and entry table offsets
+        push_u32(&mut subtable, &[5, 20, 28, 38]);
+        // Insertion action offset
+        push_u32(&mut subtable, &[54]);
+        // Class table, format 8: glyph 1 is class 4
+        push_u16(&mut subtable, &[8, 1, 1, 4]);
+        // State array
+        push_u16(&mut subtable, &[0, 0, 0, 0, 1]);
+        // Entry table: no insertion; insert one glyph after the current glyph
+        push_u16(&mut subtable, &[0, 0, 0xFFFF, 0xFFFF]);
+        push_u16(&mut subtable, &[0, 1 << 5, 0, 0xFFFF]);
+        // Insertion glyphs
+        push_u16(&mut subtable, &[20]);
+
+        let data = make_morx(5, &subtable);
+        let morx = ReadScope::new(&data).read::<MorxTable<'_>>().unwrap();
+        let mut glyphs = make_direct_glyphs(&[1, 2, 1]);
+        morx_apply(&morx, GsubFeatureMask::default(), &mut glyphs).unwrap();
+        assert_eq!(glyph_indices(&glyphs), vec![1, 20, 2, 1, 20]);
+    }
+}
@@ -0,0 +1,73 @@
+#![deny(missing_docs)]
+
+//! Glyph outline visiting.
+//!
+//! The outlines of `glyf` and CFF glyphs are passed to an `OutlineSink` as a series of path
+//! commands. See `FontDataImpl::visit_outline`.
+
+/// Receives the outline of a glyph as a series of path commands.
+///
+/// Coordinates are absolute, in font units. Each contour starts with `move_to` and ends with
+/// `close`. TrueType outlines produce quadratic curves, CFF outlines produce cubic curves.
+pub trait OutlineSink {
+    /// Begin a new contour at `x`, `y`.
+    fn move_to(&mut self, x: f32, y: f32);
+
+    /// Add a straight line from the current point to `x`, `y`.
+    fn line_to(&mut self, x: f32, y: f32);
+
+    /// Add a quadratic Bézier curve from the current point to `x`, `y` with control point `x1`,
+    /// `y1`.
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32);
+
+    /// Add a cubic Bézier curve from the current point to `x`, `y` with control points `x1`,
+    /// `y1` and `x2`, `y2`.
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32);
+
+    /// Close the current contour.
+    fn close(&mut self);
+}
+
+/// An `OutlineSink` that computes the control box of an outline.
+///
+/// The control box is the bounding box of all the points of the outline, including the control
+/// points of curves. It contains the outline but may be larger than its exact bounding box.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ControlBox {
+    /// The control box as `(x_min, y_min, x_max, y_max)`, `None` if the outline is empty.
+    pub bounds: Option<(f32, f32, f32, f32)>,
+}
+
+impl ControlBox {
+    fn add_point(&mut self, x: f32, y: f32) {
+        self.bounds = Some(match self.bounds {
+            Some((x_min, y_min, x_max, y_max)) => {
+                (x_min.min(x), y_min.min(y), x_max.max(x), y_max.max(y))
+            }
+            None => (x, y, x, y),
+        });
+    }
+}
+
+impl OutlineSink for ControlBox {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.add_point(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.add_point(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.add_point(x1, y1);
+        self.add_point(x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.add_point(x1, y1);
+        self.add_point(x2, y2);
+        self.add_point(x, y);
+    }
+
+    fn close(&mut self) {}
+}
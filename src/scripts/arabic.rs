@@ -5,7 +5,8 @@
 
 use crate::error::{ParseError, ShapingError};
 use crate::gsub::{self, build_lookups, GlyphData, GlyphOrigin, RawGlyph};
-use crate::layout::{GDEFTable, LangSys, LayoutCache, LayoutTable, GSUB};
+use crate::layout::{GDEFTable, LangSys, LayoutCache, GSUB};
+use crate::tables::F2Dot14;
 use crate::tag;
 
 use std::convert::From;
@@ -104,12 +105,13 @@ impl From<&ArabicGlyph> for RawGlyph<()> {
 
 pub fn gsub_apply_arabic(
     gsub_cache: &LayoutCache<GSUB>,
-    gsub_table: &LayoutTable<GSUB>,
     gdef_table: Option<&GDEFTable>,
     script_tag: u32,
     opt_lang_tag: Option<u32>,
+    coords: &[F2Dot14],
     raw_glyphs: &mut Vec<RawGlyph<()>>,
 ) -> Result<(), ShapingError> {
+    let gsub_table = &gsub_cache.layout_table;
     let langsys = match gsub_table.find_script(script_tag)? {
         Some(s) => match s.find_langsys_or_default(opt_lang_tag)? {
             Some(v) => v,
@@ -117,6 +119,7 @@ pub fn gsub_apply_arabic(
         },
         None => return Ok(()),
     };
+    let opt_feature_variation = gsub_table.find_feature_variation(coords);
 
     let arabic_glyphs = &mut raw_glyphs.iter().map(ArabicGlyph::from).collect();
 
@@ -125,9 +128,9 @@ pub fn gsub_apply_arabic(
     apply_lookup(
         &[tag::CCMP],
         gsub_cache,
-        gsub_table,
         gdef_table,
         langsys,
+        opt_feature_variation,
         arabic_glyphs,
         |_, _| true,
     )?;
@@ -168,9 +171,9 @@ pub fn gsub_apply_arabic(
     apply_lookup(
         &[tag::LOCL],
         gsub_cache,
-        gsub_table,
         gdef_table,
         langsys,
+        opt_feature_variation,
         arabic_glyphs,
         |_, _| true,
     )?;
@@ -178,9 +181,9 @@ pub fn gsub_apply_arabic(
     apply_lookup(
         &[tag::ISOL, tag::FINA, tag::MEDI, tag::INIT],
         gsub_cache,
-        gsub_table,
         gdef_table,
         langsys,
+        opt_feature_variation,
         arabic_glyphs,
         |g, feature_tag| g.feature_tag() == feature_tag,
     )?;
@@ -190,9 +193,9 @@ pub fn gsub_apply_arabic(
     apply_lookup(
         &[tag::RLIG],
         gsub_cache,
-        gsub_table,
         gdef_table,
         langsys,
+        opt_feature_variation,
         arabic_glyphs,
         |_, _| true,
     )?;
@@ -200,9 +203,9 @@ pub fn gsub_apply_arabic(
     apply_lookup(
         &[tag::RCLT, tag::CALT],
         gsub_cache,
-        gsub_table,
         gdef_table,
         langsys,
+        opt_feature_variation,
         arabic_glyphs,
         |_, _| true,
     )?;
@@ -215,9 +218,9 @@ pub fn gsub_apply_arabic(
     apply_lookup(
         &[tag::LIGA, tag::MSET],
         gsub_cache,
-        gsub_table,
         gdef_table,
         langsys,
+        opt_feature_variation,
         arabic_glyphs,
         |_, _| true,
     )?;
@@ -234,13 +237,15 @@ pub fn gsub_apply_arabic(
 fn apply_lookup(
     feature_tags: &[u32],
     gsub_cache: &LayoutCache<GSUB>,
-    gsub_table: &LayoutTable<GSUB>,
     gdef_table: Option<&GDEFTable>,
     langsys: &LangSys,
+    opt_feature_variation: Option<usize>,
     arabic_glyphs: &mut Vec<RawGlyph<ArabicData>>,
     pred: impl Fn(&RawGlyph<ArabicData>, u32) -> bool + Copy,
 ) -> Result<(), ParseError> {
-    for (lookup_index, feature_tag) in build_lookups(gsub_table, langsys, feature_tags)? {
+    let gsub_table = &gsub_cache.layout_table;
+    let lookups = build_lookups(gsub_table, langsys, opt_feature_variation, feature_tags)?;
+    for (lookup_index, feature_tag) in lookups {
         gsub::gsub_apply_lookup(
             gsub_cache,
             gsub_table,
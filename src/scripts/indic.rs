@@ -4,6 +4,7 @@ use crate::error::{IndicError, ParseError, ShapingError};
 use crate::gpos::{self, Info};
 use crate::gsub::{self, GlyphData, GlyphOrigin, RawGlyph};
 use crate::layout::{GDEFTable, LangSys, LayoutCache, LayoutTable, GPOS, GSUB};
+use crate::tables::F2Dot14;
 use crate::tag;
 
 use bitflags::bitflags;
@@ -1206,6 +1207,7 @@ struct IndicShapingData<'tables> {
     gsub_table: &'tables LayoutTable<GSUB>,
     gdef_table: Option<&'tables GDEFTable>,
     langsys: &'tables LangSys,
+    opt_feature_variation: Option<usize>,
     script: Script,
     shaping_model: ShapingModel,
 }
@@ -1219,9 +1221,9 @@ impl IndicShapingData<'_> {
     ) -> Result<bool, ParseError> {
         gsub::gsub_feature_would_apply(
             self.gsub_cache,
-            self.gsub_table,
             self.gdef_table,
             self.langsys,
+            self.opt_feature_variation,
             feature_tag,
             glyphs,
             start_index,
@@ -1229,7 +1231,12 @@ impl IndicShapingData<'_> {
     }
 
     fn build_lookups_default(&self, feature_tags: &[u32]) -> Result<Vec<(usize, u32)>, ParseError> {
-        gsub::build_lookups(self.gsub_table, self.langsys, feature_tags)
+        gsub::build_lookups(
+            self.gsub_table,
+            self.langsys,
+            self.opt_feature_variation,
+            feature_tags,
+        )
     }
 
     fn apply_lookup(
@@ -1265,12 +1272,13 @@ impl IndicShapingData<'_> {
 pub fn gsub_apply_indic<'data>(
     make_dotted_circle: &impl Fn() -> Vec<RawGlyph<()>>,
     gsub_cache: &LayoutCache<GSUB>,
-    gsub_table: &LayoutTable<GSUB>,
     gdef_table: Option<&GDEFTable>,
     indic1_tag: u32,
     opt_lang_tag: Option<u32>,
+    coords: &[F2Dot14],
     glyphs: &mut Vec<RawGlyph<()>>,
 ) -> Result<(), ShapingError> {
+    let gsub_table = &gsub_cache.layout_table;
     if glyphs.is_empty() {
         return Err(IndicError::EmptyBuffer.into());
     }
@@ -1300,6 +1308,7 @@ pub fn gsub_apply_indic<'data>(
         gsub_table,
         gdef_table,
         langsys: &langsys,
+        opt_feature_variation: gsub_table.find_feature_variation(coords),
         script,
         shaping_model,
     };
@@ -2391,6 +2400,7 @@ pub fn gpos_apply_indic(
     gdef_table: Option<&GDEFTable>,
     indic1_tag: u32,
     opt_lang_tag: Option<u32>,
+    coords: &[F2Dot14],
     infos: &mut [Info],
 ) -> Result<(), ParseError> {
     let indic2_tag = indic2_tag(indic1_tag);
@@ -2422,6 +2432,7 @@ pub fn gpos_apply_indic(
         gpos_table,
         gdef_table,
         &langsys,
+        coords,
         FEATURES,
         infos,
     )
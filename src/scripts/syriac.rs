@@ -6,7 +6,8 @@
 
 use crate::error::{ParseError, ShapingError};
 use crate::gsub::{self, build_lookups, GlyphData, GlyphOrigin, RawGlyph};
-use crate::layout::{GDEFTable, LangSys, LayoutCache, LayoutTable, GSUB};
+use crate::layout::{GDEFTable, LangSys, LayoutCache, GSUB};
+use crate::tables::F2Dot14;
 use crate::tag;
 
 use std::convert::From;
@@ -125,12 +126,13 @@ impl From<&SyriacGlyph> for RawGlyph<()> {
 
 pub fn gsub_apply_syriac(
     gsub_cache: &LayoutCache<GSUB>,
-    gsub_table: &LayoutTable<GSUB>,
     gdef_table: Option<&GDEFTable>,
     script_tag: u32,
     opt_lang_tag: Option<u32>,
+    coords: &[F2Dot14],
     raw_glyphs: &mut Vec<RawGlyph<()>>,
 ) -> Result<(), ShapingError> {
+    let gsub_table = &gsub_cache.layout_table;
     let langsys = match gsub_table.find_script(script_tag)? {
         Some(s) => match s.find_langsys_or_default(opt_lang_tag)? {
             Some(v) => v,
@@ -138,6 +140,7 @@ pub fn gsub_apply_syriac(
         },
         None => return Ok(()),
     };
+    let opt_feature_variation = gsub_table.find_feature_variation(coords);
 
     let syriac_glyphs: &mut Vec<SyriacGlyph> =
         &mut raw_glyphs.iter().map(SyriacGlyph::from).collect();
@@ -147,9 +150,9 @@ pub fn gsub_apply_syriac(
     apply_lookup(
         &[tag::CCMP],
         gsub_cache,
-        gsub_table,
         gdef_table,
         langsys,
+        opt_feature_variation,
         syriac_glyphs,
         |_, _| true,
     )?;
@@ -211,9 +214,9 @@ pub fn gsub_apply_syriac(
     apply_lookup(
         &[tag::LOCL],
         gsub_cache,
-        gsub_table,
         gdef_table,
         langsys,
+        opt_feature_variation,
         syriac_glyphs,
         |_, _| true,
     )?;
@@ -229,9 +232,9 @@ pub fn gsub_apply_syriac(
             tag::INIT,
         ],
         gsub_cache,
-        gsub_table,
         gdef_table,
         langsys,
+        opt_feature_variation,
         syriac_glyphs,
         |g, feature_tag| g.feature_tag() == feature_tag,
     )?;
@@ -241,9 +244,9 @@ pub fn gsub_apply_syriac(
     apply_lookup(
         &[tag::RLIG],
         gsub_cache,
-        gsub_table,
         gdef_table,
         langsys,
+        opt_feature_variation,
         syriac_glyphs,
         |_, _| true,
     )?;
@@ -251,9 +254,9 @@ pub fn gsub_apply_syriac(
     apply_lookup(
         &[tag::CALT],
         gsub_cache,
-        gsub_table,
         gdef_table,
         langsys,
+        opt_feature_variation,
         syriac_glyphs,
         |_, _| true,
     )?;
@@ -265,9 +268,9 @@ pub fn gsub_apply_syriac(
     apply_lookup(
         &[tag::LIGA],
         gsub_cache,
-        gsub_table,
         gdef_table,
         langsys,
+        opt_feature_variation,
         syriac_glyphs,
         |_, _| true,
     )?;
@@ -284,13 +287,15 @@ pub fn gsub_apply_syriac(
 fn apply_lookup(
     feature_tags: &[u32],
     gsub_cache: &LayoutCache<GSUB>,
-    gsub_table: &LayoutTable<GSUB>,
     gdef_table: Option<&GDEFTable>,
     langsys: &LangSys,
+    opt_feature_variation: Option<usize>,
     syriac_glyphs: &mut Vec<RawGlyph<SyriacData>>,
     pred: impl Fn(&RawGlyph<SyriacData>, u32) -> bool + Copy,
 ) -> Result<(), ParseError> {
-    for (lookup_index, feature_tag) in build_lookups(gsub_table, langsys, feature_tags)? {
+    let gsub_table = &gsub_cache.layout_table;
+    let lookups = build_lookups(gsub_table, langsys, opt_feature_variation, feature_tags)?;
+    for (lookup_index, feature_tag) in lookups {
         gsub::gsub_apply_lookup(
             gsub_cache,
             gsub_table,
@@ -2,7 +2,7 @@
 
 //! Font subsetting.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryFrom;
 use std::num::Wrapping;
 
@@ -14,12 +14,17 @@ use crate::binary::write::{WriteBinaryDep, WriteBuffer, WriteContext};
 use crate::binary::{long_align, U16Be, U32Be};
 use crate::cff::CFF;
 use crate::error::{ParseError, ReadWriteError, WriteError};
+use crate::font_data_impl::{read_cmap_subtable, Encoding};
+use crate::macroman::macroman_to_char;
 use crate::post::PostTable;
+use crate::tables::cmap::Cmap;
 use crate::tables::glyf::GlyfTable;
 use crate::tables::loca::{self, LocaTable};
+use crate::tables::os2::Os2;
+use crate::tables::vorg::VorgTable;
 use crate::tables::{
     self, cmap, FontTableProvider, HeadTable, HheaTable, HmtxTable, IndexToLocFormat, MaxpTable,
-    TableRecord,
+    TableRecord, VheaTable, VmtxTable,
 };
 use crate::{checksum, tag};
 
@@ -112,6 +117,9 @@ fn subset_ttf(
     // Build the new glyf table
     let (glyf, new_to_old_glyph_id) = glyf.subset(glyph_ids)?;
 
+    // Build new vhea and vmtx tables
+    let vertical = create_vertical_tables(provider, maxp.num_glyphs, &new_to_old_glyph_id)?;
+
     // Build new maxp table
     let num_glyphs = u16::try_from(glyf.records.len()).map_err(ParseError::from)?;
     maxp.num_glyphs = num_glyphs;
@@ -128,6 +136,15 @@ fn subset_ttf(
         &new_to_old_glyph_id,
     )?;
 
+    // Build new OS/2 table
+    let os_2 = create_os2_table(
+        provider,
+        &hmtx,
+        num_glyphs,
+        glyph_ids,
+        cmap0.as_ref().map(Box::as_ref),
+    )?;
+
     // Get the remaining tables
     let cvt = provider.table_data(tag::CVT)?;
     let fpgm = provider.table_data(tag::FPGM)?;
@@ -153,10 +170,17 @@ fn subset_ttf(
     if let Some(name) = name {
         builder.add_table::<_, ReadScope<'_>>(tag::NAME, ReadScope::new(&name), ())?;
     }
+    if let Some(os_2) = os_2 {
+        builder.add_table::<_, Os2>(tag::OS_2, &os_2, ())?;
+    }
     builder.add_table::<_, PostTable<'_>>(tag::POST, &post, ())?;
     if let Some(prep) = prep {
         builder.add_table::<_, ReadScope<'_>>(tag::PREP, ReadScope::new(&prep), ())?;
     }
+    if let Some((vhea, vmtx)) = vertical {
+        builder.add_table::<_, VheaTable>(tag::VHEA, &vhea, ())?;
+        builder.add_table::<_, VmtxTable<'_>>(tag::VMTX, &vmtx, ())?;
+    }
     let mut builder = builder.add_head_table(&head)?;
     builder.add_glyf_table(glyf)?;
     builder.data()
@@ -195,6 +219,17 @@ fn subset_cff(
     let (cff, new_to_old_glyph_id) =
         cff.subset(glyph_ids, convert_cff_to_cid_if_more_than_255_glyphs)?;
 
+    // Build new vhea, vmtx and VORG tables
+    let vertical = create_vertical_tables(provider, maxp.num_glyphs, &new_to_old_glyph_id)?;
+    let vorg = provider
+        .table_data(tag::VORG)?
+        .map(|data| {
+            ReadScope::new(&data)
+                .read::<VorgTable<'_>>()
+                .map(|vorg| vorg.subset(&new_to_old_glyph_id))
+        })
+        .transpose()?;
+
     // Build new maxp table
     let num_glyphs = u16::try_from(new_to_old_glyph_id.len()).map_err(ParseError::from)?;
     maxp.num_glyphs = num_glyphs;
@@ -211,12 +246,20 @@ fn subset_cff(
         &new_to_old_glyph_id,
     )?;
 
+    // Build new OS/2 table
+    let os_2 = create_os2_table(
+        provider,
+        &hmtx,
+        num_glyphs,
+        glyph_ids,
+        cmap0.as_ref().map(Box::as_ref),
+    )?;
+
     // Get the remaining tables
     let cvt = provider.table_data(tag::CVT)?;
     let fpgm = provider.table_data(tag::FPGM)?;
     let name = provider.table_data(tag::NAME)?;
     let prep = provider.table_data(tag::PREP)?;
-    let os_2 = provider.read_table_data(tag::OS_2)?;
 
     // Build the new font
     let mut builder = FontBuilder::new(tag::OTTO);
@@ -237,11 +280,20 @@ fn subset_cff(
     if let Some(name) = name {
         builder.add_table::<_, ReadScope<'_>>(tag::NAME, ReadScope::new(&name), ())?;
     }
-    builder.add_table::<_, ReadScope<'_>>(tag::OS_2, ReadScope::new(&os_2), ())?;
+    if let Some(os_2) = os_2 {
+        builder.add_table::<_, Os2>(tag::OS_2, &os_2, ())?;
+    }
     builder.add_table::<_, PostTable<'_>>(tag::POST, &post, ())?;
     if let Some(prep) = prep {
         builder.add_table::<_, ReadScope<'_>>(tag::PREP, ReadScope::new(&prep), ())?;
     }
+    if let Some((vhea, vmtx)) = vertical {
+        builder.add_table::<_, VheaTable>(tag::VHEA, &vhea, ())?;
+        builder.add_table::<_, VmtxTable<'_>>(tag::VMTX, &vmtx, ())?;
+    }
+    if let Some(vorg) = vorg {
+        builder.add_table::<_, VorgTable<'_>>(tag::VORG, &vorg, ())?;
+    }
     builder.add_table::<_, CFF<'_>>(tag::CFF, &cff, ())?;
     let builder = builder.add_head_table(&head)?;
     builder.data()
@@ -327,6 +379,66 @@ fn create_cmap_table(
     })
 }
 
+/// Build a new `OS/2` table with the values that depend on the glyphs and characters of the
+/// subset font recomputed.
+///
+/// Returns `None` if the font doesn't have an `OS/2` table. The character ranges are computed
+/// from `cmap0` if there is one, otherwise from the characters the Unicode `cmap` sub-table of
+/// the font maps to the glyphs in `glyph_ids`.
+fn create_os2_table(
+    provider: &impl FontTableProvider,
+    hmtx: &HmtxTable<'_>,
+    num_glyphs: u16,
+    glyph_ids: &[u16],
+    cmap0: Option<&[u8; 256]>,
+) -> Result<Option<Os2>, ReadWriteError> {
+    let os_2_data = match provider.table_data(tag::OS_2)? {
+        Some(os_2_data) => os_2_data,
+        None => return Ok(None),
+    };
+    let mut os_2 = ReadScope::new(&os_2_data).read_dep::<Os2>(os_2_data.len())?;
+    // The new hmtx table has a metric for every glyph
+    os_2.recompute_x_avg_char_width(hmtx, num_glyphs, num_glyphs)?;
+    let code_points = match cmap0 {
+        Some(cmap0) => cmap0
+            .iter()
+            .enumerate()
+            .filter(|&(_, &glyph_id)| glyph_id != 0)
+            .filter_map(|(byte, _)| macroman_to_char(byte as u8))
+            .map(u32::from)
+            .collect::<BTreeSet<_>>(),
+        None => unicode_code_points(provider, glyph_ids)?,
+    };
+    os_2.recompute_char_indices(&code_points);
+    os_2.recompute_unicode_ranges(&code_points);
+    os_2.recompute_code_page_ranges(&code_points);
+    Ok(Some(os_2))
+}
+
+/// Returns the characters that the Unicode `cmap` sub-table of the font maps to `glyph_ids`.
+///
+/// The set is empty if the font has no Unicode `cmap` sub-table.
+fn unicode_code_points(
+    provider: &impl FontTableProvider,
+    glyph_ids: &[u16],
+) -> Result<BTreeSet<u32>, ParseError> {
+    let cmap_data = match provider.table_data(tag::CMAP)? {
+        Some(cmap_data) => cmap_data,
+        None => return Ok(BTreeSet::new()),
+    };
+    let cmap = ReadScope::new(&cmap_data).read::<Cmap<'_>>()?;
+    let mut code_points = BTreeSet::new();
+    if let Some((Encoding::Unicode, subtable)) = read_cmap_subtable(&cmap)? {
+        let glyph_ids = glyph_ids.iter().collect::<BTreeSet<_>>();
+        subtable.mappings_fn(|ch, glyph_id| {
+            if glyph_ids.contains(&glyph_id) {
+                code_points.insert(ch);
+            }
+        })?;
+    }
+    Ok(code_points)
+}
+
 fn create_hmtx_table<'b>(
     hmtx: &HmtxTable<'_>,
     glyph_count: usize,
@@ -356,6 +468,33 @@ fn create_hmtx_table<'b>(
     })
 }
 
+/// Build new `vhea` and `vmtx` tables containing the glyphs in `new_to_old_id`.
+///
+/// Returns `None` if the font does not have vertical metrics. `num_glyphs` is the number of
+/// glyphs in the original font.
+fn create_vertical_tables<'b>(
+    provider: &impl FontTableProvider,
+    num_glyphs: u16,
+    new_to_old_id: &[u16],
+) -> Result<Option<(VheaTable, VmtxTable<'b>)>, ReadWriteError> {
+    let (vhea_data, vmtx_data) = match (
+        provider.table_data(tag::VHEA)?,
+        provider.table_data(tag::VMTX)?,
+    ) {
+        (Some(vhea_data), Some(vmtx_data)) => (vhea_data, vmtx_data),
+        _ => return Ok(None),
+    };
+    let mut vhea = ReadScope::new(&vhea_data).read::<VheaTable>()?;
+    let num_v_metrics = usize::from(vhea.num_h_metrics);
+    let vmtx = ReadScope::new(&vmtx_data)
+        .read_dep::<VmtxTable<'_>>((usize::from(num_glyphs), num_v_metrics))?;
+
+    let vmtx = create_hmtx_table(&vmtx, new_to_old_id.len(), num_v_metrics, new_to_old_id)?;
+    vhea.num_h_metrics = u16::try_from(new_to_old_id.len()).map_err(ParseError::from)?;
+
+    Ok(Some((vhea, vmtx)))
+}
+
 impl FontBuilder {
     pub fn new(sfnt_version: u32) -> Self {
         FontBuilder {
@@ -530,8 +669,9 @@ mod tests {
     };
     use crate::tables::{LongHorMetric, OpenTypeFile, OpenTypeFont};
     use crate::tag::DisplayTag;
-    use crate::tests::read_fixture;
+    use crate::tests::{push_u16, push_u32, read_fixture};
 
+    use std::borrow::Cow;
     use std::collections::HashSet;
 
     macro_rules! read_table {
@@ -961,6 +1101,118 @@ mod tests {
         }
     }
 
+    #[test]
+    fn subset_keeps_os2() {
+        let buffer = read_fixture("tests/fonts/opentype/SFNT-TTF-Composite.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let mut cmap0 = Box::new([0; 256]);
+        cmap0[usize::from(b'A')] = 1;
+        cmap0[usize::from(b'B')] = 2;
+        let glyph_ids = [0, 4, 5];
+
+        let subset_buffer = subset(
+            &opentype_file.font_provider(0).unwrap(),
+            &glyph_ids,
+            Some(cmap0),
+        )
+        .unwrap();
+        let subset_file = ReadScope::new(&subset_buffer)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let provider = subset_file.font_provider(0).unwrap();
+        let os_2_data = provider.read_table_data(tag::OS_2).unwrap();
+        let os_2 = ReadScope::new(&os_2_data)
+            .read_dep::<Os2>(os_2_data.len())
+            .unwrap();
+        assert_eq!(os_2.us_first_char_index, u16::from(b'A'));
+        assert_eq!(os_2.us_last_char_index, u16::from(b'B'));
+        assert_eq!(os_2.ul_unicode_range1, 1);
+        assert_eq!(os_2.ul_unicode_range2, 0);
+    }
+
+    #[test]
+    fn subset_recomputes_os2_without_cmap0() {
+        let buffer = read_fixture("tests/fonts/opentype/SFNT-TTF-Composite.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let glyph_ids = [0, 2, 3];
+
+        let subset_buffer =
+            subset(&opentype_file.font_provider(0).unwrap(), &glyph_ids, None).unwrap();
+        let subset_file = ReadScope::new(&subset_buffer)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let provider = subset_file.font_provider(0).unwrap();
+        let os_2_data = provider.read_table_data(tag::OS_2).unwrap();
+        let os_2 = ReadScope::new(&os_2_data)
+            .read_dep::<Os2>(os_2_data.len())
+            .unwrap();
+        assert_eq!(os_2.us_first_char_index, u16::from(b'F'));
+        assert_eq!(os_2.us_last_char_index, u16::from(b'P'));
+        assert_eq!(os_2.ul_unicode_range1, 1);
+        assert_eq!(os_2.ul_unicode_range2, 0);
+    }
+
+    #[test]
+    fn subset_recomputes_os2_from_shared_glyphs() {
+        // Provides the tables of a font with a replacement cmap table
+        struct WithCmap<'a, P: FontTableProvider> {
+            provider: &'a P,
+            cmap: Vec<u8>,
+        }
+
+        impl<'a, P: FontTableProvider> FontTableProvider for WithCmap<'a, P> {
+            fn table_data(&self, tag: u32) -> Result<Option<Cow<'_, [u8]>>, ParseError> {
+                if tag == tag::CMAP {
+                    Ok(Some(Cow::Borrowed(&self.cmap)))
+                } else {
+                    self.provider.table_data(tag)
+                }
+            }
+
+            fn has_table(&self, tag: u32) -> bool {
+                tag == tag::CMAP || self.provider.has_table(tag)
+            }
+        }
+
+        // A format 12 sub-table where space and no-break space map to glyph 1, and Latin B and
+        // Cyrillic Be to glyph 2
+        let groups = [(0x20, 1), (0x42, 2), (0xA0, 1), (u32::from('Б'), 2)];
+        let mut cmap = Vec::new();
+        push_u16(&mut cmap, &[0, 1, 3, 10]);
+        push_u32(&mut cmap, &[12]);
+        push_u16(&mut cmap, &[12, 0]);
+        push_u32(
+            &mut cmap,
+            &[16 + 12 * groups.len() as u32, 0, groups.len() as u32],
+        );
+        for &(ch, glyph_id) in &groups {
+            push_u32(&mut cmap, &[ch, ch, glyph_id]);
+        }
+
+        let buffer = read_fixture("tests/fonts/opentype/SFNT-TTF-Composite.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = WithCmap {
+            provider: &opentype_file.font_provider(0).unwrap(),
+            cmap,
+        };
+        let subset_buffer = subset(&provider, &[0, 1, 2], None).unwrap();
+        let subset_file = ReadScope::new(&subset_buffer)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let provider = subset_file.font_provider(0).unwrap();
+        let os_2_data = provider.read_table_data(tag::OS_2).unwrap();
+        let os_2 = ReadScope::new(&os_2_data)
+            .read_dep::<Os2>(os_2_data.len())
+            .unwrap();
+        assert_eq!(os_2.us_first_char_index, 0x20);
+        assert_eq!(os_2.us_last_char_index, 0x411);
+        // Basic Latin, Latin-1 Supplement and Cyrillic
+        assert_eq!(os_2.ul_unicode_range1, (1 << 9) | 0b11);
+        // Cyrillic
+        let version1 = os_2.version1.as_ref().unwrap();
+        assert_eq!(version1.ul_code_page_range1, 1 << 2);
+    }
+
     #[test]
     #[cfg(feature = "prince")]
     fn invalid_glyph_id() {
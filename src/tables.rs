@@ -1,10 +1,22 @@
 //! OpenType font table parsing and writing.
 
+pub mod aat;
+pub mod ankr;
+pub mod base;
 pub mod cmap;
+pub mod colr;
+pub mod cpal;
 pub mod glyf;
+pub mod jstf;
+pub mod kern;
+pub mod kerx;
 pub mod loca;
+pub mod math;
+pub mod morx;
 pub mod os2;
 pub mod svg;
+pub mod variable_fonts;
+pub mod vorg;
 
 use crate::binary::read::{
     CheckIndex, ReadArray, ReadArrayCow, ReadBinary, ReadBinaryDep, ReadCtxt, ReadFrom, ReadScope,
@@ -169,6 +181,22 @@ pub struct HmtxTable<'a> {
     pub left_side_bearings: ReadArrayCow<'a, I16Be>,
 }
 
+/// `vhea` vertical header table
+///
+/// <https://docs.microsoft.com/en-us/typography/opentype/spec/vhea>
+///
+/// The `vhea` table has the same layout as `hhea`. The horizontal field names of `HheaTable`
+/// hold the corresponding vertical values, e.g. `num_h_metrics` holds `numOfLongVerMetrics`.
+pub type VheaTable = HheaTable;
+
+/// `vmtx` vertical metrics table
+///
+/// <https://docs.microsoft.com/en-us/typography/opentype/spec/vmtx>
+///
+/// The `vmtx` table has the same layout as `hmtx`. The left side bearings of `HmtxTable` hold
+/// the top side bearings.
+pub type VmtxTable<'a> = HmtxTable<'a>;
+
 /// A `longHorMetric` record in the `hmtx` table.
 ///
 /// <https://docs.microsoft.com/en-us/typography/opentype/spec/hmtx>
@@ -633,6 +661,23 @@ impl<'a> HmtxTable<'a> {
             .and_then(|_| self.h_metrics.read_item(index))
             .map(|long_hor_metric| long_hor_metric.advance_width)
     }
+
+    /// Returns the left side bearing of `glyph_id`, or the top side bearing if this is a `vmtx`
+    /// table.
+    pub fn side_bearing(&self, glyph_id: u16, num_h_metrics: u16) -> Result<i16, ParseError> {
+        if glyph_id < num_h_metrics {
+            let index = usize::from(glyph_id);
+            self.h_metrics
+                .check_index(index)
+                .and_then(|_| self.h_metrics.read_item(index))
+                .map(|long_hor_metric| long_hor_metric.lsb)
+        } else {
+            let index = usize::from(glyph_id - num_h_metrics);
+            self.left_side_bearings
+                .check_index(index)
+                .and_then(|_| self.left_side_bearings.read_item(index))
+        }
+    }
 }
 
 impl<'a> ReadFrom<'a> for LongHorMetric {
@@ -895,13 +940,302 @@ impl F2Dot14 {
     pub fn new(value: u16) -> Self {
         F2Dot14(value)
     }
+
+    /// Convert an `f32` to the nearest `F2Dot14`, saturating values outside its range.
+    pub fn from_f32(value: f32) -> Self {
+        F2Dot14((value * 16384.0).round() as i16 as u16)
+    }
+}
+
+impl From<F2Dot14> for f32 {
+    fn from(value: F2Dot14) -> f32 {
+        f32::from(value.0 as i16) / 16384.0
+    }
+}
+
+pub mod owned {
+    //! Owned `name` table, for editing and writing names.
+
+    use std::collections::HashMap;
+    use std::convert::TryFrom;
+
+    use super::{ParseError, U16Be, WriteBinary, WriteContext, WriteError};
+    use crate::get_name::{mac_language_id, windows_language_id};
+    use crate::macroman::char_to_macroman;
+
+    const PLATFORM_UNICODE: u16 = 0;
+    const PLATFORM_MACINTOSH: u16 = 1;
+    const PLATFORM_WINDOWS: u16 = 3;
+    const MACINTOSH_ROMAN: u16 = 0;
+    const WINDOWS_UNICODE_BMP: u16 = 1;
+    const FIRST_LANG_TAG_ID: u16 = 0x8000;
+
+    /// `name` table
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct NameTable {
+        pub name_records: Vec<NameRecord>,
+        /// BCP 47 language tags, referenced by language IDs from `0x8000`. Tables with
+        /// language tags are written as version 1.
+        pub langtag_records: Vec<String>,
+    }
+
+    /// Record within the `name` table
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct NameRecord {
+        pub platform_id: u16,
+        pub encoding_id: u16,
+        pub language_id: u16,
+        pub name_id: u16,
+        /// The encoded string.
+        pub string: Vec<u8>,
+    }
+
+    impl NameTable {
+        /// Add `string` for `name_id` in the language with BCP 47 tag `language`.
+        ///
+        /// A Windows Unicode record is added, using a language-tag record if `language` has no
+        /// Windows language ID. A Macintosh Roman record is added as well if `language` has a
+        /// Macintosh language ID and `string` can be encoded in Mac OS Roman. Existing records
+        /// for the same platform, encoding, language and name ID are replaced.
+        pub fn add_name(&mut self, name_id: u16, language: &str, string: &str) {
+            let language_id = match windows_language_id(language) {
+                Some(language_id) => language_id,
+                None => self.langtag_id(language),
+            };
+            self.replace_record(NameRecord {
+                platform_id: PLATFORM_WINDOWS,
+                encoding_id: WINDOWS_UNICODE_BMP,
+                language_id,
+                name_id,
+                string: encode_utf16be(string),
+            });
+            if let Some(language_id) = mac_language_id(language) {
+                if let Some(string) = encode_macroman(string) {
+                    self.replace_record(NameRecord {
+                        platform_id: PLATFORM_MACINTOSH,
+                        encoding_id: MACINTOSH_ROMAN,
+                        language_id,
+                        name_id,
+                        string,
+                    });
+                }
+            }
+        }
+
+        /// Add `string` for `name_id` with the given platform, encoding and language IDs.
+        ///
+        /// Strings are encoded as UTF-16BE for the Unicode and Windows platforms, and in
+        /// Mac OS Roman for Macintosh Roman records. Returns `WriteError::BadValue` if the
+        /// string can't be encoded, or `WriteError::NotImplemented` for other encodings.
+        /// An existing record with the same IDs is replaced.
+        pub fn add_record(
+            &mut self,
+            platform_id: u16,
+            encoding_id: u16,
+            language_id: u16,
+            name_id: u16,
+            string: &str,
+        ) -> Result<(), WriteError> {
+            let string = match (platform_id, encoding_id) {
+                (PLATFORM_UNICODE, _)
+                | (PLATFORM_WINDOWS, 0)
+                | (PLATFORM_WINDOWS, 1)
+                | (PLATFORM_WINDOWS, 10) => encode_utf16be(string),
+                (PLATFORM_MACINTOSH, MACINTOSH_ROMAN) => {
+                    encode_macroman(string).ok_or(WriteError::BadValue)?
+                }
+                _ => return Err(WriteError::NotImplemented),
+            };
+            self.replace_record(NameRecord {
+                platform_id,
+                encoding_id,
+                language_id,
+                name_id,
+                string,
+            });
+            Ok(())
+        }
+
+        /// Remove all records for `name_id`.
+        pub fn remove_name(&mut self, name_id: u16) {
+            self.name_records.retain(|record| record.name_id != name_id);
+        }
+
+        fn replace_record(&mut self, record: NameRecord) {
+            let existing = self.name_records.iter_mut().find(|existing| {
+                existing.platform_id == record.platform_id
+                    && existing.encoding_id == record.encoding_id
+                    && existing.language_id == record.language_id
+                    && existing.name_id == record.name_id
+            });
+            match existing {
+                Some(existing) => *existing = record,
+                None => self.name_records.push(record),
+            }
+        }
+
+        /// Returns the language ID of the language-tag record for `language`, adding it if
+        /// necessary.
+        fn langtag_id(&mut self, language: &str) -> u16 {
+            let index = match self
+                .langtag_records
+                .iter()
+                .position(|tag| tag.eq_ignore_ascii_case(language))
+            {
+                Some(index) => index,
+                None => {
+                    self.langtag_records.push(language.to_string());
+                    self.langtag_records.len() - 1
+                }
+            };
+            // Overflow is caught when the table is written
+            FIRST_LANG_TAG_ID.wrapping_add(index as u16)
+        }
+    }
+
+    impl<'a> TryFrom<&super::NameTable<'a>> for NameTable {
+        type Error = ParseError;
+
+        fn try_from(name_table: &super::NameTable<'a>) -> Result<Self, Self::Error> {
+            let name_records = name_table
+                .name_records
+                .iter()
+                .map(|record| {
+                    let string = name_table
+                        .string_storage
+                        .offset_length(usize::from(record.offset), usize::from(record.length))?
+                        .data()
+                        .to_vec();
+                    Ok(NameRecord {
+                        platform_id: record.platform_id,
+                        encoding_id: record.encoding_id,
+                        language_id: record.language_id,
+                        name_id: record.name_id,
+                        string,
+                    })
+                })
+                .collect::<Result<Vec<_>, ParseError>>()?;
+            let langtag_records = match name_table.opt_langtag_records {
+                Some(ref records) => records
+                    .iter()
+                    .map(|record| {
+                        let data = name_table
+                            .string_storage
+                            .offset_length(usize::from(record.offset), usize::from(record.length))?
+                            .data();
+                        decode_utf16be(data).ok_or(ParseError::BadValue)
+                    })
+                    .collect::<Result<Vec<_>, ParseError>>()?,
+                None => Vec::new(),
+            };
+            Ok(NameTable {
+                name_records,
+                langtag_records,
+            })
+        }
+    }
+
+    impl WriteBinary<&Self> for NameTable {
+        type Output = ();
+
+        /// Write the table, with the records sorted and identical strings stored once.
+        fn write<C: WriteContext>(ctxt: &mut C, name: &NameTable) -> Result<(), WriteError> {
+            if usize::from(std::u16::MAX - FIRST_LANG_TAG_ID) < name.langtag_records.len() {
+                return Err(WriteError::BadValue);
+            }
+            let mut records = name.name_records.iter().collect::<Vec<_>>();
+            records.sort_by_key(|record| {
+                (
+                    record.platform_id,
+                    record.encoding_id,
+                    record.language_id,
+                    record.name_id,
+                )
+            });
+            let langtags = name
+                .langtag_records
+                .iter()
+                .map(|tag| encode_utf16be(tag))
+                .collect::<Vec<_>>();
+
+            let mut storage = Vec::new();
+            let mut offsets = HashMap::new();
+            let mut store = |string: &[u8]| -> Result<(u16, u16), WriteError> {
+                let length = u16::try_from(string.len())?;
+                let offset = match offsets.get(string) {
+                    Some(&offset) => offset,
+                    None => {
+                        let offset = u16::try_from(storage.len())?;
+                        storage.extend_from_slice(string);
+                        offsets.insert(string.to_vec(), offset);
+                        offset
+                    }
+                };
+                Ok((length, offset))
+            };
+
+            let format = if langtags.is_empty() { 0u16 } else { 1 };
+            let count = u16::try_from(records.len())?;
+            // format, count and string offset, then the records and language-tag records
+            let mut string_offset = 3 * 2 + usize::from(count) * 12;
+            if format == 1 {
+                string_offset += 2 + langtags.len() * 4;
+            }
+            U16Be::write(ctxt, format)?;
+            U16Be::write(ctxt, count)?;
+            U16Be::write(ctxt, u16::try_from(string_offset)?)?;
+            for record in records {
+                let (length, offset) = store(&record.string)?;
+                U16Be::write(ctxt, record.platform_id)?;
+                U16Be::write(ctxt, record.encoding_id)?;
+                U16Be::write(ctxt, record.language_id)?;
+                U16Be::write(ctxt, record.name_id)?;
+                U16Be::write(ctxt, length)?;
+                U16Be::write(ctxt, offset)?;
+            }
+            if format == 1 {
+                U16Be::write(ctxt, u16::try_from(langtags.len())?)?;
+                for tag in &langtags {
+                    let (length, offset) = store(tag)?;
+                    U16Be::write(ctxt, length)?;
+                    U16Be::write(ctxt, offset)?;
+                }
+            }
+            ctxt.write_bytes(&storage)
+        }
+    }
+
+    fn encode_utf16be(string: &str) -> Vec<u8> {
+        string
+            .encode_utf16()
+            .flat_map(|unit| unit.to_be_bytes().to_vec())
+            .collect()
+    }
+
+    fn decode_utf16be(data: &[u8]) -> Option<String> {
+        let pairs = data.chunks_exact(2);
+        if !pairs.remainder().is_empty() {
+            return None;
+        }
+        let units = pairs
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+        String::from_utf16(&units).ok()
+    }
+
+    fn encode_macroman(string: &str) -> Option<Vec<u8>> {
+        string.chars().map(char_to_macroman).collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{HeadTable, HmtxTable, NameTable};
+    use super::{owned, HeadTable, HmtxTable, NameTable};
     use crate::binary::read::ReadScope;
     use crate::binary::write::{WriteBinary, WriteBuffer, WriteContext};
+    use crate::error::WriteError;
+    use crate::get_name;
+    use std::convert::TryFrom;
 
     #[test]
     fn test_write_head_table() {
@@ -945,4 +1279,72 @@ mod tests {
 
         assert_eq!(ctxt.bytes(), &name_data[..]);
     }
+
+    #[test]
+    fn test_write_owned_name_table() {
+        let mut name = owned::NameTable::default();
+        name.add_name(1, "en-US", "Subset Sans");
+        name.add_name(2, "en-US", "Regular");
+        name.add_name(2, "de", "Standard");
+        name.add_name(2, "tlh", "Standard");
+        name.add_name(4, "en-US", "Subset Sans Regular");
+        name.add_name(1, "en-US", "Subset Serif");
+        name.add_record(0, 3, 0, 16, "Subset Serif").unwrap();
+        match name.add_record(1, 0, 0, 17, "\u{3042}") {
+            Err(WriteError::BadValue) => {}
+            _ => panic!("expected WriteError::BadValue"),
+        }
+        name.remove_name(4);
+
+        let mut ctxt = WriteBuffer::new();
+        owned::NameTable::write(&mut ctxt, &name).unwrap();
+        let data = ctxt.into_inner();
+        let table = ReadScope::new(&data).read::<NameTable<'_>>().unwrap();
+        let names = get_name::names(&table)
+            .map(|name| {
+                (
+                    name.platform_id,
+                    name.language_tag,
+                    name.name_id,
+                    name.string,
+                )
+            })
+            .collect::<Vec<_>>();
+        let expected = [
+            (0, None, 16, "Subset Serif"),
+            (1, Some("en"), 1, "Subset Serif"),
+            (1, Some("en"), 2, "Regular"),
+            (1, Some("de"), 2, "Standard"),
+            (3, Some("de-DE"), 2, "Standard"),
+            (3, Some("en-US"), 1, "Subset Serif"),
+            (3, Some("en-US"), 2, "Regular"),
+            (3, Some("tlh"), 2, "Standard"),
+        ];
+        assert_eq!(names.len(), expected.len());
+        for (name, &(platform_id, language_tag, name_id, string)) in names.iter().zip(&expected) {
+            assert_eq!(name.0, platform_id);
+            assert_eq!(name.1, language_tag.map(String::from));
+            assert_eq!(name.2, name_id);
+            assert_eq!(name.3, string);
+        }
+
+        // Identical strings are only stored once
+        let string_offset = usize::from(u16::from_be_bytes([data[4], data[5]]));
+        let storage_len =
+            "Subset Serif".len() * 3 + "Regular".len() * 3 + "Standard".len() * 3 + "tlh".len() * 2;
+        assert_eq!(data.len() - string_offset, storage_len);
+    }
+
+    #[test]
+    fn test_owned_name_table_round_trip() {
+        let name_data = include_bytes!("../tests/fonts/opentype/name.bin");
+        let name = ReadScope::new(name_data).read::<NameTable<'_>>().unwrap();
+        let owned_name = owned::NameTable::try_from(&name).unwrap();
+
+        let mut ctxt = WriteBuffer::new();
+        owned::NameTable::write(&mut ctxt, &owned_name).unwrap();
+        let data = ctxt.into_inner();
+        let written = ReadScope::new(&data).read::<NameTable<'_>>().unwrap();
+        assert_eq!(owned::NameTable::try_from(&written).unwrap(), owned_name);
+    }
 }
@@ -0,0 +1,364 @@
+#![deny(missing_docs)]
+
+//! Structures shared by the Apple Advanced Typography (AAT) tables.
+//!
+//! <https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6Tables.html>
+
+use std::convert::TryFrom;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt, ReadFrom, ReadScope, ReadUnchecked};
+use crate::binary::{U16Be, U32Be, U8};
+use crate::error::ParseError;
+use crate::size;
+
+/// The glyph id used to mark a glyph as deleted.
+pub const DELETED_GLYPH: u16 = 0xFFFF;
+
+/// The class of the end of the glyph sequence.
+pub const CLASS_END_OF_TEXT: u16 = 0;
+/// The class of glyphs not covered by a class table.
+pub const CLASS_OUT_OF_BOUNDS: u16 = 1;
+/// The class of deleted glyphs.
+pub const CLASS_DELETED_GLYPH: u16 = 2;
+/// The class of the end of a line.
+pub const CLASS_END_OF_LINE: u16 = 3;
+
+/// The state a state machine starts in at the beginning of the glyph sequence.
+pub const STATE_START_OF_TEXT: u16 = 0;
+
+/// A lookup table mapping glyph ids to 16-bit values.
+pub enum LookupTable<'a> {
+    /// Format 0: simple array indexed by glyph id.
+    Format0(ReadArray<'a, U16Be>),
+    /// Format 2: segments of glyphs mapping to a single value.
+    Format2(ReadArray<'a, LookupSegment>),
+    /// Format 4: segments of glyphs mapping to an array of values.
+    Format4 {
+        /// The scope of the lookup table, which the value offsets are relative to.
+        scope: ReadScope<'a>,
+        /// The segments, where `value` is the offset of the values for the segment.
+        segments: ReadArray<'a, LookupSegment>,
+    },
+    /// Format 6: sorted pairs of glyph id and value.
+    Format6(ReadArray<'a, LookupSingle>),
+    /// Format 8: trimmed array of values for a contiguous range of glyphs.
+    Format8 {
+        /// The glyph id of the first value.
+        first_glyph: u16,
+        /// The values, one per glyph.
+        values: ReadArray<'a, U16Be>,
+    },
+    /// Format 10: trimmed array of values of `unit_size` bytes each.
+    Format10 {
+        /// The size of each value in bytes.
+        unit_size: usize,
+        /// The glyph id of the first value.
+        first_glyph: u16,
+        /// The number of values.
+        glyph_count: usize,
+        /// The values.
+        values: ReadScope<'a>,
+    },
+}
+
+/// A segment of a format 2 or 4 lookup table.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LookupSegment {
+    /// The last glyph id in the segment.
+    pub last_glyph: u16,
+    /// The first glyph id in the segment.
+    pub first_glyph: u16,
+    /// The value for the segment (format 2) or the offset to its values (format 4).
+    pub value: u16,
+}
+
+/// A single glyph entry of a format 6 lookup table.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LookupSingle {
+    /// The glyph id.
+    pub glyph: u16,
+    /// The value for the glyph.
+    pub value: u16,
+}
+
+/// An extended state table, as used by `morx` and `kerx` subtables.
+pub struct ExtendedStateTable<'a> {
+    n_classes: u32,
+    class_table: LookupTable<'a>,
+    state_array: ReadScope<'a>,
+    entry_table: ReadScope<'a>,
+}
+
+impl<'a> ReadFrom<'a> for LookupSegment {
+    type ReadType = (U16Be, U16Be, U16Be);
+    fn from((last_glyph, first_glyph, value): (u16, u16, u16)) -> Self {
+        LookupSegment {
+            last_glyph,
+            first_glyph,
+            value,
+        }
+    }
+}
+
+impl<'a> ReadFrom<'a> for LookupSingle {
+    type ReadType = (U16Be, U16Be);
+    fn from((glyph, value): (u16, u16)) -> Self {
+        LookupSingle { glyph, value }
+    }
+}
+
+impl<'a> ReadBinary<'a> for LookupTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        match ctxt.read_u16be()? {
+            0 => {
+                // The array covers every glyph in the font but the number of glyphs isn't known
+                // here, so all remaining data is treated as the array.
+                let count = ctxt.scope().data().len() / size::U16;
+                Ok(LookupTable::Format0(ctxt.read_array::<U16Be>(count)?))
+            }
+            2 => {
+                let segments = read_binary_search_array::<LookupSegment>(ctxt)?;
+                Ok(LookupTable::Format2(segments))
+            }
+            4 => {
+                let segments = read_binary_search_array::<LookupSegment>(ctxt)?;
+                Ok(LookupTable::Format4 { scope, segments })
+            }
+            6 => {
+                let entries = read_binary_search_array::<LookupSingle>(ctxt)?;
+                Ok(LookupTable::Format6(entries))
+            }
+            8 => {
+                let first_glyph = ctxt.read_u16be()?;
+                let glyph_count = usize::from(ctxt.read_u16be()?);
+                let values = ctxt.read_array::<U16Be>(glyph_count)?;
+                Ok(LookupTable::Format8 {
+                    first_glyph,
+                    values,
+                })
+            }
+            10 => {
+                let unit_size = usize::from(ctxt.read_u16be()?);
+                ctxt.check((1..=4).contains(&unit_size))?;
+                let first_glyph = ctxt.read_u16be()?;
+                let glyph_count = usize::from(ctxt.read_u16be()?);
+                let values = ctxt.read_scope(unit_size * glyph_count)?;
+                Ok(LookupTable::Format10 {
+                    unit_size,
+                    first_glyph,
+                    glyph_count,
+                    values,
+                })
+            }
+            _ => Err(ParseError::BadVersion),
+        }
+    }
+}
+
+/// Read the units described by a binary search header.
+fn read_binary_search_array<'a, T: ReadUnchecked<'a>>(
+    ctxt: &mut ReadCtxt<'a>,
+) -> Result<ReadArray<'a, T>, ParseError> {
+    let unit_size = usize::from(ctxt.read_u16be()?);
+    let n_units = usize::from(ctxt.read_u16be()?);
+    let _search_range = ctxt.read_u16be()?;
+    let _entry_selector = ctxt.read_u16be()?;
+    let _range_shift = ctxt.read_u16be()?;
+    ctxt.check(unit_size == T::SIZE)?;
+    ctxt.read_array::<T>(n_units)
+}
+
+impl<'a> LookupTable<'a> {
+    /// Look up the value for `glyph`, returning `None` if the glyph is not in the table.
+    pub fn lookup(&self, glyph: u16) -> Result<Option<u16>, ParseError> {
+        match *self {
+            LookupTable::Format0(ref values) => {
+                let index = usize::from(glyph);
+                if index < values.len() {
+                    Ok(Some(values.get_item(index)))
+                } else {
+                    Ok(None)
+                }
+            }
+            LookupTable::Format2(ref segments) => {
+                Ok(find_segment(segments, glyph).map(|segment| segment.value))
+            }
+            LookupTable::Format4 {
+                ref scope,
+                ref segments,
+            } => match find_segment(segments, glyph) {
+                Some(segment) => {
+                    let offset = usize::from(segment.value)
+                        + usize::from(glyph - segment.first_glyph) * size::U16;
+                    scope.offset(offset).read::<U16Be>().map(Some)
+                }
+                None => Ok(None),
+            },
+            LookupTable::Format6(ref entries) => {
+                let (mut lo, mut hi) = (0, entries.len());
+                while lo < hi {
+                    let mid = lo + (hi - lo) / 2;
+                    let entry = entries.get_item(mid);
+                    if entry.glyph < glyph {
+                        lo = mid + 1;
+                    } else if entry.glyph > glyph {
+                        hi = mid;
+                    } else {
+                        return Ok(Some(entry.value));
+                    }
+                }
+                Ok(None)
+            }
+            LookupTable::Format8 {
+                first_glyph,
+                ref values,
+            } => match glyph.checked_sub(first_glyph).map(usize::from) {
+                Some(index) if index < values.len() => Ok(Some(values.get_item(index))),
+                _ => Ok(None),
+            },
+            LookupTable::Format10 {
+                unit_size,
+                first_glyph,
+                glyph_count,
+                ref values,
+            } => match glyph.checked_sub(first_glyph).map(usize::from) {
+                Some(index) if index < glyph_count => {
+                    let mut ctxt = values.offset(index * unit_size).ctxt();
+                    let bytes = ctxt.read_array::<U8>(unit_size)?;
+                    let value = bytes
+                        .iter()
+                        .fold(0u32, |value, byte| (value << 8) | u32::from(byte));
+                    // Values wider than 16 bits are truncated, as all users of lookup tables
+                    // expect 16-bit values.
+                    Ok(Some(value as u16))
+                }
+                _ => Ok(None),
+            },
+        }
+    }
+}
+
+/// Binary search for the segment containing `glyph`.
+fn find_segment(segments: &ReadArray<'_, LookupSegment>, glyph: u16) -> Option<LookupSegment> {
+    let (mut lo, mut hi) = (0, segments.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let segment = segments.get_item(mid);
+        if segment.last_glyph < glyph {
+            lo = mid + 1;
+        } else if segment.first_glyph > glyph {
+            hi = mid;
+        } else {
+            return Some(segment);
+        }
+    }
+    None
+}
+
+impl<'a> ReadBinary<'a> for ExtendedStateTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let n_classes = ctxt.read_u32be()?;
+        let class_table_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let state_array_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let entry_table_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let class_table = scope.offset(class_table_offset).read::<LookupTable<'a>>()?;
+        Ok(ExtendedStateTable {
+            n_classes,
+            class_table,
+            state_array: scope.offset(state_array_offset),
+            entry_table: scope.offset(entry_table_offset),
+        })
+    }
+}
+
+impl<'a> ExtendedStateTable<'a> {
+    /// The size of the extended state table header.
+    pub const HEADER_SIZE: usize = 4 * size::U32;
+
+    /// Returns the class of `glyph`.
+    pub fn class(&self, glyph: u16) -> Result<u16, ParseError> {
+        if glyph == DELETED_GLYPH {
+            return Ok(CLASS_DELETED_GLYPH);
+        }
+        match self.class_table.lookup(glyph)? {
+            Some(class) if u32::from(class) < self.n_classes => Ok(class),
+            _ => Ok(CLASS_OUT_OF_BOUNDS),
+        }
+    }
+
+    /// Read the entry for the transition from `state` on a glyph of `class`.
+    ///
+    /// `T` is the type of the entries in the entry table, which differs between subtable types.
+    pub fn entry<T: ReadUnchecked<'a>>(
+        &self,
+        state: u16,
+        class: u16,
+    ) -> Result<T::HostType, ParseError> {
+        let n_classes = usize::try_from(self.n_classes)?;
+        let state_index = usize::from(state) * n_classes + usize::from(class);
+        let entry_index = self
+            .state_array
+            .offset(state_index * size::U16)
+            .read::<U16Be>()?;
+        self.entry_table
+            .offset(usize::from(entry_index) * T::SIZE)
+            .read::<T>()
+    }
+}
+
+/// Read a `U32Be` value at `index` in an array of unknown length starting at `scope`.
+pub(crate) fn read_u32_at(scope: &ReadScope<'_>, index: usize) -> Result<u32, ParseError> {
+    scope.offset(index * size::U32).read::<U32Be>()
+}
+
+/// Read a `U16Be` value at `index` in an array of unknown length starting at `scope`.
+pub(crate) fn read_u16_at(scope: &ReadScope<'_>, index: usize) -> Result<u16, ParseError> {
+    scope.offset(index * size::U16).read::<U16Be>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_table_formats() {
+        let format2 = [
+            0x00, 0x02, 0x00, 0x06, 0x00, 0x02, 0x00, 0x0C, 0x00, 0x01, 0x00, 0x00, //
+            0x00, 0x0A, 0x00, 0x05, 0x00, 0x07, // 5-10: 7
+            0x00, 0x14, 0x00, 0x14, 0x00, 0x08, // 20: 8
+        ];
+        let table = ReadScope::new(&format2).read::<LookupTable<'_>>().unwrap();
+        assert_eq!(table.lookup(4).unwrap(), None);
+        assert_eq!(table.lookup(5).unwrap(), Some(7));
+        assert_eq!(table.lookup(10).unwrap(), Some(7));
+        assert_eq!(table.lookup(20).unwrap(), Some(8));
+        assert_eq!(table.lookup(21).unwrap(), None);
+
+        let format4 = [
+            0x00, 0x04, 0x00, 0x06, 0x00, 0x01, 0x00, 0x06, 0x00, 0x00, 0x00, 0x00, //
+            0x00, 0x03, 0x00, 0x02, 0x00, 0x12, // 2-3: values at 18
+            0x00, 0x64, 0x00, 0x65,
+        ];
+        let table = ReadScope::new(&format4).read::<LookupTable<'_>>().unwrap();
+        assert_eq!(table.lookup(2).unwrap(), Some(100));
+        assert_eq!(table.lookup(3).unwrap(), Some(101));
+        assert_eq!(table.lookup(4).unwrap(), None);
+
+        let format8 = [0x00, 0x08, 0x00, 0x03, 0x00, 0x02, 0x00, 0x01, 0x00, 0x02];
+        let table = ReadScope::new(&format8).read::<LookupTable<'_>>().unwrap();
+        assert_eq!(table.lookup(2).unwrap(), None);
+        assert_eq!(table.lookup(3).unwrap(), Some(1));
+        assert_eq!(table.lookup(4).unwrap(), Some(2));
+        assert_eq!(table.lookup(5).unwrap(), None);
+
+        let format10 = [0x00, 0x0A, 0x00, 0x01, 0x00, 0x03, 0x00, 0x02, 0x05, 0x06];
+        let table = ReadScope::new(&format10).read::<LookupTable<'_>>().unwrap();
+        assert_eq!(table.lookup(4).unwrap(), Some(6));
+    }
+}
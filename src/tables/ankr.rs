@@ -0,0 +1,84 @@
+#![deny(missing_docs)]
+
+//! `ankr` Anchor Point Table parsing.
+//!
+//! <https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6ankr.html>
+
+use std::convert::TryFrom;
+
+use crate::binary::read::{ReadBinary, ReadCtxt, ReadScope};
+use crate::error::ParseError;
+use crate::layout::Anchor;
+use crate::size;
+use crate::tables::aat::LookupTable;
+
+/// `ankr` Anchor Point Table.
+pub struct AnkrTable<'a> {
+    lookup_table: LookupTable<'a>,
+    glyph_data: ReadScope<'a>,
+}
+
+impl<'a> ReadBinary<'a> for AnkrTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let version = ctxt.read_u16be()?;
+        ctxt.check_version(version == 0)?;
+        let _flags = ctxt.read_u16be()?;
+        let lookup_table_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let glyph_data_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let lookup_table = scope
+            .offset(lookup_table_offset)
+            .read::<LookupTable<'a>>()?;
+        Ok(AnkrTable {
+            lookup_table,
+            glyph_data: scope.offset(glyph_data_offset),
+        })
+    }
+}
+
+impl<'a> AnkrTable<'a> {
+    /// Returns the anchor point at `index` of `glyph`.
+    ///
+    /// Returns `None` if the glyph has no anchor points or fewer than `index + 1`.
+    pub fn anchor(&self, glyph: u16, index: u16) -> Result<Option<Anchor>, ParseError> {
+        let offset = match self.lookup_table.lookup(glyph)? {
+            Some(offset) => usize::from(offset),
+            None => return Ok(None),
+        };
+        let mut ctxt = self.glyph_data.offset(offset).ctxt();
+        let n_points = ctxt.read_u32be()?;
+        if u32::from(index) >= n_points {
+            return Ok(None);
+        }
+        let mut ctxt = self
+            .glyph_data
+            .offset(offset + size::U32 + usize::from(index) * 2 * size::I16)
+            .ctxt();
+        let x = ctxt.read_i16be()?;
+        let y = ctxt.read_i16be()?;
+        Ok(Some(Anchor { x, y }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anchor() {
+        let data = [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x00, 0x00, 0x00, 0x16, //
+            // lookup table: format 8, glyph 3 has anchors at offset 0
+            0x00, 0x08, 0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, //
+            // glyph data: two anchors
+            0x00, 0x00, 0x00, 0x02, 0x00, 0x64, 0x00, 0xC8, 0xFF, 0x9C, 0x00, 0x32,
+        ];
+        let ankr = ReadScope::new(&data).read::<AnkrTable<'_>>().unwrap();
+        let anchor = ankr.anchor(3, 1).unwrap().unwrap();
+        assert_eq!((anchor.x, anchor.y), (-100, 50));
+        assert!(ankr.anchor(3, 2).unwrap().is_none());
+        assert!(ankr.anchor(4, 0).unwrap().is_none());
+    }
+}
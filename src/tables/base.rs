@@ -0,0 +1,485 @@
+#![deny(missing_docs)]
+
+//! `BASE` Baseline Table parsing.
+//!
+//! The `BASE` table gives the positions of baselines, such as the roman, ideographic and hanging
+//! baselines, for each script, along with the minimum and maximum extents of glyphs. It's used
+//! to align runs of text in different scripts on the same line.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/base>
+
+use std::convert::TryFrom;
+
+use crate::binary::read::{ReadBinary, ReadBinaryDep, ReadCtxt, ReadFixedSizeDep, ReadScope};
+use crate::binary::U32Be;
+use crate::error::ParseError;
+use crate::size;
+use crate::tables::os2::Os2;
+use crate::tag;
+
+/// `BASE` Baseline Table.
+pub struct BaseTable {
+    /// Major version of the table, `1`.
+    pub major_version: u16,
+    /// Minor version of the table, `0` or `1`.
+    pub minor_version: u16,
+    /// Baseline data for horizontal text.
+    pub horiz_axis: Option<Axis>,
+    /// Baseline data for vertical text.
+    pub vert_axis: Option<Axis>,
+}
+
+/// Baseline data for horizontal or vertical text.
+pub struct Axis {
+    /// The baselines described by the axis. The baseline coordinates of each script are in the
+    /// same order.
+    pub baseline_tags: Vec<u32>,
+    /// Baseline data for each script, sorted by script tag.
+    pub base_script_records: Vec<BaseScriptRecord>,
+}
+
+/// Baseline data for a script.
+pub struct BaseScriptRecord {
+    /// The script tag.
+    pub script_tag: u32,
+    /// The baseline data.
+    pub base_script: BaseScript,
+}
+
+/// The baseline coordinates and extents of a script.
+pub struct BaseScript {
+    /// Coordinates of the baselines of the script.
+    pub base_values: Option<BaseValues>,
+    /// Extents of the script when no language-specific extents apply.
+    pub default_min_max: Option<MinMax>,
+    /// Language-specific extents of the script, sorted by language system tag.
+    pub base_lang_sys_records: Vec<BaseLangSysRecord>,
+}
+
+/// Extents of a language system.
+pub struct BaseLangSysRecord {
+    /// The language system tag.
+    pub lang_sys_tag: u32,
+    /// The extents.
+    pub min_max: MinMax,
+}
+
+/// Coordinates of the baselines of a script.
+pub struct BaseValues {
+    /// Index into `Axis::baseline_tags` of the baseline used by the script.
+    pub default_baseline_index: u16,
+    /// The coordinate of each baseline in `Axis::baseline_tags`, in font units.
+    pub base_coords: Vec<i16>,
+}
+
+/// Minimum and maximum extents of glyphs, in font units.
+///
+/// For horizontal text these are the lowest and highest y coordinates, for vertical text the
+/// left-most and right-most x coordinates.
+pub struct MinMax {
+    /// The minimum extent.
+    pub min_coord: Option<i16>,
+    /// The maximum extent.
+    pub max_coord: Option<i16>,
+    /// Extents that apply when a feature is enabled, sorted by feature tag.
+    pub feat_min_max_records: Vec<FeatMinMaxRecord>,
+}
+
+/// Extents that apply when a feature is enabled.
+pub struct FeatMinMaxRecord {
+    /// The feature tag.
+    pub feature_tag: u32,
+    /// The minimum extent when the feature is enabled.
+    pub min_coord: Option<i16>,
+    /// The maximum extent when the feature is enabled.
+    pub max_coord: Option<i16>,
+}
+
+impl<'a> ReadBinary<'a> for BaseTable {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let major_version = ctxt.read_u16be()?;
+        ctxt.check_version(major_version == 1)?;
+        let minor_version = ctxt.read_u16be()?;
+        let horiz_axis_offset = usize::from(ctxt.read_u16be()?);
+        let vert_axis_offset = usize::from(ctxt.read_u16be()?);
+        // Version 1.1 adds an item variation store, which isn't used
+
+        Ok(BaseTable {
+            major_version,
+            minor_version,
+            horiz_axis: read_optional::<Axis>(&scope, horiz_axis_offset)?,
+            vert_axis: read_optional::<Axis>(&scope, vert_axis_offset)?,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for Axis {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let base_tag_list_offset = usize::from(ctxt.read_u16be()?);
+        let base_script_list_offset = usize::from(ctxt.read_u16be()?);
+
+        let baseline_tags = if base_tag_list_offset != 0 {
+            let mut ctxt = scope.offset(base_tag_list_offset).ctxt();
+            let base_tag_count = usize::from(ctxt.read_u16be()?);
+            ctxt.read_array::<U32Be>(base_tag_count)?.to_vec()
+        } else {
+            Vec::new()
+        };
+
+        let base_script_list = scope.offset(base_script_list_offset);
+        let mut ctxt = base_script_list.ctxt();
+        let base_script_count = usize::from(ctxt.read_u16be()?);
+        let base_script_records = ctxt
+            .read_array_dep::<BaseScriptRecord>(base_script_count, base_script_list)?
+            .read_to_vec()?;
+
+        Ok(Axis {
+            baseline_tags,
+            base_script_records,
+        })
+    }
+}
+
+impl<'a> ReadBinaryDep<'a> for BaseScriptRecord {
+    type Args = ReadScope<'a>;
+    type HostType = Self;
+
+    fn read_dep(ctxt: &mut ReadCtxt<'a>, scope: Self::Args) -> Result<Self, ParseError> {
+        let script_tag = ctxt.read_u32be()?;
+        let base_script_offset = usize::from(ctxt.read_u16be()?);
+        let base_script = scope.offset(base_script_offset).read::<BaseScript>()?;
+        Ok(BaseScriptRecord {
+            script_tag,
+            base_script,
+        })
+    }
+}
+
+impl<'a> ReadFixedSizeDep<'a> for BaseScriptRecord {
+    fn size(_scope: Self::Args) -> usize {
+        size::U32 + size::U16
+    }
+}
+
+impl<'a> ReadBinary<'a> for BaseScript {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let base_values_offset = usize::from(ctxt.read_u16be()?);
+        let default_min_max_offset = usize::from(ctxt.read_u16be()?);
+        let base_lang_sys_count = usize::from(ctxt.read_u16be()?);
+        let base_lang_sys_records = ctxt
+            .read_array_dep::<BaseLangSysRecord>(base_lang_sys_count, scope.clone())?
+            .read_to_vec()?;
+        Ok(BaseScript {
+            base_values: read_optional::<BaseValues>(&scope, base_values_offset)?,
+            default_min_max: read_optional::<MinMax>(&scope, default_min_max_offset)?,
+            base_lang_sys_records,
+        })
+    }
+}
+
+impl<'a> ReadBinaryDep<'a> for BaseLangSysRecord {
+    type Args = ReadScope<'a>;
+    type HostType = Self;
+
+    fn read_dep(ctxt: &mut ReadCtxt<'a>, scope: Self::Args) -> Result<Self, ParseError> {
+        let lang_sys_tag = ctxt.read_u32be()?;
+        let min_max_offset = usize::from(ctxt.read_u16be()?);
+        let min_max = scope.offset(min_max_offset).read::<MinMax>()?;
+        Ok(BaseLangSysRecord {
+            lang_sys_tag,
+            min_max,
+        })
+    }
+}
+
+impl<'a> ReadFixedSizeDep<'a> for BaseLangSysRecord {
+    fn size(_scope: Self::Args) -> usize {
+        size::U32 + size::U16
+    }
+}
+
+impl<'a> ReadBinary<'a> for BaseValues {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let default_baseline_index = ctxt.read_u16be()?;
+        let base_coord_count = usize::from(ctxt.read_u16be()?);
+        let base_coords = (0..base_coord_count)
+            .map(|_| {
+                let base_coord_offset = usize::from(ctxt.read_u16be()?);
+                if base_coord_offset == 0 {
+                    return Err(ParseError::BadOffset);
+                }
+                scope.offset(base_coord_offset).read::<BaseCoord>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BaseValues {
+            default_baseline_index,
+            base_coords,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for MinMax {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let min_coord_offset = usize::from(ctxt.read_u16be()?);
+        let max_coord_offset = usize::from(ctxt.read_u16be()?);
+        let feat_min_max_count = usize::from(ctxt.read_u16be()?);
+        let feat_min_max_records = ctxt
+            .read_array_dep::<FeatMinMaxRecord>(feat_min_max_count, scope.clone())?
+            .read_to_vec()?;
+        Ok(MinMax {
+            min_coord: read_optional::<BaseCoord>(&scope, min_coord_offset)?,
+            max_coord: read_optional::<BaseCoord>(&scope, max_coord_offset)?,
+            feat_min_max_records,
+        })
+    }
+}
+
+impl<'a> ReadBinaryDep<'a> for FeatMinMaxRecord {
+    type Args = ReadScope<'a>;
+    type HostType = Self;
+
+    /// `scope` is the scope of the `MinMax` table.
+    fn read_dep(ctxt: &mut ReadCtxt<'a>, scope: Self::Args) -> Result<Self, ParseError> {
+        let feature_tag = ctxt.read_u32be()?;
+        let min_coord_offset = usize::from(ctxt.read_u16be()?);
+        let max_coord_offset = usize::from(ctxt.read_u16be()?);
+        Ok(FeatMinMaxRecord {
+            feature_tag,
+            min_coord: read_optional::<BaseCoord>(&scope, min_coord_offset)?,
+            max_coord: read_optional::<BaseCoord>(&scope, max_coord_offset)?,
+        })
+    }
+}
+
+impl<'a> ReadFixedSizeDep<'a> for FeatMinMaxRecord {
+    fn size(_scope: Self::Args) -> usize {
+        size::U32 + 2 * size::U16
+    }
+}
+
+/// A baseline or extent coordinate.
+enum BaseCoord {}
+
+impl<'a> ReadBinary<'a> for BaseCoord {
+    type HostType = i16;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<i16, ParseError> {
+        match ctxt.read_u16be()? {
+            1..=3 => {
+                // Doesn't read the glyph contour point of format 2, or the device table of
+                // format 3, because we don't use them
+                Ok(ctxt.read_i16be()?)
+            }
+            _ => Err(ParseError::BadVersion),
+        }
+    }
+}
+
+/// Read a `T` at `offset`, where an offset of zero indicates it's absent.
+fn read_optional<'a, T: ReadBinary<'a>>(
+    scope: &ReadScope<'a>,
+    offset: usize,
+) -> Result<Option<T::HostType>, ParseError> {
+    if offset == 0 {
+        Ok(None)
+    } else {
+        scope.offset(offset).read::<T>().map(Some)
+    }
+}
+
+impl BaseTable {
+    /// Returns the coordinate of the horizontal baseline `baseline_tag` of `script_tag`.
+    ///
+    /// See `Axis::baseline`.
+    pub fn baseline(&self, script_tag: u32, baseline_tag: u32) -> Option<i16> {
+        self.horiz_axis
+            .as_ref()
+            .and_then(|axis| axis.baseline(script_tag, baseline_tag))
+    }
+}
+
+impl Axis {
+    /// Returns the baseline data of `script_tag`, falling back to the `DFLT` script.
+    pub fn base_script(&self, script_tag: u32) -> Option<&BaseScript> {
+        let find = |script_tag| {
+            self.base_script_records
+                .iter()
+                .find(|record| record.script_tag == script_tag)
+                .map(|record| &record.base_script)
+        };
+        find(script_tag).or_else(|| find(tag::DFLT))
+    }
+
+    /// Returns the coordinate of the baseline `baseline_tag` of `script_tag`, in font units.
+    ///
+    /// Returns `None` if the axis doesn't describe the baseline, or has no coordinates for the
+    /// script or `DFLT` script.
+    pub fn baseline(&self, script_tag: u32, baseline_tag: u32) -> Option<i16> {
+        let index = self
+            .baseline_tags
+            .iter()
+            .position(|&tag| tag == baseline_tag)?;
+        let base_values = self.base_script(script_tag)?.base_values.as_ref()?;
+        base_values.base_coords.get(index).copied()
+    }
+
+    /// Returns the tag of the baseline used by `script_tag`.
+    pub fn default_baseline(&self, script_tag: u32) -> Option<u32> {
+        let base_values = self.base_script(script_tag)?.base_values.as_ref()?;
+        self.baseline_tags
+            .get(usize::from(base_values.default_baseline_index))
+            .copied()
+    }
+
+    /// Returns the extents of `script_tag` for the language system `lang_sys_tag`, falling back
+    /// to the default extents of the script when there are none specific to the language.
+    pub fn min_max(&self, script_tag: u32, lang_sys_tag: Option<u32>) -> Option<&MinMax> {
+        let base_script = self.base_script(script_tag)?;
+        lang_sys_tag
+            .and_then(|lang_sys_tag| {
+                base_script
+                    .base_lang_sys_records
+                    .iter()
+                    .find(|record| record.lang_sys_tag == lang_sys_tag)
+            })
+            .map(|record| &record.min_max)
+            .or(base_script.default_min_max.as_ref())
+    }
+}
+
+impl MinMax {
+    /// Returns the minimum and maximum extents when `feature_tag` is enabled, or the default
+    /// extents if `feature_tag` is `None`.
+    ///
+    /// Extents not given for the feature fall back to the default extents.
+    pub fn extents(&self, feature_tag: Option<u32>) -> (Option<i16>, Option<i16>) {
+        let feature = feature_tag.and_then(|feature_tag| {
+            self.feat_min_max_records
+                .iter()
+                .find(|record| record.feature_tag == feature_tag)
+        });
+        match feature {
+            Some(record) => (
+                record.min_coord.or(self.min_coord),
+                record.max_coord.or(self.max_coord),
+            ),
+            None => (self.min_coord, self.max_coord),
+        }
+    }
+}
+
+/// Synthesize the coordinate of the horizontal baseline `baseline_tag` from the `OS/2` table,
+/// for fonts without a `BASE` table.
+///
+/// The ideographic em-box (`ideo` and `idtp`) is centered between the typographic ascender and
+/// descender, and the ideographic character face (`icfb` and `icft`) is inset from the em-box by
+/// 5% of the em. The hanging baseline (`hang`) is the cap height, or 80% of the typographic
+/// ascender when the table doesn't have a cap height. The roman baseline (`romn`) is zero. Other
+/// baselines, and all but the roman baseline when the table lacks typographic metrics, aren't
+/// synthesized.
+pub fn synthesize_baseline(os2: &Os2, units_per_em: u16, baseline_tag: u32) -> Option<i16> {
+    if baseline_tag == tag::ROMN {
+        return Some(0);
+    }
+    let version0 = os2.version0.as_ref()?;
+    let ascender = i32::from(version0.s_typo_ascender);
+    let descender = i32::from(version0.s_typo_descender);
+    let units_per_em = i32::from(units_per_em);
+    let em_box_bottom = (ascender + descender - units_per_em) / 2;
+    let em_box_top = em_box_bottom + units_per_em;
+    let face_inset = units_per_em / 20;
+    let coordinate = match baseline_tag {
+        tag::IDEO => em_box_bottom,
+        tag::IDTP => em_box_top,
+        tag::ICFB => em_box_bottom + face_inset,
+        tag::ICFT => em_box_top - face_inset,
+        tag::HANG => match os2.version2to4 {
+            Some(ref version2to4) if version2to4.s_cap_height > 0 => {
+                i32::from(version2to4.s_cap_height)
+            }
+            _ => ascender * 4 / 5,
+        },
+        _ => return None,
+    };
+    i16::try_from(coordinate).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_base() {
+        #[rustfmt::skip]
+        let data = [
+            0x00, 0x01, 0x00, 0x00, // version
+            0x00, 0x08, // horiz_axis_offset
+            0x00, 0x00, // vert_axis_offset
+            // 0x08: horizontal axis
+            0x00, 0x04, // base_tag_list_offset
+            0x00, 0x12, // base_script_list_offset
+            // 0x0C: base tag list
+            0x00, 0x03,
+            b'h', b'a', b'n', b'g',
+            b'i', b'd', b'e', b'o',
+            b'r', b'o', b'm', b'n',
+            // 0x1A: base script list
+            0x00, 0x02,
+            b'D', b'F', b'L', b'T', 0x00, 0x0E,
+            b'l', b'a', b't', b'n', 0x00, 0x2E,
+            // 0x28: DFLT base script
+            0x00, 0x00, // base_values_offset
+            0x00, 0x06, // default_min_max_offset
+            0x00, 0x00, // base_lang_sys_count
+            // 0x2E: DFLT min max
+            0x00, 0x0E, 0x00, 0x12, 0x00, 0x01,
+            b'v', b'e', b'r', b't', 0x00, 0x00, 0x00, 0x16,
+            // 0x3C: base coords
+            0x00, 0x01, 0xFF, 0x10,
+            0x00, 0x01, 0x03, 0x84,
+            0x00, 0x01, 0x04, 0x00,
+            // 0x48: latn base script
+            0x00, 0x06, // base_values_offset
+            0x00, 0x00, // default_min_max_offset
+            0x00, 0x00, // base_lang_sys_count
+            // 0x4E: latn base values
+            0x00, 0x02, 0x00, 0x03, 0x00, 0x0A, 0x00, 0x0E, 0x00, 0x16,
+            0x00, 0x01, 0x02, 0xBC,
+            0x00, 0x02, 0xFF, 0x88, 0x00, 0x05, 0x00, 0x01,
+            0x00, 0x03, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let base = ReadScope::new(&data).read::<BaseTable>().unwrap();
+        assert!(base.vert_axis.is_none());
+        let axis = base.horiz_axis.as_ref().unwrap();
+        assert_eq!(axis.baseline_tags, vec![tag::HANG, tag::IDEO, tag::ROMN]);
+
+        assert_eq!(base.baseline(tag::LATN, tag::HANG), Some(700));
+        assert_eq!(base.baseline(tag::LATN, tag::IDEO), Some(-120));
+        assert_eq!(base.baseline(tag::LATN, tag::ROMN), Some(0));
+        assert_eq!(base.baseline(tag::LATN, tag::IDTP), None);
+        assert_eq!(axis.default_baseline(tag::LATN), Some(tag::ROMN));
+        // Scripts without a record use DFLT, which has no baseline coordinates
+        assert_eq!(base.baseline(tag::DEVA, tag::HANG), None);
+
+        let min_max = axis.min_max(tag::DEVA, Some(tag::LATN)).unwrap();
+        assert_eq!(min_max.extents(None), (Some(-240), Some(900)));
+        assert_eq!(min_max.extents(Some(tag::VERT)), (Some(-240), Some(1024)));
+        assert_eq!(min_max.extents(Some(tag::KERN)), (Some(-240), Some(900)));
+        assert!(axis.min_max(tag::LATN, None).is_none());
+    }
+}
@@ -6,14 +6,18 @@
 //!
 //! — <https://docs.microsoft.com/en-us/typography/opentype/spec/cmap>
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 
 use itertools::izip;
 
-use crate::binary::read::{CheckIndex, ReadArray, ReadBinary, ReadCtxt, ReadFrom, ReadScope};
+use crate::binary::read::{
+    CheckIndex, ReadArray, ReadBinary, ReadCtxt, ReadFixedSizeDep, ReadFrom, ReadScope,
+    ReadUnchecked,
+};
 use crate::binary::write::{WriteBinary, WriteContext};
-use crate::binary::{I16Be, U16Be, U32Be, U8};
+use crate::binary::{I16Be, U16Be, U24Be, U32Be, U8};
 use crate::error::{ParseError, WriteError};
 use crate::size;
 
@@ -47,6 +51,9 @@ impl EncodingId {
 
     pub const MACINTOSH_APPLE_ROMAN: EncodingId = EncodingId(0);
     pub const MACINTOSH_UNICODE_UCS4: EncodingId = EncodingId(4);
+
+    /// Unicode Variation Sequences, only used with format 14 sub-tables
+    pub const UNICODE_VARIATION_SEQUENCES: EncodingId = EncodingId(5);
 }
 
 pub struct Cmap<'a> {
@@ -94,6 +101,16 @@ pub enum CmapSubtable<'a> {
         language: u32,
         groups: ReadArray<'a, SequentialMapGroup>,
     },
+    /// Many-to-one range mappings. The groups have the same layout as format 12 but every
+    /// character in a group maps to `start_glyph_id`.
+    Format13 {
+        language: u32,
+        groups: ReadArray<'a, SequentialMapGroup>,
+    },
+    Format14 {
+        scope: ReadScope<'a>,
+        var_selector_records: ReadArray<'a, VariationSelectorRecord>,
+    },
 }
 
 // cmap subtable format 2 sub-header
@@ -115,6 +132,35 @@ pub struct SequentialMapGroup {
     start_glyph_id: u32,
 }
 
+// cmap subtable format 14 variation selector record
+pub struct VariationSelectorRecord {
+    var_selector: u32,
+    default_uvs_offset: u32,
+    non_default_uvs_offset: u32,
+}
+
+// cmap subtable format 14 default UVS table range
+struct UnicodeRange {
+    start_unicode_value: u32,
+    additional_count: u8,
+}
+
+// cmap subtable format 14 non-default UVS table mapping
+struct UVSMapping {
+    unicode_value: u32,
+    glyph_id: u16,
+}
+
+/// The result of looking up a Unicode Variation Sequence in a format 14 sub-table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VariationMapping {
+    /// The sequence is supported by the font and is displayed with the glyph that the base
+    /// character maps to in the Unicode sub-table.
+    UseDefault,
+    /// The sequence maps to this glyph.
+    Glyph(u16),
+}
+
 impl<'a> ReadBinary<'a> for Cmap<'a> {
     type HostType = Self;
 
@@ -146,6 +192,7 @@ impl<'a> ReadBinary<'a> for CmapSubtable<'a> {
     type HostType = Self;
 
     fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
         let subtable_format = ctxt.read_u16be()?;
         match subtable_format {
             0 => {
@@ -241,6 +288,25 @@ impl<'a> ReadBinary<'a> for CmapSubtable<'a> {
                 let groups = ctxt.read_array::<SequentialMapGroup>(num_groups)?;
                 Ok(CmapSubtable::Format12 { language, groups })
             }
+            13 => {
+                let reserved = ctxt.read_u16be()?;
+                ctxt.check(reserved == 0)?;
+                let _length = ctxt.read_u32be()?;
+                let language = ctxt.read_u32be()?;
+                let num_groups = usize::try_from(ctxt.read_u32be()?)?;
+                let groups = ctxt.read_array::<SequentialMapGroup>(num_groups)?;
+                Ok(CmapSubtable::Format13 { language, groups })
+            }
+            14 => {
+                let length = usize::try_from(ctxt.read_u32be()?)?;
+                let num_var_selector_records = usize::try_from(ctxt.read_u32be()?)?;
+                let var_selector_records =
+                    ctxt.read_array::<VariationSelectorRecord>(num_var_selector_records)?;
+                Ok(CmapSubtable::Format14 {
+                    scope: scope.offset_length(0, length)?,
+                    var_selector_records,
+                })
+            }
             _ => Err(ParseError::BadVersion),
         }
     }
@@ -335,6 +401,22 @@ impl<'a> WriteBinary<&Self> for CmapSubtable<'a> {
                 <&ReadArray<'_, _>>::write(ctxt, groups)?;
                 ctxt.write_placeholder(length, u32::try_from(ctxt.bytes_written() - start)?)?;
             }
+            CmapSubtable::Format13 { language, groups } => {
+                let start = ctxt.bytes_written();
+
+                U16Be::write(ctxt, 13u16)?; // format
+                U16Be::write(ctxt, 0u16)?; // reserved
+                let length = ctxt.placeholder::<U32Be, _>()?;
+                U32Be::write(ctxt, *language)?;
+                U32Be::write(ctxt, u32::try_from(groups.len())?)?;
+                <&ReadArray<'_, _>>::write(ctxt, groups)?;
+                ctxt.write_placeholder(length, u32::try_from(ctxt.bytes_written() - start)?)?;
+            }
+            CmapSubtable::Format14 { scope, .. } => {
+                // The offsets within a format 14 sub-table are relative to its start so the
+                // sub-table can be copied as-is.
+                ctxt.write_bytes(scope.data())?;
+            }
         }
 
         Ok(())
@@ -433,6 +515,83 @@ impl WriteBinary for SequentialMapGroup {
     }
 }
 
+impl<'a> ReadFrom<'a> for VariationSelectorRecord {
+    type ReadType = (U24Be, U32Be, U32Be);
+    fn from((var_selector, default_uvs_offset, non_default_uvs_offset): (u32, u32, u32)) -> Self {
+        VariationSelectorRecord {
+            var_selector,
+            default_uvs_offset,
+            non_default_uvs_offset,
+        }
+    }
+}
+
+impl<'a> ReadFrom<'a> for UnicodeRange {
+    type ReadType = (U24Be, U8);
+    fn from((start_unicode_value, additional_count): (u32, u8)) -> Self {
+        UnicodeRange {
+            start_unicode_value,
+            additional_count,
+        }
+    }
+}
+
+impl<'a> ReadFrom<'a> for UVSMapping {
+    type ReadType = (U24Be, U16Be);
+    fn from((unicode_value, glyph_id): (u32, u16)) -> Self {
+        UVSMapping {
+            unicode_value,
+            glyph_id,
+        }
+    }
+}
+
+impl VariationSelectorRecord {
+    fn map_variant(
+        &self,
+        scope: &ReadScope<'_>,
+        ch: u32,
+    ) -> Result<Option<VariationMapping>, ParseError> {
+        if self.default_uvs_offset != 0 {
+            let mut ctxt = scope
+                .offset(usize::try_from(self.default_uvs_offset)?)
+                .ctxt();
+            let num_unicode_value_ranges = usize::try_from(ctxt.read_u32be()?)?;
+            let ranges = ctxt.read_array::<UnicodeRange>(num_unicode_value_ranges)?;
+            // Ranges are sorted by start value, so the only candidate is the last range that
+            // starts at or before `ch`
+            let in_range =
+                match binary_search_by_key(&ranges, ch, |range| range.start_unicode_value) {
+                    Ok(_) => true,
+                    Err(0) => false,
+                    Err(index) => {
+                        let range = ranges.get_item(index - 1);
+                        ch - range.start_unicode_value <= u32::from(range.additional_count)
+                    }
+                };
+            if in_range {
+                return Ok(Some(VariationMapping::UseDefault));
+            }
+        }
+
+        if self.non_default_uvs_offset != 0 {
+            let mut ctxt = scope
+                .offset(usize::try_from(self.non_default_uvs_offset)?)
+                .ctxt();
+            let num_uvs_mappings = usize::try_from(ctxt.read_u32be()?)?;
+            let mappings = ctxt.read_array::<UVSMapping>(num_uvs_mappings)?;
+            if let Ok(index) = binary_search_by_key(&mappings, ch, |mapping| mapping.unicode_value)
+            {
+                return Ok(Some(VariationMapping::Glyph(
+                    mappings.get_item(index).glyph_id,
+                )));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
 impl<'a> Cmap<'a> {
     /// Find the first encoding record for the given `platform_id`
     pub fn find_subtable_for_platform(&self, platform_id: PlatformId) -> Option<EncodingRecord> {
@@ -599,6 +758,122 @@ impl<'a> CmapSubtable<'a> {
                 }
                 Ok(None)
             }
+            CmapSubtable::Format13 { ref groups, .. } => {
+                for group in groups {
+                    if group.start_char_code <= ch && ch <= group.end_char_code {
+                        return Ok(Some(u16::try_from(group.start_glyph_id)?));
+                    }
+                }
+                Ok(None)
+            }
+            // Format 14 maps variation sequences, not single characters. See `map_variant`.
+            CmapSubtable::Format14 { .. } => Ok(None),
+        }
+    }
+
+    /// Call `f` with each character mapped by this sub-table and the glyph it maps to.
+    ///
+    /// Characters mapped to glyph 0 are skipped. Format 14 sub-tables map variation sequences,
+    /// not single characters, so `f` is not called for them.
+    pub fn mappings_fn(&self, mut f: impl FnMut(u32, u16)) -> Result<(), ParseError> {
+        let mut map = |ch: u32, glyph_id: u16| {
+            if glyph_id != 0 {
+                f(ch, glyph_id)
+            }
+        };
+        match *self {
+            CmapSubtable::Format0 {
+                ref glyph_id_array, ..
+            } => {
+                for (ch, glyph_id) in glyph_id_array.iter().enumerate() {
+                    map(ch as u32, u16::from(glyph_id));
+                }
+            }
+            CmapSubtable::Format2 { .. } => {
+                for ch in 0..=0xFFFF {
+                    // Characters with an invalid high byte are not mapped
+                    if let Ok(Some(glyph_id)) = self.map_glyph(ch) {
+                        map(ch, glyph_id);
+                    }
+                }
+            }
+            CmapSubtable::Format4 {
+                ref end_codes,
+                ref start_codes,
+                ..
+            } => {
+                for (start_code, end_code) in start_codes.iter().zip(end_codes.iter()) {
+                    for ch in u32::from(start_code)..=u32::from(end_code) {
+                        if let Some(glyph_id) = self.map_glyph(ch)? {
+                            map(ch, glyph_id);
+                        }
+                    }
+                }
+            }
+            CmapSubtable::Format6 {
+                first_code,
+                ref glyph_id_array,
+                ..
+            } => {
+                for (ch, glyph_id) in (u32::from(first_code)..).zip(glyph_id_array.iter()) {
+                    map(ch, glyph_id);
+                }
+            }
+            CmapSubtable::Format10 {
+                start_char_code,
+                ref glyph_id_array,
+                ..
+            } => {
+                for (ch, glyph_id) in (start_char_code..).zip(glyph_id_array.iter()) {
+                    map(ch, glyph_id);
+                }
+            }
+            CmapSubtable::Format12 { ref groups, .. } => {
+                for group in groups {
+                    for ch in group.start_char_code..=group.end_char_code {
+                        let glyph_id = group
+                            .start_glyph_id
+                            .checked_add(ch - group.start_char_code)
+                            .ok_or(ParseError::BadValue)?;
+                        map(ch, u16::try_from(glyph_id)?);
+                    }
+                }
+            }
+            CmapSubtable::Format13 { ref groups, .. } => {
+                for group in groups {
+                    let glyph_id = u16::try_from(group.start_glyph_id)?;
+                    for ch in group.start_char_code..=group.end_char_code {
+                        map(ch, glyph_id);
+                    }
+                }
+            }
+            CmapSubtable::Format14 { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Look up the variation sequence made up of `ch` followed by the variation selector
+    /// `selector`.
+    ///
+    /// Returns `None` if this is not a format 14 sub-table or the sequence is not supported by
+    /// the font. When the result is `VariationMapping::UseDefault` the glyph should be looked up
+    /// with `map_glyph` on the font's Unicode sub-table.
+    pub fn map_variant(
+        &self,
+        ch: u32,
+        selector: u32,
+    ) -> Result<Option<VariationMapping>, ParseError> {
+        match *self {
+            CmapSubtable::Format14 {
+                ref scope,
+                ref var_selector_records,
+            } => match binary_search_by_key(var_selector_records, selector, |record| {
+                record.var_selector
+            }) {
+                Ok(index) => var_selector_records.get_item(index).map_variant(scope, ch),
+                Err(_) => Ok(None),
+            },
+            _ => Ok(None),
         }
     }
 
@@ -693,15 +968,49 @@ impl<'a> CmapSubtable<'a> {
                 let mut mappings = HashMap::new();
                 for record in groups.iter() {
                     for (i, ch) in (record.start_char_code..=record.end_char_code).enumerate() {
-                        mappings
-                            .entry(u16::try_from(record.start_glyph_id)? + u16::try_from(i)?)
-                            .or_insert(ch);
+                        let glyph_id = u32::try_from(i)
+                            .ok()
+                            .and_then(|i| record.start_glyph_id.checked_add(i))
+                            .ok_or(ParseError::BadValue)?;
+                        mappings.entry(u16::try_from(glyph_id)?).or_insert(ch);
                     }
                 }
                 Ok(mappings)
             }
+            CmapSubtable::Format13 { groups, .. } => {
+                // Every char code in a group maps to the same glyph so only the first one is
+                // recorded, which avoids iterating the potentially very large ranges.
+                let mut mappings = HashMap::with_capacity(groups.len());
+                for record in groups.iter() {
+                    mappings
+                        .entry(u16::try_from(record.start_glyph_id)?)
+                        .or_insert(record.start_char_code);
+                }
+                Ok(mappings)
+            }
+            // Format 14 only describes variation sequences so has no mappings of its own
+            CmapSubtable::Format14 { .. } => Ok(HashMap::new()),
+        }
+    }
+}
+
+// Binary search a sorted `ReadArray`, with the same return value as `slice::binary_search_by_key`.
+fn binary_search_by_key<'a, T, F>(array: &ReadArray<'a, T>, key: u32, f: F) -> Result<usize, usize>
+where
+    T: ReadUnchecked<'a> + ReadFixedSizeDep<'a>,
+    F: Fn(&<T as ReadUnchecked<'a>>::HostType) -> u32,
+{
+    let mut low = 0;
+    let mut high = array.len();
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match f(&array.get_item(mid)).cmp(&key) {
+            Ordering::Less => low = mid + 1,
+            Ordering::Greater => high = mid,
+            Ordering::Equal => return Ok(mid),
         }
     }
+    Err(low)
 }
 
 // For converting cmap format 4 offsets to indexes into the glyph id array.
@@ -768,6 +1077,10 @@ pub mod owned {
             language: u32,
             groups: Vec<SequentialMapGroup>,
         },
+        Format13 {
+            language: u32,
+            groups: Vec<SequentialMapGroup>,
+        },
     }
 
     impl<'a> WriteBinary<Self> for Cmap {
@@ -883,6 +1196,17 @@ pub mod owned {
                     ctxt.write_vec::<SequentialMapGroup>(groups)?;
                     ctxt.write_placeholder(length, u32::try_from(ctxt.bytes_written() - start)?)?;
                 }
+                CmapSubtable::Format13 { language, groups } => {
+                    let start = ctxt.bytes_written();
+
+                    U16Be::write(ctxt, 13u16)?; // format
+                    U16Be::write(ctxt, 0u16)?; // reserved
+                    let length = ctxt.placeholder::<U32Be, _>()?;
+                    U32Be::write(ctxt, language)?;
+                    U32Be::write(ctxt, u32::try_from(groups.len())?)?;
+                    ctxt.write_vec::<SequentialMapGroup>(groups)?;
+                    ctxt.write_placeholder(length, u32::try_from(ctxt.bytes_written() - start)?)?;
+                }
             }
 
             Ok(())
@@ -893,6 +1217,7 @@ pub mod owned {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::binary::write::WriteBuffer;
     use crate::tables::{OpenTypeFile, OpenTypeFont};
     use crate::tag;
     use crate::tests::read_fixture;
@@ -1026,6 +1351,68 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mappings_fn() {
+        with_cmap_subtable(
+            "tests/fonts/opentype/Klei.otf",
+            PlatformId::WINDOWS,
+            EncodingId::WINDOWS_UNICODE_BMP_UCS2,
+            |cmap_subtable| {
+                let mut mappings = Vec::new();
+                cmap_subtable
+                    .mappings_fn(|ch, glyph_id| mappings.push((ch, glyph_id)))
+                    .unwrap();
+                assert!(!mappings.is_empty());
+                assert!(mappings.iter().all(|&(_, glyph_id)| glyph_id != 0));
+                for &(ch, glyph_id) in &mappings {
+                    assert_eq!(cmap_subtable.map_glyph(ch).unwrap(), Some(glyph_id));
+                }
+                let a = cmap_subtable.map_glyph('a' as u32).unwrap().unwrap();
+                assert!(mappings.contains(&('a' as u32, a)));
+            },
+        );
+    }
+
+    #[test]
+    fn test_format13_round_trip() {
+        let subtable = owned::CmapSubtable::Format13 {
+            language: 0,
+            groups: vec![
+                SequentialMapGroup {
+                    start_char_code: 0x20,
+                    end_char_code: 0x7E,
+                    start_glyph_id: 1,
+                },
+                SequentialMapGroup {
+                    start_char_code: 0x10000,
+                    end_char_code: 0x10FFFF,
+                    start_glyph_id: 2,
+                },
+            ],
+        };
+        let mut buffer = WriteBuffer::new();
+        owned::CmapSubtable::write(&mut buffer, subtable).unwrap();
+        let data = buffer.into_inner();
+
+        let cmap_subtable = ReadScope::new(&data).read::<CmapSubtable<'_>>().unwrap();
+        match cmap_subtable {
+            CmapSubtable::Format13 { .. } => {}
+            _ => panic!("expected CmapSubtable::Format13"),
+        }
+        assert_eq!(cmap_subtable.map_glyph(0x10).unwrap(), None);
+        assert_eq!(cmap_subtable.map_glyph('a' as u32).unwrap(), Some(1));
+        assert_eq!(cmap_subtable.map_glyph('~' as u32).unwrap(), Some(1));
+        assert_eq!(cmap_subtable.map_glyph(0x10FFFF).unwrap(), Some(2));
+
+        let mappings = cmap_subtable.mappings().unwrap();
+        assert_eq!(mappings[&1], 0x20);
+        assert_eq!(mappings[&2], 0x10000);
+
+        let mut buffer = WriteBuffer::new();
+        CmapSubtable::write(&mut buffer, &cmap_subtable).unwrap();
+        assert_eq!(buffer.into_inner(), data);
+    }
+
     #[test]
     fn test_mappings_format12() {
         with_cmap_subtable(
@@ -0,0 +1,1051 @@
+#![deny(missing_docs)]
+
+//! `COLR` Color Table parsing.
+//!
+//! Version 0 color glyphs, which are made of layers of glyphs each filled with a solid color
+//! from the `CPAL` table, are supported, as are the paint graphs of version 1 color glyphs.
+//! Version 1 paints are read with their default values; the variation indices of variable
+//! paints are exposed but deltas are not applied. See the `colr` module for walking a paint
+//! graph.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/colr>
+
+use std::convert::TryFrom;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt, ReadFrom, ReadScope};
+use crate::binary::{U16Be, U24Be, U32Be};
+use crate::error::ParseError;
+use crate::tables::cpal::{ColorRecord, CpalTable};
+use crate::tables::F2Dot14;
+
+/// Palette index indicating that a layer uses the text foreground color.
+pub const FOREGROUND_PALETTE_INDEX: u16 = 0xFFFF;
+
+/// `COLR` Color Table.
+pub struct ColrTable<'a> {
+    /// The version of the table, either `0` or `1`.
+    pub version: u16,
+    scope: ReadScope<'a>,
+    base_glyph_records: ReadArray<'a, BaseGlyphRecord>,
+    layer_records: ReadArray<'a, LayerRecord>,
+    base_glyph_paint_records: ReadArray<'a, BaseGlyphPaintRecord>,
+    base_glyph_list_offset: usize,
+    layer_paint_offsets: ReadArray<'a, U32Be>,
+    layer_list_offset: usize,
+    clip_records: ReadArray<'a, ClipRecord>,
+    clip_list_offset: usize,
+}
+
+/// Associates a base glyph with the layers that make up its color glyph.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BaseGlyphRecord {
+    /// The glyph id of the base glyph.
+    pub glyph_id: u16,
+    /// The index of the first layer record of the color glyph.
+    pub first_layer_index: u16,
+    /// The number of layers in the color glyph.
+    pub num_layers: u16,
+}
+
+/// A layer of a version 0 color glyph.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LayerRecord {
+    /// The glyph id of the glyph that provides the outline of the layer.
+    pub glyph_id: u16,
+    /// The index of the palette entry that fills the layer, or `FOREGROUND_PALETTE_INDEX`.
+    pub palette_index: u16,
+}
+
+/// Associates a base glyph with the root paint of its version 1 color glyph.
+#[derive(Debug, Copy, Clone)]
+struct BaseGlyphPaintRecord {
+    glyph_id: u16,
+    paint_offset: u32,
+}
+
+/// Associates a range of glyphs with a clip box.
+#[derive(Debug, Copy, Clone)]
+struct ClipRecord {
+    start_glyph_id: u16,
+    end_glyph_id: u16,
+    clip_box_offset: u32,
+}
+
+/// A reference to a paint table of a version 1 color glyph.
+///
+/// Read the paint with `ColrTable::paint`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PaintRef(usize);
+
+/// A paint table of a version 1 color glyph.
+///
+/// Paints form a directed acyclic graph, with the root paint of each color glyph found via
+/// `ColrTable::base_glyph_paint`. Coordinates are in font units. Angles are in half turns
+/// counter-clockwise, so `1.0` is 180°.
+///
+/// The variable formats of paint tables are read into the same variants as their non-variable
+/// counterparts, with `var_index_base` set.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+    /// Paints a slice of the layer list in order, bottom-most first (format 1).
+    ColrLayers {
+        /// The number of layers.
+        num_layers: u8,
+        /// The index of the first layer in the layer list.
+        first_layer_index: u32,
+    },
+    /// Fills with a solid color (formats 2 and 3).
+    Solid {
+        /// The index of the palette entry, or `FOREGROUND_PALETTE_INDEX`.
+        palette_index: u16,
+        /// Alpha multiplier for the color.
+        alpha: f32,
+        /// Base index into the variation index map, for variable paints.
+        var_index_base: Option<u32>,
+    },
+    /// Fills with a linear gradient (formats 4 and 5).
+    ///
+    /// The gradient runs from `p0` towards `p1`, with its color lines parallel to the
+    /// line from `p0` to `p2`.
+    LinearGradient {
+        /// The color stops of the gradient.
+        color_line: ColorLine,
+        /// The start point of the gradient.
+        p0: (i16, i16),
+        /// The end point of the gradient.
+        p1: (i16, i16),
+        /// The rotation point of the gradient.
+        p2: (i16, i16),
+        /// Base index into the variation index map, for variable paints.
+        var_index_base: Option<u32>,
+    },
+    /// Fills with a radial gradient between two circles (formats 6 and 7).
+    RadialGradient {
+        /// The color stops of the gradient.
+        color_line: ColorLine,
+        /// The center of the start circle.
+        c0: (i16, i16),
+        /// The radius of the start circle.
+        r0: u16,
+        /// The center of the end circle.
+        c1: (i16, i16),
+        /// The radius of the end circle.
+        r1: u16,
+        /// Base index into the variation index map, for variable paints.
+        var_index_base: Option<u32>,
+    },
+    /// Fills with a sweep gradient around a center (formats 8 and 9).
+    SweepGradient {
+        /// The color stops of the gradient.
+        color_line: ColorLine,
+        /// The center of the gradient.
+        center: (i16, i16),
+        /// The start angle of the gradient.
+        start_angle: f32,
+        /// The end angle of the gradient.
+        end_angle: f32,
+        /// Base index into the variation index map, for variable paints.
+        var_index_base: Option<u32>,
+    },
+    /// Clips `paint` to the outline of `glyph_id` (format 10).
+    Glyph {
+        /// The paint to clip.
+        paint: PaintRef,
+        /// The glyph that provides the clip outline.
+        glyph_id: u16,
+    },
+    /// Reuses the paint graph of the version 1 color glyph of `glyph_id` (format 11).
+    ColrGlyph {
+        /// The base glyph of the color glyph to paint.
+        glyph_id: u16,
+    },
+    /// Applies an affine transform to `paint` (formats 12 and 13).
+    Transform {
+        /// The paint to transform.
+        paint: PaintRef,
+        /// The transform.
+        transform: Affine2x3,
+        /// Base index into the variation index map, for variable paints.
+        var_index_base: Option<u32>,
+    },
+    /// Translates `paint` (formats 14 and 15).
+    Translate {
+        /// The paint to translate.
+        paint: PaintRef,
+        /// The horizontal translation.
+        dx: i16,
+        /// The vertical translation.
+        dy: i16,
+        /// Base index into the variation index map, for variable paints.
+        var_index_base: Option<u32>,
+    },
+    /// Scales `paint`, around `center` or else the origin (formats 16 to 23).
+    Scale {
+        /// The paint to scale.
+        paint: PaintRef,
+        /// The horizontal scale factor.
+        scale_x: f32,
+        /// The vertical scale factor. Equal to `scale_x` for uniform scale formats.
+        scale_y: f32,
+        /// The center of the scale, if not the origin.
+        center: Option<(i16, i16)>,
+        /// Base index into the variation index map, for variable paints.
+        var_index_base: Option<u32>,
+    },
+    /// Rotates `paint` counter-clockwise, around `center` or else the origin (formats 24 to
+    /// 27).
+    Rotate {
+        /// The paint to rotate.
+        paint: PaintRef,
+        /// The angle of rotation.
+        angle: f32,
+        /// The center of the rotation, if not the origin.
+        center: Option<(i16, i16)>,
+        /// Base index into the variation index map, for variable paints.
+        var_index_base: Option<u32>,
+    },
+    /// Skews `paint`, around `center` or else the origin (formats 28 to 31).
+    Skew {
+        /// The paint to skew.
+        paint: PaintRef,
+        /// The angle of the skew in the direction of the x axis, counter-clockwise.
+        x_skew_angle: f32,
+        /// The angle of the skew in the direction of the y axis, counter-clockwise.
+        y_skew_angle: f32,
+        /// The center of the skew, if not the origin.
+        center: Option<(i16, i16)>,
+        /// Base index into the variation index map, for variable paints.
+        var_index_base: Option<u32>,
+    },
+    /// Composites `source` over `backdrop` using `mode` (format 32).
+    Composite {
+        /// The source paint.
+        source: PaintRef,
+        /// The compositing or blending mode.
+        mode: CompositeMode,
+        /// The backdrop paint.
+        backdrop: PaintRef,
+    },
+}
+
+/// An affine transform, mapping `x`, `y` to `xx * x + xy * y + dx`, `yx * x + yy * y + dy`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Affine2x3 {
+    /// The x component of the transformed x-basis vector.
+    pub xx: f32,
+    /// The y component of the transformed x-basis vector.
+    pub yx: f32,
+    /// The x component of the transformed y-basis vector.
+    pub xy: f32,
+    /// The y component of the transformed y-basis vector.
+    pub yy: f32,
+    /// The horizontal translation.
+    pub dx: f32,
+    /// The vertical translation.
+    pub dy: f32,
+}
+
+/// The color stops of a gradient.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorLine {
+    /// How the gradient is extended outside of the range of its color stops.
+    pub extend: Extend,
+    /// The color stops of the gradient, in the order they appear in the font.
+    pub stops: Vec<ColorStop>,
+}
+
+/// A color stop of a gradient.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ColorStop {
+    /// The position of the stop on the color line.
+    pub stop_offset: f32,
+    /// The index of the palette entry, or `FOREGROUND_PALETTE_INDEX`.
+    pub palette_index: u16,
+    /// Alpha multiplier for the color.
+    pub alpha: f32,
+    /// Base index into the variation index map, for variable color lines.
+    pub var_index_base: Option<u32>,
+}
+
+/// How a gradient is extended outside of the range of its color stops.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Extend {
+    /// Use the color of the nearest stop.
+    Pad,
+    /// Repeat the color line.
+    Repeat,
+    /// Repeat the color line, reversing every other repetition.
+    Reflect,
+}
+
+/// How the source of a composite paint is combined with its backdrop.
+#[allow(missing_docs)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CompositeMode {
+    Clear,
+    Src,
+    Dest,
+    SrcOver,
+    DestOver,
+    SrcIn,
+    DestIn,
+    SrcOut,
+    DestOut,
+    SrcAtop,
+    DestAtop,
+    Xor,
+    Plus,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Multiply,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
+/// A clip box of a version 1 color glyph, in font units.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClipBox {
+    /// Minimum x of the box.
+    pub x_min: i16,
+    /// Minimum y of the box.
+    pub y_min: i16,
+    /// Maximum x of the box.
+    pub x_max: i16,
+    /// Maximum y of the box.
+    pub y_max: i16,
+    /// Base index into the variation index map, for variable clip boxes.
+    pub var_index_base: Option<u32>,
+}
+
+/// A layer of a version 0 color glyph with its color resolved from a palette.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ColorLayer {
+    /// The glyph id of the glyph that provides the outline of the layer.
+    pub glyph_id: u16,
+    /// The color of the layer. `None` indicates the text foreground color should be used.
+    pub color: Option<ColorRecord>,
+}
+
+impl<'a> ReadBinary<'a> for ColrTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let version = ctxt.read_u16be()?;
+        ctxt.check_version(version <= 1)?;
+        let num_base_glyph_records = usize::from(ctxt.read_u16be()?);
+        let base_glyph_records_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let layer_records_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let num_layer_records = usize::from(ctxt.read_u16be()?);
+
+        let base_glyph_records = scope
+            .offset(base_glyph_records_offset)
+            .ctxt()
+            .read_array::<BaseGlyphRecord>(num_base_glyph_records)?;
+        let layer_records = scope
+            .offset(layer_records_offset)
+            .ctxt()
+            .read_array::<LayerRecord>(num_layer_records)?;
+
+        let mut base_glyph_paint_records = ReadArray::empty();
+        let mut base_glyph_list_offset = 0;
+        let mut layer_paint_offsets = ReadArray::empty();
+        let mut layer_list_offset = 0;
+        let mut clip_records = ReadArray::empty();
+        let mut clip_list_offset = 0;
+        if version >= 1 {
+            base_glyph_list_offset = usize::try_from(ctxt.read_u32be()?)?;
+            layer_list_offset = usize::try_from(ctxt.read_u32be()?)?;
+            clip_list_offset = usize::try_from(ctxt.read_u32be()?)?;
+            // The variation index map and item variation store offsets follow. Deltas are not
+            // applied so they're not read.
+
+            if base_glyph_list_offset != 0 {
+                let mut ctxt = scope.offset(base_glyph_list_offset).ctxt();
+                let num_records = usize::try_from(ctxt.read_u32be()?)?;
+                base_glyph_paint_records = ctxt.read_array::<BaseGlyphPaintRecord>(num_records)?;
+            }
+            if layer_list_offset != 0 {
+                let mut ctxt = scope.offset(layer_list_offset).ctxt();
+                let num_layers = usize::try_from(ctxt.read_u32be()?)?;
+                layer_paint_offsets = ctxt.read_array::<U32Be>(num_layers)?;
+            }
+            if clip_list_offset != 0 {
+                let mut ctxt = scope.offset(clip_list_offset).ctxt();
+                let format = ctxt.read_u8()?;
+                ctxt.check(format == 1)?;
+                let num_clips = usize::try_from(ctxt.read_u32be()?)?;
+                clip_records = ctxt.read_array::<ClipRecord>(num_clips)?;
+            }
+        }
+
+        Ok(ColrTable {
+            version,
+            scope,
+            base_glyph_records,
+            layer_records,
+            base_glyph_paint_records,
+            base_glyph_list_offset,
+            layer_paint_offsets,
+            layer_list_offset,
+            clip_records,
+            clip_list_offset,
+        })
+    }
+}
+
+impl<'a> ReadFrom<'a> for BaseGlyphRecord {
+    type ReadType = (U16Be, U16Be, U16Be);
+    fn from((glyph_id, first_layer_index, num_layers): (u16, u16, u16)) -> Self {
+        BaseGlyphRecord {
+            glyph_id,
+            first_layer_index,
+            num_layers,
+        }
+    }
+}
+
+impl<'a> ReadFrom<'a> for LayerRecord {
+    type ReadType = (U16Be, U16Be);
+    fn from((glyph_id, palette_index): (u16, u16)) -> Self {
+        LayerRecord {
+            glyph_id,
+            palette_index,
+        }
+    }
+}
+
+impl<'a> ReadFrom<'a> for BaseGlyphPaintRecord {
+    type ReadType = (U16Be, U32Be);
+    fn from((glyph_id, paint_offset): (u16, u32)) -> Self {
+        BaseGlyphPaintRecord {
+            glyph_id,
+            paint_offset,
+        }
+    }
+}
+
+impl<'a> ReadFrom<'a> for ClipRecord {
+    type ReadType = (U16Be, U16Be, U24Be);
+    fn from((start_glyph_id, end_glyph_id, clip_box_offset): (u16, u16, u32)) -> Self {
+        ClipRecord {
+            start_glyph_id,
+            end_glyph_id,
+            clip_box_offset,
+        }
+    }
+}
+
+impl<'a> ColrTable<'a> {
+    /// Returns the base glyph record of `glyph_id` if it has a version 0 color glyph.
+    pub fn base_glyph_record(&self, glyph_id: u16) -> Option<BaseGlyphRecord> {
+        // Base glyph records are sorted by glyph id
+        let (mut lo, mut hi) = (0, self.base_glyph_records.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let record = self.base_glyph_records.get_item(mid);
+            if record.glyph_id < glyph_id {
+                lo = mid + 1;
+            } else if record.glyph_id > glyph_id {
+                hi = mid;
+            } else {
+                return Some(record);
+            }
+        }
+        None
+    }
+
+    /// Returns the layers of the version 0 color glyph of `glyph_id`, bottom-most first.
+    ///
+    /// Returns `None` if `glyph_id` doesn't have a version 0 color glyph.
+    pub fn layers(&self, glyph_id: u16) -> Result<Option<Vec<LayerRecord>>, ParseError> {
+        let record = match self.base_glyph_record(glyph_id) {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let first = usize::from(record.first_layer_index);
+        let end = first + usize::from(record.num_layers);
+        if end > self.layer_records.len() {
+            return Err(ParseError::BadIndex);
+        }
+        Ok(Some(
+            (first..end)
+                .map(|index| self.layer_records.get_item(index))
+                .collect(),
+        ))
+    }
+
+    /// Returns the layers of the version 0 color glyph of `glyph_id`, bottom-most first, with
+    /// colors from the palette at `palette_index` in `cpal`.
+    ///
+    /// Returns `None` if `glyph_id` doesn't have a version 0 color glyph.
+    pub fn color_layers(
+        &self,
+        glyph_id: u16,
+        cpal: &CpalTable<'_>,
+        palette_index: u16,
+    ) -> Result<Option<Vec<ColorLayer>>, ParseError> {
+        let layers = match self.layers(glyph_id)? {
+            Some(layers) => layers,
+            None => return Ok(None),
+        };
+        layers
+            .iter()
+            .map(|layer| {
+                let color = if layer.palette_index == FOREGROUND_PALETTE_INDEX {
+                    None
+                } else {
+                    Some(cpal.color(palette_index, layer.palette_index)?)
+                };
+                Ok(ColorLayer {
+                    glyph_id: layer.glyph_id,
+                    color,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some)
+    }
+
+    /// Returns the root paint of the version 1 color glyph of `glyph_id`.
+    ///
+    /// Returns `None` if `glyph_id` doesn't have a version 1 color glyph.
+    pub fn base_glyph_paint(&self, glyph_id: u16) -> Option<PaintRef> {
+        // Base glyph paint records are sorted by glyph id
+        let (mut lo, mut hi) = (0, self.base_glyph_paint_records.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let record = self.base_glyph_paint_records.get_item(mid);
+            if record.glyph_id < glyph_id {
+                lo = mid + 1;
+            } else if record.glyph_id > glyph_id {
+                hi = mid;
+            } else {
+                let offset = usize::try_from(record.paint_offset).ok()?;
+                return Some(PaintRef(self.base_glyph_list_offset + offset));
+            }
+        }
+        None
+    }
+
+    /// Returns the paint of layer `index` of the layer list.
+    pub fn layer_paint(&self, index: u32) -> Result<PaintRef, ParseError> {
+        let index = usize::try_from(index)?;
+        if index >= self.layer_paint_offsets.len() {
+            return Err(ParseError::BadIndex);
+        }
+        let offset = usize::try_from(self.layer_paint_offsets.get_item(index))?;
+        Ok(PaintRef(self.layer_list_offset + offset))
+    }
+
+    /// Returns the clip box of the version 1 color glyph of `glyph_id`, if it has one.
+    pub fn clip_box(&self, glyph_id: u16) -> Result<Option<ClipBox>, ParseError> {
+        let record = match self
+            .clip_records
+            .iter()
+            .find(|clip| clip.start_glyph_id <= glyph_id && glyph_id <= clip.end_glyph_id)
+        {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let offset = usize::try_from(record.clip_box_offset)?;
+        let mut ctxt = self.scope.offset(self.clip_list_offset + offset).ctxt();
+        let format = ctxt.read_u8()?;
+        ctxt.check(format == 1 || format == 2)?;
+        let x_min = ctxt.read_i16be()?;
+        let y_min = ctxt.read_i16be()?;
+        let x_max = ctxt.read_i16be()?;
+        let y_max = ctxt.read_i16be()?;
+        let var_index_base = read_var_index_base(&mut ctxt, format == 2)?;
+        Ok(Some(ClipBox {
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+            var_index_base,
+        }))
+    }
+
+    /// Read the paint table referenced by `paint_ref`.
+    pub fn paint(&self, paint_ref: PaintRef) -> Result<Paint, ParseError> {
+        let PaintRef(paint_offset) = paint_ref;
+        let scope = self.scope.offset(paint_offset);
+        let mut ctxt = scope.ctxt();
+        let format = ctxt.read_u8()?;
+        // Odd formats from 3 to 31 are the variable versions of the preceding format
+        let var = (3..=31).contains(&format) && format % 2 == 1;
+        let read_child = |ctxt: &mut ReadCtxt<'a>| -> Result<PaintRef, ParseError> {
+            let offset = usize::try_from(ctxt.read::<U24Be>()?)?;
+            Ok(PaintRef(paint_offset + offset))
+        };
+        let paint = match format {
+            1 => Paint::ColrLayers {
+                num_layers: ctxt.read_u8()?,
+                first_layer_index: ctxt.read_u32be()?,
+            },
+            2 | 3 => Paint::Solid {
+                palette_index: ctxt.read_u16be()?,
+                alpha: read_f2dot14(&mut ctxt)?,
+                var_index_base: read_var_index_base(&mut ctxt, var)?,
+            },
+            4 | 5 => {
+                let color_line = read_color_line(&scope, &mut ctxt, var)?;
+                Paint::LinearGradient {
+                    color_line,
+                    p0: read_point(&mut ctxt)?,
+                    p1: read_point(&mut ctxt)?,
+                    p2: read_point(&mut ctxt)?,
+                    var_index_base: read_var_index_base(&mut ctxt, var)?,
+                }
+            }
+            6 | 7 => {
+                let color_line = read_color_line(&scope, &mut ctxt, var)?;
+                Paint::RadialGradient {
+                    color_line,
+                    c0: read_point(&mut ctxt)?,
+                    r0: ctxt.read_u16be()?,
+                    c1: read_point(&mut ctxt)?,
+                    r1: ctxt.read_u16be()?,
+                    var_index_base: read_var_index_base(&mut ctxt, var)?,
+                }
+            }
+            8 | 9 => {
+                let color_line = read_color_line(&scope, &mut ctxt, var)?;
+                Paint::SweepGradient {
+                    color_line,
+                    center: read_point(&mut ctxt)?,
+                    start_angle: read_f2dot14(&mut ctxt)?,
+                    end_angle: read_f2dot14(&mut ctxt)?,
+                    var_index_base: read_var_index_base(&mut ctxt, var)?,
+                }
+            }
+            10 => Paint::Glyph {
+                paint: read_child(&mut ctxt)?,
+                glyph_id: ctxt.read_u16be()?,
+            },
+            11 => Paint::ColrGlyph {
+                glyph_id: ctxt.read_u16be()?,
+            },
+            12 | 13 => {
+                let paint = read_child(&mut ctxt)?;
+                let transform_offset = usize::try_from(ctxt.read::<U24Be>()?)?;
+                let mut transform_ctxt = scope.offset(transform_offset).ctxt();
+                let mut values = [0.; 6];
+                for value in values.iter_mut() {
+                    *value = read_fixed(&mut transform_ctxt)?;
+                }
+                let var_index_base = read_var_index_base(&mut transform_ctxt, var)?;
+                Paint::Transform {
+                    paint,
+                    transform: Affine2x3 {
+                        xx: values[0],
+                        yx: values[1],
+                        xy: values[2],
+                        yy: values[3],
+                        dx: values[4],
+                        dy: values[5],
+                    },
+                    var_index_base,
+                }
+            }
+            14 | 15 => Paint::Translate {
+                paint: read_child(&mut ctxt)?,
+                dx: ctxt.read_i16be()?,
+                dy: ctxt.read_i16be()?,
+                var_index_base: read_var_index_base(&mut ctxt, var)?,
+            },
+            16..=23 => {
+                // 16, 17: scale; 18, 19: scale around center; 20, 21: uniform scale;
+                // 22, 23: uniform scale around center
+                let paint = read_child(&mut ctxt)?;
+                let scale_x = read_f2dot14(&mut ctxt)?;
+                let scale_y = if format < 20 {
+                    read_f2dot14(&mut ctxt)?
+                } else {
+                    scale_x
+                };
+                let center = if format == 18 || format == 19 || format >= 22 {
+                    Some(read_point(&mut ctxt)?)
+                } else {
+                    None
+                };
+                Paint::Scale {
+                    paint,
+                    scale_x,
+                    scale_y,
+                    center,
+                    var_index_base: read_var_index_base(&mut ctxt, var)?,
+                }
+            }
+            24..=27 => {
+                let paint = read_child(&mut ctxt)?;
+                let angle = read_f2dot14(&mut ctxt)?;
+                let center = if format >= 26 {
+                    Some(read_point(&mut ctxt)?)
+                } else {
+                    None
+                };
+                Paint::Rotate {
+                    paint,
+                    angle,
+                    center,
+                    var_index_base: read_var_index_base(&mut ctxt, var)?,
+                }
+            }
+            28..=31 => {
+                let paint = read_child(&mut ctxt)?;
+                let x_skew_angle = read_f2dot14(&mut ctxt)?;
+                let y_skew_angle = read_f2dot14(&mut ctxt)?;
+                let center = if format >= 30 {
+                    Some(read_point(&mut ctxt)?)
+                } else {
+                    None
+                };
+                Paint::Skew {
+                    paint,
+                    x_skew_angle,
+                    y_skew_angle,
+                    center,
+                    var_index_base: read_var_index_base(&mut ctxt, var)?,
+                }
+            }
+            32 => Paint::Composite {
+                source: read_child(&mut ctxt)?,
+                mode: CompositeMode::from(ctxt.read_u8()?),
+                backdrop: read_child(&mut ctxt)?,
+            },
+            _ => return Err(ParseError::BadValue),
+        };
+        Ok(paint)
+    }
+}
+
+impl From<u8> for CompositeMode {
+    /// Unrecognised modes are treated as `Clear`, as required by the specification.
+    fn from(mode: u8) -> CompositeMode {
+        match mode {
+            1 => CompositeMode::Src,
+            2 => CompositeMode::Dest,
+            3 => CompositeMode::SrcOver,
+            4 => CompositeMode::DestOver,
+            5 => CompositeMode::SrcIn,
+            6 => CompositeMode::DestIn,
+            7 => CompositeMode::SrcOut,
+            8 => CompositeMode::DestOut,
+            9 => CompositeMode::SrcAtop,
+            10 => CompositeMode::DestAtop,
+            11 => CompositeMode::Xor,
+            12 => CompositeMode::Plus,
+            13 => CompositeMode::Screen,
+            14 => CompositeMode::Overlay,
+            15 => CompositeMode::Darken,
+            16 => CompositeMode::Lighten,
+            17 => CompositeMode::ColorDodge,
+            18 => CompositeMode::ColorBurn,
+            19 => CompositeMode::HardLight,
+            20 => CompositeMode::SoftLight,
+            21 => CompositeMode::Difference,
+            22 => CompositeMode::Exclusion,
+            23 => CompositeMode::Multiply,
+            24 => CompositeMode::Hue,
+            25 => CompositeMode::Saturation,
+            26 => CompositeMode::Color,
+            27 => CompositeMode::Luminosity,
+            _ => CompositeMode::Clear,
+        }
+    }
+}
+
+impl Affine2x3 {
+    /// The identity transform.
+    pub const IDENTITY: Affine2x3 = Affine2x3 {
+        xx: 1.,
+        yx: 0.,
+        xy: 0.,
+        yy: 1.,
+        dx: 0.,
+        dy: 0.,
+    };
+
+    /// A transform that translates by `dx`, `dy`.
+    pub fn translate(dx: f32, dy: f32) -> Affine2x3 {
+        Affine2x3 {
+            dx,
+            dy,
+            ..Affine2x3::IDENTITY
+        }
+    }
+
+    /// A transform that scales by `scale_x`, `scale_y`.
+    pub fn scale(scale_x: f32, scale_y: f32) -> Affine2x3 {
+        Affine2x3 {
+            xx: scale_x,
+            yy: scale_y,
+            ..Affine2x3::IDENTITY
+        }
+    }
+
+    /// A transform that rotates counter-clockwise by `angle` radians.
+    pub fn rotate(angle: f32) -> Affine2x3 {
+        let (sin, cos) = angle.sin_cos();
+        Affine2x3 {
+            xx: cos,
+            yx: sin,
+            xy: -sin,
+            yy: cos,
+            dx: 0.,
+            dy: 0.,
+        }
+    }
+
+    /// A transform that skews counter-clockwise by `x_angle` radians in the direction of the x
+    /// axis and `y_angle` radians in the direction of the y axis.
+    pub fn skew(x_angle: f32, y_angle: f32) -> Affine2x3 {
+        Affine2x3 {
+            yx: y_angle.tan(),
+            xy: -x_angle.tan(),
+            ..Affine2x3::IDENTITY
+        }
+    }
+
+    /// Returns the transform that applies `other` and then `self`.
+    pub fn pre_concat(&self, other: &Affine2x3) -> Affine2x3 {
+        Affine2x3 {
+            xx: self.xx * other.xx + self.xy * other.yx,
+            yx: self.yx * other.xx + self.yy * other.yx,
+            xy: self.xx * other.xy + self.xy * other.yy,
+            yy: self.yx * other.xy + self.yy * other.yy,
+            dx: self.xx * other.dx + self.xy * other.dy + self.dx,
+            dy: self.yx * other.dx + self.yy * other.dy + self.dy,
+        }
+    }
+
+    /// Returns `self` applied around `center`, rather than the origin.
+    pub fn around_center(&self, center: (f32, f32)) -> Affine2x3 {
+        let (x, y) = center;
+        Affine2x3::translate(x, y)
+            .pre_concat(self)
+            .pre_concat(&Affine2x3::translate(-x, -y))
+    }
+
+    /// Apply the transform to the point `x`, `y`.
+    pub fn transform_point(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.xx * x + self.xy * y + self.dx,
+            self.yx * x + self.yy * y + self.dy,
+        )
+    }
+}
+
+fn read_color_line<'a>(
+    paint_scope: &ReadScope<'a>,
+    ctxt: &mut ReadCtxt<'a>,
+    var: bool,
+) -> Result<ColorLine, ParseError> {
+    let offset = usize::try_from(ctxt.read::<U24Be>()?)?;
+    let mut ctxt = paint_scope.offset(offset).ctxt();
+    let extend = match ctxt.read_u8()? {
+        1 => Extend::Repeat,
+        2 => Extend::Reflect,
+        // Unrecognised values are treated as pad
+        _ => Extend::Pad,
+    };
+    let num_stops = ctxt.read_u16be()?;
+    let stops = (0..num_stops)
+        .map(|_| {
+            Ok(ColorStop {
+                stop_offset: read_f2dot14(&mut ctxt)?,
+                palette_index: ctxt.read_u16be()?,
+                alpha: read_f2dot14(&mut ctxt)?,
+                var_index_base: read_var_index_base(&mut ctxt, var)?,
+            })
+        })
+        .collect::<Result<Vec<_>, ParseError>>()?;
+    Ok(ColorLine { extend, stops })
+}
+
+fn read_var_index_base(ctxt: &mut ReadCtxt<'_>, var: bool) -> Result<Option<u32>, ParseError> {
+    if var {
+        Ok(Some(ctxt.read_u32be()?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn read_point(ctxt: &mut ReadCtxt<'_>) -> Result<(i16, i16), ParseError> {
+    Ok((ctxt.read_i16be()?, ctxt.read_i16be()?))
+}
+
+fn read_f2dot14(ctxt: &mut ReadCtxt<'_>) -> Result<f32, ParseError> {
+    ctxt.read::<F2Dot14>().map(f32::from)
+}
+
+/// Read a 16.16 fixed point number.
+fn read_fixed(ctxt: &mut ReadCtxt<'_>) -> Result<f32, ParseError> {
+    Ok(ctxt.read_i32be()? as f32 / 65536.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+
+    #[test]
+    fn test_color_layers() {
+        #[rustfmt::skip]
+        let colr_data = [
+            0x00, 0x00, // version
+            0x00, 0x02, // num_base_glyph_records
+            0x00, 0x00, 0x00, 0x0E, // base_glyph_records_offset
+            0x00, 0x00, 0x00, 0x1A, // layer_records_offset
+            0x00, 0x03, // num_layer_records
+            // base glyph records
+            0x00, 0x05, 0x00, 0x00, 0x00, 0x02,
+            0x00, 0x09, 0x00, 0x02, 0x00, 0x01,
+            // layer records
+            0x00, 0x0A, 0x00, 0x01,
+            0x00, 0x0B, 0xFF, 0xFF,
+            0x00, 0x0C, 0x00, 0x00,
+        ];
+        #[rustfmt::skip]
+        let cpal_data = [
+            0x00, 0x00, // version
+            0x00, 0x02, // num_palette_entries
+            0x00, 0x01, // num_palettes
+            0x00, 0x02, // num_color_records
+            0x00, 0x00, 0x00, 0x0E, // color_records_offset
+            0x00, 0x00, // color_record_indices
+            0x00, 0x00, 0xFF, 0xFF,
+            0xFF, 0x00, 0x00, 0xFF,
+        ];
+        let colr = ReadScope::new(&colr_data).read::<ColrTable<'_>>().unwrap();
+        let cpal = ReadScope::new(&cpal_data).read::<CpalTable<'_>>().unwrap();
+
+        let blue = ColorRecord {
+            blue: 0xFF,
+            green: 0,
+            red: 0,
+            alpha: 0xFF,
+        };
+        assert_eq!(
+            colr.color_layers(5, &cpal, 0).unwrap(),
+            Some(vec![
+                ColorLayer {
+                    glyph_id: 10,
+                    color: Some(blue)
+                },
+                ColorLayer {
+                    glyph_id: 11,
+                    color: None
+                },
+            ])
+        );
+        assert_eq!(colr.layers(9).unwrap().map(|layers| layers.len()), Some(1));
+        assert_eq!(colr.layers(6).unwrap(), None);
+        assert!(colr.color_layers(5, &cpal, 1).is_err());
+    }
+
+    #[test]
+    fn test_read_var_paints() {
+        #[rustfmt::skip]
+        let data = [
+            0x00, 0x01, // version
+            0x00, 0x00, // num_base_glyph_records
+            0x00, 0x00, 0x00, 0x00, // base_glyph_records_offset
+            0x00, 0x00, 0x00, 0x00, // layer_records_offset
+            0x00, 0x00, // num_layer_records
+            0x00, 0x00, 0x00, 0x22, // base_glyph_list_offset
+            0x00, 0x00, 0x00, 0x00, // layer_list_offset
+            0x00, 0x00, 0x00, 0x00, // clip_list_offset
+            0x00, 0x00, 0x00, 0x00, // var_index_map_offset
+            0x00, 0x00, 0x00, 0x00, // item_variation_store_offset
+            // base glyph list
+            0x00, 0x00, 0x00, 0x01,
+            0x00, 0x03, 0x00, 0x00, 0x00, 0x0A,
+            // 0x2C: PaintVarScaleUniformAroundCenter, paint at 0x3A
+            0x17, 0x00, 0x00, 0x0E, 0x60, 0x00, 0x00, 0x64, 0xFF, 0x9C, 0x00, 0x00, 0x00, 0x07,
+            // 0x3A: PaintVarTransform, paint at 0x5D, transform at 0x41
+            0x0D, 0x00, 0x00, 0x23, 0x00, 0x00, 0x07,
+            // 0x41: VarAffine2x3
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x80, 0x00, 0x00, 0x01, 0x00, 0x00,
+            0x00, 0x0A, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x09,
+            // 0x5D: PaintVarSweepGradient, color line at 0x6D
+            0x09, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00,
+            0x00, 0x00, 0x00, 0x02,
+            // 0x6D: VarColorLine
+            0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x40, 0x00, 0x00, 0x00, 0x00, 0x05,
+        ];
+        let colr = ReadScope::new(&data).read::<ColrTable<'_>>().unwrap();
+        assert!(colr.base_glyph_paint(2).is_none());
+        assert_eq!(colr.clip_box(3), Ok(None));
+        assert_eq!(colr.layer_paint(0), Err(ParseError::BadIndex));
+
+        let scale_ref = colr.base_glyph_paint(3).unwrap();
+        let transform_ref = match colr.paint(scale_ref).unwrap() {
+            Paint::Scale {
+                paint,
+                scale_x,
+                scale_y,
+                center,
+                var_index_base,
+            } => {
+                assert_eq!((scale_x, scale_y), (1.5, 1.5));
+                assert_eq!(center, Some((100, -100)));
+                assert_eq!(var_index_base, Some(7));
+                paint
+            }
+            paint => panic!("unexpected paint {:?}", paint),
+        };
+        let sweep_ref = match colr.paint(transform_ref).unwrap() {
+            Paint::Transform {
+                paint,
+                transform,
+                var_index_base,
+            } => {
+                assert_eq!(
+                    transform,
+                    Affine2x3 {
+                        xx: 2.,
+                        yx: 0.,
+                        xy: 0.5,
+                        yy: 1.,
+                        dx: 10.,
+                        dy: -1.,
+                    }
+                );
+                assert_eq!(var_index_base, Some(9));
+                paint
+            }
+            paint => panic!("unexpected paint {:?}", paint),
+        };
+        assert_eq!(
+            colr.paint(sweep_ref).unwrap(),
+            Paint::SweepGradient {
+                color_line: ColorLine {
+                    extend: Extend::Repeat,
+                    stops: vec![ColorStop {
+                        stop_offset: 0.,
+                        palette_index: 2,
+                        alpha: 1.,
+                        var_index_base: Some(5),
+                    }],
+                },
+                center: (0, 0),
+                start_angle: 0.,
+                end_angle: 0.5,
+                var_index_base: Some(2),
+            }
+        );
+    }
+}
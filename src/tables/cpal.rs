@@ -0,0 +1,262 @@
+#![deny(missing_docs)]
+
+//! `CPAL` Color Palette Table parsing.
+//!
+//! Versions 0 and 1 are supported. Version 1 adds palette types and `name` table labels for
+//! palettes and palette entries.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/cpal>
+
+use std::convert::TryFrom;
+
+use bitflags::bitflags;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt, ReadFrom, ReadScope, ReadUnchecked};
+use crate::binary::{U16Be, U32Be, U8};
+use crate::error::ParseError;
+
+/// Label value indicating that a palette or palette entry has no name.
+const NO_NAME_ID: u16 = 0xFFFF;
+
+/// `CPAL` Color Palette Table.
+pub struct CpalTable<'a> {
+    /// The version of the table, either `0` or `1`.
+    pub version: u16,
+    /// The number of entries in each palette.
+    pub num_palette_entries: u16,
+    /// The number of palettes.
+    pub num_palettes: u16,
+    color_records: ReadArray<'a, ColorRecord>,
+    color_record_indices: ReadArray<'a, U16Be>,
+    palette_types: Option<ReadArray<'a, U32Be>>,
+    palette_labels: Option<ReadArray<'a, U16Be>>,
+    palette_entry_labels: Option<ReadArray<'a, U16Be>>,
+}
+
+/// A color in the sRGB color space with straight (not premultiplied) alpha.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ColorRecord {
+    /// Blue value.
+    pub blue: u8,
+    /// Green value.
+    pub green: u8,
+    /// Red value.
+    pub red: u8,
+    /// Alpha value, where `0` is fully transparent and `255` is fully opaque.
+    pub alpha: u8,
+}
+
+bitflags! {
+    /// Flags describing the intended use of a palette. Only present in `CPAL` version 1.
+    pub struct PaletteType: u32 {
+        /// The palette is appropriate to use when displaying the font on a light background.
+        const USABLE_WITH_LIGHT_BACKGROUND = 0x0001;
+        /// The palette is appropriate to use when displaying the font on a dark background.
+        const USABLE_WITH_DARK_BACKGROUND = 0x0002;
+    }
+}
+
+impl<'a> ReadBinary<'a> for CpalTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let version = ctxt.read_u16be()?;
+        ctxt.check_version(version <= 1)?;
+        let num_palette_entries = ctxt.read_u16be()?;
+        let num_palettes = ctxt.read_u16be()?;
+        let num_color_records = usize::from(ctxt.read_u16be()?);
+        let color_records_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let color_record_indices = ctxt.read_array::<U16Be>(usize::from(num_palettes))?;
+        let color_records = scope
+            .offset(color_records_offset)
+            .ctxt()
+            .read_array::<ColorRecord>(num_color_records)?;
+
+        let (palette_types, palette_labels, palette_entry_labels) = if version >= 1 {
+            let palette_types_offset = usize::try_from(ctxt.read_u32be()?)?;
+            let palette_labels_offset = usize::try_from(ctxt.read_u32be()?)?;
+            let palette_entry_labels_offset = usize::try_from(ctxt.read_u32be()?)?;
+            (
+                read_optional_array::<U32Be>(&scope, palette_types_offset, num_palettes)?,
+                read_optional_array::<U16Be>(&scope, palette_labels_offset, num_palettes)?,
+                read_optional_array::<U16Be>(
+                    &scope,
+                    palette_entry_labels_offset,
+                    num_palette_entries,
+                )?,
+            )
+        } else {
+            (None, None, None)
+        };
+
+        Ok(CpalTable {
+            version,
+            num_palette_entries,
+            num_palettes,
+            color_records,
+            color_record_indices,
+            palette_types,
+            palette_labels,
+            palette_entry_labels,
+        })
+    }
+}
+
+impl<'a> ReadFrom<'a> for ColorRecord {
+    type ReadType = ((U8, U8), (U8, U8));
+    fn from(((blue, green), (red, alpha)): ((u8, u8), (u8, u8))) -> Self {
+        ColorRecord {
+            blue,
+            green,
+            red,
+            alpha,
+        }
+    }
+}
+
+impl<'a> CpalTable<'a> {
+    /// Returns the colors of the palette at `palette_index`.
+    pub fn palette(&self, palette_index: u16) -> Result<Vec<ColorRecord>, ParseError> {
+        let first = self.first_color_record(palette_index)?;
+        Ok((first..first + usize::from(self.num_palette_entries))
+            .map(|index| self.color_records.get_item(index))
+            .collect())
+    }
+
+    /// Returns the color of entry `entry_index` in the palette at `palette_index`.
+    pub fn color(&self, palette_index: u16, entry_index: u16) -> Result<ColorRecord, ParseError> {
+        if entry_index >= self.num_palette_entries {
+            return Err(ParseError::BadIndex);
+        }
+        let first = self.first_color_record(palette_index)?;
+        Ok(self
+            .color_records
+            .get_item(first + usize::from(entry_index)))
+    }
+
+    /// Returns the type of the palette at `palette_index`.
+    ///
+    /// Returns `None` for version 0 tables and tables without palette types.
+    pub fn palette_type(&self, palette_index: u16) -> Option<PaletteType> {
+        self.palette_types
+            .as_ref()
+            .filter(|types| usize::from(palette_index) < types.len())
+            .map(|types| {
+                PaletteType::from_bits_truncate(types.get_item(usize::from(palette_index)))
+            })
+    }
+
+    /// Returns the `name` table id of the label of the palette at `palette_index`.
+    ///
+    /// Returns `None` if the palette has no label.
+    pub fn palette_label(&self, palette_index: u16) -> Option<u16> {
+        name_id(self.palette_labels.as_ref(), palette_index)
+    }
+
+    /// Returns the `name` table id of the label of palette entry `entry_index`.
+    ///
+    /// Entry labels apply to the entry at that index in every palette. Returns `None` if the
+    /// entry has no label.
+    pub fn palette_entry_label(&self, entry_index: u16) -> Option<u16> {
+        name_id(self.palette_entry_labels.as_ref(), entry_index)
+    }
+
+    /// Returns the index of the first color record of the palette at `palette_index`.
+    fn first_color_record(&self, palette_index: u16) -> Result<usize, ParseError> {
+        if palette_index >= self.num_palettes {
+            return Err(ParseError::BadIndex);
+        }
+        let first = usize::from(
+            self.color_record_indices
+                .get_item(usize::from(palette_index)),
+        );
+        if first + usize::from(self.num_palette_entries) > self.color_records.len() {
+            return Err(ParseError::BadIndex);
+        }
+        Ok(first)
+    }
+}
+
+/// Read an array of `len` items at `offset`, where an offset of zero indicates it's absent.
+fn read_optional_array<'a, T: ReadUnchecked<'a>>(
+    scope: &ReadScope<'a>,
+    offset: usize,
+    len: u16,
+) -> Result<Option<ReadArray<'a, T>>, ParseError> {
+    if offset == 0 {
+        Ok(None)
+    } else {
+        scope
+            .offset(offset)
+            .ctxt()
+            .read_array::<T>(usize::from(len))
+            .map(Some)
+    }
+}
+
+fn name_id(labels: Option<&ReadArray<'_, U16Be>>, index: u16) -> Option<u16> {
+    labels
+        .filter(|labels| usize::from(index) < labels.len())
+        .map(|labels| labels.get_item(usize::from(index)))
+        .filter(|&name_id| name_id != NO_NAME_ID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_cpal_v1() {
+        #[rustfmt::skip]
+        let data = [
+            0x00, 0x01, // version
+            0x00, 0x02, // num_palette_entries
+            0x00, 0x02, // num_palettes
+            0x00, 0x04, // num_color_records
+            0x00, 0x00, 0x00, 0x1C, // color_records_offset
+            0x00, 0x00, 0x00, 0x02, // color_record_indices
+            0x00, 0x00, 0x00, 0x2C, // palette_types_offset
+            0x00, 0x00, 0x00, 0x34, // palette_labels_offset
+            0x00, 0x00, 0x00, 0x00, // palette_entry_labels_offset
+            // color records
+            0x00, 0x00, 0xFF, 0xFF, // red
+            0xFF, 0x00, 0x00, 0xFF, // blue
+            0xFF, 0xFF, 0xFF, 0xFF, // white
+            0x00, 0x00, 0x00, 0x80, // translucent black
+            // palette types
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+            // palette labels
+            0x01, 0x00, 0xFF, 0xFF,
+        ];
+        let cpal = ReadScope::new(&data).read::<CpalTable<'_>>().unwrap();
+        assert_eq!(cpal.num_palettes, 2);
+        assert_eq!(
+            cpal.palette(1).unwrap(),
+            vec![
+                ColorRecord {
+                    blue: 0xFF,
+                    green: 0xFF,
+                    red: 0xFF,
+                    alpha: 0xFF
+                },
+                ColorRecord {
+                    blue: 0,
+                    green: 0,
+                    red: 0,
+                    alpha: 0x80
+                },
+            ]
+        );
+        assert_eq!(cpal.color(0, 0).unwrap().red, 0xFF);
+        assert_eq!(cpal.color(0, 2), Err(ParseError::BadIndex));
+        assert_eq!(cpal.palette(2), Err(ParseError::BadIndex));
+        assert_eq!(
+            cpal.palette_type(1),
+            Some(PaletteType::USABLE_WITH_DARK_BACKGROUND)
+        );
+        assert_eq!(cpal.palette_label(0), Some(256));
+        assert_eq!(cpal.palette_label(1), None);
+        assert_eq!(cpal.palette_entry_label(0), None);
+    }
+}
@@ -16,9 +16,13 @@ use crate::binary::read::{ReadBinary, ReadBinaryDep, ReadCtxt, ReadFrom, ReadSco
 use crate::binary::write::{WriteBinary, WriteBinaryDep, WriteContext};
 use crate::binary::{word_align, I16Be, U16Be, I8, U8};
 use crate::error::{ParseError, WriteError};
+use crate::outline::OutlineSink;
 use crate::tables::loca::{owned, LocaTable};
 use crate::tables::{F2Dot14, IndexToLocFormat};
 
+// Limit on the nesting of composite glyphs, which guards against cycles
+const MAX_COMPONENT_DEPTH: usize = 16;
+
 bitflags! {
     #[rustfmt::skip]
     pub struct SimpleGlyphFlag: u8 {
@@ -155,42 +159,7 @@ impl<'a> ReadBinaryDep<'a> for GlyfTable<'a> {
     type HostType = Self;
 
     fn read_dep(ctxt: &mut ReadCtxt<'a>, loca: Self::Args) -> Result<Self, ParseError> {
-        if loca.offsets.len() < 2 {
-            return Err(ParseError::BadIndex);
-        }
-
-        let glyph_records = loca
-            .offsets
-            .iter()
-            .tuple_windows()
-            .map(|(start, end)| match end.checked_sub(start) {
-                Some(0) => Ok(GlyfRecord::Empty),
-                Some(length) => {
-                    let offset = usize::try_from(start)?;
-                    let glyph_scope = ctxt.scope().offset_length(offset, usize::try_from(length)?);
-                    match glyph_scope {
-                        Ok(scope) => Ok(GlyfRecord::Present(scope)),
-                        Err(ParseError::BadEof) => {
-                            // The length specified by `loca` is beyond the end of the `glyf`
-                            // table. Try parsing the glyph without a length limit to see if it's
-                            // valid. This is a workaround for a font where the last `loca` offset
-                            // was incorrectly 1 byte beyond the end of the `glyf` table but the
-                            // actual glyph data was valid.
-                            warn!("glyph length out of bounds, trying to parse");
-                            let mut glyph = GlyfRecord::Present(ctxt.scope().offset(offset));
-                            glyph.parse()?;
-                            Ok(glyph)
-                        }
-                        Err(err) => Err(err),
-                    }
-                }
-                None => Err(ParseError::BadOffset),
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(GlyfTable {
-            records: glyph_records,
-        })
+        GlyfTable::read_records(ctxt.scope(), loca)
     }
 }
 
@@ -708,6 +677,51 @@ fn add_glyph(glyph_ids: &mut Vec<u16>, record: &mut GlyfRecord<'_>) {
 }
 
 impl<'a> GlyfTable<'a> {
+    /// Read the glyph records in `scope` at the offsets in `loca`.
+    ///
+    /// Unlike `read_dep` the `loca` table does not need to outlive the returned table.
+    pub(crate) fn read_records(
+        scope: ReadScope<'a>,
+        loca: &LocaTable<'_>,
+    ) -> Result<Self, ParseError> {
+        if loca.offsets.len() < 2 {
+            return Err(ParseError::BadIndex);
+        }
+
+        let glyph_records = loca
+            .offsets
+            .iter()
+            .tuple_windows()
+            .map(|(start, end)| match end.checked_sub(start) {
+                Some(0) => Ok(GlyfRecord::Empty),
+                Some(length) => {
+                    let offset = usize::try_from(start)?;
+                    let glyph_scope = scope.offset_length(offset, usize::try_from(length)?);
+                    match glyph_scope {
+                        Ok(scope) => Ok(GlyfRecord::Present(scope)),
+                        Err(ParseError::BadEof) => {
+                            // The length specified by `loca` is beyond the end of the `glyf`
+                            // table. Try parsing the glyph without a length limit to see if it's
+                            // valid. This is a workaround for a font where the last `loca` offset
+                            // was incorrectly 1 byte beyond the end of the `glyf` table but the
+                            // actual glyph data was valid.
+                            warn!("glyph length out of bounds, trying to parse");
+                            let mut glyph = GlyfRecord::Present(scope.offset(offset));
+                            glyph.parse()?;
+                            Ok(glyph)
+                        }
+                        Err(err) => Err(err),
+                    }
+                }
+                None => Err(ParseError::BadOffset),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(GlyfTable {
+            records: glyph_records,
+        })
+    }
+
     /// Returns a copy of this table that only contains the glyphs specified by `glyph_ids`.
     pub fn subset(&self, glyph_ids: &[u16]) -> Result<(GlyfTable<'a>, Vec<u16>), ParseError> {
         let mut glyph_ids = glyph_ids.to_vec();
@@ -744,6 +758,182 @@ impl<'a> GlyfTable<'a> {
 
         Ok((GlyfTable { records }, new_to_old_id))
     }
+
+    /// Visit the outline of `glyph_id`, passing its contours to `sink`.
+    ///
+    /// The components of composite glyphs are resolved and transformed. Curves are emitted as
+    /// quadratic curves, with the on-curve points implied between consecutive off-curve points
+    /// reconstructed.
+    pub fn visit_outline<S: OutlineSink>(
+        &self,
+        glyph_id: u16,
+        sink: &mut S,
+    ) -> Result<(), ParseError> {
+        let outline = self.outline_points(glyph_id, 0)?;
+        let mut start = 0;
+        for &end in &outline.end_points {
+            visit_contour(&outline.points[start..end], sink);
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// Collect the points of `glyph_id`, resolving the components of composite glyphs.
+    fn outline_points(&self, glyph_id: u16, depth: usize) -> Result<OutlinePoints, ParseError> {
+        if depth > MAX_COMPONENT_DEPTH {
+            return Err(ParseError::LimitExceeded);
+        }
+        let parsed;
+        let glyph = match self.records.get(usize::from(glyph_id)) {
+            Some(GlyfRecord::Empty) => return Ok(OutlinePoints::default()),
+            Some(GlyfRecord::Present(scope)) => {
+                parsed = scope.read::<Glyph<'_>>()?;
+                &parsed
+            }
+            Some(GlyfRecord::Parsed(glyph)) => glyph,
+            None => return Err(ParseError::BadIndex),
+        };
+
+        let mut outline = OutlinePoints::default();
+        match glyph.data {
+            GlyphData::Simple(ref simple_glyph) => {
+                outline.points = simple_glyph
+                    .coordinates
+                    .iter()
+                    .zip(simple_glyph.flags.iter())
+                    .map(|(&Point(x, y), flag)| (f32::from(x), f32::from(y), flag.is_on_curve()))
+                    .collect();
+                outline.end_points = simple_glyph
+                    .end_pts_of_contours
+                    .iter()
+                    .map(|&end| usize::from(end) + 1)
+                    .collect();
+                if outline
+                    .end_points
+                    .iter()
+                    .any(|&end| end > outline.points.len())
+                {
+                    return Err(ParseError::BadIndex);
+                }
+            }
+            GlyphData::Composite { ref glyphs, .. } => {
+                for component in glyphs {
+                    let mut component_outline =
+                        self.outline_points(component.glyph_index, depth + 1)?;
+                    let matrix = component
+                        .scale
+                        .as_ref()
+                        .map(CompositeGlyphScale::matrix)
+                        .unwrap_or([[1., 0.], [0., 1.]]);
+                    for point in component_outline.points.iter_mut() {
+                        let (x, y) = transform(&matrix, point.0, point.1);
+                        point.0 = x;
+                        point.1 = y;
+                    }
+
+                    let (dx, dy) = if component.flags.args_are_xy_values() {
+                        let offset = (
+                            component.argument1.value() as f32,
+                            component.argument2.value() as f32,
+                        );
+                        if component.flags & CompositeGlyphFlag::SCALED_COMPONENT_OFFSET
+                            == CompositeGlyphFlag::SCALED_COMPONENT_OFFSET
+                        {
+                            transform(&matrix, offset.0, offset.1)
+                        } else {
+                            offset
+                        }
+                    } else {
+                        // The arguments are points in the parent and component to be matched
+                        let parent = usize::try_from(component.argument1.value())
+                            .ok()
+                            .and_then(|index| outline.points.get(index))
+                            .ok_or(ParseError::BadIndex)?;
+                        let child = usize::try_from(component.argument2.value())
+                            .ok()
+                            .and_then(|index| component_outline.points.get(index))
+                            .ok_or(ParseError::BadIndex)?;
+                        (parent.0 - child.0, parent.1 - child.1)
+                    };
+
+                    let base = outline.points.len();
+                    outline.points.extend(
+                        component_outline
+                            .points
+                            .iter()
+                            .map(|&(x, y, on_curve)| (x + dx, y + dy, on_curve)),
+                    );
+                    outline
+                        .end_points
+                        .extend(component_outline.end_points.iter().map(|end| base + end));
+                }
+            }
+        }
+
+        Ok(outline)
+    }
+}
+
+/// The points of a glyph with composite glyphs resolved.
+#[derive(Default)]
+struct OutlinePoints {
+    /// The x and y coordinates of each point and whether it is on the curve.
+    points: Vec<(f32, f32, bool)>,
+    /// The index one past the last point of each contour.
+    end_points: Vec<usize>,
+}
+
+/// Pass a contour of TrueType points to `sink`.
+fn visit_contour<S: OutlineSink>(points: &[(f32, f32, bool)], sink: &mut S) {
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(&first), Some(&last)) => (first, last),
+        _ => return,
+    };
+
+    // Start at an on-curve point, which may be implied between the first and last points
+    let (start, rest) = if first.2 {
+        ((first.0, first.1), &points[1..])
+    } else if last.2 {
+        ((last.0, last.1), &points[..points.len() - 1])
+    } else {
+        (midpoint(first, last), points)
+    };
+    sink.move_to(start.0, start.1);
+
+    let mut control: Option<(f32, f32)> = None;
+    for &point in rest {
+        let (x, y, on_curve) = point;
+        match (control, on_curve) {
+            (None, true) => sink.line_to(x, y),
+            (None, false) => control = Some((x, y)),
+            (Some((cx, cy)), true) => {
+                sink.quad_to(cx, cy, x, y);
+                control = None;
+            }
+            (Some((cx, cy)), false) => {
+                let (mx, my) = midpoint((cx, cy, false), point);
+                sink.quad_to(cx, cy, mx, my);
+                control = Some((x, y));
+            }
+        }
+    }
+    match control {
+        Some((cx, cy)) => sink.quad_to(cx, cy, start.0, start.1),
+        None => sink.line_to(start.0, start.1),
+    }
+    sink.close();
+}
+
+fn midpoint(a: (f32, f32, bool), b: (f32, f32, bool)) -> (f32, f32) {
+    ((a.0 + b.0) / 2., (a.1 + b.1) / 2.)
+}
+
+/// Transform `x`, `y` by a composite glyph scale `matrix`.
+fn transform(matrix: &[[f32; 2]; 2], x: f32, y: f32) -> (f32, f32) {
+    (
+        matrix[0][0] * x + matrix[1][0] * y,
+        matrix[0][1] * x + matrix[1][1] * y,
+    )
 }
 
 impl<'a> GlyfRecord<'a> {
@@ -768,6 +958,40 @@ impl<'a> GlyfRecord<'a> {
     }
 }
 
+impl CompositeGlyphArgument {
+    /// Returns the value of this argument.
+    pub fn value(&self) -> i32 {
+        match *self {
+            CompositeGlyphArgument::U8(value) => i32::from(value),
+            CompositeGlyphArgument::I8(value) => i32::from(value),
+            CompositeGlyphArgument::U16(value) => i32::from(value),
+            CompositeGlyphArgument::I16(value) => i32::from(value),
+        }
+    }
+}
+
+impl CompositeGlyphScale {
+    /// Returns this scale as a 2x2 transformation matrix.
+    ///
+    /// The matrix is laid out as `[[xscale, scale01], [scale10, yscale]]`, as in the `glyf`
+    /// table.
+    pub fn matrix(&self) -> [[f32; 2]; 2] {
+        match *self {
+            CompositeGlyphScale::Scale(scale) => {
+                let scale = f32::from(scale);
+                [[scale, 0.], [0., scale]]
+            }
+            CompositeGlyphScale::XY { x_scale, y_scale } => {
+                [[f32::from(x_scale), 0.], [0., f32::from(y_scale)]]
+            }
+            CompositeGlyphScale::Matrix(matrix) => [
+                [f32::from(matrix[0][0]), f32::from(matrix[0][1])],
+                [f32::from(matrix[1][0]), f32::from(matrix[1][1])],
+            ],
+        }
+    }
+}
+
 impl CompositeGlyphFlag {
     pub fn arg_1_and_2_are_words(self) -> bool {
         self & Self::ARG_1_AND_2_ARE_WORDS == Self::ARG_1_AND_2_ARE_WORDS
@@ -845,11 +1069,73 @@ mod tests {
     use super::{BoundingBox, GlyfRecord, GlyfTable, IndexToLocFormat, Point};
     use crate::binary::read::ReadScope;
     use crate::binary::write::{WriteBinary, WriteBinaryDep, WriteBuffer, WriteContext};
+    use crate::outline::OutlineSink;
     use crate::tables::glyf::{
-        CompositeGlyph, CompositeGlyphArgument, CompositeGlyphFlag, Glyph, GlyphData, SimpleGlyph,
-        SimpleGlyphFlag,
+        CompositeGlyph, CompositeGlyphArgument, CompositeGlyphFlag, CompositeGlyphScale, Glyph,
+        GlyphData, SimpleGlyph, SimpleGlyphFlag,
     };
     use crate::tables::loca::{owned, LocaTable};
+    use crate::tables::F2Dot14;
+
+    #[derive(Debug, PartialEq)]
+    enum Command {
+        MoveTo(f32, f32),
+        LineTo(f32, f32),
+        QuadTo(f32, f32, f32, f32),
+        Close,
+    }
+
+    impl OutlineSink for Vec<Command> {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.push(Command::MoveTo(x, y));
+        }
+
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.push(Command::LineTo(x, y));
+        }
+
+        fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+            self.push(Command::QuadTo(x1, y1, x, y));
+        }
+
+        fn curve_to(&mut self, _x1: f32, _y1: f32, _x2: f32, _y2: f32, _x: f32, _y: f32) {
+            unreachable!()
+        }
+
+        fn close(&mut self) {
+            self.push(Command::Close);
+        }
+    }
+
+    fn outline_glyph_fixture(
+        end_pts_of_contours: Vec<u16>,
+        points: &[(i16, i16, bool)],
+    ) -> Glyph<'static> {
+        let coordinates = points
+            .iter()
+            .map(|&(x, y, _)| Point(x, y))
+            .collect::<Vec<_>>();
+        let simple_glyph = SimpleGlyph {
+            end_pts_of_contours,
+            instructions: vec![],
+            flags: points
+                .iter()
+                .map(|&(_, _, on_curve)| {
+                    if on_curve {
+                        SimpleGlyphFlag::ON_CURVE_POINT
+                    } else {
+                        SimpleGlyphFlag::empty()
+                    }
+                })
+                .collect(),
+            coordinates,
+        };
+        Glyph {
+            number_of_contours: simple_glyph.end_pts_of_contours.len() as i16,
+            bounding_box: simple_glyph.bounding_box(),
+            data: GlyphData::Simple(simple_glyph),
+        }
+    }
 
     fn simple_glyph_fixture() -> Glyph<'static> {
         let simple_glyph = SimpleGlyph {
@@ -1130,4 +1416,129 @@ mod tests {
             Err(_) => panic!("unable to read back glyph"),
         }
     }
+
+    #[test]
+    fn visit_outline_implied_points() {
+        let glyph = outline_glyph_fixture(
+            vec![3, 6],
+            &[
+                (0, 0, true),
+                (100, 0, false),
+                (100, 100, false),
+                (0, 100, true),
+                // Second contour starts off-curve
+                (0, 0, false),
+                (100, 0, true),
+                (100, 100, true),
+            ],
+        );
+        let glyf = GlyfTable {
+            records: vec![GlyfRecord::Parsed(glyph)],
+        };
+        let mut commands: Vec<Command> = Vec::new();
+        glyf.visit_outline(0, &mut commands).unwrap();
+        assert_eq!(
+            commands,
+            vec![
+                Command::MoveTo(0., 0.),
+                Command::QuadTo(100., 0., 100., 50.),
+                Command::QuadTo(100., 100., 0., 100.),
+                Command::LineTo(0., 0.),
+                Command::Close,
+                Command::MoveTo(100., 100.),
+                Command::QuadTo(0., 0., 100., 0.),
+                Command::LineTo(100., 100.),
+                Command::Close,
+            ]
+        );
+    }
+
+    #[test]
+    fn visit_outline_composite() {
+        let triangle =
+            outline_glyph_fixture(vec![2], &[(0, 0, true), (10, 0, true), (0, 10, true)]);
+        let composite = Glyph {
+            number_of_contours: -1,
+            bounding_box: BoundingBox {
+                x_min: 0,
+                x_max: 0,
+                y_min: 0,
+                y_max: 0,
+            },
+            data: GlyphData::Composite {
+                glyphs: vec![
+                    // Scaled by 0.5 then offset
+                    CompositeGlyph {
+                        flags: CompositeGlyphFlag::ARGS_ARE_XY_VALUES
+                            | CompositeGlyphFlag::WE_HAVE_A_SCALE
+                            | CompositeGlyphFlag::MORE_COMPONENTS,
+                        glyph_index: 0,
+                        argument1: CompositeGlyphArgument::I8(100),
+                        argument2: CompositeGlyphArgument::I8(0),
+                        scale: Some(CompositeGlyphScale::Scale(F2Dot14::new(0x2000))),
+                    },
+                    // Rotated 90 degrees, then point 1 matched to point 2 of the first component
+                    CompositeGlyph {
+                        flags: CompositeGlyphFlag::WE_HAVE_A_TWO_BY_TWO,
+                        glyph_index: 0,
+                        argument1: CompositeGlyphArgument::U8(2),
+                        argument2: CompositeGlyphArgument::U8(1),
+                        scale: Some(CompositeGlyphScale::Matrix([
+                            [F2Dot14::new(0), F2Dot14::new(0x4000)],
+                            [F2Dot14::new(0xC000), F2Dot14::new(0)],
+                        ])),
+                    },
+                ],
+                instructions: &[],
+            },
+        };
+        let glyf = GlyfTable {
+            records: vec![GlyfRecord::Parsed(triangle), GlyfRecord::Parsed(composite)],
+        };
+        let mut commands: Vec<Command> = Vec::new();
+        glyf.visit_outline(1, &mut commands).unwrap();
+        assert_eq!(
+            commands,
+            vec![
+                Command::MoveTo(100., 0.),
+                Command::LineTo(105., 0.),
+                Command::LineTo(100., 5.),
+                Command::LineTo(100., 0.),
+                Command::Close,
+                Command::MoveTo(100., -5.),
+                Command::LineTo(100., 5.),
+                Command::LineTo(90., -5.),
+                Command::LineTo(100., -5.),
+                Command::Close,
+            ]
+        );
+    }
+
+    #[test]
+    fn visit_outline_component_cycle() {
+        let composite = Glyph {
+            number_of_contours: -1,
+            bounding_box: BoundingBox {
+                x_min: 0,
+                x_max: 0,
+                y_min: 0,
+                y_max: 0,
+            },
+            data: GlyphData::Composite {
+                glyphs: vec![CompositeGlyph {
+                    flags: CompositeGlyphFlag::ARGS_ARE_XY_VALUES,
+                    glyph_index: 0,
+                    argument1: CompositeGlyphArgument::I8(0),
+                    argument2: CompositeGlyphArgument::I8(0),
+                    scale: None,
+                }],
+                instructions: &[],
+            },
+        };
+        let glyf = GlyfTable {
+            records: vec![GlyfRecord::Parsed(composite)],
+        };
+        let mut commands: Vec<Command> = Vec::new();
+        assert!(glyf.visit_outline(0, &mut commands).is_err());
+    }
 }
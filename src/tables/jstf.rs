@@ -0,0 +1,401 @@
+#![deny(missing_docs)]
+
+//! `JSTF` Justification Table parsing.
+//!
+//! The `JSTF` table lists, for each script and language system, prioritised sets of `GSUB` and
+//! `GPOS` lookups to enable or disable to shrink or extend a line of text. See the `jstf`
+//! module for applying them.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/jstf>
+
+use crate::binary::read::{ReadBinary, ReadBinaryDep, ReadCtxt, ReadFixedSizeDep, ReadScope};
+use crate::binary::U16Be;
+use crate::error::ParseError;
+use crate::layout::{new_layout_cache, LayoutCache, LayoutTable, LookupList, GPOS};
+use crate::size;
+use crate::tag;
+
+/// `JSTF` Justification Table.
+pub struct JstfTable {
+    /// Major version of the table, `1`.
+    pub major_version: u16,
+    /// Minor version of the table, `0`.
+    pub minor_version: u16,
+    /// Justification data for each script, sorted by script tag.
+    pub jstf_script_records: Vec<JstfScriptRecord>,
+}
+
+/// Justification data for a script.
+pub struct JstfScriptRecord {
+    /// The script tag.
+    pub jstf_script_tag: u32,
+    /// The justification data.
+    pub jstf_script: JstfScript,
+}
+
+/// Justification data for a script.
+pub struct JstfScript {
+    /// Glyphs, such as the Arabic kashida, that may be inserted to extend a line.
+    pub extender_glyphs: Vec<u16>,
+    /// Justification data for language systems without a record of their own.
+    pub default_jstf_lang_sys: Option<JstfLangSys>,
+    /// Justification data for each language system, sorted by language system tag.
+    pub jstf_lang_sys_records: Vec<JstfLangSysRecord>,
+}
+
+/// Justification data for a language system.
+pub struct JstfLangSysRecord {
+    /// The language system tag.
+    pub jstf_lang_sys_tag: u32,
+    /// The justification data.
+    pub jstf_lang_sys: JstfLangSys,
+}
+
+/// Justification data for a language system.
+pub struct JstfLangSys {
+    /// The justification priorities, in the order they should be tried.
+    pub jstf_priorities: Vec<JstfPriority>,
+}
+
+/// Lookups to enable and disable at a justification priority.
+///
+/// Lookups are given by their index in the `GSUB` or `GPOS` lookup list.
+#[derive(Clone, Default)]
+pub struct JstfPriority {
+    /// `GSUB` lookups to enable to shrink a line.
+    pub shrinkage_enable_gsub: Vec<u16>,
+    /// `GSUB` lookups to disable to shrink a line.
+    pub shrinkage_disable_gsub: Vec<u16>,
+    /// `GPOS` lookups to enable to shrink a line.
+    pub shrinkage_enable_gpos: Vec<u16>,
+    /// `GPOS` lookups to disable to shrink a line.
+    pub shrinkage_disable_gpos: Vec<u16>,
+    /// `GPOS` lookups that give the maximum shrinkage at this priority.
+    ///
+    /// The lookups are stored in the `JSTF` table, so they are held in a cache of their own with
+    /// only a lookup list.
+    pub shrinkage_jstf_max: Option<LayoutCache<GPOS>>,
+    /// `GSUB` lookups to enable to extend a line.
+    pub extension_enable_gsub: Vec<u16>,
+    /// `GSUB` lookups to disable to extend a line.
+    pub extension_disable_gsub: Vec<u16>,
+    /// `GPOS` lookups to enable to extend a line.
+    pub extension_enable_gpos: Vec<u16>,
+    /// `GPOS` lookups to disable to extend a line.
+    pub extension_disable_gpos: Vec<u16>,
+    /// `GPOS` lookups that give the maximum extension at this priority.
+    pub extension_jstf_max: Option<LayoutCache<GPOS>>,
+}
+
+impl<'a> ReadBinary<'a> for JstfTable {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let major_version = ctxt.read_u16be()?;
+        ctxt.check_version(major_version == 1)?;
+        let minor_version = ctxt.read_u16be()?;
+        let jstf_script_count = usize::from(ctxt.read_u16be()?);
+        let jstf_script_records = ctxt
+            .read_array_dep::<JstfScriptRecord>(jstf_script_count, scope)?
+            .read_to_vec()?;
+        Ok(JstfTable {
+            major_version,
+            minor_version,
+            jstf_script_records,
+        })
+    }
+}
+
+impl<'a> ReadBinaryDep<'a> for JstfScriptRecord {
+    type Args = ReadScope<'a>;
+    type HostType = Self;
+
+    fn read_dep(ctxt: &mut ReadCtxt<'a>, scope: Self::Args) -> Result<Self, ParseError> {
+        let jstf_script_tag = ctxt.read_u32be()?;
+        let jstf_script_offset = usize::from(ctxt.read_u16be()?);
+        let jstf_script = scope.offset(jstf_script_offset).read::<JstfScript>()?;
+        Ok(JstfScriptRecord {
+            jstf_script_tag,
+            jstf_script,
+        })
+    }
+}
+
+impl<'a> ReadFixedSizeDep<'a> for JstfScriptRecord {
+    fn size(_scope: Self::Args) -> usize {
+        size::U32 + size::U16
+    }
+}
+
+impl<'a> ReadBinary<'a> for JstfScript {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let extender_glyph_offset = usize::from(ctxt.read_u16be()?);
+        let default_jstf_lang_sys_offset = usize::from(ctxt.read_u16be()?);
+        let jstf_lang_sys_count = usize::from(ctxt.read_u16be()?);
+        let jstf_lang_sys_records = ctxt
+            .read_array_dep::<JstfLangSysRecord>(jstf_lang_sys_count, scope.clone())?
+            .read_to_vec()?;
+
+        let extender_glyphs = if extender_glyph_offset != 0 {
+            read_u16_list(&scope, extender_glyph_offset)?
+        } else {
+            Vec::new()
+        };
+        let default_jstf_lang_sys = if default_jstf_lang_sys_offset != 0 {
+            Some(
+                scope
+                    .offset(default_jstf_lang_sys_offset)
+                    .read::<JstfLangSys>()?,
+            )
+        } else {
+            None
+        };
+
+        Ok(JstfScript {
+            extender_glyphs,
+            default_jstf_lang_sys,
+            jstf_lang_sys_records,
+        })
+    }
+}
+
+impl<'a> ReadBinaryDep<'a> for JstfLangSysRecord {
+    type Args = ReadScope<'a>;
+    type HostType = Self;
+
+    fn read_dep(ctxt: &mut ReadCtxt<'a>, scope: Self::Args) -> Result<Self, ParseError> {
+        let jstf_lang_sys_tag = ctxt.read_u32be()?;
+        let jstf_lang_sys_offset = usize::from(ctxt.read_u16be()?);
+        let jstf_lang_sys = scope.offset(jstf_lang_sys_offset).read::<JstfLangSys>()?;
+        Ok(JstfLangSysRecord {
+            jstf_lang_sys_tag,
+            jstf_lang_sys,
+        })
+    }
+}
+
+impl<'a> ReadFixedSizeDep<'a> for JstfLangSysRecord {
+    fn size(_scope: Self::Args) -> usize {
+        size::U32 + size::U16
+    }
+}
+
+impl<'a> ReadBinary<'a> for JstfLangSys {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let jstf_priority_count = ctxt.read_u16be()?;
+        let jstf_priorities = (0..jstf_priority_count)
+            .map(|_| {
+                let offset = usize::from(ctxt.read_u16be()?);
+                scope.offset(offset).read::<JstfPriority>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(JstfLangSys { jstf_priorities })
+    }
+}
+
+impl<'a> ReadBinary<'a> for JstfPriority {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let read_mod_list = |ctxt: &mut ReadCtxt<'a>| {
+            let offset = usize::from(ctxt.read_u16be()?);
+            if offset != 0 {
+                read_u16_list(&scope, offset)
+            } else {
+                Ok(Vec::new())
+            }
+        };
+        let read_max = |ctxt: &mut ReadCtxt<'a>| {
+            let offset = usize::from(ctxt.read_u16be()?);
+            if offset != 0 {
+                read_jstf_max(&scope, offset).map(Some)
+            } else {
+                Ok(None)
+            }
+        };
+        let shrinkage_enable_gsub = read_mod_list(ctxt)?;
+        let shrinkage_disable_gsub = read_mod_list(ctxt)?;
+        let shrinkage_enable_gpos = read_mod_list(ctxt)?;
+        let shrinkage_disable_gpos = read_mod_list(ctxt)?;
+        let shrinkage_jstf_max = read_max(ctxt)?;
+        let extension_enable_gsub = read_mod_list(ctxt)?;
+        let extension_disable_gsub = read_mod_list(ctxt)?;
+        let extension_enable_gpos = read_mod_list(ctxt)?;
+        let extension_disable_gpos = read_mod_list(ctxt)?;
+        let extension_jstf_max = read_max(ctxt)?;
+        Ok(JstfPriority {
+            shrinkage_enable_gsub,
+            shrinkage_disable_gsub,
+            shrinkage_enable_gpos,
+            shrinkage_disable_gpos,
+            shrinkage_jstf_max,
+            extension_enable_gsub,
+            extension_disable_gsub,
+            extension_enable_gpos,
+            extension_disable_gpos,
+            extension_jstf_max,
+        })
+    }
+}
+
+/// Read the `JstfMax` table at `offset` from `scope`.
+///
+/// A `JstfMax` table has the same layout as a `GPOS` `LookupList`.
+fn read_jstf_max(scope: &ReadScope<'_>, offset: usize) -> Result<LayoutCache<GPOS>, ParseError> {
+    let lookup_list = scope.offset(offset).read::<LookupList<GPOS>>()?;
+    Ok(new_layout_cache(LayoutTable {
+        opt_script_list: None,
+        opt_feature_list: None,
+        opt_lookup_list: Some(lookup_list),
+        opt_feature_variations: None,
+    }))
+}
+
+/// Read a count followed by that many `u16` values, as used by the extender glyph table and
+/// `JstfModList`.
+fn read_u16_list(scope: &ReadScope<'_>, offset: usize) -> Result<Vec<u16>, ParseError> {
+    let mut ctxt = scope.offset(offset).ctxt();
+    let count = usize::from(ctxt.read_u16be()?);
+    Ok(ctxt.read_array::<U16Be>(count)?.to_vec())
+}
+
+impl JstfTable {
+    /// Returns the justification data of `script_tag`, falling back to the `DFLT` script.
+    pub fn find_script(&self, script_tag: u32) -> Option<&JstfScript> {
+        let find = |script_tag| {
+            self.jstf_script_records
+                .iter()
+                .find(|record| record.jstf_script_tag == script_tag)
+                .map(|record| &record.jstf_script)
+        };
+        find(script_tag).or_else(|| find(tag::DFLT))
+    }
+
+    /// Returns the justification data of the language system `opt_lang_tag` of `script_tag`,
+    /// falling back to the default language system of the script.
+    pub fn find_lang_sys(
+        &self,
+        script_tag: u32,
+        opt_lang_tag: Option<u32>,
+    ) -> Option<&JstfLangSys> {
+        self.find_script(script_tag)?.find_lang_sys(opt_lang_tag)
+    }
+}
+
+impl JstfScript {
+    /// Returns the justification data of the language system `opt_lang_tag`, falling back to
+    /// the default language system.
+    pub fn find_lang_sys(&self, opt_lang_tag: Option<u32>) -> Option<&JstfLangSys> {
+        opt_lang_tag
+            .and_then(|lang_tag| {
+                self.jstf_lang_sys_records
+                    .iter()
+                    .find(|record| record.jstf_lang_sys_tag == lang_tag)
+            })
+            .map(|record| &record.jstf_lang_sys)
+            .or(self.default_jstf_lang_sys.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::PosLookup;
+
+    fn no_lookups(lists: &[&Vec<u16>]) -> bool {
+        lists.iter().all(|list| list.is_empty())
+    }
+
+    #[test]
+    fn test_read_jstf() {
+        #[rustfmt::skip]
+        let data = [
+            0x00, 0x01, 0x00, 0x00, // version
+            0x00, 0x01, // jstf_script_count
+            b'a', b'r', b'a', b'b', 0x00, 0x0C,
+            // 0x0C: JstfScript
+            0x00, 0x0C, // extender_glyph_offset
+            0x00, 0x10, // default_jstf_lang_sys_offset
+            0x00, 0x01, // jstf_lang_sys_count
+            b'U', b'R', b'D', b' ', 0x00, 0x10,
+            // 0x18: ExtenderGlyph
+            0x00, 0x01, 0x00, 0x2A,
+            // 0x1C: JstfLangSys
+            0x00, 0x02, 0x00, 0x06, 0x00, 0x1A,
+            // 0x22: JstfPriority
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x28, 0x00, 0x00, 0x00, 0x2C, 0x00, 0x00, 0x00, 0x00,
+            // 0x36: JstfPriority
+            0x00, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1E,
+            // 0x4A: JstfModLists
+            0x00, 0x01, 0x00, 0x03,
+            0x00, 0x02, 0x00, 0x01, 0x00, 0x04,
+            // 0x54: JstfMax
+            0x00, 0x01, 0x00, 0x04,
+            // 0x58: Lookup
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08,
+            // 0x60: SinglePosFormat1, XAdvance 100
+            0x00, 0x01, 0x00, 0x08, 0x00, 0x04, 0x00, 0x64,
+            // 0x68: Coverage
+            0x00, 0x01, 0x00, 0x01, 0x00, 0x2A,
+        ];
+        let jstf = ReadScope::new(&data).read::<JstfTable>().unwrap();
+        let script = jstf.find_script(tag::ARAB).unwrap();
+        assert_eq!(script.extender_glyphs, vec![42]);
+        assert!(jstf.find_script(tag::LATN).is_none());
+
+        let lang_sys = jstf.find_lang_sys(tag::ARAB, None).unwrap();
+        assert_eq!(lang_sys.jstf_priorities.len(), 2);
+        let priority = &lang_sys.jstf_priorities[0];
+        assert_eq!(priority.extension_enable_gsub, vec![3]);
+        assert_eq!(priority.extension_enable_gpos, vec![1, 4]);
+        assert!(no_lookups(&[
+            &priority.shrinkage_enable_gsub,
+            &priority.shrinkage_disable_gsub,
+            &priority.shrinkage_enable_gpos,
+            &priority.shrinkage_disable_gpos,
+            &priority.extension_disable_gsub,
+            &priority.extension_disable_gpos,
+        ]));
+        assert!(priority.shrinkage_jstf_max.is_none());
+        assert!(priority.extension_jstf_max.is_none());
+
+        let priority = &lang_sys.jstf_priorities[1];
+        assert_eq!(priority.shrinkage_enable_gsub, vec![3]);
+        assert!(no_lookups(&[
+            &priority.shrinkage_disable_gsub,
+            &priority.shrinkage_enable_gpos,
+            &priority.shrinkage_disable_gpos,
+            &priority.extension_enable_gsub,
+            &priority.extension_disable_gsub,
+            &priority.extension_enable_gpos,
+            &priority.extension_disable_gpos,
+        ]));
+        assert!(priority.shrinkage_jstf_max.is_none());
+        let jstf_max = priority.extension_jstf_max.as_ref().unwrap();
+        let lookup_list = jstf_max.layout_table.opt_lookup_list.as_ref().unwrap();
+        let lookup = lookup_list.lookup_cache_gpos(jstf_max, 0).unwrap();
+        match lookup.lookup_subtables {
+            PosLookup::SinglePos(ref subtables) => {
+                assert_eq!(subtables.len(), 1);
+                assert!(subtables[0].apply(42).unwrap().is_some());
+            }
+            _ => panic!("expected a single positioning lookup"),
+        }
+        // Both the language system and the default share the same data
+        assert_eq!(
+            jstf.find_lang_sys(tag::ARAB, Some(tag::URD))
+                .map(|lang_sys| lang_sys.jstf_priorities.len()),
+            Some(2)
+        );
+    }
+}
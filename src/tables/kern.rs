@@ -0,0 +1,366 @@
+#![deny(missing_docs)]
+
+//! `kern` Kerning Table parsing.
+//!
+//! Both the Microsoft (version 0) and Apple (version 1) headers are supported, along with format
+//! 0 (ordered pairs) and format 2 (class-based) subtables.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/kern>
+//! <https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6kern.html>
+
+use std::convert::TryFrom;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt, ReadFrom, ReadScope};
+use crate::binary::{I16Be, U16Be};
+use crate::error::ParseError;
+
+/// `kern` Kerning Table.
+pub struct KernTable<'a> {
+    /// The kerning subtables, in the order they appear in the table.
+    pub subtables: Vec<KernSubtable<'a>>,
+}
+
+/// A subtable of the `kern` table.
+pub struct KernSubtable<'a> {
+    /// `true` if the subtable holds horizontal kerning values.
+    pub horizontal: bool,
+    /// `true` if the subtable holds minimum values rather than kerning values.
+    pub minimum: bool,
+    /// `true` if kerning is perpendicular to the flow of the text.
+    pub cross_stream: bool,
+    /// `true` if the values replace, rather than add to, the values accumulated so far.
+    pub override_accumulator: bool,
+    /// `true` if the subtable holds variation values. Only set in Apple `kern` tables.
+    pub variation: bool,
+    /// The kerning data of the subtable.
+    pub data: KernData<'a>,
+}
+
+/// The kerning data of a `kern` subtable.
+pub enum KernData<'a> {
+    /// Format 0: kerning pairs sorted by left and right glyph.
+    Format0(ReadArray<'a, KernPair>),
+    /// Format 2: a two-dimensional array of kerning values indexed by glyph class.
+    Format2(KernFormat2<'a>),
+    /// A subtable format that is not supported. Holds the format number.
+    Unsupported(u8),
+}
+
+/// A kerning value for a pair of glyphs.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct KernPair {
+    /// The glyph id of the left glyph of the pair.
+    pub left: u16,
+    /// The glyph id of the right glyph of the pair.
+    pub right: u16,
+    /// The kerning value in font design units.
+    pub value: i16,
+}
+
+/// Class-based kerning data.
+pub struct KernFormat2<'a> {
+    /// The scope of the subtable, including its header. Class values and the kerning array are
+    /// located relative to this scope.
+    scope: ReadScope<'a>,
+    left_class_table: KernClassTable<'a>,
+    right_class_table: KernClassTable<'a>,
+    array_offset: usize,
+}
+
+/// Maps glyph ids to byte offsets of rows or columns in a format 2 kerning array.
+struct KernClassTable<'a> {
+    first_glyph: u16,
+    offsets: ReadArray<'a, U16Be>,
+}
+
+/// The size of the Microsoft subtable header.
+const MS_SUBTABLE_HEADER_SIZE: usize = 6;
+/// The size of the Apple subtable header.
+const APPLE_SUBTABLE_HEADER_SIZE: usize = 8;
+
+impl<'a> ReadBinary<'a> for KernTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let major_version = ctxt.read_u16be()?;
+        let mut subtables = Vec::new();
+        match major_version {
+            0 => {
+                let n_tables = ctxt.read_u16be()?;
+                for _ in 0..n_tables {
+                    let scope = ctxt.scope();
+                    let _version = ctxt.read_u16be()?;
+                    let length = usize::from(ctxt.read_u16be()?);
+                    let coverage = ctxt.read_u16be()?;
+                    let format = (coverage >> 8) as u8;
+                    subtables.push(KernSubtable {
+                        horizontal: coverage & 0x0001 != 0,
+                        minimum: coverage & 0x0002 != 0,
+                        cross_stream: coverage & 0x0004 != 0,
+                        override_accumulator: coverage & 0x0008 != 0,
+                        variation: false,
+                        data: read_kern_data(scope, MS_SUBTABLE_HEADER_SIZE, format)?,
+                    });
+                    // Some fonts have format 0 subtables with more than 64KiB of pairs, which
+                    // overflows the length field. Since the pair count is stored separately the
+                    // data is still readable if such a subtable is the last one.
+                    ctxt.check(length >= MS_SUBTABLE_HEADER_SIZE)?;
+                    skip_subtable(ctxt, length - MS_SUBTABLE_HEADER_SIZE)?;
+                }
+            }
+            1 => {
+                let minor_version = ctxt.read_u16be()?;
+                ctxt.check_version(minor_version == 0)?;
+                let n_tables = ctxt.read_u32be()?;
+                for _ in 0..n_tables {
+                    let scope = ctxt.scope();
+                    let length = usize::try_from(ctxt.read_u32be()?)?;
+                    let coverage = ctxt.read_u16be()?;
+                    let _tuple_index = ctxt.read_u16be()?;
+                    let format = (coverage & 0x00FF) as u8;
+                    subtables.push(KernSubtable {
+                        horizontal: coverage & 0x8000 == 0,
+                        minimum: false,
+                        cross_stream: coverage & 0x4000 != 0,
+                        override_accumulator: false,
+                        variation: coverage & 0x2000 != 0,
+                        data: read_kern_data(scope, APPLE_SUBTABLE_HEADER_SIZE, format)?,
+                    });
+                    ctxt.check(length >= APPLE_SUBTABLE_HEADER_SIZE)?;
+                    skip_subtable(ctxt, length - APPLE_SUBTABLE_HEADER_SIZE)?;
+                }
+            }
+            _ => return Err(ParseError::BadVersion),
+        }
+        Ok(KernTable { subtables })
+    }
+}
+
+/// Skip the body of a subtable, stopping at the end of the table if the body is truncated.
+fn skip_subtable(ctxt: &mut ReadCtxt<'_>, length: usize) -> Result<(), ParseError> {
+    let remaining = ctxt.scope().data().len();
+    ctxt.read_scope(length.min(remaining))?;
+    Ok(())
+}
+
+fn read_kern_data<'a>(
+    scope: ReadScope<'a>,
+    header_size: usize,
+    format: u8,
+) -> Result<KernData<'a>, ParseError> {
+    match format {
+        0 => {
+            let mut ctxt = scope.offset(header_size).ctxt();
+            let n_pairs = usize::from(ctxt.read_u16be()?);
+            let _search_range = ctxt.read_u16be()?;
+            let _entry_selector = ctxt.read_u16be()?;
+            let _range_shift = ctxt.read_u16be()?;
+            let pairs = ctxt.read_array::<KernPair>(n_pairs)?;
+            Ok(KernData::Format0(pairs))
+        }
+        2 => {
+            let mut ctxt = scope.offset(header_size).ctxt();
+            let _row_width = ctxt.read_u16be()?;
+            let left_class_table_offset = usize::from(ctxt.read_u16be()?);
+            let right_class_table_offset = usize::from(ctxt.read_u16be()?);
+            let array_offset = usize::from(ctxt.read_u16be()?);
+            let left_class_table = scope
+                .offset(left_class_table_offset)
+                .read::<KernClassTable<'_>>()?;
+            let right_class_table = scope
+                .offset(right_class_table_offset)
+                .read::<KernClassTable<'_>>()?;
+            Ok(KernData::Format2(KernFormat2 {
+                scope,
+                left_class_table,
+                right_class_table,
+                array_offset,
+            }))
+        }
+        _ => Ok(KernData::Unsupported(format)),
+    }
+}
+
+impl<'a> ReadFrom<'a> for KernPair {
+    type ReadType = (U16Be, U16Be, I16Be);
+    fn from((left, right, value): (u16, u16, i16)) -> Self {
+        KernPair { left, right, value }
+    }
+}
+
+impl<'a> ReadBinary<'a> for KernClassTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let first_glyph = ctxt.read_u16be()?;
+        let n_glyphs = usize::from(ctxt.read_u16be()?);
+        let offsets = ctxt.read_array::<U16Be>(n_glyphs)?;
+        Ok(KernClassTable {
+            first_glyph,
+            offsets,
+        })
+    }
+}
+
+impl<'a> KernClassTable<'a> {
+    fn get(&self, glyph: u16) -> Option<usize> {
+        let index = usize::from(glyph.checked_sub(self.first_glyph)?);
+        if index < self.offsets.len() {
+            Some(usize::from(self.offsets.get_item(index)))
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> KernTable<'a> {
+    /// Look up the horizontal kerning value for the pair of glyphs `left` and `right`.
+    ///
+    /// Values from all horizontal subtables are accumulated. Cross-stream, minimum and
+    /// variation subtables are ignored. Returns `None` if no subtable has a value for the
+    /// pair.
+    pub fn horizontal_kerning(&self, left: u16, right: u16) -> Result<Option<i16>, ParseError> {
+        let mut opt_kerning: Option<i32> = None;
+        for subtable in &self.subtables {
+            if !subtable.horizontal
+                || subtable.minimum
+                || subtable.cross_stream
+                || subtable.variation
+            {
+                continue;
+            }
+            if let Some(value) = subtable.data.kerning(left, right)? {
+                let value = i32::from(value);
+                opt_kerning = match opt_kerning {
+                    Some(kerning) if !subtable.override_accumulator => Some(kerning + value),
+                    _ => Some(value),
+                };
+            }
+        }
+        Ok(opt_kerning.map(|kerning| {
+            i16::try_from(kerning).unwrap_or(if kerning < 0 { -0x8000 } else { 0x7FFF })
+        }))
+    }
+}
+
+impl<'a> KernData<'a> {
+    /// Look up the kerning value for the pair of glyphs `left` and `right` in this subtable.
+    pub fn kerning(&self, left: u16, right: u16) -> Result<Option<i16>, ParseError> {
+        match *self {
+            KernData::Format0(ref pairs) => Ok(find_pair(pairs, left, right)),
+            KernData::Format2(ref format2) => format2.kerning(left, right),
+            KernData::Unsupported(_) => Ok(None),
+        }
+    }
+}
+
+/// Binary search `pairs`, which are sorted by left and then right glyph, for the kerning value of
+/// the pair of glyphs `left` and `right`.
+pub(crate) fn find_pair(pairs: &ReadArray<'_, KernPair>, left: u16, right: u16) -> Option<i16> {
+    let key = (u32::from(left) << 16) | u32::from(right);
+    let (mut lo, mut hi) = (0, pairs.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let pair = pairs.get_item(mid);
+        let mid_key = (u32::from(pair.left) << 16) | u32::from(pair.right);
+        if mid_key < key {
+            lo = mid + 1;
+        } else if mid_key > key {
+            hi = mid;
+        } else {
+            return Some(pair.value);
+        }
+    }
+    None
+}
+
+impl<'a> KernFormat2<'a> {
+    fn kerning(&self, left: u16, right: u16) -> Result<Option<i16>, ParseError> {
+        let left_offset = match self.left_class_table.get(left) {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        let right_offset = match self.right_class_table.get(right) {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        // The left class values include the offset of the array from the start of the
+        // subtable, so a sum that lies before the array means the glyph is not in a class.
+        let value_offset = left_offset + right_offset;
+        if value_offset < self.array_offset {
+            return Ok(None);
+        }
+        let value = self.scope.offset(value_offset).read::<I16Be>()?;
+        Ok(Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_ms_format0() {
+        let data = [
+            0x00, 0x00, 0x00, 0x01, // version 0, one subtable
+            0x00, 0x00, 0x00, 0x20, 0x00, 0x01, // subtable header, format 0, horizontal
+            0x00, 0x03, 0x00, 0x0C, 0x00, 0x01, 0x00, 0x06, // nPairs, search fields
+            0x00, 0x04, 0x00, 0x05, 0xFF, 0xCE, // 4, 5: -50
+            0x00, 0x04, 0x00, 0x07, 0xFF, 0xEC, // 4, 7: -20
+            0x00, 0x06, 0x00, 0x05, 0x00, 0x0A, // 6, 5: 10
+        ];
+        let kern = ReadScope::new(&data).read::<KernTable<'_>>().unwrap();
+        assert_eq!(kern.horizontal_kerning(4, 5).unwrap(), Some(-50));
+        assert_eq!(kern.horizontal_kerning(4, 7).unwrap(), Some(-20));
+        assert_eq!(kern.horizontal_kerning(6, 5).unwrap(), Some(10));
+        assert_eq!(kern.horizontal_kerning(5, 4).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_apple_format2() {
+        let data = [
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, // version 1, one subtable
+            0x00, 0x00, 0x00, 0x2A, 0x00, 0x02, 0x00, 0x00, // subtable header, format 2
+            0x00, 0x04, 0x00, 0x10, 0x00, 0x18, 0x00, 0x20, // rowWidth and offsets
+            // Left class table, glyphs 3 and 4 in rows 0 and 1
+            0x00, 0x03, 0x00, 0x02, 0x00, 0x20, 0x00, 0x24, //
+            // Right class table, glyphs 8 and 9 in columns 0 and 1
+            0x00, 0x08, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, //
+            // Kerning array
+            0x00, 0x00, 0xFF, 0xF6, 0xFF, 0xEC, 0x00, 0x05, //
+            0x00, 0x00,
+        ];
+        let kern = ReadScope::new(&data).read::<KernTable<'_>>().unwrap();
+        assert_eq!(kern.horizontal_kerning(3, 8).unwrap(), Some(0));
+        assert_eq!(kern.horizontal_kerning(3, 9).unwrap(), Some(-10));
+        assert_eq!(kern.horizontal_kerning(4, 8).unwrap(), Some(-20));
+        assert_eq!(kern.horizontal_kerning(4, 9).unwrap(), Some(5));
+        assert_eq!(kern.horizontal_kerning(5, 9).unwrap(), None);
+    }
+
+    #[test]
+    fn test_kern_table_apply() {
+        use crate::gpos::{self, Info};
+        use crate::tag;
+        use crate::tests::make_direct_glyphs;
+
+        let data = [
+            0x00, 0x00, 0x00, 0x01, // version 0, one subtable
+            0x00, 0x00, 0x00, 0x14, 0x00, 0x01, // subtable header, format 0, horizontal
+            0x00, 0x01, 0x00, 0x06, 0x00, 0x00, 0x00, 0x00, // nPairs, search fields
+            0x00, 0x04, 0x00, 0x05, 0xFF, 0xCE, // 4, 5: -50
+        ];
+        let kern = ReadScope::new(&data).read::<KernTable<'_>>().unwrap();
+        let mut infos = Info::init_from_glyphs(None, make_direct_glyphs(&[4, 9, 5])).unwrap();
+        // Treat the middle glyph as a mark, which is skipped when kerning
+        infos[1].is_mark = true;
+
+        gpos::kern_table_apply(None, &kern, false, tag::LATN, None, &[], &mut infos).unwrap();
+        assert_eq!(infos[0].kerning, 0);
+
+        gpos::kern_table_apply(None, &kern, true, tag::LATN, None, &[], &mut infos).unwrap();
+        assert_eq!(infos[0].kerning, -50);
+        assert_eq!(infos[1].kerning, 0);
+        assert_eq!(infos[2].kerning, 0);
+    }
+}
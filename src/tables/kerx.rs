@@ -0,0 +1,407 @@
+#![deny(missing_docs)]
+
+//! `kerx` Extended Kerning Table parsing.
+//!
+//! Subtable formats 0 (ordered pairs), 1 (state table kerning), 2 (class-based), 4 (anchor
+//! attachment) and 6 (index-based) are supported. Format 6 subtables with 32-bit values are not.
+//!
+//! <https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6kerx.html>
+
+use std::convert::TryFrom;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt, ReadFrom, ReadScope};
+use crate::binary::{I16Be, U16Be};
+use crate::error::ParseError;
+use crate::layout::Anchor;
+use crate::size;
+use crate::tables::aat::{read_u16_at, read_u32_at, ExtendedStateTable, LookupTable};
+use crate::tables::kern::{find_pair, KernPair};
+
+/// `kerx` Extended Kerning Table.
+pub struct KerxTable<'a> {
+    /// The table version, 2, 3 or 4.
+    pub version: u16,
+    /// The kerning subtables, applied in order.
+    pub subtables: Vec<KerxSubtable<'a>>,
+}
+
+/// A subtable of the `kerx` table.
+pub struct KerxSubtable<'a> {
+    /// The coverage flags and subtable format.
+    pub coverage: u32,
+    /// The number of variation tuples of each kerning value, 0 if values are not varied.
+    pub tuple_count: u32,
+    /// The scope of the subtable, including its header.
+    scope: ReadScope<'a>,
+    /// The format specific data of the subtable.
+    pub data: KerxData<'a>,
+}
+
+/// The format specific data of a `kerx` subtable.
+pub enum KerxData<'a> {
+    /// Format 0: kerning pairs sorted by left and right glyph.
+    Format0(ReadArray<'a, KernPair>),
+    /// Format 1: kerning driven by a state table.
+    Format1(KerxFormat1<'a>),
+    /// Format 2: a two-dimensional array of kerning values indexed by glyph class.
+    Format2(KerxFormat2<'a>),
+    /// Format 4: attachment of glyphs driven by a state table.
+    Format4(KerxFormat4<'a>),
+    /// Format 6: a two-dimensional array of kerning values indexed by glyph.
+    Format6(KerxFormat6<'a>),
+    /// A subtable format that is not supported. Holds the format number.
+    Unsupported(u8),
+}
+
+/// State table kerning data.
+pub struct KerxFormat1<'a> {
+    /// The state table pushing glyphs and popping them to apply kerning values.
+    pub state_table: ExtendedStateTable<'a>,
+    values: ReadScope<'a>,
+}
+
+/// Class-based kerning data.
+pub struct KerxFormat2<'a> {
+    left_class_table: LookupTable<'a>,
+    right_class_table: LookupTable<'a>,
+    array: ReadScope<'a>,
+}
+
+/// State table attachment data.
+pub struct KerxFormat4<'a> {
+    /// The state table marking glyphs and attaching the current glyph to the marked one.
+    pub state_table: ExtendedStateTable<'a>,
+    action_type: u8,
+    actions: ReadScope<'a>,
+}
+
+/// Index-based kerning data.
+pub struct KerxFormat6<'a> {
+    row_index_table: LookupTable<'a>,
+    column_index_table: LookupTable<'a>,
+    array: ReadScope<'a>,
+    vector: ReadScope<'a>,
+}
+
+/// An entry of a format 1 or format 4 state table.
+#[derive(Debug, Copy, Clone)]
+pub struct KerxEntry {
+    /// The state to transition to.
+    pub new_state: u16,
+    /// The entry flags.
+    pub flags: u16,
+    /// The index of the kerning values or attachment action, `0xFFFF` for none.
+    pub value_index: u16,
+}
+
+/// An attachment action of a format 4 subtable.
+///
+/// Each action holds a point of the marked glyph followed by a point of the current glyph. The
+/// current glyph is positioned so that the two points coincide.
+#[derive(Debug, Copy, Clone)]
+pub enum AttachmentAction {
+    /// Indices of points in the outlines of the glyphs.
+    ControlPoints(u16, u16),
+    /// Indices of anchor points of the glyphs in the `ankr` table.
+    AnchorPoints(u16, u16),
+    /// Coordinates of the points.
+    Coordinates(Anchor, Anchor),
+}
+
+/// The size of the `kerx` subtable header.
+const SUBTABLE_HEADER_SIZE: usize = 12;
+
+impl<'a> ReadBinary<'a> for KerxTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let version = ctxt.read_u16be()?;
+        ctxt.check_version((2..=4).contains(&version))?;
+        let _padding = ctxt.read_u16be()?;
+        let n_tables = ctxt.read_u32be()?;
+        let subtables = (0..n_tables)
+            .map(|_| {
+                let scope = ctxt.scope();
+                let length = usize::try_from(ctxt.read_u32be()?)?;
+                ctxt.check(length >= SUBTABLE_HEADER_SIZE)?;
+                let subtable = scope.offset_length(0, length)?.read::<KerxSubtable<'a>>()?;
+                ctxt.read_scope(length - size::U32)?;
+                Ok(subtable)
+            })
+            .collect::<Result<Vec<_>, ParseError>>()?;
+        // Version 3 and later tables follow the subtables with glyph coverage tables, which are
+        // an optimisation that isn't needed here.
+        Ok(KerxTable { version, subtables })
+    }
+}
+
+impl<'a> ReadBinary<'a> for KerxSubtable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let _length = ctxt.read_u32be()?;
+        let coverage = ctxt.read_u32be()?;
+        let tuple_count = ctxt.read_u32be()?;
+        let data = read_kerx_data(&scope, (coverage & KerxSubtable::FORMAT_MASK) as u8)?;
+        Ok(KerxSubtable {
+            coverage,
+            tuple_count,
+            scope,
+            data,
+        })
+    }
+}
+
+fn read_kerx_data<'a>(scope: &ReadScope<'a>, format: u8) -> Result<KerxData<'a>, ParseError> {
+    let body = scope.offset(SUBTABLE_HEADER_SIZE);
+    match format {
+        0 => {
+            let mut ctxt = body.ctxt();
+            let n_pairs = usize::try_from(ctxt.read_u32be()?)?;
+            let _search_range = ctxt.read_u32be()?;
+            let _entry_selector = ctxt.read_u32be()?;
+            let _range_shift = ctxt.read_u32be()?;
+            let pairs = ctxt.read_array::<KernPair>(n_pairs)?;
+            Ok(KerxData::Format0(pairs))
+        }
+        1 => {
+            // The offset of the values is relative to the state table header
+            let state_table = body.read::<ExtendedStateTable<'a>>()?;
+            let values_offset = usize::try_from(read_u32_at(&body, 4)?)?;
+            Ok(KerxData::Format1(KerxFormat1 {
+                state_table,
+                values: body.offset(values_offset),
+            }))
+        }
+        2 => {
+            // Offsets are relative to the start of the subtable, including its header
+            let left_class_table_offset = usize::try_from(read_u32_at(&body, 1)?)?;
+            let right_class_table_offset = usize::try_from(read_u32_at(&body, 2)?)?;
+            let array_offset = usize::try_from(read_u32_at(&body, 3)?)?;
+            Ok(KerxData::Format2(KerxFormat2 {
+                left_class_table: scope
+                    .offset(left_class_table_offset)
+                    .read::<LookupTable<'a>>()?,
+                right_class_table: scope
+                    .offset(right_class_table_offset)
+                    .read::<LookupTable<'a>>()?,
+                array: scope.offset(array_offset),
+            }))
+        }
+        4 => {
+            let state_table = body.read::<ExtendedStateTable<'a>>()?;
+            let flags = read_u32_at(&body, 4)?;
+            let action_type = (flags >> 30) as u8;
+            if action_type > KerxFormat4::COORDINATES {
+                return Ok(KerxData::Unsupported(format));
+            }
+            // The offset of the actions is relative to the state table header
+            let actions_offset = usize::try_from(flags & 0x00FF_FFFF)?;
+            Ok(KerxData::Format4(KerxFormat4 {
+                state_table,
+                action_type,
+                actions: body.offset(actions_offset),
+            }))
+        }
+        6 => {
+            let mut ctxt = body.ctxt();
+            let flags = ctxt.read_u32be()?;
+            if flags & 0x0000_0001 != 0 {
+                // Values are 32-bit, which lookup tables don't support
+                return Ok(KerxData::Unsupported(format));
+            }
+            let _row_count = ctxt.read_u16be()?;
+            let _column_count = ctxt.read_u16be()?;
+            // Offsets are relative to the start of the subtable, including its header
+            let row_index_table_offset = usize::try_from(ctxt.read_u32be()?)?;
+            let column_index_table_offset = usize::try_from(ctxt.read_u32be()?)?;
+            let array_offset = usize::try_from(ctxt.read_u32be()?)?;
+            let vector_offset = usize::try_from(ctxt.read_u32be()?)?;
+            Ok(KerxData::Format6(KerxFormat6 {
+                row_index_table: scope
+                    .offset(row_index_table_offset)
+                    .read::<LookupTable<'a>>()?,
+                column_index_table: scope
+                    .offset(column_index_table_offset)
+                    .read::<LookupTable<'a>>()?,
+                array: scope.offset(array_offset),
+                vector: scope.offset(vector_offset),
+            }))
+        }
+        _ => Ok(KerxData::Unsupported(format)),
+    }
+}
+
+impl<'a> ReadFrom<'a> for KerxEntry {
+    type ReadType = (U16Be, U16Be, U16Be);
+    fn from((new_state, flags, value_index): (u16, u16, u16)) -> Self {
+        KerxEntry {
+            new_state,
+            flags,
+            value_index,
+        }
+    }
+}
+
+impl<'a> KerxSubtable<'a> {
+    /// Coverage flag set if the subtable only applies to vertical text.
+    pub const VERTICAL: u32 = 0x8000_0000;
+    /// Coverage flag set if kerning is perpendicular to the flow of the text.
+    pub const CROSS_STREAM: u32 = 0x4000_0000;
+    /// Coverage flag set if the subtable holds variation values.
+    pub const VARIATION: u32 = 0x2000_0000;
+    /// Coverage flag set if glyphs are processed in descending order.
+    pub const PROCESS_DIRECTION: u32 = 0x1000_0000;
+    /// Mask of the subtable format in the coverage field.
+    pub const FORMAT_MASK: u32 = 0x0000_00FF;
+
+    /// Look up the kerning value for the pair of glyphs `left` and `right`.
+    ///
+    /// Only format 0, 2 and 6 subtables hold values for pairs of glyphs. Returns `None` for
+    /// other formats and if the subtable has no value for the pair.
+    pub fn kerning(&self, left: u16, right: u16) -> Result<Option<i16>, ParseError> {
+        match self.data {
+            KerxData::Format0(ref pairs) => match find_pair(pairs, left, right) {
+                Some(value) => self.tuple_value(&self.scope, value).map(Some),
+                None => Ok(None),
+            },
+            KerxData::Format2(ref format2) => {
+                let left_offset = format2.left_class_table.lookup(left)?;
+                let right_offset = format2.right_class_table.lookup(right)?;
+                match (left_offset, right_offset) {
+                    (Some(left_offset), Some(right_offset)) => {
+                        // The class values are byte offsets of the row and column
+                        let offset = usize::from(left_offset) + usize::from(right_offset);
+                        let value = format2.array.offset(offset).read::<I16Be>()?;
+                        self.tuple_value(&self.scope, value).map(Some)
+                    }
+                    _ => Ok(None),
+                }
+            }
+            KerxData::Format6(ref format6) => {
+                let row = format6.row_index_table.lookup(left)?;
+                let column = format6.column_index_table.lookup(right)?;
+                match (row, column) {
+                    (Some(row), Some(column)) => {
+                        // The index values are indices into the array
+                        let index = usize::from(row) + usize::from(column);
+                        let value = format6.array.offset(index * size::I16).read::<I16Be>()?;
+                        self.tuple_value(&format6.vector, value).map(Some)
+                    }
+                    _ => Ok(None),
+                }
+            }
+            KerxData::Format1(_) | KerxData::Format4(_) | KerxData::Unsupported(_) => Ok(None),
+        }
+    }
+
+    /// Resolve a kerning value of a subtable with variation tuples.
+    ///
+    /// When the subtable has tuples its values are indices of the tuples of the actual values
+    /// in `base`. The first, default, value of the tuple is returned.
+    fn tuple_value(&self, base: &ReadScope<'a>, value: i16) -> Result<i16, ParseError> {
+        if self.tuple_count == 0 {
+            Ok(value)
+        } else {
+            let index = usize::from(value as u16);
+            base.offset(index * size::I16).read::<I16Be>()
+        }
+    }
+}
+
+impl<'a> KerxFormat1<'a> {
+    /// Read the kerning value at `index` in the value table.
+    pub fn value(&self, index: usize) -> Result<i16, ParseError> {
+        self.values.offset(index * size::I16).read::<I16Be>()
+    }
+}
+
+impl<'a> KerxFormat4<'a> {
+    /// Action type of actions holding indices of outline points.
+    pub const CONTROL_POINTS: u8 = 0;
+    /// Action type of actions holding indices of `ankr` anchor points.
+    pub const ANCHOR_POINTS: u8 = 1;
+    /// Action type of actions holding point coordinates.
+    pub const COORDINATES: u8 = 2;
+
+    /// Read the attachment action at `index`.
+    pub fn action(&self, index: u16) -> Result<AttachmentAction, ParseError> {
+        let index = usize::from(index);
+        match self.action_type {
+            KerxFormat4::CONTROL_POINTS | KerxFormat4::ANCHOR_POINTS => {
+                let mark_point = read_u16_at(&self.actions, index * 2)?;
+                let current_point = read_u16_at(&self.actions, index * 2 + 1)?;
+                if self.action_type == KerxFormat4::CONTROL_POINTS {
+                    Ok(AttachmentAction::ControlPoints(mark_point, current_point))
+                } else {
+                    Ok(AttachmentAction::AnchorPoints(mark_point, current_point))
+                }
+            }
+            _ => {
+                let mut ctxt = self.actions.offset(index * 4 * size::I16).ctxt();
+                let mark_x = ctxt.read_i16be()?;
+                let mark_y = ctxt.read_i16be()?;
+                let current_x = ctxt.read_i16be()?;
+                let current_y = ctxt.read_i16be()?;
+                Ok(AttachmentAction::Coordinates(
+                    Anchor {
+                        x: mark_x,
+                        y: mark_y,
+                    },
+                    Anchor {
+                        x: current_x,
+                        y: current_y,
+                    },
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_format2() {
+        let data = [
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, // version 2, 1 subtable
+            0x00, 0x00, 0x00, 0x3C, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, // header
+            0x00, 0x00, 0x00, 0x04, // row width
+            0x00, 0x00, 0x00, 0x1C, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x34, //
+            // left class table: format 8, glyphs 5-6
+            0x00, 0x08, 0x00, 0x05, 0x00, 0x02, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00,
+            // right class table: format 8, glyphs 7-8
+            0x00, 0x08, 0x00, 0x07, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00,
+            // kerning array
+            0x00, 0x00, 0xFF, 0xF6, 0xFF, 0xEC, 0x00, 0x00,
+        ];
+        let kerx = ReadScope::new(&data).read::<KerxTable<'_>>().unwrap();
+        assert_eq!(kerx.subtables.len(), 1);
+        let subtable = &kerx.subtables[0];
+        assert_eq!(subtable.kerning(5, 7).unwrap(), Some(0));
+        assert_eq!(subtable.kerning(5, 8).unwrap(), Some(-10));
+        assert_eq!(subtable.kerning(6, 7).unwrap(), Some(-20));
+        assert_eq!(subtable.kerning(6, 9).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_format6() {
+        let data = [
+            0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x00, // header
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, // flags, rows, columns
+            0x00, 0x00, 0x00, 0x24, 0x00, 0x00, 0x00, 0x2E, 0x00, 0x00, 0x00, 0x38, //
+            0x00, 0x00, 0x00, 0x00, //
+            // row index table: format 8, glyph 1
+            0x00, 0x08, 0x00, 0x01, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00,
+            // column index table: format 8, glyphs 2-3
+            0x00, 0x08, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, // kerning array
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0xFF, 0xFB,
+        ];
+        let subtable = ReadScope::new(&data).read::<KerxSubtable<'_>>().unwrap();
+        assert_eq!(subtable.kerning(1, 2).unwrap(), Some(5));
+        assert_eq!(subtable.kerning(1, 3).unwrap(), Some(-5));
+        assert_eq!(subtable.kerning(2, 3).unwrap(), None);
+    }
+}
@@ -0,0 +1,889 @@
+#![deny(missing_docs)]
+
+//! `MATH` Mathematical Typesetting Table parsing.
+//!
+//! The `MATH` table provides the constants, per-glyph information and glyph variants needed to
+//! lay out mathematical formulas. `MathVariants::stretch` builds a stretched glyph, such as a
+//! delimiter or radical sign, of a requested size from the variants and glyph assemblies in the
+//! table.
+//!
+//! Device tables and variation data are not read.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/math>
+
+use std::convert::TryFrom;
+
+use bitflags::bitflags;
+
+use crate::binary::read::{ReadBinary, ReadCtxt, ReadScope};
+use crate::error::ParseError;
+use crate::layout::Coverage;
+
+/// The maximum number of parts in a stretched glyph assembly.
+const MAX_ASSEMBLY_PARTS: usize = 256;
+
+/// `MATH` Mathematical Typesetting Table.
+pub struct MathTable {
+    /// Major version of the table, `1`.
+    pub major_version: u16,
+    /// Minor version of the table, `0`.
+    pub minor_version: u16,
+    /// Constants used to position elements of formulas.
+    pub constants: Option<MathConstants>,
+    /// Per-glyph positioning information.
+    pub glyph_info: Option<MathGlyphInfo>,
+    /// Larger variants and assemblies of glyphs that stretch.
+    pub variants: Option<MathVariants>,
+}
+
+/// Constants used to position elements of formulas, in font units unless stated otherwise.
+pub struct MathConstants {
+    /// Percentage of scaling down for level 1 superscripts and subscripts.
+    pub script_percent_scale_down: i16,
+    /// Percentage of scaling down for level 2 (scriptScript) superscripts and subscripts.
+    pub script_script_percent_scale_down: i16,
+    /// Minimum height required for a delimited expression to be treated as a sub-formula.
+    pub delimited_sub_formula_min_height: u16,
+    /// Minimum height of n-ary operators, such as integral and summation, in display style.
+    pub display_operator_min_height: u16,
+    /// White space to be left between math formulas to ensure proper line spacing.
+    pub math_leading: i16,
+    /// Axis height of the font.
+    pub axis_height: i16,
+    /// Maximum (ink) height of an accent base that does not require raising the accents.
+    pub accent_base_height: i16,
+    /// Maximum (ink) height of an accent base that does not require flattening the accents.
+    pub flattened_accent_base_height: i16,
+    /// Standard shift down applied to subscript elements.
+    pub subscript_shift_down: i16,
+    /// Maximum height of the (ink) top of subscripts that does not require moving them further
+    /// down.
+    pub subscript_top_max: i16,
+    /// Minimum drop of the baseline of subscripts relative to the (ink) bottom of the base.
+    pub subscript_baseline_drop_min: i16,
+    /// Standard shift up applied to superscript elements.
+    pub superscript_shift_up: i16,
+    /// Standard shift up applied to superscript elements in cramped style.
+    pub superscript_shift_up_cramped: i16,
+    /// Minimum height of the (ink) bottom of superscripts that does not require moving them further
+    /// up.
+    pub superscript_bottom_min: i16,
+    /// Maximum drop of the baseline of superscripts relative to the (ink) top of the base.
+    pub superscript_baseline_drop_max: i16,
+    /// Minimum gap between the superscript and subscript ink.
+    pub sub_superscript_gap_min: i16,
+    /// Maximum height of the (ink) bottom of a superscript when the gap between it and a subscript
+    /// is increased.
+    pub superscript_bottom_max_with_subscript: i16,
+    /// Extra white space added after each subscript and superscript.
+    pub space_after_script: i16,
+    /// Minimum gap between the (ink) bottom of the upper limit and the (ink) top of the base
+    /// operator.
+    pub upper_limit_gap_min: i16,
+    /// Minimum distance between the baseline of the upper limit and the (ink) top of the base
+    /// operator.
+    pub upper_limit_baseline_rise_min: i16,
+    /// Minimum gap between the (ink) top of the lower limit and the (ink) bottom of the base
+    /// operator.
+    pub lower_limit_gap_min: i16,
+    /// Minimum distance between the baseline of the lower limit and the (ink) bottom of the base
+    /// operator.
+    pub lower_limit_baseline_drop_min: i16,
+    /// Standard shift up applied to the top element of a stack.
+    pub stack_top_shift_up: i16,
+    /// Standard shift up applied to the top element of a stack in display style.
+    pub stack_top_display_style_shift_up: i16,
+    /// Standard shift down applied to the bottom element of a stack.
+    pub stack_bottom_shift_down: i16,
+    /// Standard shift down applied to the bottom element of a stack in display style.
+    pub stack_bottom_display_style_shift_down: i16,
+    /// Minimum gap between the (ink) bottom of the top element of a stack and the (ink) top of the
+    /// bottom element.
+    pub stack_gap_min: i16,
+    /// Minimum gap between the elements of a stack in display style.
+    pub stack_display_style_gap_min: i16,
+    /// Standard shift up applied to the top element of a stretch stack.
+    pub stretch_stack_top_shift_up: i16,
+    /// Standard shift down applied to the bottom element of a stretch stack.
+    pub stretch_stack_bottom_shift_down: i16,
+    /// Minimum gap between the ink of the stretched element and the (ink) bottom of the element
+    /// above.
+    pub stretch_stack_gap_above_min: i16,
+    /// Minimum gap between the ink of the stretched element and the (ink) top of the element below.
+    pub stretch_stack_gap_below_min: i16,
+    /// Standard shift up applied to the numerator.
+    pub fraction_numerator_shift_up: i16,
+    /// Standard shift up applied to the numerator in display style.
+    pub fraction_numerator_display_style_shift_up: i16,
+    /// Standard shift down applied to the denominator.
+    pub fraction_denominator_shift_down: i16,
+    /// Standard shift down applied to the denominator in display style.
+    pub fraction_denominator_display_style_shift_down: i16,
+    /// Minimum gap between the (ink) bottom of the numerator and the ink of the fraction bar.
+    pub fraction_numerator_gap_min: i16,
+    /// Minimum gap between the (ink) bottom of the numerator and the ink of the fraction bar in
+    /// display style.
+    pub fraction_num_display_style_gap_min: i16,
+    /// Thickness of the fraction bar.
+    pub fraction_rule_thickness: i16,
+    /// Minimum gap between the (ink) top of the denominator and the ink of the fraction bar.
+    pub fraction_denominator_gap_min: i16,
+    /// Minimum gap between the (ink) top of the denominator and the ink of the fraction bar in
+    /// display style.
+    pub fraction_denom_display_style_gap_min: i16,
+    /// Horizontal distance between the top and bottom elements of a skewed fraction.
+    pub skewed_fraction_horizontal_gap: i16,
+    /// Vertical distance between the ink of the top and bottom elements of a skewed fraction.
+    pub skewed_fraction_vertical_gap: i16,
+    /// Distance between the overbar and the (ink) top of the base.
+    pub overbar_vertical_gap: i16,
+    /// Thickness of the overbar.
+    pub overbar_rule_thickness: i16,
+    /// Extra white space reserved above the overbar.
+    pub overbar_extra_ascender: i16,
+    /// Distance between the underbar and the (ink) bottom of the base.
+    pub underbar_vertical_gap: i16,
+    /// Thickness of the underbar.
+    pub underbar_rule_thickness: i16,
+    /// Extra white space reserved below the underbar.
+    pub underbar_extra_descender: i16,
+    /// Space between the (ink) top of the expression and the bar over it.
+    pub radical_vertical_gap: i16,
+    /// Space between the (ink) top of the expression and the bar over it in display style.
+    pub radical_display_style_vertical_gap: i16,
+    /// Thickness of the radical rule.
+    pub radical_rule_thickness: i16,
+    /// Extra white space reserved above the radical.
+    pub radical_extra_ascender: i16,
+    /// Extra horizontal kern before the degree of a radical, if present.
+    pub radical_kern_before_degree: i16,
+    /// Negative kern after the degree of a radical, if present.
+    pub radical_kern_after_degree: i16,
+    /// Height of the bottom of the degree of a radical, if present, as a percentage of the ascender
+    /// of the radical sign.
+    pub radical_degree_bottom_raise_percent: i16,
+}
+
+/// Per-glyph positioning information.
+pub struct MathGlyphInfo {
+    italics_correction: Option<CoverageValues>,
+    top_accent_attachment: Option<CoverageValues>,
+    extended_shape_coverage: Option<Coverage>,
+    kern_info: Option<(Coverage, Vec<MathKernInfoRecord>)>,
+}
+
+/// A value for each glyph of a coverage table.
+struct CoverageValues {
+    coverage: Coverage,
+    values: Vec<i16>,
+}
+
+/// The kerning of the four corners of a glyph.
+pub struct MathKernInfoRecord {
+    /// Kerning of the top right corner.
+    pub top_right: Option<MathKern>,
+    /// Kerning of the top left corner.
+    pub top_left: Option<MathKern>,
+    /// Kerning of the bottom right corner.
+    pub bottom_right: Option<MathKern>,
+    /// Kerning of the bottom left corner.
+    pub bottom_left: Option<MathKern>,
+}
+
+/// A corner of a glyph, for math kerning.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MathKernCorner {
+    /// The top right corner, used for superscripts.
+    TopRight,
+    /// The top left corner, used for pre-superscripts.
+    TopLeft,
+    /// The bottom right corner, used for subscripts.
+    BottomRight,
+    /// The bottom left corner, used for pre-subscripts.
+    BottomLeft,
+}
+
+/// Kerning of a corner of a glyph, which varies with height.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MathKern {
+    /// The heights at which the kern value changes, in increasing order.
+    pub correction_heights: Vec<i16>,
+    /// The kern values, one more than `correction_heights`. Kern value `i` applies to heights
+    /// below correction height `i`, the last to heights above all the correction heights.
+    pub kern_values: Vec<i16>,
+}
+
+/// Larger variants and assemblies of glyphs that stretch.
+pub struct MathVariants {
+    /// The minimum overlap of connecting glyphs in a glyph assembly, in font units.
+    pub min_connector_overlap: u16,
+    vert_glyph_coverage: Option<Coverage>,
+    horiz_glyph_coverage: Option<Coverage>,
+    vert_glyph_constructions: Vec<MathGlyphConstruction>,
+    horiz_glyph_constructions: Vec<MathGlyphConstruction>,
+}
+
+/// The direction in which a glyph is stretched.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StretchDirection {
+    /// Stretched vertically, such as parentheses and radical signs.
+    Vertical,
+    /// Stretched horizontally, such as over and under braces.
+    Horizontal,
+}
+
+/// The variants and assembly of a glyph in a stretch direction.
+pub struct MathGlyphConstruction {
+    /// An assembly of parts that can be stretched to an arbitrary size.
+    pub glyph_assembly: Option<GlyphAssembly>,
+    /// Variants of the glyph in increasing size, usually starting with the glyph itself.
+    pub variants: Vec<MathGlyphVariantRecord>,
+}
+
+/// A variant of a glyph.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MathGlyphVariantRecord {
+    /// The glyph id of the variant.
+    pub variant_glyph: u16,
+    /// The advance of the variant in the stretch direction, in font units.
+    pub advance_measurement: u16,
+}
+
+/// Parts that are combined to produce a glyph of arbitrary size.
+pub struct GlyphAssembly {
+    /// Italics correction of the assembled glyph.
+    pub italics_correction: i16,
+    /// The parts of the assembly, from bottom to top or left to right.
+    pub parts: Vec<GlyphPart>,
+}
+
+/// A part of a glyph assembly.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GlyphPart {
+    /// The glyph id of the part.
+    pub glyph_id: u16,
+    /// The length of the connector at the start of the part, in font units.
+    pub start_connector_length: u16,
+    /// The length of the connector at the end of the part, in font units.
+    pub end_connector_length: u16,
+    /// The advance of the part in the stretch direction, in font units.
+    pub full_advance: u16,
+    /// Part qualifiers.
+    pub part_flags: PartFlags,
+}
+
+bitflags! {
+    /// Qualifiers of a glyph assembly part.
+    pub struct PartFlags: u16 {
+        /// The part can be repeated, or omitted, to stretch the assembly.
+        const EXTENDER = 0x0001;
+    }
+}
+
+/// A glyph stretched to a requested size, see `MathVariants::stretch`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StretchedGlyph {
+    /// The glyphs that make up the stretched glyph: a single variant, or the parts of an
+    /// assembly.
+    pub parts: Vec<PositionedPart>,
+    /// The size of the stretched glyph in the stretch direction, in font units.
+    pub size: i32,
+    /// Italics correction of the stretched glyph. Only set for assemblies, the italics
+    /// correction of a variant is found in `MathGlyphInfo`.
+    pub italics_correction: i16,
+}
+
+/// A glyph of a stretched glyph.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PositionedPart {
+    /// The glyph id.
+    pub glyph_id: u16,
+    /// The offset of the glyph from the start of the stretched glyph, in font units. That is
+    /// from the bottom for vertical stretching and from the left for horizontal stretching.
+    pub offset: i32,
+}
+
+impl<'a> ReadBinary<'a> for MathTable {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let major_version = ctxt.read_u16be()?;
+        ctxt.check_version(major_version == 1)?;
+        let minor_version = ctxt.read_u16be()?;
+        let constants_offset = usize::from(ctxt.read_u16be()?);
+        let glyph_info_offset = usize::from(ctxt.read_u16be()?);
+        let variants_offset = usize::from(ctxt.read_u16be()?);
+        Ok(MathTable {
+            major_version,
+            minor_version,
+            constants: read_optional::<MathConstants>(&scope, constants_offset)?,
+            glyph_info: read_optional::<MathGlyphInfo>(&scope, glyph_info_offset)?,
+            variants: read_optional::<MathVariants>(&scope, variants_offset)?,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for MathConstants {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        Ok(MathConstants {
+            script_percent_scale_down: ctxt.read_i16be()?,
+            script_script_percent_scale_down: ctxt.read_i16be()?,
+            delimited_sub_formula_min_height: ctxt.read_u16be()?,
+            display_operator_min_height: ctxt.read_u16be()?,
+            math_leading: read_math_value(ctxt)?,
+            axis_height: read_math_value(ctxt)?,
+            accent_base_height: read_math_value(ctxt)?,
+            flattened_accent_base_height: read_math_value(ctxt)?,
+            subscript_shift_down: read_math_value(ctxt)?,
+            subscript_top_max: read_math_value(ctxt)?,
+            subscript_baseline_drop_min: read_math_value(ctxt)?,
+            superscript_shift_up: read_math_value(ctxt)?,
+            superscript_shift_up_cramped: read_math_value(ctxt)?,
+            superscript_bottom_min: read_math_value(ctxt)?,
+            superscript_baseline_drop_max: read_math_value(ctxt)?,
+            sub_superscript_gap_min: read_math_value(ctxt)?,
+            superscript_bottom_max_with_subscript: read_math_value(ctxt)?,
+            space_after_script: read_math_value(ctxt)?,
+            upper_limit_gap_min: read_math_value(ctxt)?,
+            upper_limit_baseline_rise_min: read_math_value(ctxt)?,
+            lower_limit_gap_min: read_math_value(ctxt)?,
+            lower_limit_baseline_drop_min: read_math_value(ctxt)?,
+            stack_top_shift_up: read_math_value(ctxt)?,
+            stack_top_display_style_shift_up: read_math_value(ctxt)?,
+            stack_bottom_shift_down: read_math_value(ctxt)?,
+            stack_bottom_display_style_shift_down: read_math_value(ctxt)?,
+            stack_gap_min: read_math_value(ctxt)?,
+            stack_display_style_gap_min: read_math_value(ctxt)?,
+            stretch_stack_top_shift_up: read_math_value(ctxt)?,
+            stretch_stack_bottom_shift_down: read_math_value(ctxt)?,
+            stretch_stack_gap_above_min: read_math_value(ctxt)?,
+            stretch_stack_gap_below_min: read_math_value(ctxt)?,
+            fraction_numerator_shift_up: read_math_value(ctxt)?,
+            fraction_numerator_display_style_shift_up: read_math_value(ctxt)?,
+            fraction_denominator_shift_down: read_math_value(ctxt)?,
+            fraction_denominator_display_style_shift_down: read_math_value(ctxt)?,
+            fraction_numerator_gap_min: read_math_value(ctxt)?,
+            fraction_num_display_style_gap_min: read_math_value(ctxt)?,
+            fraction_rule_thickness: read_math_value(ctxt)?,
+            fraction_denominator_gap_min: read_math_value(ctxt)?,
+            fraction_denom_display_style_gap_min: read_math_value(ctxt)?,
+            skewed_fraction_horizontal_gap: read_math_value(ctxt)?,
+            skewed_fraction_vertical_gap: read_math_value(ctxt)?,
+            overbar_vertical_gap: read_math_value(ctxt)?,
+            overbar_rule_thickness: read_math_value(ctxt)?,
+            overbar_extra_ascender: read_math_value(ctxt)?,
+            underbar_vertical_gap: read_math_value(ctxt)?,
+            underbar_rule_thickness: read_math_value(ctxt)?,
+            underbar_extra_descender: read_math_value(ctxt)?,
+            radical_vertical_gap: read_math_value(ctxt)?,
+            radical_display_style_vertical_gap: read_math_value(ctxt)?,
+            radical_rule_thickness: read_math_value(ctxt)?,
+            radical_extra_ascender: read_math_value(ctxt)?,
+            radical_kern_before_degree: read_math_value(ctxt)?,
+            radical_kern_after_degree: read_math_value(ctxt)?,
+            radical_degree_bottom_raise_percent: ctxt.read_i16be()?,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for MathGlyphInfo {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let italics_correction_offset = usize::from(ctxt.read_u16be()?);
+        let top_accent_attachment_offset = usize::from(ctxt.read_u16be()?);
+        let extended_shape_coverage_offset = usize::from(ctxt.read_u16be()?);
+        let kern_info_offset = usize::from(ctxt.read_u16be()?);
+
+        let kern_info = if kern_info_offset != 0 {
+            let kern_info_scope = scope.offset(kern_info_offset);
+            let mut ctxt = kern_info_scope.ctxt();
+            let coverage_offset = usize::from(ctxt.read_u16be()?);
+            let coverage = kern_info_scope.offset(coverage_offset).read::<Coverage>()?;
+            let kern_count = ctxt.read_u16be()?;
+            let records = (0..kern_count)
+                .map(|_| {
+                    let mut read_kern = || {
+                        let offset = usize::from(ctxt.read_u16be()?);
+                        read_optional::<MathKern>(&kern_info_scope, offset)
+                    };
+                    Ok(MathKernInfoRecord {
+                        top_right: read_kern()?,
+                        top_left: read_kern()?,
+                        bottom_right: read_kern()?,
+                        bottom_left: read_kern()?,
+                    })
+                })
+                .collect::<Result<Vec<_>, ParseError>>()?;
+            Some((coverage, records))
+        } else {
+            None
+        };
+
+        Ok(MathGlyphInfo {
+            italics_correction: read_optional::<CoverageValues>(&scope, italics_correction_offset)?,
+            top_accent_attachment: read_optional::<CoverageValues>(
+                &scope,
+                top_accent_attachment_offset,
+            )?,
+            extended_shape_coverage: read_optional::<Coverage>(
+                &scope,
+                extended_shape_coverage_offset,
+            )?,
+            kern_info,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for CoverageValues {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let coverage_offset = usize::from(ctxt.read_u16be()?);
+        let coverage = scope.offset(coverage_offset).read::<Coverage>()?;
+        let count = usize::from(ctxt.read_u16be()?);
+        let values = read_math_values(ctxt, count)?;
+        Ok(CoverageValues { coverage, values })
+    }
+}
+
+impl<'a> ReadBinary<'a> for MathKern {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let height_count = usize::from(ctxt.read_u16be()?);
+        let correction_heights = read_math_values(ctxt, height_count)?;
+        let kern_values = read_math_values(ctxt, height_count + 1)?;
+        Ok(MathKern {
+            correction_heights,
+            kern_values,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for MathVariants {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let min_connector_overlap = ctxt.read_u16be()?;
+        let vert_glyph_coverage_offset = usize::from(ctxt.read_u16be()?);
+        let horiz_glyph_coverage_offset = usize::from(ctxt.read_u16be()?);
+        let vert_glyph_count = ctxt.read_u16be()?;
+        let horiz_glyph_count = ctxt.read_u16be()?;
+        let mut read_constructions = |count| {
+            (0..count)
+                .map(|_| {
+                    let offset = usize::from(ctxt.read_u16be()?);
+                    scope.offset(offset).read::<MathGlyphConstruction>()
+                })
+                .collect::<Result<Vec<_>, ParseError>>()
+        };
+        let vert_glyph_constructions = read_constructions(vert_glyph_count)?;
+        let horiz_glyph_constructions = read_constructions(horiz_glyph_count)?;
+        Ok(MathVariants {
+            min_connector_overlap,
+            vert_glyph_coverage: read_optional::<Coverage>(&scope, vert_glyph_coverage_offset)?,
+            horiz_glyph_coverage: read_optional::<Coverage>(&scope, horiz_glyph_coverage_offset)?,
+            vert_glyph_constructions,
+            horiz_glyph_constructions,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for MathGlyphConstruction {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let glyph_assembly_offset = usize::from(ctxt.read_u16be()?);
+        let variant_count = ctxt.read_u16be()?;
+        let variants = (0..variant_count)
+            .map(|_| {
+                Ok(MathGlyphVariantRecord {
+                    variant_glyph: ctxt.read_u16be()?,
+                    advance_measurement: ctxt.read_u16be()?,
+                })
+            })
+            .collect::<Result<Vec<_>, ParseError>>()?;
+        Ok(MathGlyphConstruction {
+            glyph_assembly: read_optional::<GlyphAssembly>(&scope, glyph_assembly_offset)?,
+            variants,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for GlyphAssembly {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let italics_correction = read_math_value(ctxt)?;
+        let part_count = ctxt.read_u16be()?;
+        let parts = (0..part_count)
+            .map(|_| {
+                Ok(GlyphPart {
+                    glyph_id: ctxt.read_u16be()?,
+                    start_connector_length: ctxt.read_u16be()?,
+                    end_connector_length: ctxt.read_u16be()?,
+                    full_advance: ctxt.read_u16be()?,
+                    part_flags: PartFlags::from_bits_truncate(ctxt.read_u16be()?),
+                })
+            })
+            .collect::<Result<Vec<_>, ParseError>>()?;
+        Ok(GlyphAssembly {
+            italics_correction,
+            parts,
+        })
+    }
+}
+
+/// Read the value of a `MathValueRecord`.
+fn read_math_value(ctxt: &mut ReadCtxt<'_>) -> Result<i16, ParseError> {
+    let value = ctxt.read_i16be()?;
+    // Doesn't read the device table because we don't use it
+    let _device_offset = ctxt.read_u16be()?;
+    Ok(value)
+}
+
+fn read_math_values(ctxt: &mut ReadCtxt<'_>, count: usize) -> Result<Vec<i16>, ParseError> {
+    (0..count).map(|_| read_math_value(ctxt)).collect()
+}
+
+/// Read a `T` at `offset`, where an offset of zero indicates it's absent.
+fn read_optional<'a, T: ReadBinary<'a>>(
+    scope: &ReadScope<'a>,
+    offset: usize,
+) -> Result<Option<T::HostType>, ParseError> {
+    if offset == 0 {
+        Ok(None)
+    } else {
+        scope.offset(offset).read::<T>().map(Some)
+    }
+}
+
+impl MathGlyphInfo {
+    /// Returns the italics correction of `glyph_id`, used when a glyph is followed by an upright
+    /// glyph or positioning superscripts.
+    pub fn italics_correction(&self, glyph_id: u16) -> Option<i16> {
+        self.italics_correction.as_ref()?.value(glyph_id)
+    }
+
+    /// Returns the horizontal position at which accents are attached to `glyph_id`.
+    ///
+    /// If `None`, accents are centered over the glyph.
+    pub fn top_accent_attachment(&self, glyph_id: u16) -> Option<i16> {
+        self.top_accent_attachment.as_ref()?.value(glyph_id)
+    }
+
+    /// Returns `true` if `glyph_id` is an extended shape, such as a large operator, which
+    /// affects the positioning of its subscripts and superscripts.
+    pub fn is_extended_shape(&self, glyph_id: u16) -> bool {
+        self.extended_shape_coverage
+            .as_ref()
+            .and_then(|coverage| coverage.glyph_coverage_value(glyph_id))
+            .is_some()
+    }
+
+    /// Returns the kerning of `corner` of `glyph_id`.
+    pub fn kern(&self, glyph_id: u16, corner: MathKernCorner) -> Option<&MathKern> {
+        let (coverage, records) = self.kern_info.as_ref()?;
+        let index = coverage.glyph_coverage_value(glyph_id)?;
+        let record = records.get(usize::from(index))?;
+        match corner {
+            MathKernCorner::TopRight => record.top_right.as_ref(),
+            MathKernCorner::TopLeft => record.top_left.as_ref(),
+            MathKernCorner::BottomRight => record.bottom_right.as_ref(),
+            MathKernCorner::BottomLeft => record.bottom_left.as_ref(),
+        }
+    }
+}
+
+impl CoverageValues {
+    fn value(&self, glyph_id: u16) -> Option<i16> {
+        let index = self.coverage.glyph_coverage_value(glyph_id)?;
+        self.values.get(usize::from(index)).copied()
+    }
+}
+
+impl MathKern {
+    /// Returns the kern value at `height`.
+    pub fn kern_value(&self, height: i16) -> i16 {
+        let index = self
+            .correction_heights
+            .iter()
+            .position(|&correction_height| height < correction_height)
+            .unwrap_or(self.correction_heights.len());
+        self.kern_values.get(index).copied().unwrap_or(0)
+    }
+}
+
+impl MathVariants {
+    /// Returns the variants and assembly of `glyph_id` in `direction`.
+    pub fn construction(
+        &self,
+        glyph_id: u16,
+        direction: StretchDirection,
+    ) -> Option<&MathGlyphConstruction> {
+        let (coverage, constructions) = match direction {
+            StretchDirection::Vertical => (
+                self.vert_glyph_coverage.as_ref(),
+                &self.vert_glyph_constructions,
+            ),
+            StretchDirection::Horizontal => (
+                self.horiz_glyph_coverage.as_ref(),
+                &self.horiz_glyph_constructions,
+            ),
+        };
+        let index = coverage?.glyph_coverage_value(glyph_id)?;
+        constructions.get(usize::from(index))
+    }
+
+    /// Stretch `glyph_id` in `direction` to at least `target_size` font units.
+    ///
+    /// The smallest variant that is at least `target_size` is used. If there is no such variant
+    /// the glyph assembly is stretched to `target_size`, and if there is no assembly the largest
+    /// variant is used. The stretched glyph may be smaller than `target_size` if the font
+    /// doesn't allow it to grow that large.
+    ///
+    /// Returns `None` if `glyph_id` can't be stretched in `direction`.
+    pub fn stretch(
+        &self,
+        glyph_id: u16,
+        target_size: i32,
+        direction: StretchDirection,
+    ) -> Option<StretchedGlyph> {
+        let construction = self.construction(glyph_id, direction)?;
+        let variant = |variant: &MathGlyphVariantRecord| StretchedGlyph {
+            parts: vec![PositionedPart {
+                glyph_id: variant.variant_glyph,
+                offset: 0,
+            }],
+            size: i32::from(variant.advance_measurement),
+            italics_correction: 0,
+        };
+        construction
+            .variants
+            .iter()
+            .find(|record| i32::from(record.advance_measurement) >= target_size)
+            .map(variant)
+            .or_else(|| {
+                construction
+                    .glyph_assembly
+                    .as_ref()
+                    .and_then(|assembly| assembly.assemble(target_size, self.min_connector_overlap))
+            })
+            .or_else(|| construction.variants.last().map(variant))
+    }
+}
+
+impl GlyphPart {
+    /// Returns `true` if the part can be repeated, or omitted, to stretch the assembly.
+    pub fn is_extender(&self) -> bool {
+        self.part_flags.contains(PartFlags::EXTENDER)
+    }
+}
+
+impl GlyphAssembly {
+    /// Assemble the parts to produce a glyph of at least `target_size` font units, with
+    /// connecting parts overlapping by at least `min_connector_overlap`.
+    ///
+    /// Extenders are repeated as few times as needed to reach `target_size`, then the overlaps
+    /// of connectors are increased, up to the length of the connectors, to bring the size as
+    /// close to `target_size` as possible. Returns `None` if the assembly has no parts.
+    pub fn assemble(&self, target_size: i32, min_connector_overlap: u16) -> Option<StretchedGlyph> {
+        let min_overlap = i64::from(min_connector_overlap);
+        let target_size = i64::from(target_size);
+        let (extenders, non_extenders): (Vec<&GlyphPart>, Vec<&GlyphPart>) =
+            self.parts.iter().partition(|part| part.is_extender());
+        let advance = |parts: &[&GlyphPart]| {
+            parts
+                .iter()
+                .map(|part| i64::from(part.full_advance))
+                .sum::<i64>()
+        };
+
+        // The size with `repeats` repetitions of each extender and the minimum overlap
+        let max_size = |repeats: i64| {
+            let count = non_extenders.len() as i64 + repeats * extenders.len() as i64;
+            advance(&non_extenders) + repeats * advance(&extenders)
+                - (count - 1).max(0) * min_overlap
+        };
+        let mut repeats = if non_extenders.is_empty() { 1 } else { 0 };
+        if !extenders.is_empty() && max_size(repeats) < target_size {
+            let growth = advance(&extenders) - extenders.len() as i64 * min_overlap;
+            if growth > 0 {
+                repeats += (target_size - max_size(repeats) + growth - 1) / growth;
+            }
+        }
+        if !extenders.is_empty() {
+            let max_repeats =
+                MAX_ASSEMBLY_PARTS.saturating_sub(non_extenders.len()) / extenders.len();
+            repeats = repeats.min(max_repeats as i64);
+        }
+
+        let parts = self
+            .parts
+            .iter()
+            .flat_map(|part| {
+                let count = if part.is_extender() { repeats } else { 1 };
+                (0..count).map(move |_| part)
+            })
+            .collect::<Vec<_>>();
+        if parts.is_empty() {
+            return None;
+        }
+
+        // Increase the overlaps evenly to shrink the assembly towards the target size
+        let mut excess = (max_size(repeats) - target_size).max(0);
+        let mut overlaps = Vec::with_capacity(parts.len() - 1);
+        for (index, pair) in parts.windows(2).enumerate() {
+            let connector = i64::from(
+                pair[0]
+                    .end_connector_length
+                    .min(pair[1].start_connector_length),
+            );
+            let remaining = (parts.len() - 1 - index) as i64;
+            let extra =
+                ((excess + remaining - 1) / remaining).min((connector - min_overlap).max(0));
+            excess -= extra;
+            overlaps.push(min_overlap + extra);
+        }
+
+        let mut offset = 0;
+        let mut positioned = Vec::with_capacity(parts.len());
+        for (index, part) in parts.iter().enumerate() {
+            positioned.push(PositionedPart {
+                glyph_id: part.glyph_id,
+                offset: i32::try_from(offset).ok()?,
+            });
+            offset += i64::from(part.full_advance) - overlaps.get(index).copied().unwrap_or(0);
+        }
+        Some(StretchedGlyph {
+            parts: positioned,
+            size: i32::try_from(offset).ok()?,
+            italics_correction: self.italics_correction,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::push_u16;
+
+    fn math_table_data() -> Vec<u8> {
+        let mut data = Vec::new();
+        // Header
+        push_u16(&mut data, &[1, 0, 10, 224, 284]);
+        // 10: MathConstants
+        push_u16(&mut data, &[80, 60, 1500, 1300]);
+        for i in 0..51 {
+            push_u16(&mut data, &[100 + i, 0]);
+        }
+        push_u16(&mut data, &[60]);
+        // 224: MathGlyphInfo
+        push_u16(&mut data, &[8, 0, 22, 28]);
+        // 232: MathItalicsCorrectionInfo and its coverage
+        push_u16(&mut data, &[8, 1, 50, 0]);
+        push_u16(&mut data, &[1, 1, 3]);
+        // 246: extended shape coverage
+        push_u16(&mut data, &[1, 1, 4]);
+        // 252: MathKernInfo, its coverage, and a top right MathKern
+        push_u16(&mut data, &[12, 1, 18, 0, 0, 0]);
+        push_u16(&mut data, &[1, 1, 3]);
+        push_u16(&mut data, &[1, 100, 0, (-10i16) as u16, 0, 20, 0]);
+        // 284: MathVariants and its vertical coverage
+        push_u16(&mut data, &[10, 12, 0, 1, 0, 18]);
+        push_u16(&mut data, &[1, 1, 5]);
+        // 302: MathGlyphConstruction
+        push_u16(&mut data, &[12, 2, 5, 100, 6, 200]);
+        // 314: GlyphAssembly
+        push_u16(&mut data, &[7, 0, 3]);
+        push_u16(&mut data, &[10, 0, 50, 150, 0]);
+        push_u16(&mut data, &[11, 50, 50, 100, 1]);
+        push_u16(&mut data, &[12, 50, 0, 150, 0]);
+        data
+    }
+
+    #[test]
+    fn test_read_math() {
+        let data = math_table_data();
+        let math = ReadScope::new(&data).read::<MathTable>().unwrap();
+
+        let constants = math.constants.unwrap();
+        assert_eq!(constants.script_percent_scale_down, 80);
+        assert_eq!(constants.display_operator_min_height, 1300);
+        assert_eq!(constants.math_leading, 100);
+        assert_eq!(constants.axis_height, 101);
+        assert_eq!(constants.radical_kern_after_degree, 150);
+        assert_eq!(constants.radical_degree_bottom_raise_percent, 60);
+
+        let glyph_info = math.glyph_info.unwrap();
+        assert_eq!(glyph_info.italics_correction(3), Some(50));
+        assert_eq!(glyph_info.italics_correction(4), None);
+        assert_eq!(glyph_info.top_accent_attachment(3), None);
+        assert!(glyph_info.is_extended_shape(4));
+        assert!(!glyph_info.is_extended_shape(3));
+        let kern = glyph_info.kern(3, MathKernCorner::TopRight).unwrap();
+        assert_eq!(kern.kern_value(50), -10);
+        assert_eq!(kern.kern_value(100), 20);
+        assert!(glyph_info.kern(3, MathKernCorner::BottomLeft).is_none());
+        assert!(glyph_info.kern(4, MathKernCorner::TopRight).is_none());
+    }
+
+    #[test]
+    fn test_stretch() {
+        let data = math_table_data();
+        let variants = ReadScope::new(&data)
+            .read::<MathTable>()
+            .unwrap()
+            .variants
+            .unwrap();
+        let part = |glyph_id, offset| PositionedPart { glyph_id, offset };
+
+        let stretched = variants.stretch(5, 150, StretchDirection::Vertical);
+        assert_eq!(
+            stretched,
+            Some(StretchedGlyph {
+                parts: vec![part(6, 0)],
+                size: 200,
+                italics_correction: 0,
+            })
+        );
+
+        // Three extenders are needed, with the overlaps increased from 10 to 25
+        let stretched = variants.stretch(5, 500, StretchDirection::Vertical);
+        assert_eq!(
+            stretched,
+            Some(StretchedGlyph {
+                parts: vec![
+                    part(10, 0),
+                    part(11, 125),
+                    part(11, 200),
+                    part(11, 275),
+                    part(12, 350),
+                ],
+                size: 500,
+                italics_correction: 7,
+            })
+        );
+
+        let stretched = variants
+            .stretch(5, 3001, StretchDirection::Vertical)
+            .unwrap();
+        assert_eq!(stretched.size, 3001);
+        assert_eq!(stretched.parts.len(), 33);
+
+        assert!(variants
+            .stretch(5, 100, StretchDirection::Horizontal)
+            .is_none());
+        assert!(variants
+            .stretch(6, 100, StretchDirection::Vertical)
+            .is_none());
+    }
+}
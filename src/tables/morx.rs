@@ -0,0 +1,367 @@
+#![deny(missing_docs)]
+
+//! `morx` Extended Glyph Metamorphosis Table parsing.
+//!
+//! <https://developer.apple.com/fonts/TrueType-Reference-Manual/RM06/Chap6morx.html>
+
+use std::convert::TryFrom;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt, ReadFrom, ReadScope};
+use crate::binary::{U16Be, U32Be};
+use crate::error::ParseError;
+use crate::size;
+use crate::tables::aat::{read_u16_at, read_u32_at, ExtendedStateTable, LookupTable};
+
+/// `morx` Extended Glyph Metamorphosis Table.
+pub struct MorxTable<'a> {
+    /// The table version, 2 or 3.
+    pub version: u16,
+    /// The chains of subtables, applied in order.
+    pub chains: Vec<Chain<'a>>,
+}
+
+/// A chain of `morx` subtables, with the features that enable them.
+pub struct Chain<'a> {
+    /// The subtable feature flags that are enabled by default.
+    pub default_flags: u32,
+    /// The features of the chain, mapping feature settings to subtable feature flags.
+    pub features: ReadArray<'a, Feature>,
+    /// The subtables of the chain, applied in order.
+    pub subtables: Vec<Subtable<'a>>,
+}
+
+/// A feature entry of a `morx` chain.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Feature {
+    /// The feature type, such as ligatures (1).
+    pub feature_type: u16,
+    /// The feature selector, such as common ligatures on (2).
+    pub feature_setting: u16,
+    /// The subtable feature flags to enable when the feature setting is selected.
+    pub enable_flags: u32,
+    /// The mask applied to the subtable feature flags when the feature setting is selected.
+    pub disable_flags: u32,
+}
+
+/// A `morx` subtable.
+pub struct Subtable<'a> {
+    /// The coverage flags and subtable type.
+    pub coverage: u32,
+    /// The feature flags that enable this subtable.
+    pub sub_feature_flags: u32,
+    /// The type specific data of the subtable.
+    pub subtable: SubtableType<'a>,
+}
+
+/// The type specific data of a `morx` subtable.
+pub enum SubtableType<'a> {
+    /// Type 0: reorders glyphs.
+    Rearrangement(ExtendedStateTable<'a>),
+    /// Type 1: substitutes glyphs depending on their context.
+    Contextual(ContextualSubtable<'a>),
+    /// Type 2: forms ligatures.
+    Ligature(LigatureSubtable<'a>),
+    /// Type 4: substitutes glyphs independently of their context.
+    Noncontextual(LookupTable<'a>),
+    /// Type 5: inserts glyphs.
+    Insertion(InsertionSubtable<'a>),
+    /// A subtable type that is not supported. Holds the subtable type.
+    Unsupported(u8),
+}
+
+/// A contextual glyph substitution subtable.
+pub struct ContextualSubtable<'a> {
+    /// The state table driving the substitutions.
+    pub state_table: ExtendedStateTable<'a>,
+    substitution_table: ReadScope<'a>,
+}
+
+/// A ligature subtable.
+pub struct LigatureSubtable<'a> {
+    /// The state table driving ligature formation.
+    pub state_table: ExtendedStateTable<'a>,
+    lig_actions: ReadScope<'a>,
+    components: ReadScope<'a>,
+    ligatures: ReadScope<'a>,
+}
+
+/// A glyph insertion subtable.
+pub struct InsertionSubtable<'a> {
+    /// The state table driving the insertions.
+    pub state_table: ExtendedStateTable<'a>,
+    insertion_actions: ReadScope<'a>,
+}
+
+/// An entry of a rearrangement state table.
+#[derive(Debug, Copy, Clone)]
+pub struct RearrangementEntry {
+    /// The state to transition to.
+    pub new_state: u16,
+    /// The entry flags and rearrangement verb.
+    pub flags: u16,
+}
+
+/// An entry of a contextual substitution state table.
+#[derive(Debug, Copy, Clone)]
+pub struct ContextualEntry {
+    /// The state to transition to.
+    pub new_state: u16,
+    /// The entry flags.
+    pub flags: u16,
+    /// The index of the substitution table for the marked glyph, `0xFFFF` for none.
+    pub mark_index: u16,
+    /// The index of the substitution table for the current glyph, `0xFFFF` for none.
+    pub current_index: u16,
+}
+
+/// An entry of a ligature state table.
+#[derive(Debug, Copy, Clone)]
+pub struct LigatureEntry {
+    /// The state to transition to.
+    pub new_state: u16,
+    /// The entry flags.
+    pub flags: u16,
+    /// The index of the first ligature action to perform.
+    pub lig_action_index: u16,
+}
+
+/// An entry of an insertion state table.
+#[derive(Debug, Copy, Clone)]
+pub struct InsertionEntry {
+    /// The state to transition to.
+    pub new_state: u16,
+    /// The entry flags and insertion counts.
+    pub flags: u16,
+    /// The index of the glyphs to insert at the current glyph, `0xFFFF` for none.
+    pub current_insert_index: u16,
+    /// The index of the glyphs to insert at the marked glyph, `0xFFFF` for none.
+    pub marked_insert_index: u16,
+}
+
+/// The size of the `morx` chain header.
+const CHAIN_HEADER_SIZE: usize = 16;
+/// The size of the `morx` subtable header.
+const SUBTABLE_HEADER_SIZE: usize = 12;
+
+impl<'a> ReadBinary<'a> for MorxTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let version = ctxt.read_u16be()?;
+        ctxt.check_version(version == 2 || version == 3)?;
+        let _unused = ctxt.read_u16be()?;
+        let n_chains = ctxt.read_u32be()?;
+        let chains = (0..n_chains)
+            .map(|_| {
+                let scope = ctxt.scope();
+                let _default_flags = ctxt.read_u32be()?;
+                let chain_length = usize::try_from(ctxt.read_u32be()?)?;
+                ctxt.check(chain_length >= CHAIN_HEADER_SIZE)?;
+                let chain = scope.offset_length(0, chain_length)?.read::<Chain<'a>>()?;
+                ctxt.read_scope(chain_length - 2 * size::U32)?;
+                Ok(chain)
+            })
+            .collect::<Result<Vec<_>, ParseError>>()?;
+        Ok(MorxTable { version, chains })
+    }
+}
+
+impl<'a> ReadBinary<'a> for Chain<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let default_flags = ctxt.read_u32be()?;
+        let _chain_length = ctxt.read_u32be()?;
+        let n_feature_entries = usize::try_from(ctxt.read_u32be()?)?;
+        let n_subtables = ctxt.read_u32be()?;
+        let features = ctxt.read_array::<Feature>(n_feature_entries)?;
+        let subtables = (0..n_subtables)
+            .map(|_| {
+                let scope = ctxt.scope();
+                let length = usize::try_from(ctxt.read_u32be()?)?;
+                ctxt.check(length >= SUBTABLE_HEADER_SIZE)?;
+                let subtable = scope.offset_length(0, length)?.read::<Subtable<'a>>()?;
+                ctxt.read_scope(length - size::U32)?;
+                Ok(subtable)
+            })
+            .collect::<Result<Vec<_>, ParseError>>()?;
+        // Version 3 tables follow the subtables with glyph coverage tables, which are an
+        // optimisation that isn't needed here.
+        Ok(Chain {
+            default_flags,
+            features,
+            subtables,
+        })
+    }
+}
+
+impl<'a> ReadFrom<'a> for Feature {
+    type ReadType = ((U16Be, U16Be), (U32Be, U32Be));
+    fn from(
+        ((feature_type, feature_setting), (enable_flags, disable_flags)): ((u16, u16), (u32, u32)),
+    ) -> Self {
+        Feature {
+            feature_type,
+            feature_setting,
+            enable_flags,
+            disable_flags,
+        }
+    }
+}
+
+impl<'a> ReadBinary<'a> for Subtable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let _length = ctxt.read_u32be()?;
+        let coverage = ctxt.read_u32be()?;
+        let sub_feature_flags = ctxt.read_u32be()?;
+        let scope = ctxt.scope();
+        let subtable = match (coverage & Subtable::TYPE_MASK) as u8 {
+            0 => SubtableType::Rearrangement(scope.read::<ExtendedStateTable<'a>>()?),
+            1 => {
+                let state_table = scope.read::<ExtendedStateTable<'a>>()?;
+                let substitution_table_offset = read_u32_at(&scope, 4)?;
+                SubtableType::Contextual(ContextualSubtable {
+                    state_table,
+                    substitution_table: scope.offset(usize::try_from(substitution_table_offset)?),
+                })
+            }
+            2 => {
+                let state_table = scope.read::<ExtendedStateTable<'a>>()?;
+                let lig_action_offset = usize::try_from(read_u32_at(&scope, 4)?)?;
+                let component_offset = usize::try_from(read_u32_at(&scope, 5)?)?;
+                let ligature_offset = usize::try_from(read_u32_at(&scope, 6)?)?;
+                SubtableType::Ligature(LigatureSubtable {
+                    state_table,
+                    lig_actions: scope.offset(lig_action_offset),
+                    components: scope.offset(component_offset),
+                    ligatures: scope.offset(ligature_offset),
+                })
+            }
+            4 => SubtableType::Noncontextual(scope.read::<LookupTable<'a>>()?),
+            5 => {
+                let state_table = scope.read::<ExtendedStateTable<'a>>()?;
+                let insertion_action_offset = usize::try_from(read_u32_at(&scope, 4)?)?;
+                SubtableType::Insertion(InsertionSubtable {
+                    state_table,
+                    insertion_actions: scope.offset(insertion_action_offset),
+                })
+            }
+            subtable_type => SubtableType::Unsupported(subtable_type),
+        };
+        Ok(Subtable {
+            coverage,
+            sub_feature_flags,
+            subtable,
+        })
+    }
+}
+
+impl<'a> Subtable<'a> {
+    /// Coverage flag set if the subtable only applies to vertical text.
+    pub const VERTICAL: u32 = 0x8000_0000;
+    /// Coverage flag set if the subtable processes glyphs in descending order.
+    pub const DESCENDING: u32 = 0x4000_0000;
+    /// Coverage flag set if the subtable applies to both horizontal and vertical text.
+    pub const ALL_DIRECTIONS: u32 = 0x2000_0000;
+    /// Coverage flag set if the processing order is relative to logical rather than layout
+    /// order.
+    pub const LOGICAL: u32 = 0x1000_0000;
+    /// Mask of the subtable type in the coverage field.
+    pub const TYPE_MASK: u32 = 0x0000_00FF;
+}
+
+impl<'a> Chain<'a> {
+    /// Calculate the subtable feature flags of the chain for the selected feature settings.
+    ///
+    /// `features` holds pairs of feature type and feature selector.
+    pub fn subtable_flags(&self, features: &[(u16, u16)]) -> u32 {
+        self.features
+            .iter()
+            .filter(|feature| features.contains(&(feature.feature_type, feature.feature_setting)))
+            .fold(self.default_flags, |flags, feature| {
+                (flags & feature.disable_flags) | feature.enable_flags
+            })
+    }
+}
+
+impl<'a> ContextualSubtable<'a> {
+    /// Look up the substitute for `glyph` in the substitution table at `index`.
+    pub fn substitute(&self, index: u16, glyph: u16) -> Result<Option<u16>, ParseError> {
+        // The offsets in the substitution table are relative to the substitution table itself
+        let offset = read_u32_at(&self.substitution_table, usize::from(index))?;
+        self.substitution_table
+            .offset(usize::try_from(offset)?)
+            .read::<LookupTable<'_>>()?
+            .lookup(glyph)
+    }
+}
+
+impl<'a> LigatureSubtable<'a> {
+    /// Read the ligature action at `index`.
+    pub fn lig_action(&self, index: usize) -> Result<u32, ParseError> {
+        read_u32_at(&self.lig_actions, index)
+    }
+
+    /// Read the component value at `index`.
+    pub fn component(&self, index: usize) -> Result<u16, ParseError> {
+        read_u16_at(&self.components, index)
+    }
+
+    /// Read the ligature glyph at `index`.
+    pub fn ligature(&self, index: usize) -> Result<u16, ParseError> {
+        read_u16_at(&self.ligatures, index)
+    }
+}
+
+impl<'a> InsertionSubtable<'a> {
+    /// Read the glyph at `index` in the insertion glyph table.
+    pub fn insertion_glyph(&self, index: usize) -> Result<u16, ParseError> {
+        read_u16_at(&self.insertion_actions, index)
+    }
+}
+
+impl<'a> ReadFrom<'a> for RearrangementEntry {
+    type ReadType = (U16Be, U16Be);
+    fn from((new_state, flags): (u16, u16)) -> Self {
+        RearrangementEntry { new_state, flags }
+    }
+}
+
+impl<'a> ReadFrom<'a> for ContextualEntry {
+    type ReadType = ((U16Be, U16Be), (U16Be, U16Be));
+    fn from(((new_state, flags), (mark_index, current_index)): ((u16, u16), (u16, u16))) -> Self {
+        ContextualEntry {
+            new_state,
+            flags,
+            mark_index,
+            current_index,
+        }
+    }
+}
+
+impl<'a> ReadFrom<'a> for LigatureEntry {
+    type ReadType = (U16Be, U16Be, U16Be);
+    fn from((new_state, flags, lig_action_index): (u16, u16, u16)) -> Self {
+        LigatureEntry {
+            new_state,
+            flags,
+            lig_action_index,
+        }
+    }
+}
+
+impl<'a> ReadFrom<'a> for InsertionEntry {
+    type ReadType = ((U16Be, U16Be), (U16Be, U16Be));
+    fn from(
+        ((new_state, flags), (current_insert_index, marked_insert_index)): ((u16, u16), (u16, u16)),
+    ) -> Self {
+        InsertionEntry {
+            new_state,
+            flags,
+            current_insert_index,
+            marked_insert_index,
+        }
+    }
+}
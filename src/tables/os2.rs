@@ -4,15 +4,20 @@
 //!
 //! — <https://docs.microsoft.com/en-us/typography/opentype/spec/os2>
 
-use std::convert::TryInto;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::convert::{TryFrom, TryInto};
 
 use crate::binary::read::{ReadBinaryDep, ReadCtxt};
+use crate::binary::write::{WriteBinary, WriteContext};
 use crate::binary::{I16Be, U16Be, U32Be};
-use crate::error::ParseError;
+use crate::error::{ParseError, WriteError};
+use crate::tables::HmtxTable;
 
 /// `OS/2` table
 ///
 /// <https://docs.microsoft.com/en-us/typography/opentype/spec/os2>
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Os2 {
     pub version: u16,
     pub x_avg_char_width: i16,
@@ -50,6 +55,7 @@ pub struct Os2 {
     pub version5: Option<Version5>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Version0 {
     pub s_typo_ascender: i16,
     pub s_typo_descender: i16,
@@ -58,11 +64,13 @@ pub struct Version0 {
     pub us_win_descent: u16,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Version1 {
     pub ul_code_page_range1: u32,
     pub ul_code_page_range2: u32,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Version2to4 {
     pub sx_height: i16,
     pub s_cap_height: i16,
@@ -71,6 +79,7 @@ pub struct Version2to4 {
     pub us_max_context: u16,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Version5 {
     pub us_lower_optical_point_size: u16,
     pub us_upper_optical_point_size: u16,
@@ -203,14 +212,405 @@ impl<'a> ReadBinaryDep<'a> for Os2 {
     }
 }
 
+impl WriteBinary<&Self> for Os2 {
+    type Output = ();
+
+    /// Write the table. The version specific fields required by `version` must be present.
+    /// The version 0 fields are optional for version 0 tables.
+    fn write<C: WriteContext>(ctxt: &mut C, table: &Os2) -> Result<(), WriteError> {
+        U16Be::write(ctxt, table.version)?;
+        I16Be::write(ctxt, table.x_avg_char_width)?;
+        U16Be::write(ctxt, table.us_weight_class)?;
+        U16Be::write(ctxt, table.us_width_class)?;
+        U16Be::write(ctxt, table.fs_type)?;
+        I16Be::write(ctxt, table.y_subscript_x_size)?;
+        I16Be::write(ctxt, table.y_subscript_y_size)?;
+        I16Be::write(ctxt, table.y_subscript_x_offset)?;
+        I16Be::write(ctxt, table.y_subscript_y_offset)?;
+        I16Be::write(ctxt, table.y_superscript_x_size)?;
+        I16Be::write(ctxt, table.y_superscript_y_size)?;
+        I16Be::write(ctxt, table.y_superscript_x_offset)?;
+        I16Be::write(ctxt, table.y_superscript_y_offset)?;
+        I16Be::write(ctxt, table.y_strikeout_size)?;
+        I16Be::write(ctxt, table.y_strikeout_position)?;
+        I16Be::write(ctxt, table.s_family_class)?;
+        ctxt.write_bytes(&table.panose)?;
+        U32Be::write(ctxt, table.ul_unicode_range1)?;
+        U32Be::write(ctxt, table.ul_unicode_range2)?;
+        U32Be::write(ctxt, table.ul_unicode_range3)?;
+        U32Be::write(ctxt, table.ul_unicode_range4)?;
+        U32Be::write(ctxt, table.ach_vend_id)?;
+        U16Be::write(ctxt, table.fs_selection)?;
+        U16Be::write(ctxt, table.us_first_char_index)?;
+        U16Be::write(ctxt, table.us_last_char_index)?;
+
+        match (table.version, &table.version0) {
+            (_, Some(version0)) => {
+                I16Be::write(ctxt, version0.s_typo_ascender)?;
+                I16Be::write(ctxt, version0.s_typo_descender)?;
+                I16Be::write(ctxt, version0.s_typo_line_gap)?;
+                U16Be::write(ctxt, version0.us_win_ascent)?;
+                U16Be::write(ctxt, version0.us_win_descent)?;
+            }
+            (0, None) => return Ok(()),
+            (_, None) => return Err(WriteError::BadValue),
+        }
+
+        if table.version >= 1 {
+            let version1 = table.version1.as_ref().ok_or(WriteError::BadValue)?;
+            U32Be::write(ctxt, version1.ul_code_page_range1)?;
+            U32Be::write(ctxt, version1.ul_code_page_range2)?;
+        }
+
+        if table.version >= 2 {
+            let version2to4 = table.version2to4.as_ref().ok_or(WriteError::BadValue)?;
+            I16Be::write(ctxt, version2to4.sx_height)?;
+            I16Be::write(ctxt, version2to4.s_cap_height)?;
+            U16Be::write(ctxt, version2to4.us_default_char)?;
+            U16Be::write(ctxt, version2to4.us_break_char)?;
+            U16Be::write(ctxt, version2to4.us_max_context)?;
+        }
+
+        if table.version >= 5 {
+            let version5 = table.version5.as_ref().ok_or(WriteError::BadValue)?;
+            U16Be::write(ctxt, version5.us_lower_optical_point_size)?;
+            U16Be::write(ctxt, version5.us_upper_optical_point_size)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Os2 {
+    /// Recompute `x_avg_char_width` from the advance widths in `hmtx`.
+    ///
+    /// This is the average of all non-zero advance widths, as specified for version 3 and
+    /// later. It is used for all versions, since the weighted average of lowercase letters
+    /// specified for earlier versions is rarely used in practice.
+    pub fn recompute_x_avg_char_width(
+        &mut self,
+        hmtx: &HmtxTable<'_>,
+        num_glyphs: u16,
+        num_h_metrics: u16,
+    ) -> Result<(), ParseError> {
+        let mut total = 0u64;
+        let mut count = 0u64;
+        for glyph_id in 0..num_glyphs {
+            let advance = hmtx.horizontal_advance(glyph_id, num_h_metrics)?;
+            if advance != 0 {
+                total += u64::from(advance);
+                count += 1;
+            }
+        }
+        self.x_avg_char_width = (total + count / 2)
+            .checked_div(count)
+            .map_or(0, |average| i16::try_from(average).unwrap_or(std::i16::MAX));
+        Ok(())
+    }
+
+    /// Recompute `us_first_char_index` and `us_last_char_index` from the code points mapped by
+    /// the font's `cmap`.
+    ///
+    /// Values above `0xFFFF` are set to `0xFFFF`.
+    pub fn recompute_char_indices(&mut self, code_points: &BTreeSet<u32>) {
+        let clamp = |code_point: u32| u16::try_from(code_point).unwrap_or(0xFFFF);
+        self.us_first_char_index = code_points.iter().next().map_or(0, |&ch| clamp(ch));
+        self.us_last_char_index = code_points.iter().next_back().map_or(0, |&ch| clamp(ch));
+    }
+
+    /// Recompute the `ul_unicode_range*` bits from the code points mapped by the font's `cmap`.
+    ///
+    /// A bit is set if any code point falls within one of its Unicode blocks. Bit 57 is set if
+    /// there are code points outside the Basic Multilingual Plane.
+    pub fn recompute_unicode_ranges(&mut self, code_points: &BTreeSet<u32>) {
+        let mut ranges = [0u32; 4];
+        let mut set_bit = |bit: u8| ranges[usize::from(bit / 32)] |= 1 << (bit % 32);
+        for &code_point in code_points {
+            if code_point > 0xFFFF {
+                set_bit(NON_PLANE_0_BIT);
+            }
+            let index = match UNICODE_RANGES.binary_search_by(|&(first, last, _)| {
+                if last < code_point {
+                    Ordering::Less
+                } else if first > code_point {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            }) {
+                Ok(index) => index,
+                Err(_) => continue,
+            };
+            set_bit(UNICODE_RANGES[index].2);
+        }
+        self.ul_unicode_range1 = ranges[0];
+        self.ul_unicode_range2 = ranges[1];
+        self.ul_unicode_range3 = ranges[2];
+        self.ul_unicode_range4 = ranges[3];
+    }
+
+    /// Recompute the `ul_code_page_range*` bits from the code points mapped by the font's
+    /// `cmap`.
+    ///
+    /// Code pages are detected by characteristic characters that they contain. If no code page
+    /// is detected the Latin 1 bit is set. Does nothing for version 0 tables, which don't have
+    /// code page ranges.
+    pub fn recompute_code_page_ranges(&mut self, code_points: &BTreeSet<u32>) {
+        if let Some(version1) = self.version1.as_mut() {
+            let code_pages = code_page_ranges(code_points);
+            version1.ul_code_page_range1 = code_pages as u32;
+            version1.ul_code_page_range2 = (code_pages >> 32) as u32;
+        }
+    }
+}
+
+/// Returns the code page bits for `code_points`, with `ul_code_page_range1` in the low 32 bits.
+fn code_page_ranges(code_points: &BTreeSet<u32>) -> u64 {
+    let has = |ch: char| code_points.contains(&u32::from(ch));
+    let has_ascii = (0x20..0x7E).all(|code_point| code_points.contains(&code_point));
+    let has_line_art = has('┤');
+    let has_radical = has('√');
+
+    let mut bits = 0u64;
+    for ch in code_points
+        .iter()
+        .filter_map(|&code_point| std::char::from_u32(code_point))
+    {
+        match ch {
+            'Þ' if has_ascii => bits |= 1 << 0, // Latin 1
+            'Ľ' if has_ascii => {
+                bits |= 1 << 1; // Latin 2: Eastern Europe
+                if has_line_art {
+                    bits |= 1 << 58; // Latin 2
+                }
+            }
+            'Б' => {
+                bits |= 1 << 2; // Cyrillic
+                if has('Ѕ') && has_line_art {
+                    bits |= 1 << 57; // IBM Cyrillic
+                }
+                if has('╜') && has_line_art {
+                    bits |= 1 << 49; // MS-DOS Russian
+                }
+            }
+            'Ά' => {
+                bits |= 1 << 3; // Greek
+                if has_line_art && has('½') {
+                    bits |= 1 << 48; // IBM Greek
+                }
+                if has_line_art && has_radical {
+                    bits |= 1 << 60; // Greek, former 437 G
+                }
+            }
+            'İ' if has_ascii => {
+                bits |= 1 << 4; // Turkish
+                if has_line_art {
+                    bits |= 1 << 56; // IBM Turkish
+                }
+            }
+            'א' => {
+                bits |= 1 << 5; // Hebrew
+                if has_line_art && has_radical {
+                    bits |= 1 << 53; // Hebrew
+                }
+            }
+            'ر' => {
+                bits |= 1 << 6; // Arabic
+                if has_radical {
+                    bits |= 1 << 51; // Arabic
+                }
+                if has_line_art {
+                    bits |= 1 << 61; // Arabic, ASMO 708
+                }
+            }
+            'ŗ' if has_ascii => {
+                bits |= 1 << 7; // Windows Baltic
+                if has_line_art {
+                    bits |= 1 << 59; // MS-DOS Baltic
+                }
+            }
+            '₫' if has_ascii => bits |= 1 << 8,  // Vietnamese
+            'ๅ' => bits |= 1 << 16,              // Thai
+            'エ' => bits |= 1 << 17,             // JIS/Japan
+            'ㄅ' => bits |= 1 << 18,             // Chinese: Simplified
+            'ㄱ' => bits |= 1 << 19,             // Korean Wansung
+            '央' => bits |= 1 << 20,             // Chinese: Traditional
+            '곴' => bits |= 1 << 21,             // Korean Johab
+            '♥' if has_ascii => bits |= 1 << 30, // OEM Character Set
+            'þ' if has_ascii && has_line_art => bits |= 1 << 54, // MS-DOS Icelandic
+            '╚' if has_ascii => {
+                bits |= 1 << 62; // WE/Latin 1
+                bits |= 1 << 63; // US
+            }
+            'Å' if has_ascii && has_line_art && has_radical => bits |= 1 << 50, // MS-DOS Nordic
+            // MS-DOS Canadian French
+            'é' if has_ascii && has_line_art && has_radical => bits |= 1 << 52,
+            'õ' if has_ascii && has_line_art && has_radical => bits |= 1 << 55, // MS-DOS Portuguese
+            _ => {}
+        }
+    }
+    if has_ascii && has('‰') && has('∑') {
+        bits |= 1 << 29; // Macintosh Character Set (US Roman)
+    }
+    if bits == 0 {
+        bits |= 1 << 0; // Latin 1
+    }
+    bits
+}
+
+/// Bit set in the `ul_unicode_range*` fields if the font has characters outside the Basic
+/// Multilingual Plane.
+const NON_PLANE_0_BIT: u8 = 57;
+
+/// Unicode ranges of the `ul_unicode_range` bits, sorted by first code point.
+///
+/// (first, last, bit)
+#[rustfmt::skip]
+const UNICODE_RANGES: [(u32, u32, u8); 169] = [
+    (0x0000, 0x007F, 0), (0x0080, 0x00FF, 1), (0x0100, 0x017F, 2), (0x0180, 0x024F, 3),
+    (0x0250, 0x02AF, 4), (0x02B0, 0x02FF, 5), (0x0300, 0x036F, 6), (0x0370, 0x03FF, 7),
+    (0x0400, 0x04FF, 9), (0x0500, 0x052F, 9), (0x0530, 0x058F, 10), (0x0590, 0x05FF, 11),
+    (0x0600, 0x06FF, 13), (0x0700, 0x074F, 71), (0x0750, 0x077F, 13), (0x0780, 0x07BF, 72),
+    (0x07C0, 0x07FF, 14), (0x0900, 0x097F, 15), (0x0980, 0x09FF, 16), (0x0A00, 0x0A7F, 17),
+    (0x0A80, 0x0AFF, 18), (0x0B00, 0x0B7F, 19), (0x0B80, 0x0BFF, 20), (0x0C00, 0x0C7F, 21),
+    (0x0C80, 0x0CFF, 22), (0x0D00, 0x0D7F, 23), (0x0D80, 0x0DFF, 73), (0x0E00, 0x0E7F, 24),
+    (0x0E80, 0x0EFF, 25), (0x0F00, 0x0FFF, 70), (0x1000, 0x109F, 74), (0x10A0, 0x10FF, 26),
+    (0x1100, 0x11FF, 28), (0x1200, 0x137F, 75), (0x1380, 0x139F, 75), (0x13A0, 0x13FF, 76),
+    (0x1400, 0x167F, 77), (0x1680, 0x169F, 78), (0x16A0, 0x16FF, 79), (0x1700, 0x171F, 84),
+    (0x1720, 0x173F, 84), (0x1740, 0x175F, 84), (0x1760, 0x177F, 84), (0x1780, 0x17FF, 80),
+    (0x1800, 0x18AF, 81), (0x1900, 0x194F, 93), (0x1950, 0x197F, 94), (0x1980, 0x19DF, 95),
+    (0x19E0, 0x19FF, 80), (0x1A00, 0x1A1F, 96), (0x1B00, 0x1B7F, 27), (0x1B80, 0x1BBF, 112),
+    (0x1C00, 0x1C4F, 113), (0x1C50, 0x1C7F, 114), (0x1D00, 0x1D7F, 4), (0x1D80, 0x1DBF, 4),
+    (0x1DC0, 0x1DFF, 6), (0x1E00, 0x1EFF, 29), (0x1F00, 0x1FFF, 30), (0x2000, 0x206F, 31),
+    (0x2070, 0x209F, 32), (0x20A0, 0x20CF, 33), (0x20D0, 0x20FF, 34), (0x2100, 0x214F, 35),
+    (0x2150, 0x218F, 36), (0x2190, 0x21FF, 37), (0x2200, 0x22FF, 38), (0x2300, 0x23FF, 39),
+    (0x2400, 0x243F, 40), (0x2440, 0x245F, 41), (0x2460, 0x24FF, 42), (0x2500, 0x257F, 43),
+    (0x2580, 0x259F, 44), (0x25A0, 0x25FF, 45), (0x2600, 0x26FF, 46), (0x2700, 0x27BF, 47),
+    (0x27C0, 0x27EF, 38), (0x27F0, 0x27FF, 37), (0x2800, 0x28FF, 82), (0x2900, 0x297F, 37),
+    (0x2980, 0x29FF, 38), (0x2A00, 0x2AFF, 38), (0x2B00, 0x2BFF, 37), (0x2C00, 0x2C5F, 97),
+    (0x2C60, 0x2C7F, 29), (0x2C80, 0x2CFF, 8), (0x2D00, 0x2D2F, 26), (0x2D30, 0x2D7F, 98),
+    (0x2D80, 0x2DDF, 75), (0x2DE0, 0x2DFF, 9), (0x2E00, 0x2E7F, 31), (0x2E80, 0x2EFF, 59),
+    (0x2F00, 0x2FDF, 59), (0x2FF0, 0x2FFF, 59), (0x3000, 0x303F, 48), (0x3040, 0x309F, 49),
+    (0x30A0, 0x30FF, 50), (0x3100, 0x312F, 51), (0x3130, 0x318F, 52), (0x3190, 0x319F, 59),
+    (0x31A0, 0x31BF, 51), (0x31C0, 0x31EF, 61), (0x31F0, 0x31FF, 50), (0x3200, 0x32FF, 54),
+    (0x3300, 0x33FF, 55), (0x3400, 0x4DBF, 59), (0x4DC0, 0x4DFF, 99), (0x4E00, 0x9FFF, 59),
+    (0xA000, 0xA48F, 83), (0xA490, 0xA4CF, 83), (0xA500, 0xA63F, 12), (0xA640, 0xA69F, 9),
+    (0xA700, 0xA71F, 5), (0xA720, 0xA7FF, 29), (0xA800, 0xA82F, 100), (0xA840, 0xA87F, 53),
+    (0xA880, 0xA8DF, 115), (0xA900, 0xA92F, 116), (0xA930, 0xA95F, 117), (0xAA00, 0xAA5F, 118),
+    (0xAC00, 0xD7AF, 56), (0xD800, 0xDFFF, 57), (0xE000, 0xF8FF, 60), (0xF900, 0xFAFF, 61),
+    (0xFB00, 0xFB4F, 62), (0xFB50, 0xFDFF, 63), (0xFE00, 0xFE0F, 91), (0xFE10, 0xFE1F, 65),
+    (0xFE20, 0xFE2F, 64), (0xFE30, 0xFE4F, 65), (0xFE50, 0xFE6F, 66), (0xFE70, 0xFEFF, 67),
+    (0xFF00, 0xFFEF, 68), (0xFFF0, 0xFFFF, 69), (0x10000, 0x1007F, 101), (0x10080, 0x100FF, 101),
+    (0x10100, 0x1013F, 101), (0x10140, 0x1018F, 102), (0x10190, 0x101CF, 119),
+    (0x101D0, 0x101FF, 120), (0x10280, 0x1029F, 121), (0x102A0, 0x102DF, 121),
+    (0x10300, 0x1032F, 85), (0x10330, 0x1034F, 86), (0x10380, 0x1039F, 103),
+    (0x103A0, 0x103DF, 104), (0x10400, 0x1044F, 87), (0x10450, 0x1047F, 105),
+    (0x10480, 0x104AF, 106), (0x10800, 0x1083F, 107), (0x10900, 0x1091F, 58),
+    (0x10920, 0x1093F, 121), (0x10A00, 0x10A5F, 108), (0x12000, 0x123FF, 110),
+    (0x12400, 0x1247F, 110), (0x1D000, 0x1D0FF, 88), (0x1D100, 0x1D1FF, 88), (0x1D200, 0x1D24F, 88),
+    (0x1D300, 0x1D35F, 109), (0x1D360, 0x1D37F, 111), (0x1D400, 0x1D7FF, 89),
+    (0x1F000, 0x1F02F, 122), (0x1F030, 0x1F09F, 122), (0x20000, 0x2A6DF, 59),
+    (0x2F800, 0x2FA1F, 61), (0xE0000, 0xE007F, 92), (0xE0100, 0xE01EF, 91), (0xF0000, 0xFFFFD, 90),
+    (0x100000, 0x10FFFD, 90),
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::binary::read::ReadArrayCow;
     use crate::binary::read::ReadScope;
+    use crate::binary::write::WriteBuffer;
+    use crate::tables::LongHorMetric;
     use crate::tables::{FontTableProvider, OpenTypeFile};
     use crate::tag;
     use crate::tests::read_fixture;
 
+    fn read_os2_data(path: &str) -> Vec<u8> {
+        let buffer = read_fixture(path);
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        provider.read_table_data(tag::OS_2).unwrap().into_owned()
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        for path in &[
+            "tests/fonts/opentype/test-font.ttf",
+            "tests/fonts/opentype/Klei.otf",
+        ] {
+            let os_2_data = read_os2_data(path);
+            let os_2 = ReadScope::new(&os_2_data)
+                .read_dep::<Os2>(os_2_data.len())
+                .unwrap();
+
+            let mut buffer = WriteBuffer::new();
+            Os2::write(&mut buffer, &os_2).unwrap();
+            assert_eq!(buffer.bytes(), &os_2_data[..]);
+        }
+    }
+
+    #[test]
+    fn test_write_missing_version_fields() {
+        let os_2_data = read_os2_data("tests/fonts/opentype/Klei.otf");
+        let mut os_2 = ReadScope::new(&os_2_data)
+            .read_dep::<Os2>(os_2_data.len())
+            .unwrap();
+        os_2.version1 = None;
+
+        let mut buffer = WriteBuffer::new();
+        match Os2::write(&mut buffer, &os_2) {
+            Err(WriteError::BadValue) => {}
+            result => panic!("expected WriteError::BadValue, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_recompute() {
+        let os_2_data = read_os2_data("tests/fonts/opentype/Klei.otf");
+        let mut os_2 = ReadScope::new(&os_2_data)
+            .read_dep::<Os2>(os_2_data.len())
+            .unwrap();
+
+        let mut code_points = (0x20..=0x7E).collect::<BTreeSet<u32>>();
+        code_points.insert(u32::from('Þ'));
+        code_points.insert(0x1F600);
+        os_2.recompute_char_indices(&code_points);
+        os_2.recompute_unicode_ranges(&code_points);
+        os_2.recompute_code_page_ranges(&code_points);
+
+        assert_eq!(os_2.us_first_char_index, 0x20);
+        assert_eq!(os_2.us_last_char_index, 0xFFFF);
+        // Basic Latin, Latin-1 Supplement and non-plane 0
+        assert_eq!(os_2.ul_unicode_range1, 0b11);
+        assert_eq!(os_2.ul_unicode_range2, 1 << (57 - 32));
+        assert_eq!(os_2.ul_unicode_range3, 0);
+        assert_eq!(os_2.ul_unicode_range4, 0);
+        // Latin 1
+        let version1 = os_2.version1.as_ref().unwrap();
+        assert_eq!(version1.ul_code_page_range1, 1);
+        assert_eq!(version1.ul_code_page_range2, 0);
+
+        let hmtx = HmtxTable {
+            h_metrics: ReadArrayCow::Owned(vec![
+                LongHorMetric {
+                    advance_width: 0,
+                    lsb: 0,
+                },
+                LongHorMetric {
+                    advance_width: 500,
+                    lsb: 0,
+                },
+                LongHorMetric {
+                    advance_width: 601,
+                    lsb: 0,
+                },
+            ]),
+            left_side_bearings: ReadArrayCow::Owned(vec![0]),
+        };
+        os_2.recompute_x_avg_char_width(&hmtx, 4, 3).unwrap();
+        assert_eq!(os_2.x_avg_char_width, 567);
+    }
+
     #[test]
     #[cfg(feature = "prince")]
     fn test_read() {
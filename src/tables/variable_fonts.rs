@@ -0,0 +1,34 @@
+//! Parsing of the tables that make up OpenType Font Variations.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/otvaroverview>
+
+pub mod avar;
+pub mod fvar;
+pub mod gvar;
+pub mod hvar;
+pub mod item_variation_store;
+pub mod mvar;
+pub mod stat;
+
+/// Convert a 16.16 fixed-point value to `f32`.
+pub(crate) fn fixed_to_f32(value: i32) -> f32 {
+    value as f32 / 65536.0
+}
+
+/// Calculate the scalar contribution of a single axis of a region at `coord`.
+///
+/// `start`, `peak` and `end` are the normalized coordinates of the region on the axis. Invalid
+/// regions and regions with a peak of zero do not constrain the axis and yield `1.0`.
+pub(crate) fn axis_scalar(start: f32, peak: f32, end: f32, coord: f32) -> f32 {
+    if peak == 0. || start > peak || peak > end || (start < 0. && end > 0.) {
+        1.
+    } else if coord < start || coord > end {
+        0.
+    } else if coord == peak {
+        1.
+    } else if coord < peak {
+        (coord - start) / (peak - start)
+    } else {
+        (end - coord) / (end - peak)
+    }
+}
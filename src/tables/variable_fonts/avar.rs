@@ -0,0 +1,133 @@
+#![deny(missing_docs)]
+
+//! `avar` Axis Variations Table parsing.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/avar>
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt, ReadFrom};
+use crate::binary::U16Be;
+use crate::error::ParseError;
+use crate::tables::F2Dot14;
+
+/// `avar` Axis Variations Table.
+///
+/// Modifies the mapping of user coordinates to normalized coordinates for each axis.
+pub struct AvarTable<'a> {
+    /// The segment maps, one per axis in `fvar` axis order.
+    pub segment_maps: Vec<SegmentMap<'a>>,
+}
+
+/// A piecewise linear mapping of normalized coordinates for one axis.
+pub struct SegmentMap<'a> {
+    /// The mapping pairs, in increasing `from_coordinate` order.
+    pub axis_value_maps: ReadArray<'a, AxisValueMap>,
+}
+
+/// A single mapping from a default normalized coordinate to a modified one.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AxisValueMap {
+    /// A normalized coordinate value obtained using default normalization.
+    pub from_coordinate: F2Dot14,
+    /// The modified, normalized coordinate value.
+    pub to_coordinate: F2Dot14,
+}
+
+impl<'a> SegmentMap<'a> {
+    /// Apply this mapping to a normalized coordinate.
+    ///
+    /// Values between two mapping pairs are linearly interpolated. Values outside of the pairs
+    /// are offset by the nearest pair.
+    pub fn map(&self, value: f32) -> f32 {
+        let mut prev: Option<(f32, f32)> = None;
+        for map in self.axis_value_maps.iter() {
+            let from = f32::from(map.from_coordinate);
+            let to = f32::from(map.to_coordinate);
+            if value == from {
+                return to;
+            } else if value < from {
+                return match prev {
+                    Some((prev_from, prev_to)) => {
+                        prev_to + (to - prev_to) * (value - prev_from) / (from - prev_from)
+                    }
+                    None => value + to - from,
+                };
+            }
+            prev = Some((from, to));
+        }
+
+        match prev {
+            Some((from, to)) => value + to - from,
+            None => value,
+        }
+    }
+}
+
+impl<'a> ReadBinary<'a> for AvarTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let major_version = ctxt.read_u16be()?;
+        ctxt.check_version(major_version == 1)?;
+        let _minor_version = ctxt.read_u16be()?;
+        let _reserved = ctxt.read_u16be()?;
+        let axis_count = usize::from(ctxt.read_u16be()?);
+        let segment_maps = (0..axis_count)
+            .map(|_| ctxt.read::<SegmentMap<'_>>())
+            .collect::<Result<_, _>>()?;
+
+        Ok(AvarTable { segment_maps })
+    }
+}
+
+impl<'a> ReadBinary<'a> for SegmentMap<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let position_map_count = usize::from(ctxt.read_u16be()?);
+        let axis_value_maps = ctxt.read_array::<AxisValueMap>(position_map_count)?;
+
+        Ok(SegmentMap { axis_value_maps })
+    }
+}
+
+impl<'a> ReadFrom<'a> for AxisValueMap {
+    type ReadType = (U16Be, U16Be);
+
+    fn from((from_coordinate, to_coordinate): (u16, u16)) -> Self {
+        AxisValueMap {
+            from_coordinate: F2Dot14::new(from_coordinate),
+            to_coordinate: F2Dot14::new(to_coordinate),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+
+    #[test]
+    fn test_segment_map() {
+        // -1 → -1, 0 → 0, 0.5 → 0.75, 1 → 1
+        let data = [
+            0, 1, 0, 0, // version 1.0
+            0, 0, // reserved
+            0, 1, // axisCount
+            0, 4, // positionMapCount
+            0xC0, 0x00, 0xC0, 0x00, // -1.0, -1.0
+            0x00, 0x00, 0x00, 0x00, // 0.0, 0.0
+            0x20, 0x00, 0x30, 0x00, // 0.5, 0.75
+            0x40, 0x00, 0x40, 0x00, // 1.0, 1.0
+        ];
+        let avar = ReadScope::new(&data).read::<AvarTable<'_>>().unwrap();
+        let map = &avar.segment_maps[0];
+
+        assert_eq!(map.map(-1.), -1.);
+        assert_eq!(map.map(-0.5), -0.5);
+        assert_eq!(map.map(0.), 0.);
+        assert_eq!(map.map(0.25), 0.375);
+        assert_eq!(map.map(0.5), 0.75);
+        assert_eq!(map.map(0.75), 0.875);
+        assert_eq!(map.map(1.), 1.);
+    }
+}
@@ -0,0 +1,342 @@
+#![deny(missing_docs)]
+
+//! `fvar` Font Variations Table parsing.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/fvar>
+
+use crate::binary::read::{
+    ReadArray, ReadBinary, ReadBinaryDep, ReadCtxt, ReadFixedSizeDep, ReadFrom,
+};
+use crate::binary::{I32Be, U16Be, U32Be};
+use crate::error::ParseError;
+use crate::size;
+use crate::tables::variable_fonts::avar::AvarTable;
+use crate::tables::variable_fonts::fixed_to_f32;
+use crate::tables::F2Dot14;
+
+/// `fvar` Font Variations Table.
+///
+/// Describes the axes of variation of a font and its named instances.
+pub struct FvarTable<'a> {
+    /// The variation axes of the font, in the order used by all other variation tables.
+    pub axes: ReadArray<'a, VariationAxisRecord>,
+    /// The named instances of the font.
+    pub instances: ReadArray<'a, InstanceRecord>,
+}
+
+/// A single axis of variation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VariationAxisRecord {
+    /// Tag identifying the design variation for the axis, e.g. `wght`.
+    pub axis_tag: u32,
+    /// The minimum coordinate value for the axis.
+    pub min_value: f32,
+    /// The default coordinate value for the axis.
+    pub default_value: f32,
+    /// The maximum coordinate value for the axis.
+    pub max_value: f32,
+    /// Axis qualifiers.
+    pub flags: u16,
+    /// The name ID for entries in the `name` table that provide a display name for this axis.
+    pub axis_name_id: u16,
+}
+
+/// A named instance: a set of user coordinates with a name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceRecord {
+    /// The name ID for entries in the `name` table that provide subfamily names for this
+    /// instance.
+    pub subfamily_name_id: u16,
+    /// Reserved for future use, set to 0.
+    pub flags: u16,
+    /// The user coordinates of this instance, one per axis.
+    pub coordinates: Vec<f32>,
+    /// The name ID for entries in the `name` table that provide PostScript names for this
+    /// instance, if present.
+    pub post_script_name_id: Option<u16>,
+}
+
+impl<'a> FvarTable<'a> {
+    /// The number of variation axes in the font.
+    pub fn axis_count(&self) -> usize {
+        self.axes.len()
+    }
+
+    /// Convert user coordinates to normalized coordinates.
+    ///
+    /// `user_coordinates` holds one value per axis in the order of `axes`. Axes without a value
+    /// use their default. Values are clamped to the range of their axis. If the `avar` table is
+    /// supplied its segment maps are applied to the result.
+    pub fn normalize(
+        &self,
+        user_coordinates: &[f32],
+        opt_avar: Option<&AvarTable<'_>>,
+    ) -> Result<Vec<F2Dot14>, ParseError> {
+        if user_coordinates.len() > self.axis_count() {
+            return Err(ParseError::BadValue);
+        }
+        if let Some(avar) = opt_avar {
+            if avar.segment_maps.len() != self.axis_count() {
+                return Err(ParseError::BadValue);
+            }
+        }
+
+        self.axes
+            .iter()
+            .enumerate()
+            .map(|(i, axis)| {
+                let user_value = user_coordinates
+                    .get(i)
+                    .cloned()
+                    .unwrap_or(axis.default_value);
+                let mut value = axis.normalize(user_value);
+                if let Some(avar) = opt_avar {
+                    value = avar.segment_maps[i].map(value);
+                }
+                Ok(F2Dot14::from_f32(value))
+            })
+            .collect()
+    }
+}
+
+impl VariationAxisRecord {
+    /// Map a user coordinate on this axis to the default normalized range of -1 to 1.
+    ///
+    /// This does not take `avar` into account.
+    pub fn normalize(&self, user_value: f32) -> f32 {
+        let value = user_value.max(self.min_value).min(self.max_value);
+        if value < self.default_value {
+            -(self.default_value - value) / (self.default_value - self.min_value)
+        } else if value > self.default_value {
+            (value - self.default_value) / (self.max_value - self.default_value)
+        } else {
+            0.
+        }
+    }
+}
+
+impl<'a> ReadBinary<'a> for FvarTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let major_version = ctxt.read_u16be()?;
+        ctxt.check_version(major_version == 1)?;
+        let _minor_version = ctxt.read_u16be()?;
+        let axes_array_offset = usize::from(ctxt.read_u16be()?);
+        let _reserved = ctxt.read_u16be()?;
+        let axis_count = usize::from(ctxt.read_u16be()?);
+        let axis_size = usize::from(ctxt.read_u16be()?);
+        ctxt.check(axis_size == VariationAxisRecord::SIZE)?;
+        let instance_count = usize::from(ctxt.read_u16be()?);
+        let instance_size = usize::from(ctxt.read_u16be()?);
+
+        let mut axes_ctxt = scope.offset(axes_array_offset).ctxt();
+        let axes = axes_ctxt.read_array::<VariationAxisRecord>(axis_count)?;
+        let has_post_script_name_id = if instance_size == InstanceRecord::size((axis_count, true)) {
+            true
+        } else if instance_size == InstanceRecord::size((axis_count, false)) {
+            false
+        } else {
+            return Err(ParseError::BadValue);
+        };
+        let instances = axes_ctxt.read_array_dep::<InstanceRecord>(
+            instance_count,
+            (axis_count, has_post_script_name_id),
+        )?;
+
+        Ok(FvarTable { axes, instances })
+    }
+}
+
+impl<'a> ReadFrom<'a> for VariationAxisRecord {
+    type ReadType = ((U32Be, I32Be, I32Be), (I32Be, U16Be, U16Be));
+
+    fn from(
+        ((axis_tag, min_value, default_value), (max_value, flags, axis_name_id)): (
+            (u32, i32, i32),
+            (i32, u16, u16),
+        ),
+    ) -> Self {
+        VariationAxisRecord {
+            axis_tag,
+            min_value: fixed_to_f32(min_value),
+            default_value: fixed_to_f32(default_value),
+            max_value: fixed_to_f32(max_value),
+            flags,
+            axis_name_id,
+        }
+    }
+}
+
+impl<'a> ReadBinaryDep<'a> for InstanceRecord {
+    type Args = (usize, bool);
+    type HostType = Self;
+
+    fn read_dep(
+        ctxt: &mut ReadCtxt<'a>,
+        (axis_count, has_post_script_name_id): (usize, bool),
+    ) -> Result<Self, ParseError> {
+        let subfamily_name_id = ctxt.read_u16be()?;
+        let flags = ctxt.read_u16be()?;
+        let coordinates = ctxt
+            .read_array::<I32Be>(axis_count)?
+            .iter()
+            .map(fixed_to_f32)
+            .collect();
+        let post_script_name_id = if has_post_script_name_id {
+            Some(ctxt.read_u16be()?)
+        } else {
+            None
+        };
+
+        Ok(InstanceRecord {
+            subfamily_name_id,
+            flags,
+            coordinates,
+            post_script_name_id,
+        })
+    }
+}
+
+impl<'a> ReadFixedSizeDep<'a> for InstanceRecord {
+    fn size((axis_count, has_post_script_name_id): (usize, bool)) -> usize {
+        // uint16          subfamilyNameID
+        // uint16          flags
+        // UserTuple       coordinates
+        // uint16          postScriptNameID (optional)
+        // — https://docs.microsoft.com/en-us/typography/opentype/spec/fvar#instancerecord
+        let post_script_name_id_size = if has_post_script_name_id {
+            size::U16
+        } else {
+            0
+        };
+        (2 * size::U16) + (axis_count * size::I32) + post_script_name_id_size
+    }
+}
+
+impl VariationAxisRecord {
+    // Tag, three Fixed values, then flags and name ID
+    const SIZE: usize = size::U32 + (3 * size::I32) + (2 * size::U16);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::tag;
+    use crate::tests::{push_u16, push_u32};
+
+    // An fvar table with wght (100–900, default 400) and wdth (75–100, default 100) axes and
+    // two named instances, the second of which has a PostScript name ID.
+    fn fvar_data(instance_size: u16) -> Vec<u8> {
+        let mut data = Vec::new();
+        // version 1.0
+        push_u32(&mut data, &[0x00010000]);
+        // axesArrayOffset, reserved, axisCount, axisSize, instanceCount, instanceSize
+        push_u16(&mut data, &[16, 2, 2, 20, 2, instance_size]);
+        // axisTag, minValue, defaultValue, maxValue, then flags and axisNameID
+        push_u32(&mut data, &[tag::WGHT, 100 << 16, 400 << 16, 900 << 16]);
+        push_u16(&mut data, &[0, 256]);
+        push_u32(&mut data, &[tag::WDTH, 75 << 16, 100 << 16, 100 << 16]);
+        push_u16(&mut data, &[0, 257]);
+        for &(subfamily, weight, width, post_script) in
+            &[(258u16, 700u32, 100u32, 260u16), (259, 400, 75, 261)]
+        {
+            // subfamilyNameID and flags, coordinates, then postScriptNameID
+            push_u16(&mut data, &[subfamily, 0]);
+            push_u32(&mut data, &[weight << 16, width << 16]);
+            if instance_size == 14 {
+                push_u16(&mut data, &[post_script]);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_read_fvar() {
+        let data = fvar_data(14);
+        let fvar = ReadScope::new(&data).read::<FvarTable<'_>>().unwrap();
+
+        let axes = fvar.axes.iter().collect::<Vec<_>>();
+        assert_eq!(
+            axes[0],
+            VariationAxisRecord {
+                axis_tag: tag::WGHT,
+                min_value: 100.,
+                default_value: 400.,
+                max_value: 900.,
+                flags: 0,
+                axis_name_id: 256,
+            }
+        );
+        assert_eq!(axes[1].axis_tag, tag::WDTH);
+
+        let instances = fvar.instances.read_to_vec().unwrap();
+        assert_eq!(
+            instances[0],
+            InstanceRecord {
+                subfamily_name_id: 258,
+                flags: 0,
+                coordinates: vec![700., 100.],
+                post_script_name_id: Some(260),
+            }
+        );
+        assert_eq!(instances[1].coordinates, vec![400., 75.]);
+    }
+
+    #[test]
+    fn test_read_fvar_without_post_script_name_ids() {
+        let data = fvar_data(12);
+        let fvar = ReadScope::new(&data).read::<FvarTable<'_>>().unwrap();
+        let instances = fvar.instances.read_to_vec().unwrap();
+        assert_eq!(instances[1].post_script_name_id, None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let data = fvar_data(14);
+        let fvar = ReadScope::new(&data).read::<FvarTable<'_>>().unwrap();
+
+        let normalized = |coords: &[f32]| {
+            fvar.normalize(coords, None)
+                .unwrap()
+                .into_iter()
+                .map(f32::from)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(normalized(&[]), vec![0., 0.]);
+        assert_eq!(normalized(&[900., 75.]), vec![1., -1.]);
+        assert_eq!(normalized(&[650., 87.5]), vec![0.5, -0.5]);
+        assert_eq!(normalized(&[250.]), vec![-0.5, 0.]);
+        // Out of range values are clamped
+        assert_eq!(normalized(&[1000., 50.]), vec![1., -1.]);
+        assert!(fvar.normalize(&[400., 100., 1.], None).is_err());
+    }
+
+    #[test]
+    fn test_normalize_with_avar() {
+        let data = fvar_data(14);
+        let fvar = ReadScope::new(&data).read::<FvarTable<'_>>().unwrap();
+        let avar_data = [
+            0, 1, 0, 0, // version 1.0
+            0, 0, // reserved
+            0, 2, // axisCount
+            0, 4, // wght positionMapCount
+            0xC0, 0x00, 0xC0, 0x00, // -1.0, -1.0
+            0x00, 0x00, 0x00, 0x00, // 0.0, 0.0
+            0x20, 0x00, 0x30, 0x00, // 0.5, 0.75
+            0x40, 0x00, 0x40, 0x00, // 1.0, 1.0
+            0, 0, // wdth positionMapCount
+        ];
+        let avar = ReadScope::new(&avar_data).read::<AvarTable<'_>>().unwrap();
+
+        let normalized = fvar
+            .normalize(&[650., 87.5], Some(&avar))
+            .unwrap()
+            .into_iter()
+            .map(f32::from)
+            .collect::<Vec<_>>();
+        assert_eq!(normalized, vec![0.75, -0.5]);
+    }
+}
@@ -0,0 +1,651 @@
+#![deny(missing_docs)]
+
+//! `gvar` Glyph Variations Table parsing and application.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/gvar>
+
+use std::convert::TryFrom;
+use std::iter;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadBinaryDep, ReadCtxt, ReadScope};
+use crate::binary::{U16Be, U32Be};
+use crate::error::ParseError;
+use crate::tables::glyf::{
+    CompositeGlyph, CompositeGlyphArgument, CompositeGlyphFlag, Glyph, GlyphData, Point,
+};
+use crate::tables::variable_fonts::axis_scalar;
+use crate::tables::F2Dot14;
+
+const SHARED_POINT_NUMBERS: u16 = 0x8000;
+const COUNT_MASK: u16 = 0x0FFF;
+const EMBEDDED_PEAK_TUPLE: u16 = 0x8000;
+const INTERMEDIATE_REGION: u16 = 0x4000;
+const PRIVATE_POINT_NUMBERS: u16 = 0x2000;
+const TUPLE_INDEX_MASK: u16 = 0x0FFF;
+const POINTS_ARE_WORDS: u8 = 0x80;
+const POINT_RUN_COUNT_MASK: u8 = 0x7F;
+const DELTAS_ARE_ZERO: u8 = 0x80;
+const DELTAS_ARE_WORDS: u8 = 0x40;
+const DELTA_RUN_COUNT_MASK: u8 = 0x3F;
+
+/// The number of phantom points that follow the outline points of every glyph.
+pub const NUM_PHANTOM_POINTS: usize = 4;
+
+/// `gvar` Glyph Variations Table.
+pub struct GvarTable<'a> {
+    /// The number of variation axes. Must match the axis count in `fvar`.
+    pub axis_count: u16,
+    /// The number of glyphs with variation data. Must match the number of glyphs in `glyf`.
+    pub glyph_count: u16,
+    shared_tuples: ReadArray<'a, F2Dot14>,
+    glyph_variation_data_offsets: GlyphVariationDataOffsets<'a>,
+    glyph_variation_data_array: ReadScope<'a>,
+}
+
+enum GlyphVariationDataOffsets<'a> {
+    Short(ReadArray<'a, U16Be>),
+    Long(ReadArray<'a, U32Be>),
+}
+
+/// The variation data of a single glyph.
+pub struct GlyphVariationData {
+    /// The tuple variations that make up the variation data.
+    pub tuple_variations: Vec<TupleVariation>,
+}
+
+/// A set of point deltas that apply to one region of the variation space.
+pub struct TupleVariation {
+    /// The peak of the region, one normalized coordinate per axis.
+    pub peak_tuple: Vec<F2Dot14>,
+    /// The start and end of the region if it is an intermediate region.
+    pub intermediate_region: Option<(Vec<F2Dot14>, Vec<F2Dot14>)>,
+    /// The points the deltas apply to. `None` means all points, including phantom points.
+    pub point_numbers: Option<Vec<u16>>,
+    /// The x deltas, one per entry of `point_numbers`, or one per point.
+    pub x_deltas: Vec<i16>,
+    /// The y deltas, one per entry of `point_numbers`, or one per point.
+    pub y_deltas: Vec<i16>,
+}
+
+struct TupleVariationHeader {
+    variation_data_size: u16,
+    tuple_index: u16,
+    peak_tuple: Option<Vec<F2Dot14>>,
+    intermediate_region: Option<(Vec<F2Dot14>, Vec<F2Dot14>)>,
+}
+
+impl<'a> ReadBinary<'a> for GvarTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let major_version = ctxt.read_u16be()?;
+        ctxt.check_version(major_version == 1)?;
+        let _minor_version = ctxt.read_u16be()?;
+        let axis_count = ctxt.read_u16be()?;
+        let shared_tuple_count = ctxt.read_u16be()?;
+        let shared_tuples_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let glyph_count = ctxt.read_u16be()?;
+        let flags = ctxt.read_u16be()?;
+        let glyph_variation_data_array_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let num_offsets = usize::from(glyph_count) + 1;
+        let glyph_variation_data_offsets = if flags & 1 == 1 {
+            GlyphVariationDataOffsets::Long(ctxt.read_array::<U32Be>(num_offsets)?)
+        } else {
+            GlyphVariationDataOffsets::Short(ctxt.read_array::<U16Be>(num_offsets)?)
+        };
+        let shared_tuples = scope
+            .offset(shared_tuples_offset)
+            .ctxt()
+            .read_array::<F2Dot14>(usize::from(shared_tuple_count) * usize::from(axis_count))?;
+        let glyph_variation_data_array = scope.offset(glyph_variation_data_array_offset);
+
+        Ok(GvarTable {
+            axis_count,
+            glyph_count,
+            shared_tuples,
+            glyph_variation_data_offsets,
+            glyph_variation_data_array,
+        })
+    }
+}
+
+impl<'a> GvarTable<'a> {
+    /// Read the variation data for `glyph_id`.
+    ///
+    /// `num_points` is the number of points in the glyph, including the phantom points. For
+    /// composite glyphs each component counts as one point. Returns `None` if the glyph has no
+    /// variation data.
+    pub fn glyph_variation_data(
+        &self,
+        glyph_id: u16,
+        num_points: usize,
+    ) -> Result<Option<GlyphVariationData>, ParseError> {
+        if glyph_id >= self.glyph_count {
+            return Err(ParseError::BadIndex);
+        }
+        let index = usize::from(glyph_id);
+        let (start, end) = match &self.glyph_variation_data_offsets {
+            GlyphVariationDataOffsets::Short(offsets) => (
+                usize::from(offsets.get_item(index)) * 2,
+                usize::from(offsets.get_item(index + 1)) * 2,
+            ),
+            GlyphVariationDataOffsets::Long(offsets) => (
+                usize::try_from(offsets.get_item(index))?,
+                usize::try_from(offsets.get_item(index + 1))?,
+            ),
+        };
+        if end < start {
+            return Err(ParseError::BadOffset);
+        } else if end == start {
+            return Ok(None);
+        }
+
+        self.glyph_variation_data_array
+            .offset_length(start, end - start)?
+            .read_dep::<GlyphVariationData>((
+                usize::from(self.axis_count),
+                self.shared_tuples.clone(),
+                num_points,
+            ))
+            .map(Some)
+    }
+
+    /// Apply the variations of `glyph_id` at the normalized `coords` to `glyph`.
+    ///
+    /// Simple glyphs have their points moved and bounding box recalculated. Composite glyphs
+    /// have the offsets of components positioned by x and y values moved. A glyph without
+    /// variation data is returned unchanged.
+    pub fn apply_to_glyph<'b>(
+        &self,
+        glyph_id: u16,
+        glyph: &Glyph<'b>,
+        coords: &[F2Dot14],
+    ) -> Result<Glyph<'b>, ParseError> {
+        let mut glyph = glyph.clone();
+        match glyph.data {
+            GlyphData::Simple(ref mut simple_glyph) => {
+                let num_points = simple_glyph.coordinates.len() + NUM_PHANTOM_POINTS;
+                let variation_data = match self.glyph_variation_data(glyph_id, num_points)? {
+                    Some(variation_data) => variation_data,
+                    None => return Ok(glyph),
+                };
+                let deltas = variation_data.point_deltas(
+                    coords,
+                    num_points,
+                    Some((&simple_glyph.coordinates, &simple_glyph.end_pts_of_contours)),
+                );
+                for (point, &(dx, dy)) in simple_glyph.coordinates.iter_mut().zip(deltas.iter()) {
+                    *point = Point(apply_delta(point.0, dx), apply_delta(point.1, dy));
+                }
+                if !simple_glyph.coordinates.is_empty() {
+                    glyph.bounding_box = simple_glyph.bounding_box();
+                }
+            }
+            GlyphData::Composite { ref mut glyphs, .. } => {
+                let num_points = glyphs.len() + NUM_PHANTOM_POINTS;
+                let variation_data = match self.glyph_variation_data(glyph_id, num_points)? {
+                    Some(variation_data) => variation_data,
+                    None => return Ok(glyph),
+                };
+                let deltas = variation_data.point_deltas(coords, num_points, None);
+                for (component, &(dx, dy)) in glyphs.iter_mut().zip(deltas.iter()) {
+                    if component.flags.args_are_xy_values() {
+                        apply_component_delta(component, dx, dy);
+                    }
+                }
+            }
+        }
+
+        Ok(glyph)
+    }
+}
+
+impl<'a> ReadBinaryDep<'a> for GlyphVariationData {
+    type Args = (usize, ReadArray<'a, F2Dot14>, usize);
+    type HostType = Self;
+
+    fn read_dep(
+        ctxt: &mut ReadCtxt<'a>,
+        (axis_count, shared_tuples, num_points): (usize, ReadArray<'a, F2Dot14>, usize),
+    ) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let tuple_variation_count = ctxt.read_u16be()?;
+        let data_offset = usize::from(ctxt.read_u16be()?);
+        let headers = (0..(tuple_variation_count & COUNT_MASK))
+            .map(|_| ctxt.read_dep::<TupleVariationHeader>(axis_count))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut data_ctxt = scope.offset(data_offset).ctxt();
+        let shared_point_numbers = if tuple_variation_count & SHARED_POINT_NUMBERS != 0 {
+            read_packed_point_numbers(&mut data_ctxt)?
+        } else {
+            None
+        };
+
+        let mut tuple_variations = Vec::with_capacity(headers.len());
+        for header in headers {
+            let mut ctxt = data_ctxt
+                .read_scope(usize::from(header.variation_data_size))?
+                .ctxt();
+            let point_numbers = if header.tuple_index & PRIVATE_POINT_NUMBERS != 0 {
+                read_packed_point_numbers(&mut ctxt)?
+            } else {
+                shared_point_numbers.clone()
+            };
+            let num_deltas = point_numbers
+                .as_ref()
+                .map(|point_numbers| point_numbers.len())
+                .unwrap_or(num_points);
+            let x_deltas = read_packed_deltas(&mut ctxt, num_deltas)?;
+            let y_deltas = read_packed_deltas(&mut ctxt, num_deltas)?;
+            let peak_tuple = match header.peak_tuple {
+                Some(peak_tuple) => peak_tuple,
+                None => {
+                    let start = usize::from(header.tuple_index & TUPLE_INDEX_MASK) * axis_count;
+                    if start + axis_count > shared_tuples.len() {
+                        return Err(ParseError::BadIndex);
+                    }
+                    shared_tuples
+                        .subarray(start)
+                        .iter()
+                        .take(axis_count)
+                        .collect()
+                }
+            };
+            tuple_variations.push(TupleVariation {
+                peak_tuple,
+                intermediate_region: header.intermediate_region,
+                point_numbers,
+                x_deltas,
+                y_deltas,
+            });
+        }
+
+        Ok(GlyphVariationData { tuple_variations })
+    }
+}
+
+impl<'a> ReadBinaryDep<'a> for TupleVariationHeader {
+    type Args = usize;
+    type HostType = Self;
+
+    fn read_dep(ctxt: &mut ReadCtxt<'a>, axis_count: usize) -> Result<Self, ParseError> {
+        let variation_data_size = ctxt.read_u16be()?;
+        let tuple_index = ctxt.read_u16be()?;
+        let peak_tuple = if tuple_index & EMBEDDED_PEAK_TUPLE != 0 {
+            Some(ctxt.read_array::<F2Dot14>(axis_count)?.to_vec())
+        } else {
+            None
+        };
+        let intermediate_region = if tuple_index & INTERMEDIATE_REGION != 0 {
+            let start_tuple = ctxt.read_array::<F2Dot14>(axis_count)?.to_vec();
+            let end_tuple = ctxt.read_array::<F2Dot14>(axis_count)?.to_vec();
+            Some((start_tuple, end_tuple))
+        } else {
+            None
+        };
+
+        Ok(TupleVariationHeader {
+            variation_data_size,
+            tuple_index,
+            peak_tuple,
+            intermediate_region,
+        })
+    }
+}
+
+impl GlyphVariationData {
+    /// Calculate the accumulated delta of each of the `num_points` points at `coords`.
+    ///
+    /// `outline` holds the points and contour end points of a simple glyph. When supplied,
+    /// deltas of points not referenced by a tuple variation are inferred from the neighbouring
+    /// referenced points of the same contour.
+    pub fn point_deltas(
+        &self,
+        coords: &[F2Dot14],
+        num_points: usize,
+        outline: Option<(&[Point], &[u16])>,
+    ) -> Vec<(f32, f32)> {
+        let mut deltas = vec![(0., 0.); num_points];
+        for tuple_variation in &self.tuple_variations {
+            let scalar = tuple_variation.scalar(coords);
+            if scalar == 0. {
+                continue;
+            }
+
+            let tuple_deltas = tuple_variation.point_deltas(num_points, outline);
+            for (delta, (dx, dy)) in deltas.iter_mut().zip(tuple_deltas) {
+                delta.0 += scalar * dx;
+                delta.1 += scalar * dy;
+            }
+        }
+
+        deltas
+    }
+}
+
+impl TupleVariation {
+    /// Calculate the scalar that the deltas of this tuple variation are multiplied by at the
+    /// normalized `coords`.
+    pub fn scalar(&self, coords: &[F2Dot14]) -> f32 {
+        let mut scalar = 1.;
+        for (i, &peak) in self.peak_tuple.iter().enumerate() {
+            let peak = f32::from(peak);
+            let coord = coords.get(i).map(|&coord| f32::from(coord)).unwrap_or(0.);
+            let (start, end) = match self.intermediate_region {
+                Some((ref start_tuple, ref end_tuple)) => {
+                    (f32::from(start_tuple[i]), f32::from(end_tuple[i]))
+                }
+                None => (peak.min(0.), peak.max(0.)),
+            };
+            scalar *= axis_scalar(start, peak, end, coord);
+            if scalar == 0. {
+                break;
+            }
+        }
+
+        scalar
+    }
+
+    fn point_deltas(
+        &self,
+        num_points: usize,
+        outline: Option<(&[Point], &[u16])>,
+    ) -> Vec<(f32, f32)> {
+        let point_numbers = match self.point_numbers {
+            Some(ref point_numbers) => point_numbers,
+            None => {
+                return self
+                    .x_deltas
+                    .iter()
+                    .zip(self.y_deltas.iter())
+                    .map(|(&dx, &dy)| (f32::from(dx), f32::from(dy)))
+                    .chain(iter::repeat((0., 0.)))
+                    .take(num_points)
+                    .collect();
+            }
+        };
+
+        let mut deltas = vec![(0., 0.); num_points];
+        let mut touched = vec![false; num_points];
+        for ((&point_number, &dx), &dy) in point_numbers
+            .iter()
+            .zip(self.x_deltas.iter())
+            .zip(self.y_deltas.iter())
+        {
+            let point_number = usize::from(point_number);
+            if point_number < num_points {
+                deltas[point_number] = (f32::from(dx), f32::from(dy));
+                touched[point_number] = true;
+            }
+        }
+
+        if let Some((points, end_pts_of_contours)) = outline {
+            infer_untouched_deltas(points, end_pts_of_contours, &touched, &mut deltas);
+        }
+
+        deltas
+    }
+}
+
+/// Infer the deltas of untouched points from the touched points of each contour (IUP).
+fn infer_untouched_deltas(
+    points: &[Point],
+    end_pts_of_contours: &[u16],
+    touched: &[bool],
+    deltas: &mut [(f32, f32)],
+) {
+    let mut start = 0;
+    for &end in end_pts_of_contours {
+        let end = usize::from(end);
+        if end < start || end >= points.len() {
+            break;
+        }
+
+        let touched_points = (start..=end).filter(|&i| touched[i]).collect::<Vec<_>>();
+        match touched_points.len() {
+            0 => {}
+            1 => {
+                let delta = deltas[touched_points[0]];
+                for contour_delta in &mut deltas[start..=end] {
+                    *contour_delta = delta;
+                }
+            }
+            len => {
+                let next = |i: usize| if i == end { start } else { i + 1 };
+                for (index, &prev_touched) in touched_points.iter().enumerate() {
+                    let next_touched = touched_points[(index + 1) % len];
+                    let mut i = next(prev_touched);
+                    while i != next_touched {
+                        let (p1, p2, p) = (points[prev_touched], points[next_touched], points[i]);
+                        let (d1, d2) = (deltas[prev_touched], deltas[next_touched]);
+                        deltas[i] = (
+                            interpolate_delta(p1.0, p2.0, d1.0, d2.0, p.0),
+                            interpolate_delta(p1.1, p2.1, d1.1, d2.1, p.1),
+                        );
+                        i = next(i);
+                    }
+                }
+            }
+        }
+
+        start = end + 1;
+    }
+}
+
+/// Interpolate the delta of the coordinate `p` on one axis from the reference coordinates
+/// `p1` and `p2` with deltas `d1` and `d2`.
+fn interpolate_delta(p1: i16, p2: i16, d1: f32, d2: f32, p: i16) -> f32 {
+    let (lower, lower_delta, upper, upper_delta) = if p1 <= p2 {
+        (p1, d1, p2, d2)
+    } else {
+        (p2, d2, p1, d1)
+    };
+
+    if lower == upper {
+        if d1 == d2 {
+            d1
+        } else {
+            0.
+        }
+    } else if p <= lower {
+        lower_delta
+    } else if p >= upper {
+        upper_delta
+    } else {
+        let t = (f32::from(p) - f32::from(lower)) / (f32::from(upper) - f32::from(lower));
+        lower_delta + t * (upper_delta - lower_delta)
+    }
+}
+
+fn read_packed_point_numbers(ctxt: &mut ReadCtxt<'_>) -> Result<Option<Vec<u16>>, ParseError> {
+    let first = ctxt.read_u8()?;
+    let count = if first == 0 {
+        return Ok(None);
+    } else if first & POINTS_ARE_WORDS != 0 {
+        usize::from(first & POINT_RUN_COUNT_MASK) << 8 | usize::from(ctxt.read_u8()?)
+    } else {
+        usize::from(first)
+    };
+
+    let mut point_numbers = Vec::with_capacity(count);
+    let mut point_number = 0u16;
+    while point_numbers.len() < count {
+        let control = ctxt.read_u8()?;
+        let run_count = usize::from(control & POINT_RUN_COUNT_MASK) + 1;
+        for _ in 0..run_count {
+            let increment = if control & POINTS_ARE_WORDS != 0 {
+                ctxt.read_u16be()?
+            } else {
+                u16::from(ctxt.read_u8()?)
+            };
+            point_number = point_number.wrapping_add(increment);
+            point_numbers.push(point_number);
+        }
+    }
+    ctxt.check(point_numbers.len() == count)?;
+
+    Ok(Some(point_numbers))
+}
+
+fn read_packed_deltas(ctxt: &mut ReadCtxt<'_>, count: usize) -> Result<Vec<i16>, ParseError> {
+    let mut deltas = Vec::with_capacity(count);
+    while deltas.len() < count {
+        let control = ctxt.read_u8()?;
+        let run_count = usize::from(control & DELTA_RUN_COUNT_MASK) + 1;
+        if control & DELTAS_ARE_ZERO != 0 {
+            deltas.resize(deltas.len() + run_count, 0);
+        } else if control & DELTAS_ARE_WORDS != 0 {
+            for _ in 0..run_count {
+                deltas.push(ctxt.read_i16be()?);
+            }
+        } else {
+            for _ in 0..run_count {
+                deltas.push(i16::from(ctxt.read_i8()?));
+            }
+        }
+    }
+    ctxt.check(deltas.len() == count)?;
+
+    Ok(deltas)
+}
+
+fn apply_delta(value: i16, delta: f32) -> i16 {
+    (f32::from(value) + delta).round() as i16
+}
+
+fn apply_component_delta(component: &mut CompositeGlyph, dx: f32, dy: f32) {
+    let x = apply_delta(argument_value(&component.argument1), dx);
+    let y = apply_delta(argument_value(&component.argument2), dy);
+    let fits_in_bytes = i8::try_from(x).is_ok() && i8::try_from(y).is_ok();
+    if fits_in_bytes && !component.flags.arg_1_and_2_are_words() {
+        component.argument1 = CompositeGlyphArgument::I8(x as i8);
+        component.argument2 = CompositeGlyphArgument::I8(y as i8);
+    } else {
+        component.flags |= CompositeGlyphFlag::ARG_1_AND_2_ARE_WORDS;
+        component.argument1 = CompositeGlyphArgument::I16(x);
+        component.argument2 = CompositeGlyphArgument::I16(y);
+    }
+}
+
+fn argument_value(argument: &CompositeGlyphArgument) -> i16 {
+    match *argument {
+        CompositeGlyphArgument::U8(value) => i16::from(value),
+        CompositeGlyphArgument::I8(value) => i16::from(value),
+        CompositeGlyphArgument::U16(value) => value as i16,
+        CompositeGlyphArgument::I16(value) => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::tables::glyf::{BoundingBox, SimpleGlyph, SimpleGlyphFlag};
+
+    // One axis, one shared tuple at 1.0 and a single glyph with one tuple variation that
+    // moves points 0 and 2 by 10 and 20 units along x.
+    const GVAR_DATA: [u8; 42] = [
+        0x00, 0x01, 0x00, 0x00, // version
+        0x00, 0x01, // axisCount
+        0x00, 0x01, // sharedTupleCount
+        0x00, 0x00, 0x00, 0x18, // sharedTuplesOffset
+        0x00, 0x01, // glyphCount
+        0x00, 0x00, // flags
+        0x00, 0x00, 0x00, 0x1A, // glyphVariationDataArrayOffset
+        0x00, 0x00, 0x00, 0x08, // glyphVariationDataOffsets
+        0x40, 0x00, // sharedTuples
+        0x00, 0x01, // tupleVariationCount
+        0x00, 0x08, // dataOffset
+        0x00, 0x08, // variationDataSize
+        0x20, 0x00, // tupleIndex
+        0x02, 0x01, 0x00, 0x02, // packed point numbers
+        0x01, 0x0A, 0x14, // packed x deltas
+        0x81, // packed y deltas
+    ];
+
+    fn square() -> Glyph<'static> {
+        let coordinates = vec![Point(0, 0), Point(0, 100), Point(100, 100), Point(100, 0)];
+        Glyph {
+            number_of_contours: 1,
+            bounding_box: BoundingBox::from_points(&coordinates),
+            data: GlyphData::Simple(SimpleGlyph {
+                end_pts_of_contours: vec![3],
+                instructions: vec![],
+                flags: vec![SimpleGlyphFlag::ON_CURVE_POINT; 4],
+                coordinates,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_apply_to_simple_glyph() {
+        let gvar = ReadScope::new(&GVAR_DATA).read::<GvarTable<'_>>().unwrap();
+        let glyph = square();
+
+        let varied = gvar
+            .apply_to_glyph(0, &glyph, &[F2Dot14::from_f32(1.0)])
+            .unwrap();
+        match varied.data {
+            GlyphData::Simple(simple_glyph) => assert_eq!(
+                simple_glyph.coordinates,
+                vec![Point(10, 0), Point(10, 100), Point(120, 100), Point(120, 0)]
+            ),
+            GlyphData::Composite { .. } => panic!("expected simple glyph"),
+        }
+        assert_eq!(varied.bounding_box.x_min, 10);
+        assert_eq!(varied.bounding_box.x_max, 120);
+
+        let varied = gvar
+            .apply_to_glyph(0, &glyph, &[F2Dot14::from_f32(0.5)])
+            .unwrap();
+        match varied.data {
+            GlyphData::Simple(simple_glyph) => assert_eq!(
+                simple_glyph.coordinates,
+                vec![Point(5, 0), Point(5, 100), Point(110, 100), Point(110, 0)]
+            ),
+            GlyphData::Composite { .. } => panic!("expected simple glyph"),
+        }
+
+        let varied = gvar
+            .apply_to_glyph(0, &glyph, &[F2Dot14::from_f32(-1.0)])
+            .unwrap();
+        assert_eq!(varied, glyph);
+    }
+
+    #[test]
+    fn test_tuple_scalar() {
+        let tuple_variation = TupleVariation {
+            peak_tuple: vec![F2Dot14::from_f32(0.5), F2Dot14::from_f32(0.0)],
+            intermediate_region: Some((
+                vec![F2Dot14::from_f32(0.25), F2Dot14::from_f32(-1.0)],
+                vec![F2Dot14::from_f32(1.0), F2Dot14::from_f32(1.0)],
+            )),
+            point_numbers: None,
+            x_deltas: vec![],
+            y_deltas: vec![],
+        };
+        let scalar =
+            |a: f32, b: f32| tuple_variation.scalar(&[F2Dot14::from_f32(a), F2Dot14::from_f32(b)]);
+
+        assert_eq!(scalar(0.5, 0.3), 1.0);
+        assert_eq!(scalar(0.375, 0.0), 0.5);
+        assert_eq!(scalar(0.75, -0.5), 0.5);
+        assert_eq!(scalar(0.25, 1.0), 0.0);
+        assert_eq!(scalar(0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_read_packed_point_numbers() {
+        let mut ctxt = ReadScope::new(&[0x00]).ctxt();
+        assert_eq!(read_packed_point_numbers(&mut ctxt).unwrap(), None);
+
+        let data = [0x03, 0x80, 0x00, 0x01, 0x01, 0x01, 0x02];
+        let mut ctxt = ReadScope::new(&data).ctxt();
+        assert_eq!(
+            read_packed_point_numbers(&mut ctxt).unwrap(),
+            Some(vec![1, 2, 4])
+        );
+    }
+}
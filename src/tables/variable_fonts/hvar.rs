@@ -0,0 +1,150 @@
+#![deny(missing_docs)]
+
+//! `HVAR` Horizontal Metrics Variations and `VVAR` Vertical Metrics Variations Table parsing.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/hvar>
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/vvar>
+
+use std::convert::TryFrom;
+
+use crate::binary::read::{ReadBinaryDep, ReadCtxt, ReadScope};
+use crate::error::ParseError;
+use crate::tables::variable_fonts::item_variation_store::{DeltaSetIndexMap, ItemVariationStore};
+use crate::tables::F2Dot14;
+use crate::tag;
+
+/// `HVAR` Horizontal Metrics Variations Table.
+///
+/// This struct is also used for the `VVAR` table, which additionally holds a mapping for
+/// vertical origin variations.
+pub struct HvarTable<'a> {
+    /// The store holding the metrics deltas.
+    pub item_variation_store: ItemVariationStore<'a>,
+    /// The mapping of glyph ids to advance delta-sets. Glyph ids map directly to inner indices
+    /// of the first item variation data subtable when absent.
+    pub advance_mapping: Option<DeltaSetIndexMap<'a>>,
+    /// The mapping of glyph ids to left (`HVAR`) or top (`VVAR`) side bearing delta-sets.
+    pub start_side_bearing_mapping: Option<DeltaSetIndexMap<'a>>,
+    /// The mapping of glyph ids to right (`HVAR`) or bottom (`VVAR`) side bearing delta-sets.
+    pub end_side_bearing_mapping: Option<DeltaSetIndexMap<'a>>,
+    /// The mapping of glyph ids to vertical origin delta-sets. Only present in `VVAR`.
+    pub vertical_origin_mapping: Option<DeltaSetIndexMap<'a>>,
+}
+
+impl<'a> ReadBinaryDep<'a> for HvarTable<'a> {
+    type Args = u32;
+    type HostType = Self;
+
+    /// Read a `HVAR` or `VVAR` table, as indicated by `table_tag`.
+    fn read_dep(ctxt: &mut ReadCtxt<'a>, table_tag: u32) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let major_version = ctxt.read_u16be()?;
+        ctxt.check_version(major_version == 1)?;
+        let _minor_version = ctxt.read_u16be()?;
+        let item_variation_store_offset = ctxt.read_u32be()?;
+        let advance_mapping_offset = ctxt.read_u32be()?;
+        let start_side_bearing_mapping_offset = ctxt.read_u32be()?;
+        let end_side_bearing_mapping_offset = ctxt.read_u32be()?;
+        let vertical_origin_mapping_offset = if table_tag == tag::VVAR {
+            ctxt.read_u32be()?
+        } else {
+            0
+        };
+
+        let item_variation_store = scope
+            .offset(usize::try_from(item_variation_store_offset)?)
+            .read::<ItemVariationStore<'a>>()?;
+
+        Ok(HvarTable {
+            item_variation_store,
+            advance_mapping: read_optional_mapping(&scope, advance_mapping_offset)?,
+            start_side_bearing_mapping: read_optional_mapping(
+                &scope,
+                start_side_bearing_mapping_offset,
+            )?,
+            end_side_bearing_mapping: read_optional_mapping(
+                &scope,
+                end_side_bearing_mapping_offset,
+            )?,
+            vertical_origin_mapping: read_optional_mapping(&scope, vertical_origin_mapping_offset)?,
+        })
+    }
+}
+
+impl<'a> HvarTable<'a> {
+    /// Calculate the advance adjustment of `glyph_id` at the normalized variation coordinates
+    /// `coords`.
+    pub fn advance_adjustment(&self, glyph_id: u16, coords: &[F2Dot14]) -> Result<f32, ParseError> {
+        let (outer_index, inner_index) = match self.advance_mapping {
+            Some(ref mapping) => mapping.get(u32::from(glyph_id))?,
+            None => (0, glyph_id),
+        };
+        self.item_variation_store
+            .adjustment(outer_index, inner_index, coords)
+    }
+
+    /// Calculate the left (`HVAR`) or top (`VVAR`) side bearing adjustment of `glyph_id` at the
+    /// normalized variation coordinates `coords`.
+    ///
+    /// Returns `None` if the table does not hold side bearing variations.
+    pub fn start_side_bearing_adjustment(
+        &self,
+        glyph_id: u16,
+        coords: &[F2Dot14],
+    ) -> Result<Option<f32>, ParseError> {
+        adjustment(
+            &self.item_variation_store,
+            self.start_side_bearing_mapping.as_ref(),
+            glyph_id,
+            coords,
+        )
+    }
+
+    /// Calculate the vertical origin adjustment of `glyph_id` at the normalized variation
+    /// coordinates `coords`.
+    ///
+    /// Returns `None` if the table does not hold vertical origin variations.
+    pub fn vertical_origin_adjustment(
+        &self,
+        glyph_id: u16,
+        coords: &[F2Dot14],
+    ) -> Result<Option<f32>, ParseError> {
+        adjustment(
+            &self.item_variation_store,
+            self.vertical_origin_mapping.as_ref(),
+            glyph_id,
+            coords,
+        )
+    }
+}
+
+fn adjustment(
+    item_variation_store: &ItemVariationStore<'_>,
+    mapping: Option<&DeltaSetIndexMap<'_>>,
+    glyph_id: u16,
+    coords: &[F2Dot14],
+) -> Result<Option<f32>, ParseError> {
+    match mapping {
+        Some(mapping) => {
+            let (outer_index, inner_index) = mapping.get(u32::from(glyph_id))?;
+            item_variation_store
+                .adjustment(outer_index, inner_index, coords)
+                .map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+fn read_optional_mapping<'a>(
+    scope: &ReadScope<'a>,
+    offset: u32,
+) -> Result<Option<DeltaSetIndexMap<'a>>, ParseError> {
+    if offset == 0 {
+        Ok(None)
+    } else {
+        scope
+            .offset(usize::try_from(offset)?)
+            .read::<DeltaSetIndexMap<'a>>()
+            .map(Some)
+    }
+}
@@ -0,0 +1,372 @@
+#![deny(missing_docs)]
+
+//! Item Variation Store and delta-set index map parsing.
+//!
+//! These structures are shared by the `HVAR`, `VVAR`, `MVAR`, `GDEF` and `CFF2` tables.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/otvarcommonformats>
+
+use std::convert::TryFrom;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt, ReadFrom, ReadScope};
+use crate::binary::{U16Be, U32Be};
+use crate::error::ParseError;
+use crate::tables::variable_fonts::axis_scalar;
+use crate::tables::F2Dot14;
+
+const LONG_WORDS: u16 = 0x8000;
+const WORD_DELTA_COUNT_MASK: u16 = 0x7FFF;
+const INNER_INDEX_BIT_COUNT_MASK: u8 = 0x0F;
+const MAP_ENTRY_SIZE_MASK: u8 = 0x30;
+
+/// The outer and inner index of a delta-set index that indicates there is no variation data.
+pub const NO_VARIATION_INDEX: u16 = 0xFFFF;
+
+/// Item Variation Store.
+///
+/// Holds delta values for items such as glyph advances or font-wide metrics along with the
+/// regions of the variation space the deltas apply to.
+pub struct ItemVariationStore<'a> {
+    /// The regions referenced by the item variation data.
+    pub variation_region_list: VariationRegionList<'a>,
+    /// The item variation data subtables, indexed by the outer index of a delta-set.
+    pub item_variation_data: Vec<ItemVariationData<'a>>,
+}
+
+/// The list of regions of the variation space.
+pub struct VariationRegionList<'a> {
+    /// The number of variation axes.
+    pub axis_count: u16,
+    /// The number of regions in the list.
+    pub region_count: u16,
+    region_axes: ReadArray<'a, RegionAxisCoordinates>,
+}
+
+/// The extent of a region along a single axis.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RegionAxisCoordinates {
+    /// The region start coordinate value for the axis.
+    pub start_coord: F2Dot14,
+    /// The region peak coordinate value for the axis.
+    pub peak_coord: F2Dot14,
+    /// The region end coordinate value for the axis.
+    pub end_coord: F2Dot14,
+}
+
+/// A subtable of delta-sets that share the same set of regions.
+pub struct ItemVariationData<'a> {
+    /// The number of delta-sets.
+    pub item_count: u16,
+    word_delta_count: u16,
+    /// Indices into the variation region list for the regions the deltas apply to.
+    pub region_indexes: ReadArray<'a, U16Be>,
+    delta_sets: ReadScope<'a>,
+}
+
+/// Delta-set index map.
+///
+/// Maps an item index, such as a glyph id, to the outer and inner index of a delta-set in an
+/// `ItemVariationStore`.
+pub struct DeltaSetIndexMap<'a> {
+    entry_format: u8,
+    /// The number of mapping entries.
+    pub map_count: u32,
+    map_data: ReadScope<'a>,
+}
+
+impl<'a> ReadBinary<'a> for ItemVariationStore<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let format = ctxt.read_u16be()?;
+        ctxt.check_version(format == 1)?;
+        let variation_region_list_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let item_variation_data_count = ctxt.read_u16be()?;
+        let item_variation_data_offsets =
+            ctxt.read_array::<U32Be>(usize::from(item_variation_data_count))?;
+        let variation_region_list = scope
+            .offset(variation_region_list_offset)
+            .read::<VariationRegionList<'a>>()?;
+        let item_variation_data = item_variation_data_offsets
+            .iter()
+            .map(|offset| {
+                scope
+                    .offset(usize::try_from(offset)?)
+                    .read::<ItemVariationData<'a>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ItemVariationStore {
+            variation_region_list,
+            item_variation_data,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for VariationRegionList<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let axis_count = ctxt.read_u16be()?;
+        let region_count = ctxt.read_u16be()?;
+        let region_axes = ctxt.read_array::<RegionAxisCoordinates>(
+            usize::from(region_count) * usize::from(axis_count),
+        )?;
+
+        Ok(VariationRegionList {
+            axis_count,
+            region_count,
+            region_axes,
+        })
+    }
+}
+
+impl<'a> ReadFrom<'a> for RegionAxisCoordinates {
+    type ReadType = (U16Be, U16Be, U16Be);
+
+    fn from((start_coord, peak_coord, end_coord): (u16, u16, u16)) -> Self {
+        RegionAxisCoordinates {
+            start_coord: F2Dot14::new(start_coord),
+            peak_coord: F2Dot14::new(peak_coord),
+            end_coord: F2Dot14::new(end_coord),
+        }
+    }
+}
+
+impl<'a> ReadBinary<'a> for ItemVariationData<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let item_count = ctxt.read_u16be()?;
+        let word_delta_count = ctxt.read_u16be()?;
+        let region_index_count = ctxt.read_u16be()?;
+        let region_indexes = ctxt.read_array::<U16Be>(usize::from(region_index_count))?;
+        ctxt.check(usize::from(word_delta_count & WORD_DELTA_COUNT_MASK) <= region_indexes.len())?;
+        let row_size = row_size(word_delta_count, region_indexes.len());
+        let delta_sets = ctxt.read_scope(usize::from(item_count) * row_size)?;
+
+        Ok(ItemVariationData {
+            item_count,
+            word_delta_count,
+            region_indexes,
+            delta_sets,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for DeltaSetIndexMap<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let format = ctxt.read_u8()?;
+        let entry_format = ctxt.read_u8()?;
+        let map_count = match format {
+            0 => u32::from(ctxt.read_u16be()?),
+            1 => ctxt.read_u32be()?,
+            _ => return Err(ParseError::BadVersion),
+        };
+        let entry_size = usize::from(((entry_format & MAP_ENTRY_SIZE_MASK) >> 4) + 1);
+        let map_data = ctxt.read_scope(usize::try_from(map_count)? * entry_size)?;
+
+        Ok(DeltaSetIndexMap {
+            entry_format,
+            map_count,
+            map_data,
+        })
+    }
+}
+
+impl<'a> ItemVariationStore<'a> {
+    /// Calculate the adjustment for the delta-set at `outer_index` and `inner_index` at the
+    /// normalized variation coordinates `coords`.
+    ///
+    /// The adjustment is zero when both indexes are `NO_VARIATION_INDEX`.
+    pub fn adjustment(
+        &self,
+        outer_index: u16,
+        inner_index: u16,
+        coords: &[F2Dot14],
+    ) -> Result<f32, ParseError> {
+        if outer_index == NO_VARIATION_INDEX && inner_index == NO_VARIATION_INDEX {
+            return Ok(0.);
+        }
+        let item_variation_data = self
+            .item_variation_data
+            .get(usize::from(outer_index))
+            .ok_or(ParseError::BadIndex)?;
+        let deltas = item_variation_data.delta_set(inner_index)?;
+
+        let mut adjustment = 0.;
+        for (region_index, delta) in item_variation_data.region_indexes.iter().zip(deltas) {
+            if delta == 0 {
+                continue;
+            }
+            let scalar = self
+                .variation_region_list
+                .region_scalar(region_index, coords)?;
+            adjustment += scalar * delta as f32;
+        }
+
+        Ok(adjustment)
+    }
+}
+
+impl<'a> VariationRegionList<'a> {
+    /// Calculate the scalar of the region at `region_index` at the normalized variation
+    /// coordinates `coords`.
+    pub fn region_scalar(&self, region_index: u16, coords: &[F2Dot14]) -> Result<f32, ParseError> {
+        if region_index >= self.region_count {
+            return Err(ParseError::BadIndex);
+        }
+        let axis_count = usize::from(self.axis_count);
+        let start = usize::from(region_index) * axis_count;
+
+        let mut scalar = 1.;
+        for (i, region_axis) in self
+            .region_axes
+            .subarray(start)
+            .iter()
+            .take(axis_count)
+            .enumerate()
+        {
+            let coord = coords.get(i).map(|&coord| f32::from(coord)).unwrap_or(0.);
+            scalar *= axis_scalar(
+                f32::from(region_axis.start_coord),
+                f32::from(region_axis.peak_coord),
+                f32::from(region_axis.end_coord),
+                coord,
+            );
+            if scalar == 0. {
+                break;
+            }
+        }
+
+        Ok(scalar)
+    }
+}
+
+impl<'a> ItemVariationData<'a> {
+    /// Read the deltas of the delta-set at `inner_index`, one per region index.
+    pub fn delta_set(&self, inner_index: u16) -> Result<Vec<i32>, ParseError> {
+        if inner_index >= self.item_count {
+            return Err(ParseError::BadIndex);
+        }
+        let region_count = self.region_indexes.len();
+        let row_size = row_size(self.word_delta_count, region_count);
+        let mut ctxt = self
+            .delta_sets
+            .offset_length(usize::from(inner_index) * row_size, row_size)?
+            .ctxt();
+
+        let long_words = self.word_delta_count & LONG_WORDS != 0;
+        let word_count = usize::from(self.word_delta_count & WORD_DELTA_COUNT_MASK);
+        (0..region_count)
+            .map(|i| match (long_words, i < word_count) {
+                (true, true) => ctxt.read_i32be().map_err(ParseError::from),
+                (true, false) | (false, true) => Ok(i32::from(ctxt.read_i16be()?)),
+                (false, false) => Ok(i32::from(ctxt.read_i8()?)),
+            })
+            .collect()
+    }
+}
+
+impl<'a> DeltaSetIndexMap<'a> {
+    /// Look up the outer and inner delta-set indices for `index`.
+    ///
+    /// Indices beyond the end of the map use the last entry.
+    pub fn get(&self, index: u32) -> Result<(u16, u16), ParseError> {
+        if self.map_count == 0 {
+            return Err(ParseError::BadIndex);
+        }
+        let index = usize::try_from(index.min(self.map_count - 1))?;
+        let entry_size = usize::from(((self.entry_format & MAP_ENTRY_SIZE_MASK) >> 4) + 1);
+        let mut ctxt = self
+            .map_data
+            .offset_length(index * entry_size, entry_size)?
+            .ctxt();
+        let mut entry = 0u32;
+        for _ in 0..entry_size {
+            entry = (entry << 8) | u32::from(ctxt.read_u8()?);
+        }
+
+        let inner_bit_count = u32::from(self.entry_format & INNER_INDEX_BIT_COUNT_MASK) + 1;
+        let outer_index = entry >> inner_bit_count;
+        let inner_index = entry & ((1 << inner_bit_count) - 1);
+        Ok((u16::try_from(outer_index)?, u16::try_from(inner_index)?))
+    }
+}
+
+fn row_size(word_delta_count: u16, region_index_count: usize) -> usize {
+    let word_count = usize::from(word_delta_count & WORD_DELTA_COUNT_MASK);
+    if word_delta_count & LONG_WORDS != 0 {
+        word_count * 4 + (region_index_count - word_count) * 2
+    } else {
+        word_count * 2 + (region_index_count - word_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One axis with regions peaking at -1.0 and 1.0 and a single delta-set of (-10, 300).
+    const ITEM_VARIATION_STORE_DATA: [u8; 41] = [
+        0x00, 0x01, // format
+        0x00, 0x00, 0x00, 0x0C, // variationRegionListOffset
+        0x00, 0x01, // itemVariationDataCount
+        0x00, 0x00, 0x00, 0x1C, // itemVariationDataOffsets
+        0x00, 0x01, // axisCount
+        0x00, 0x02, // regionCount
+        0xC0, 0x00, 0xC0, 0x00, 0x00, 0x00, // region 0
+        0x00, 0x00, 0x40, 0x00, 0x40, 0x00, // region 1
+        0x00, 0x01, // itemCount
+        0x00, 0x01, // wordDeltaCount
+        0x00, 0x02, // regionIndexCount
+        0x00, 0x01, 0x00, 0x00, // regionIndexes
+        0x01, 0x2C, 0xF6, // deltaSets
+    ];
+
+    #[test]
+    fn test_adjustment() {
+        let store = ReadScope::new(&ITEM_VARIATION_STORE_DATA)
+            .read::<ItemVariationStore<'_>>()
+            .unwrap();
+        let adjustment = |coord: f32| store.adjustment(0, 0, &[F2Dot14::from_f32(coord)]).unwrap();
+
+        assert_eq!(adjustment(0.0), 0.0);
+        assert_eq!(adjustment(1.0), 300.0);
+        assert_eq!(adjustment(0.5), 150.0);
+        assert_eq!(adjustment(-1.0), -10.0);
+        assert!(store.adjustment(0, 1, &[]).is_err());
+        assert!(store.adjustment(1, 0, &[]).is_err());
+    }
+
+    #[test]
+    fn test_adjustment_no_variation_index() {
+        let store = ReadScope::new(&ITEM_VARIATION_STORE_DATA)
+            .read::<ItemVariationStore<'_>>()
+            .unwrap();
+        let coords = [F2Dot14::from_f32(1.0)];
+
+        assert_eq!(
+            store
+                .adjustment(NO_VARIATION_INDEX, NO_VARIATION_INDEX, &coords)
+                .unwrap(),
+            0.0
+        );
+        assert!(store.adjustment(NO_VARIATION_INDEX, 0, &coords).is_err());
+    }
+
+    #[test]
+    fn test_delta_set_index_map() {
+        // Format 0, 2 byte entries with 4 inner index bits
+        let data = [0x00, 0x13, 0x00, 0x02, 0x00, 0x21, 0x01, 0x05];
+        let map = ReadScope::new(&data)
+            .read::<DeltaSetIndexMap<'_>>()
+            .unwrap();
+        assert_eq!(map.get(0).unwrap(), (2, 1));
+        assert_eq!(map.get(1).unwrap(), (16, 5));
+        assert_eq!(map.get(7).unwrap(), (16, 5));
+    }
+}
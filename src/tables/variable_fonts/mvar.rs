@@ -0,0 +1,256 @@
+#![deny(missing_docs)]
+
+//! `MVAR` Metrics Variations Table parsing and application.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/mvar>
+
+use crate::binary::read::{ReadBinary, ReadCtxt};
+use crate::error::ParseError;
+use crate::post;
+use crate::tables::os2::Os2;
+use crate::tables::variable_fonts::item_variation_store::ItemVariationStore;
+use crate::tables::{F2Dot14, HheaTable};
+use crate::tag;
+
+/// The size of the fields of a value record that this implementation reads.
+const VALUE_RECORD_SIZE: usize = 8;
+
+/// `MVAR` Metrics Variations Table.
+///
+/// Holds variation data for font-wide metrics found in the `hhea`, `vhea`, `OS/2` and `post`
+/// tables.
+pub struct MvarTable<'a> {
+    /// The value records, in order of increasing value tag.
+    pub value_records: Vec<ValueRecord>,
+    /// The store holding the metrics deltas. May be absent if there are no value records.
+    pub item_variation_store: Option<ItemVariationStore<'a>>,
+}
+
+/// Associates a font-wide metric with a delta-set.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ValueRecord {
+    /// The tag identifying the metric, such as `tag::XHGT`.
+    pub value_tag: u32,
+    /// The outer index of the delta-set in the item variation store.
+    pub delta_set_outer_index: u16,
+    /// The inner index of the delta-set in the item variation store.
+    pub delta_set_inner_index: u16,
+}
+
+impl<'a> ReadBinary<'a> for MvarTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let major_version = ctxt.read_u16be()?;
+        ctxt.check_version(major_version == 1)?;
+        let _minor_version = ctxt.read_u16be()?;
+        let _reserved = ctxt.read_u16be()?;
+        let value_record_size = usize::from(ctxt.read_u16be()?);
+        let value_record_count = ctxt.read_u16be()?;
+        let item_variation_store_offset = usize::from(ctxt.read_u16be()?);
+
+        // The record size may grow in future minor versions, so records are read individually
+        // and any trailing fields skipped.
+        ctxt.check(value_record_count == 0 || value_record_size >= VALUE_RECORD_SIZE)?;
+        let value_records = (0..value_record_count)
+            .map(|_| {
+                let mut record = ctxt.read_scope(value_record_size)?.ctxt();
+                Ok(ValueRecord {
+                    value_tag: record.read_u32be()?,
+                    delta_set_outer_index: record.read_u16be()?,
+                    delta_set_inner_index: record.read_u16be()?,
+                })
+            })
+            .collect::<Result<Vec<_>, ParseError>>()?;
+
+        let item_variation_store = if item_variation_store_offset != 0 {
+            Some(
+                scope
+                    .offset(item_variation_store_offset)
+                    .read::<ItemVariationStore<'a>>()?,
+            )
+        } else {
+            None
+        };
+
+        Ok(MvarTable {
+            value_records,
+            item_variation_store,
+        })
+    }
+}
+
+impl<'a> MvarTable<'a> {
+    /// Calculate the adjustment of the metric identified by `value_tag` at the normalized
+    /// variation coordinates `coords`.
+    ///
+    /// Returns `None` if the metric does not vary.
+    pub fn adjustment(
+        &self,
+        value_tag: u32,
+        coords: &[F2Dot14],
+    ) -> Result<Option<f32>, ParseError> {
+        let item_variation_store = match self.item_variation_store {
+            Some(ref item_variation_store) => item_variation_store,
+            None => return Ok(None),
+        };
+        match self
+            .value_records
+            .binary_search_by_key(&value_tag, |record| record.value_tag)
+        {
+            Ok(index) => {
+                let record = &self.value_records[index];
+                item_variation_store
+                    .adjustment(
+                        record.delta_set_outer_index,
+                        record.delta_set_inner_index,
+                        coords,
+                    )
+                    .map(Some)
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Apply the horizontal metric variations at `coords` to `hhea`.
+    ///
+    /// The `hasc`, `hdsc` and `hlgp` adjustments, defined for the typographic metrics in `OS/2`,
+    /// are also applied to the `hhea` ascender, descender and line gap.
+    pub fn apply_to_hhea(
+        &self,
+        hhea: &mut HheaTable,
+        coords: &[F2Dot14],
+    ) -> Result<(), ParseError> {
+        self.apply_i16(tag::HASC, &mut hhea.ascender, coords)?;
+        self.apply_i16(tag::HDSC, &mut hhea.descender, coords)?;
+        self.apply_i16(tag::HLGP, &mut hhea.line_gap, coords)?;
+        self.apply_i16(tag::HCRS, &mut hhea.caret_slope_rise, coords)?;
+        self.apply_i16(tag::HCRN, &mut hhea.caret_slope_run, coords)?;
+        self.apply_i16(tag::HCOF, &mut hhea.caret_offset, coords)
+    }
+
+    /// Apply the vertical metric variations at `coords` to `vhea`.
+    pub fn apply_to_vhea(
+        &self,
+        vhea: &mut HheaTable,
+        coords: &[F2Dot14],
+    ) -> Result<(), ParseError> {
+        self.apply_i16(tag::VASC, &mut vhea.ascender, coords)?;
+        self.apply_i16(tag::VDSC, &mut vhea.descender, coords)?;
+        self.apply_i16(tag::VLGP, &mut vhea.line_gap, coords)?;
+        self.apply_i16(tag::VCRS, &mut vhea.caret_slope_rise, coords)?;
+        self.apply_i16(tag::VCRN, &mut vhea.caret_slope_run, coords)?;
+        self.apply_i16(tag::VCOF, &mut vhea.caret_offset, coords)
+    }
+
+    /// Apply the metric variations at `coords` to `os2`.
+    pub fn apply_to_os2(&self, os2: &mut Os2, coords: &[F2Dot14]) -> Result<(), ParseError> {
+        self.apply_i16(tag::SBXS, &mut os2.y_subscript_x_size, coords)?;
+        self.apply_i16(tag::SBYS, &mut os2.y_subscript_y_size, coords)?;
+        self.apply_i16(tag::SBXO, &mut os2.y_subscript_x_offset, coords)?;
+        self.apply_i16(tag::SBYO, &mut os2.y_subscript_y_offset, coords)?;
+        self.apply_i16(tag::SPXS, &mut os2.y_superscript_x_size, coords)?;
+        self.apply_i16(tag::SPYS, &mut os2.y_superscript_y_size, coords)?;
+        self.apply_i16(tag::SPXO, &mut os2.y_superscript_x_offset, coords)?;
+        self.apply_i16(tag::SPYO, &mut os2.y_superscript_y_offset, coords)?;
+        self.apply_i16(tag::STRS, &mut os2.y_strikeout_size, coords)?;
+        self.apply_i16(tag::STRO, &mut os2.y_strikeout_position, coords)?;
+        if let Some(ref mut version0) = os2.version0 {
+            self.apply_i16(tag::HASC, &mut version0.s_typo_ascender, coords)?;
+            self.apply_i16(tag::HDSC, &mut version0.s_typo_descender, coords)?;
+            self.apply_i16(tag::HLGP, &mut version0.s_typo_line_gap, coords)?;
+            self.apply_u16(tag::HCLA, &mut version0.us_win_ascent, coords)?;
+            self.apply_u16(tag::HCLD, &mut version0.us_win_descent, coords)?;
+        }
+        if let Some(ref mut version2to4) = os2.version2to4 {
+            self.apply_i16(tag::XHGT, &mut version2to4.sx_height, coords)?;
+            self.apply_i16(tag::CPHT, &mut version2to4.s_cap_height, coords)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the underline metric variations at `coords` to the `post` table `header`.
+    pub fn apply_to_post_header(
+        &self,
+        header: &mut post::Header,
+        coords: &[F2Dot14],
+    ) -> Result<(), ParseError> {
+        self.apply_i16(tag::UNDO, &mut header.underline_position, coords)?;
+        self.apply_i16(tag::UNDS, &mut header.underline_thickness, coords)
+    }
+
+    fn apply_i16(
+        &self,
+        value_tag: u32,
+        value: &mut i16,
+        coords: &[F2Dot14],
+    ) -> Result<(), ParseError> {
+        if let Some(adjustment) = self.adjustment(value_tag, coords)? {
+            *value = (f32::from(*value) + adjustment).round() as i16;
+        }
+        Ok(())
+    }
+
+    fn apply_u16(
+        &self,
+        value_tag: u32,
+        value: &mut u16,
+        coords: &[F2Dot14],
+    ) -> Result<(), ParseError> {
+        if let Some(adjustment) = self.adjustment(value_tag, coords)? {
+            *value = (f32::from(*value) + adjustment).round() as u16;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+
+    #[test]
+    fn test_apply_to_hhea() {
+        let data = [
+            0x00, 0x01, 0x00, 0x00, // version
+            0x00, 0x00, // reserved
+            0x00, 0x08, // valueRecordSize
+            0x00, 0x02, // valueRecordCount
+            0x00, 0x1C, // itemVariationStoreOffset
+            0x68, 0x61, 0x73, 0x63, 0x00, 0x00, 0x00, 0x00, // hasc
+            0x68, 0x64, 0x73, 0x63, 0x00, 0x00, 0x00, 0x01, // hdsc
+            // ItemVariationStore
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x0C, 0x00, 0x01, 0x00, 0x00, 0x00, 0x16, //
+            // VariationRegionList, one region peaking at 1.0
+            0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x40, 0x00, 0x40, 0x00, //
+            // ItemVariationData, two byte delta-sets
+            0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x32, 0xEC,
+        ];
+        let mvar = ReadScope::new(&data).read::<MvarTable<'_>>().unwrap();
+        let mut hhea = HheaTable {
+            ascender: 800,
+            descender: -200,
+            line_gap: 0,
+            advance_width_max: 1000,
+            min_left_side_bearing: 0,
+            min_right_side_bearing: 0,
+            x_max_extent: 1000,
+            caret_slope_rise: 1,
+            caret_slope_run: 0,
+            caret_offset: 0,
+            num_h_metrics: 1,
+        };
+
+        mvar.apply_to_hhea(&mut hhea, &[F2Dot14::from_f32(0.5)])
+            .unwrap();
+        assert_eq!(hhea.ascender, 825);
+        assert_eq!(hhea.descender, -210);
+        assert_eq!(hhea.line_gap, 0);
+        assert_eq!(
+            mvar.adjustment(tag::XHGT, &[F2Dot14::from_f32(1.0)])
+                .unwrap(),
+            None
+        );
+    }
+}
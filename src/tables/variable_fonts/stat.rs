@@ -0,0 +1,484 @@
+#![deny(missing_docs)]
+
+//! `STAT` Style Attributes Table parsing.
+//!
+//! The `STAT` table describes the design axes of a font family and names values on those axes,
+//! which is used to group the fonts and named instances of a family in font menus. It is used
+//! by static fonts as well as variable fonts.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/stat>
+
+use std::cmp::Reverse;
+use std::convert::TryFrom;
+
+use bitflags::bitflags;
+
+use crate::binary::read::{ReadBinary, ReadCtxt, ReadFrom, ReadScope};
+use crate::binary::{U16Be, U32Be};
+use crate::error::ParseError;
+use crate::size;
+use crate::tables::variable_fonts::fixed_to_f32;
+
+/// The name ID of the font subfamily name, used if a version 1.0 table elides all names.
+const SUBFAMILY_NAME_ID: u16 = 2;
+
+/// `STAT` Style Attributes Table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatTable {
+    /// Major version of the table, set to `1`.
+    pub major_version: u16,
+    /// Minor version of the table, `0`, `1` or `2`.
+    pub minor_version: u16,
+    /// The design axes of the font family.
+    pub design_axes: Vec<AxisRecord>,
+    /// The axis values. Values with an unknown format are skipped.
+    pub axis_values: Vec<AxisValue>,
+    /// The name ID of the name to use when all axis value names of a font are elided, e.g.
+    /// "Regular". Not present in version 1.0 tables.
+    pub elided_fallback_name_id: Option<u16>,
+}
+
+/// A design axis.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AxisRecord {
+    /// Tag identifying the axis, e.g. `wght`.
+    pub axis_tag: u32,
+    /// The name ID for entries in the `name` table that provide a display name for the axis.
+    pub axis_name_id: u16,
+    /// The order of the names of values on this axis when they are combined into a name.
+    pub axis_ordering: u16,
+}
+
+bitflags! {
+    /// Axis value flags.
+    pub struct AxisValueFlags: u16 {
+        /// The value applies to older fonts in the family that lack information about this
+        /// axis, rather than to this font.
+        const OLDER_SIBLING_FONT_ATTRIBUTE = 0x0001;
+        /// The name of the value can be omitted when composing names, e.g. "Regular".
+        const ELIDABLE_AXIS_VALUE_NAME = 0x0002;
+    }
+}
+
+/// A named value on one or more design axes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisValue {
+    /// Flags describing the value.
+    pub flags: AxisValueFlags,
+    /// The name ID for entries in the `name` table that provide a display name for the value.
+    pub value_name_id: u16,
+    /// The location of the value.
+    pub data: AxisValueData,
+}
+
+/// The location of an axis value, according to its format.
+///
+/// Values are user coordinates and axes are given by their index in `design_axes`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AxisValueData {
+    /// Format 1, a single value on an axis.
+    Format1 {
+        /// The index of the axis.
+        axis_index: u16,
+        /// The value on the axis.
+        value: f32,
+    },
+    /// Format 2, a range of values on an axis.
+    Format2 {
+        /// The index of the axis.
+        axis_index: u16,
+        /// The nominal value of the range.
+        nominal_value: f32,
+        /// The minimum value of the range.
+        range_min_value: f32,
+        /// The maximum value of the range.
+        range_max_value: f32,
+    },
+    /// Format 3, a single value on an axis, with the value of its style-linked counterpart,
+    /// e.g. Bold for Regular.
+    Format3 {
+        /// The index of the axis.
+        axis_index: u16,
+        /// The value on the axis.
+        value: f32,
+        /// The value of the style-linked counterpart.
+        linked_value: f32,
+    },
+    /// Format 4, a combination of values on multiple axes.
+    Format4 {
+        /// The value on each axis.
+        axis_values: Vec<AxisValueRecord>,
+    },
+}
+
+/// A value on an axis of a format 4 axis value.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AxisValueRecord {
+    /// The index of the axis.
+    pub axis_index: u16,
+    /// The value on the axis.
+    pub value: f32,
+}
+
+impl<'a> ReadBinary<'a> for StatTable {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let major_version = ctxt.read_u16be()?;
+        ctxt.check_version(major_version == 1)?;
+        let minor_version = ctxt.read_u16be()?;
+        let design_axis_size = usize::from(ctxt.read_u16be()?);
+        let design_axis_count = usize::from(ctxt.read_u16be()?);
+        let design_axes_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let axis_value_count = usize::from(ctxt.read_u16be()?);
+        let axis_value_offsets_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let elided_fallback_name_id = if minor_version >= 1 {
+            Some(ctxt.read_u16be()?)
+        } else {
+            None
+        };
+
+        // Later versions may extend axis records, so the records are read with the size given
+        // in the header.
+        ctxt.check(design_axis_count == 0 || design_axis_size >= AxisRecord::SIZE)?;
+        let design_axes = (0..design_axis_count)
+            .map(|index| {
+                scope
+                    .offset(design_axes_offset + index * design_axis_size)
+                    .read::<AxisRecord>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut axis_values = Vec::with_capacity(axis_value_count);
+        if axis_value_count > 0 {
+            let offsets_scope = scope.offset(axis_value_offsets_offset);
+            let offsets = offsets_scope.ctxt().read_array::<U16Be>(axis_value_count)?;
+            for offset in &offsets {
+                let axis_value_scope = offsets_scope.offset(usize::from(offset));
+                if let Some(axis_value) = read_axis_value(axis_value_scope)? {
+                    axis_values.push(axis_value);
+                }
+            }
+        }
+
+        Ok(StatTable {
+            major_version,
+            minor_version,
+            design_axes,
+            axis_values,
+            elided_fallback_name_id,
+        })
+    }
+}
+
+impl<'a> ReadFrom<'a> for AxisRecord {
+    type ReadType = (U32Be, U16Be, U16Be);
+
+    fn from((axis_tag, axis_name_id, axis_ordering): (u32, u16, u16)) -> Self {
+        AxisRecord {
+            axis_tag,
+            axis_name_id,
+            axis_ordering,
+        }
+    }
+}
+
+impl AxisRecord {
+    const SIZE: usize = size::U32 + (2 * size::U16);
+}
+
+/// Read an axis value, returning `None` if its format is unknown.
+fn read_axis_value(scope: ReadScope<'_>) -> Result<Option<AxisValue>, ParseError> {
+    let mut ctxt = scope.ctxt();
+    let format = ctxt.read_u16be()?;
+    // The axis index of formats 1–3 and the axis count of format 4
+    let axis_index = ctxt.read_u16be()?;
+    let flags = AxisValueFlags::from_bits_truncate(ctxt.read_u16be()?);
+    let value_name_id = ctxt.read_u16be()?;
+    let data = match format {
+        1 => AxisValueData::Format1 {
+            axis_index,
+            value: read_fixed(&mut ctxt)?,
+        },
+        2 => AxisValueData::Format2 {
+            axis_index,
+            nominal_value: read_fixed(&mut ctxt)?,
+            range_min_value: read_fixed(&mut ctxt)?,
+            range_max_value: read_fixed(&mut ctxt)?,
+        },
+        3 => AxisValueData::Format3 {
+            axis_index,
+            value: read_fixed(&mut ctxt)?,
+            linked_value: read_fixed(&mut ctxt)?,
+        },
+        4 => {
+            let axis_count = axis_index;
+            let axis_values = (0..axis_count)
+                .map(|_| {
+                    Ok(AxisValueRecord {
+                        axis_index: ctxt.read_u16be()?,
+                        value: read_fixed(&mut ctxt)?,
+                    })
+                })
+                .collect::<Result<Vec<_>, ParseError>>()?;
+            AxisValueData::Format4 { axis_values }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(AxisValue {
+        flags,
+        value_name_id,
+        data,
+    }))
+}
+
+fn read_fixed(ctxt: &mut ReadCtxt<'_>) -> Result<f32, ParseError> {
+    Ok(fixed_to_f32(ctxt.read_i32be()?))
+}
+
+impl StatTable {
+    /// Returns the name IDs of the names that make up the subfamily name of the font or named
+    /// instance at `location`, in the order they should be combined.
+    ///
+    /// `location` holds user coordinates by axis tag. Format 4 values that match the location
+    /// on all of their axes are chosen first, preferring those with more axes. Each remaining
+    /// axis takes the value that matches its coordinate exactly, or failing that the first
+    /// format 2 range containing it. Axes missing from `location` are skipped, as are values
+    /// describing older fonts in the family.
+    ///
+    /// Elidable names are left out. If that leaves no names the elided fallback name ID is
+    /// returned, or for version 1.0 tables the subfamily name ID, `2`.
+    pub fn subfamily_name_ids(&self, location: &[(u32, f32)]) -> Vec<u16> {
+        let coordinate = |axis_index: u16| {
+            let axis = self.design_axes.get(usize::from(axis_index))?;
+            location
+                .iter()
+                .find(|&&(axis_tag, _)| axis_tag == axis.axis_tag)
+                .map(|&(_, value)| value)
+        };
+        let values = self
+            .axis_values
+            .iter()
+            .filter(|axis_value| {
+                !axis_value
+                    .flags
+                    .contains(AxisValueFlags::OLDER_SIBLING_FONT_ATTRIBUTE)
+            })
+            .collect::<Vec<_>>();
+
+        let mut covered = vec![false; self.design_axes.len()];
+        // (axis ordering, axis value)
+        let mut matched = Vec::new();
+
+        let mut combinations = values
+            .iter()
+            .filter_map(|axis_value| match axis_value.data {
+                AxisValueData::Format4 { ref axis_values } => {
+                    let matches = !axis_values.is_empty()
+                        && axis_values
+                            .iter()
+                            .all(|record| coordinate(record.axis_index) == Some(record.value));
+                    if matches {
+                        Some((*axis_value, axis_values))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        combinations.sort_by_key(|(_, records)| Reverse(records.len()));
+        for (axis_value, records) in combinations {
+            let is_covered = records
+                .iter()
+                .any(|record| covered[usize::from(record.axis_index)]);
+            if !is_covered {
+                let mut ordering = std::u16::MAX;
+                for record in records {
+                    let axis_index = usize::from(record.axis_index);
+                    covered[axis_index] = true;
+                    ordering = ordering.min(self.design_axes[axis_index].axis_ordering);
+                }
+                matched.push((ordering, axis_value));
+            }
+        }
+
+        for (index, axis) in self.design_axes.iter().enumerate() {
+            let axis_index = match u16::try_from(index) {
+                Ok(axis_index) => axis_index,
+                Err(_) => break,
+            };
+            let value = match coordinate(axis_index) {
+                Some(value) if !covered[index] => value,
+                _ => continue,
+            };
+            let on_axis = || {
+                values
+                    .iter()
+                    .filter(move |axis_value| axis_value.data.axis_index() == Some(axis_index))
+            };
+            let best = on_axis()
+                .find(|axis_value| axis_value.data.nominal_value() == Some(value))
+                .or_else(|| on_axis().find(|axis_value| axis_value.data.contains(value)));
+            if let Some(axis_value) = best {
+                matched.push((axis.axis_ordering, *axis_value));
+            }
+        }
+
+        matched.sort_by_key(|&(ordering, _)| ordering);
+        let name_ids = matched
+            .into_iter()
+            .filter(|(_, axis_value)| {
+                !axis_value
+                    .flags
+                    .contains(AxisValueFlags::ELIDABLE_AXIS_VALUE_NAME)
+            })
+            .map(|(_, axis_value)| axis_value.value_name_id)
+            .collect::<Vec<_>>();
+        if name_ids.is_empty() {
+            vec![self.elided_fallback_name_id.unwrap_or(SUBFAMILY_NAME_ID)]
+        } else {
+            name_ids
+        }
+    }
+}
+
+impl AxisValueData {
+    /// The index of the axis of a format 1, 2 or 3 value.
+    pub fn axis_index(&self) -> Option<u16> {
+        match *self {
+            AxisValueData::Format1 { axis_index, .. }
+            | AxisValueData::Format2 { axis_index, .. }
+            | AxisValueData::Format3 { axis_index, .. } => Some(axis_index),
+            AxisValueData::Format4 { .. } => None,
+        }
+    }
+
+    /// The value of a format 1 or 3 value, or the nominal value of a format 2 range.
+    pub fn nominal_value(&self) -> Option<f32> {
+        match *self {
+            AxisValueData::Format1 { value, .. } | AxisValueData::Format3 { value, .. } => {
+                Some(value)
+            }
+            AxisValueData::Format2 { nominal_value, .. } => Some(nominal_value),
+            AxisValueData::Format4 { .. } => None,
+        }
+    }
+
+    /// Returns `true` if this is a format 2 range that contains `value`.
+    fn contains(&self, value: f32) -> bool {
+        match *self {
+            AxisValueData::Format2 {
+                range_min_value,
+                range_max_value,
+                ..
+            } => range_min_value <= value && value <= range_max_value,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag;
+
+    // A STAT table with wght and ital axes, with ital names ordered after wght names.
+    #[rustfmt::skip]
+    const STAT_DATA: [u8; 140] = [
+        0x00, 0x01, 0x00, 0x01, // version 1.1
+        0x00, 0x08, // designAxisSize
+        0x00, 0x02, // designAxisCount
+        0x00, 0x00, 0x00, 0x14, // designAxesOffset
+        0x00, 0x06, // axisValueCount
+        0x00, 0x00, 0x00, 0x24, // offsetToAxisValueOffsets
+        0x00, 0x02, // elidedFallbackNameID
+        // design axes
+        0x77, 0x67, 0x68, 0x74, 0x01, 0x00, 0x00, 0x00, // wght
+        0x69, 0x74, 0x61, 0x6C, 0x01, 0x01, 0x00, 0x01, // ital
+        // axis value offsets
+        0x00, 0x0C, 0x00, 0x18, 0x00, 0x24, 0x00, 0x38, 0x00, 0x48, 0x00, 0x54,
+        // format 1: wght 400, Regular, elidable
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x01, 0x02, 0x01, 0x90, 0x00, 0x00,
+        // format 1: wght 700, Bold
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x03, 0x02, 0xBC, 0x00, 0x00,
+        // format 2: wght 100–300, nominal 200, Light
+        0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x04,
+        0x00, 0xC8, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x01, 0x2C, 0x00, 0x00,
+        // format 3: ital 0 linked to 1, Roman, elidable
+        0x00, 0x03, 0x00, 0x01, 0x00, 0x02, 0x01, 0x05,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+        // format 1: ital 1, Italic
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x01, 0x06, 0x00, 0x01, 0x00, 0x00,
+        // format 4: wght 900 and ital 1, Black Italic
+        0x00, 0x04, 0x00, 0x02, 0x00, 0x00, 0x01, 0x07,
+        0x00, 0x00, 0x03, 0x84, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_read_stat() {
+        let stat = ReadScope::new(&STAT_DATA).read::<StatTable>().unwrap();
+        assert_eq!(stat.elided_fallback_name_id, Some(2));
+        assert_eq!(
+            stat.design_axes[1],
+            AxisRecord {
+                axis_tag: tag::ITAL,
+                axis_name_id: 257,
+                axis_ordering: 1,
+            }
+        );
+        assert_eq!(stat.axis_values.len(), 6);
+        assert_eq!(
+            stat.axis_values[2].data,
+            AxisValueData::Format2 {
+                axis_index: 0,
+                nominal_value: 200.,
+                range_min_value: 100.,
+                range_max_value: 300.,
+            }
+        );
+        assert_eq!(
+            stat.axis_values[3],
+            AxisValue {
+                flags: AxisValueFlags::ELIDABLE_AXIS_VALUE_NAME,
+                value_name_id: 261,
+                data: AxisValueData::Format3 {
+                    axis_index: 1,
+                    value: 0.,
+                    linked_value: 1.,
+                },
+            }
+        );
+        assert_eq!(
+            stat.axis_values[5].data,
+            AxisValueData::Format4 {
+                axis_values: vec![
+                    AxisValueRecord {
+                        axis_index: 0,
+                        value: 900.,
+                    },
+                    AxisValueRecord {
+                        axis_index: 1,
+                        value: 1.,
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_subfamily_name_ids() {
+        let stat = ReadScope::new(&STAT_DATA).read::<StatTable>().unwrap();
+        let name_ids =
+            |wght, ital| stat.subfamily_name_ids(&[(tag::ITAL, ital), (tag::WGHT, wght)]);
+        assert_eq!(name_ids(400., 0.), vec![2]);
+        assert_eq!(name_ids(700., 0.), vec![259]);
+        assert_eq!(name_ids(700., 1.), vec![259, 262]);
+        assert_eq!(name_ids(250., 1.), vec![260, 262]);
+        assert_eq!(name_ids(900., 1.), vec![263]);
+        // No value for wght 900 on its own
+        assert_eq!(name_ids(900., 0.), vec![2]);
+        assert_eq!(stat.subfamily_name_ids(&[(tag::WGHT, 700.)]), vec![259]);
+    }
+}
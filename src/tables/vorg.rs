@@ -0,0 +1,178 @@
+//! Parsing and writing of the `VORG` table.
+//!
+//! > This optional table specifies the y coordinate of the vertical origin of every glyph in the
+//! > font.
+//!
+//! — <https://docs.microsoft.com/en-us/typography/opentype/spec/vorg>
+
+use std::convert::TryFrom;
+
+use crate::binary::read::{ReadArrayCow, ReadBinary, ReadCtxt, ReadFrom};
+use crate::binary::write::{WriteBinary, WriteContext};
+use crate::binary::{I16Be, U16Be};
+use crate::error::{ParseError, WriteError};
+
+/// `VORG` table
+///
+/// <https://docs.microsoft.com/en-us/typography/opentype/spec/vorg>
+#[derive(Debug)]
+pub struct VorgTable<'a> {
+    pub default_vert_origin_y: i16,
+    /// Vertical origins of glyphs that differ from `default_vert_origin_y`, sorted by glyph id.
+    pub vert_origin_y_metrics: ReadArrayCow<'a, VertOriginYMetrics>,
+}
+
+/// A `vertOriginYMetrics` record in the `VORG` table.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct VertOriginYMetrics {
+    pub glyph_index: u16,
+    pub vert_origin_y: i16,
+}
+
+impl<'a> VorgTable<'a> {
+    /// Returns the y coordinate of the vertical origin of `glyph_id`, in font units.
+    pub fn vert_origin_y(&self, glyph_id: u16) -> i16 {
+        let metrics = &self.vert_origin_y_metrics;
+        let (mut low, mut high) = (0, metrics.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let metric = metrics.get_item(mid);
+            if metric.glyph_index < glyph_id {
+                low = mid + 1;
+            } else if metric.glyph_index > glyph_id {
+                high = mid;
+            } else {
+                return metric.vert_origin_y;
+            }
+        }
+        self.default_vert_origin_y
+    }
+
+    /// Returns a new table containing the glyphs in `new_to_old_id`, renumbered to their index
+    /// in that slice.
+    pub fn subset<'b>(&self, new_to_old_id: &[u16]) -> VorgTable<'b> {
+        let vert_origin_y_metrics = new_to_old_id
+            .iter()
+            .enumerate()
+            .filter_map(|(new_id, &old_id)| {
+                let vert_origin_y = self.vert_origin_y(old_id);
+                // NOTE(unwrap): Safe as there are at most 65536 glyphs in a font
+                let glyph_index = u16::try_from(new_id).unwrap();
+                if vert_origin_y != self.default_vert_origin_y {
+                    Some(VertOriginYMetrics {
+                        glyph_index,
+                        vert_origin_y,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+        VorgTable {
+            default_vert_origin_y: self.default_vert_origin_y,
+            vert_origin_y_metrics: ReadArrayCow::Owned(vert_origin_y_metrics),
+        }
+    }
+}
+
+impl<'a> ReadBinary<'a> for VorgTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let major_version = ctxt.read_u16be()?;
+        let _minor_version = ctxt.read_u16be()?;
+        ctxt.check_version(major_version == 1)?;
+        let default_vert_origin_y = ctxt.read_i16be()?;
+        let num_vert_origin_y_metrics = ctxt.read_u16be()?;
+        let vert_origin_y_metrics =
+            ctxt.read_array::<VertOriginYMetrics>(usize::from(num_vert_origin_y_metrics))?;
+
+        Ok(VorgTable {
+            default_vert_origin_y,
+            vert_origin_y_metrics: ReadArrayCow::Borrowed(vert_origin_y_metrics),
+        })
+    }
+}
+
+impl<'a> WriteBinary<&Self> for VorgTable<'a> {
+    type Output = ();
+
+    fn write<C: WriteContext>(ctxt: &mut C, table: &VorgTable<'a>) -> Result<(), WriteError> {
+        U16Be::write(ctxt, 1u16)?; // major_version
+        U16Be::write(ctxt, 0u16)?; // minor_version
+        I16Be::write(ctxt, table.default_vert_origin_y)?;
+        U16Be::write(ctxt, u16::try_from(table.vert_origin_y_metrics.len())?)?;
+        ReadArrayCow::write(ctxt, &table.vert_origin_y_metrics)?;
+
+        Ok(())
+    }
+}
+
+impl<'a> ReadFrom<'a> for VertOriginYMetrics {
+    type ReadType = (U16Be, I16Be);
+    fn from((glyph_index, vert_origin_y): (u16, i16)) -> Self {
+        VertOriginYMetrics {
+            glyph_index,
+            vert_origin_y,
+        }
+    }
+}
+
+impl WriteBinary for VertOriginYMetrics {
+    type Output = ();
+
+    fn write<C: WriteContext>(ctxt: &mut C, metric: VertOriginYMetrics) -> Result<(), WriteError> {
+        U16Be::write(ctxt, metric.glyph_index)?;
+        I16Be::write(ctxt, metric.vert_origin_y)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::binary::write::WriteBuffer;
+
+    #[rustfmt::skip]
+    const VORG_DATA: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, // version 1.0
+        0x03, 0x70, // defaultVertOriginY = 880
+        0x00, 0x02, // numVertOriginYMetrics
+        0x00, 0x03, 0x03, 0x52, // glyph 3 = 850
+        0x00, 0x07, 0x02, 0xBC, // glyph 7 = 700
+    ];
+
+    #[test]
+    fn test_read_vorg() {
+        let vorg = ReadScope::new(VORG_DATA).read::<VorgTable<'_>>().unwrap();
+        assert_eq!(vorg.vert_origin_y(0), 880);
+        assert_eq!(vorg.vert_origin_y(3), 850);
+        assert_eq!(vorg.vert_origin_y(5), 880);
+        assert_eq!(vorg.vert_origin_y(7), 700);
+        assert_eq!(vorg.vert_origin_y(8), 880);
+    }
+
+    #[test]
+    fn test_write_vorg() {
+        let vorg = ReadScope::new(VORG_DATA).read::<VorgTable<'_>>().unwrap();
+        let mut buffer = WriteBuffer::new();
+        VorgTable::write(&mut buffer, &vorg).unwrap();
+        assert_eq!(buffer.bytes(), VORG_DATA);
+    }
+
+    #[test]
+    fn test_subset_vorg() {
+        let vorg = ReadScope::new(VORG_DATA).read::<VorgTable<'_>>().unwrap();
+        let subset = vorg.subset(&[0, 7, 4]);
+        assert_eq!(subset.default_vert_origin_y, 880);
+        assert_eq!(
+            subset.vert_origin_y_metrics.iter().collect::<Vec<_>>(),
+            vec![VertOriginYMetrics {
+                glyph_index: 1,
+                vert_origin_y: 700,
+            }]
+        );
+    }
+}
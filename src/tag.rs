@@ -99,6 +99,8 @@ pub const ACNT: u32 = tag!(b"acnt");
 pub const AFRC: u32 = tag!(b"afrc");
 /// `akhn`
 pub const AKHN: u32 = tag!(b"akhn");
+/// `ankr`
+pub const ANKR: u32 = tag!(b"ankr");
 /// `arab`
 pub const ARAB: u32 = tag!(b"arab");
 /// `avar`
@@ -135,6 +137,8 @@ pub const CCMP: u32 = tag!(b"ccmp");
 pub const CFAR: u32 = tag!(b"cfar");
 /// `CFF `
 pub const CFF: u32 = tag!(b"CFF ");
+/// `CFF2`
+pub const CFF2: u32 = tag!(b"CFF2");
 /// `cjct`
 pub const CJCT: u32 = tag!(b"cjct");
 /// `clig`
@@ -145,6 +149,8 @@ pub const CMAP: u32 = tag!(b"cmap");
 pub const COLR: u32 = tag!(b"COLR");
 /// `CPAL`
 pub const CPAL: u32 = tag!(b"CPAL");
+/// `cpht`
+pub const CPHT: u32 = tag!(b"cpht");
 /// `curs`
 pub const CURS: u32 = tag!(b"curs");
 /// `cvar`
@@ -223,22 +229,52 @@ pub const GVAR: u32 = tag!(b"gvar");
 pub const HALF: u32 = tag!(b"half");
 /// `haln`
 pub const HALN: u32 = tag!(b"haln");
+/// `hang`
+pub const HANG: u32 = tag!(b"hang");
+/// `hasc`
+pub const HASC: u32 = tag!(b"hasc");
+/// `hcla`
+pub const HCLA: u32 = tag!(b"hcla");
+/// `hcld`
+pub const HCLD: u32 = tag!(b"hcld");
+/// `hcof`
+pub const HCOF: u32 = tag!(b"hcof");
+/// `hcrn`
+pub const HCRN: u32 = tag!(b"hcrn");
+/// `hcrs`
+pub const HCRS: u32 = tag!(b"hcrs");
 /// `hdmx`
 pub const HDMX: u32 = tag!(b"hdmx");
+/// `hdsc`
+pub const HDSC: u32 = tag!(b"hdsc");
 /// `head`
 pub const HEAD: u32 = tag!(b"head");
 /// `hhea`
 pub const HHEA: u32 = tag!(b"hhea");
+/// `hlgp`
+pub const HLGP: u32 = tag!(b"hlgp");
 /// `hlig`
 pub const HLIG: u32 = tag!(b"hlig");
 /// `hmtx`
 pub const HMTX: u32 = tag!(b"hmtx");
 /// `hsty`
 pub const HSTY: u32 = tag!(b"hsty");
+/// `HVAR`
+pub const HVAR: u32 = tag!(b"HVAR");
+/// `icfb`
+pub const ICFB: u32 = tag!(b"icfb");
+/// `icft`
+pub const ICFT: u32 = tag!(b"icft");
+/// `ideo`
+pub const IDEO: u32 = tag!(b"ideo");
+/// `idtp`
+pub const IDTP: u32 = tag!(b"idtp");
 /// `init`
 pub const INIT: u32 = tag!(b"init");
 /// `isol`
 pub const ISOL: u32 = tag!(b"isol");
+/// `ital`
+pub const ITAL: u32 = tag!(b"ital");
 /// `jpg `
 pub const JPG: u32 = tag!(b"jpg ");
 /// `JSTF`
@@ -247,6 +283,8 @@ pub const JSTF: u32 = tag!(b"JSTF");
 pub const JUST: u32 = tag!(b"just");
 /// `kern`
 pub const KERN: u32 = tag!(b"kern");
+/// `kerx`
+pub const KERX: u32 = tag!(b"kerx");
 /// `knd2`
 pub const KND2: u32 = tag!(b"knd2");
 /// `knda`
@@ -287,6 +325,8 @@ pub const MORT: u32 = tag!(b"mort");
 pub const MORX: u32 = tag!(b"morx");
 /// `mset`
 pub const MSET: u32 = tag!(b"mset");
+/// `MVAR`
+pub const MVAR: u32 = tag!(b"MVAR");
 /// `name`
 pub const NAME: u32 = tag!(b"name");
 /// `nukt`
@@ -331,10 +371,22 @@ pub const RCLT: u32 = tag!(b"rclt");
 pub const RKRF: u32 = tag!(b"rkrf");
 /// `rlig`
 pub const RLIG: u32 = tag!(b"rlig");
+/// `romn`
+pub const ROMN: u32 = tag!(b"romn");
 /// `rphf`
 pub const RPHF: u32 = tag!(b"rphf");
+/// `rvrn`
+pub const RVRN: u32 = tag!(b"rvrn");
 /// `sbix`
 pub const SBIX: u32 = tag!(b"sbix");
+/// `sbxo`
+pub const SBXO: u32 = tag!(b"sbxo");
+/// `sbxs`
+pub const SBXS: u32 = tag!(b"sbxs");
+/// `sbyo`
+pub const SBYO: u32 = tag!(b"sbyo");
+/// `sbys`
+pub const SBYS: u32 = tag!(b"sbys");
 /// `Silf`
 pub const SILF: u32 = tag!(b"Silf");
 /// `Sill`
@@ -345,6 +397,20 @@ pub const SINH: u32 = tag!(b"sinh");
 pub const SMCP: u32 = tag!(b"smcp");
 /// `SND`
 pub const SND: u32 = tag!(b"SND ");
+/// `spxo`
+pub const SPXO: u32 = tag!(b"spxo");
+/// `spxs`
+pub const SPXS: u32 = tag!(b"spxs");
+/// `spyo`
+pub const SPYO: u32 = tag!(b"spyo");
+/// `spys`
+pub const SPYS: u32 = tag!(b"spys");
+/// `STAT`
+pub const STAT: u32 = tag!(b"STAT");
+/// `stro`
+pub const STRO: u32 = tag!(b"stro");
+/// `strs`
+pub const STRS: u32 = tag!(b"strs");
 /// `SVG `
 pub const SVG: u32 = tag!(b"SVG ");
 /// `syrc`
@@ -365,22 +431,46 @@ pub const TNUM: u32 = tag!(b"tnum");
 pub const TRAK: u32 = tag!(b"trak");
 /// `ttcf`
 pub const TTCF: u32 = tag!(b"ttcf");
+/// `undo`
+pub const UNDO: u32 = tag!(b"undo");
+/// `unds`
+pub const UNDS: u32 = tag!(b"unds");
 /// `URD`
 pub const URD: u32 = tag!(b"URD ");
+/// `vasc`
+pub const VASC: u32 = tag!(b"vasc");
 /// `vatu`
 pub const VATU: u32 = tag!(b"vatu");
+/// `vcof`
+pub const VCOF: u32 = tag!(b"vcof");
+/// `vcrn`
+pub const VCRN: u32 = tag!(b"vcrn");
+/// `vcrs`
+pub const VCRS: u32 = tag!(b"vcrs");
 /// `VDMX`
 pub const VDMX: u32 = tag!(b"VDMX");
+/// `vdsc`
+pub const VDSC: u32 = tag!(b"vdsc");
 /// `vert`
 pub const VERT: u32 = tag!(b"vert");
 /// `vhea`
 pub const VHEA: u32 = tag!(b"vhea");
+/// `vlgp`
+pub const VLGP: u32 = tag!(b"vlgp");
 /// `vmtx`
 pub const VMTX: u32 = tag!(b"vmtx");
 /// `VORG`
 pub const VORG: u32 = tag!(b"VORG");
 /// `vrt2`
 pub const VRT2: u32 = tag!(b"vrt2");
+/// `VVAR`
+pub const VVAR: u32 = tag!(b"VVAR");
+/// `wdth`
+pub const WDTH: u32 = tag!(b"wdth");
+/// `wght`
+pub const WGHT: u32 = tag!(b"wght");
+/// `xhgt`
+pub const XHGT: u32 = tag!(b"xhgt");
 /// `Zapf`
 pub const ZAPF: u32 = tag!(b"Zapf");
 /// `zero`
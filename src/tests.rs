@@ -1,3 +1,41 @@
 //! Shared test code.
 
+use tinyvec::TinyVec;
+
+use crate::gsub::{GlyphOrigin, RawGlyph};
+
 include!("../tests/common.rs");
+
+/// Make glyphs that were not mapped from characters, one for each of `glyph_indices`.
+pub fn make_direct_glyphs(glyph_indices: &[u16]) -> Vec<RawGlyph<()>> {
+    glyph_indices
+        .iter()
+        .map(|&glyph_index| RawGlyph {
+            unicodes: TinyVec::new(),
+            glyph_index,
+            liga_component_pos: 0,
+            glyph_origin: GlyphOrigin::Direct,
+            small_caps: false,
+            multi_subst_dup: false,
+            is_vert_alt: false,
+            fake_bold: false,
+            fake_italic: false,
+            variation: None,
+            extra_data: (),
+        })
+        .collect()
+}
+
+/// Append `values` to `data` as big-endian 16-bit integers.
+pub fn push_u16(data: &mut Vec<u8>, values: &[u16]) {
+    for value in values {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Append `values` to `data` as big-endian 32-bit integers.
+pub fn push_u32(data: &mut Vec<u8>, values: &[u32]) {
+    for value in values {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+}
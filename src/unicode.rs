@@ -2,19 +2,23 @@ use std::convert::TryFrom;
 
 /// A Unicode variation selector.
 ///
-/// VS04-VS14 are omitted as they aren't currently used.
-#[derive(Debug, Copy, Clone)]
-pub enum VariationSelector {
+/// The selector is stored as its character, which is in either the Variation Selectors block
+/// (U+FE00–U+FE0F, VS1–VS16) or the Variation Selectors Supplement block (U+E0100–U+E01EF,
+/// VS17–VS256). Use `TryFrom<char>` to construct a selector from any of these characters.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VariationSelector(char);
+
+impl VariationSelector {
     /// VARIATION SELECTOR-1
-    VS01 = 1,
+    pub const VS01: VariationSelector = VariationSelector('\u{FE00}');
     /// VARIATION SELECTOR-2
-    VS02 = 2,
+    pub const VS02: VariationSelector = VariationSelector('\u{FE01}');
     /// VARIATION SELECTOR-3
-    VS03 = 3,
+    pub const VS03: VariationSelector = VariationSelector('\u{FE02}');
     /// Text presentation
-    VS15 = 15,
+    pub const VS15: VariationSelector = VariationSelector('\u{FE0E}');
     /// Emoji presentation
-    VS16 = 16,
+    pub const VS16: VariationSelector = VariationSelector('\u{FE0F}');
 }
 
 impl TryFrom<char> for VariationSelector {
@@ -22,12 +26,39 @@ impl TryFrom<char> for VariationSelector {
 
     fn try_from(ch: char) -> Result<Self, Self::Error> {
         match ch {
-            '\u{FE00}' => Ok(VariationSelector::VS01),
-            '\u{FE01}' => Ok(VariationSelector::VS02),
-            '\u{FE02}' => Ok(VariationSelector::VS03),
-            '\u{FE0E}' => Ok(VariationSelector::VS15),
-            '\u{FE0F}' => Ok(VariationSelector::VS16),
+            '\u{FE00}'..='\u{FE0F}' | '\u{E0100}'..='\u{E01EF}' => Ok(VariationSelector(ch)),
             _ => Err(()),
         }
     }
 }
+
+impl From<VariationSelector> for char {
+    fn from(selector: VariationSelector) -> char {
+        selector.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variation_selector_round_trip() {
+        for value in (0xFE00..=0xFE0F).chain(0xE0100..=0xE01EF) {
+            let ch = std::char::from_u32(value).unwrap();
+            let selector = VariationSelector::try_from(ch).unwrap();
+            assert_eq!(char::from(selector), ch);
+        }
+        assert_eq!(
+            VariationSelector::try_from('\u{FE0F}'),
+            Ok(VariationSelector::VS16)
+        );
+    }
+
+    #[test]
+    fn test_variation_selector_invalid() {
+        for &ch in &['a', '\u{FDFF}', '\u{FE10}', '\u{E00FF}', '\u{E01F0}'] {
+            assert!(VariationSelector::try_from(ch).is_err());
+        }
+    }
+}
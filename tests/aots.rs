@@ -14,7 +14,7 @@ use allsorts::error::ShapingError;
 use allsorts::gpos::{self, Placement};
 use allsorts::gsub::{self, FeatureInfo, GlyphOrigin, RawGlyph};
 use allsorts::layout::{new_layout_cache, GDEFTable, LayoutTable, GPOS, GSUB};
-use allsorts::tables::cmap::{Cmap, CmapSubtable, EncodingId, PlatformId};
+use allsorts::tables::cmap::{Cmap, CmapSubtable, EncodingId, PlatformId, VariationMapping};
 use allsorts::tables::{HheaTable, HmtxTable, MaxpTable, OffsetTable, OpenTypeFile, OpenTypeFont};
 use allsorts::tag;
 
@@ -67,23 +67,32 @@ fn cmap_uvs_test(font: &str, inputs: &[u32], expected: &[u32]) {
         .unwrap()
         .read::<Cmap>()
         .unwrap();
-    let encoding_record = cmap
-        .find_subtable(PlatformId::UNICODE, EncodingId::WINDOWS_UNICODE_UCS4)
-        .unwrap();
-    let _cmap_subtable = cmap
-        .scope
-        .offset(usize::try_from(encoding_record.offset).unwrap())
-        .read::<CmapSubtable<'_>>()
-        .unwrap();
-
-    let actual: Vec<u32> = Vec::with_capacity(inputs.len() / 2);
-    for chunk in inputs.chunks(2) {
-        let _char_code = chunk[0];
-        let _variation_selector = chunk[1];
-
-        // TODO: Implement when we support CMAP Format 14
-    }
+    let read_subtable = |platform, encoding| {
+        let encoding_record = cmap.find_subtable(platform, encoding).unwrap();
+        cmap.scope
+            .offset(usize::try_from(encoding_record.offset).unwrap())
+            .read::<CmapSubtable<'_>>()
+            .unwrap()
+    };
+    let uvs_subtable = read_subtable(PlatformId::UNICODE, EncodingId::UNICODE_VARIATION_SEQUENCES);
+    let cmap_subtable = read_subtable(PlatformId::WINDOWS, EncodingId::WINDOWS_UNICODE_BMP_UCS2);
 
+    let actual = inputs
+        .chunks(2)
+        .map(|chunk| {
+            let (char_code, variation_selector) = (chunk[0], chunk[1]);
+            match uvs_subtable
+                .map_variant(char_code, variation_selector)
+                .unwrap()
+            {
+                Some(VariationMapping::UseDefault) => {
+                    u32::from(cmap_subtable.map_glyph(char_code).unwrap().unwrap_or(0))
+                }
+                Some(VariationMapping::Glyph(glyph_id)) => u32::from(glyph_id),
+                None => 0,
+            }
+        })
+        .collect_vec();
     assert_eq!(actual, expected);
 }
 
@@ -224,6 +233,7 @@ fn gpos_test(
         &cache.layout_table,
         opt_gdef_table.as_ref(),
         &langsys,
+        &[],
         &[features],
         &mut infos,
     )
@@ -310,6 +320,7 @@ fn shape_ttf<'a>(
         opt_gdef_table.as_ref(),
         script_tag,
         opt_lang_tag,
+        &[],
         &[FeatureInfo {
             feature_tag: features,
             alternate: None,
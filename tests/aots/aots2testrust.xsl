@@ -69,7 +69,6 @@ fn <xsl:value-of select='@id'/>() {
 
 <xsl:template match='aots:cmap-uvs-test'>
 #[test]
-#[ignore = "CMAP format 14 not yet implemented"]
 fn <xsl:value-of select='@id'/>() {
     cmap_uvs_test(
         "<xsl:value-of select='@font'/>.otf",
@@ -115,15 +114,6 @@ fn <xsl:value-of select='@id'/>() {
 }
 </xsl:template>
 
-<!--
-NOTE: lookupflag
-The gpos tests for these test cases are a little strange. The input glyphs look something like
-0, 20, 90, 21, 91, 22, 92, 23, 93, 94, 24, 90, 25, 91, 26, 0
-glyphs 90,91,92,93,94 are defined as marks by the GDEF table. The expected xDeltas include
-an offset for glyph 23 that can only occur if the mark glphs are ignored when applying GPOS.
-Either the test case is wrong or we don't do that. Harfbuzz has these tests disabled without
-a comment on why too.
--->
 <xsl:template match='aots:context-test'>
 #[test]
 fn <xsl:value-of select='@id'/>_gsub() {
@@ -135,10 +125,7 @@ fn <xsl:value-of select='@id'/>_gsub() {
     )
 }
 
-#[test]<xsl:if test='@id = "chaining1_lookupflag_t1" or @id = "chaining2_lookupflag_t1" or @id = "chaining3_lookupflag_t1" or @id = "context1_lookupflag_t1" or @id = "context2_lookupflag_t1" or @id = "context3_lookupflag_t1"'>
-#[ignore = "invalid test? Harfbuzz also ignores see NOTE: lookupflag in aots2testrust.xsl"]</xsl:if><xsl:if test='@id = "context1_next_glyph_t1" or @id = "context1_next_glyph_t1" or @id = "context2_next_glyph_t1" or @id = "context3_next_glyph_t1"'>
-#[ignore = "next glyph layout is not yet implemented"]</xsl:if><xsl:if test='@id = "context1_simple_t3" or @id = "context2_simple_t3"'>
-#[ignore = "pairpos next glyph layout is not yet implemented"]</xsl:if>
+#[test]
 fn <xsl:value-of select='@id'/>_gpos() {
     gpos_test(
         "gpos_<xsl:value-of select='@font'/>.otf",
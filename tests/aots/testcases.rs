@@ -1153,7 +1153,6 @@ fn context1_simple_t3_gsub() {
 }
 
 #[test]
-#[ignore = "pairpos next glyph layout is not yet implemented"]
 fn context1_simple_t3_gpos() {
     gpos_test(
         "gpos_context1_simple_f2.otf",
@@ -1176,7 +1175,6 @@ fn context1_next_glyph_t1_gsub() {
 }
 
 #[test]
-#[ignore = "next glyph layout is not yet implemented"]
 fn context1_next_glyph_t1_gpos() {
     gpos_test(
         "gpos_context1_next_glyph_f1.otf",
@@ -1309,7 +1307,6 @@ fn context1_lookupflag_t1_gsub() {
 }
 
 #[test]
-#[ignore = "invalid test? Harfbuzz also ignores see NOTE: lookupflag in aots2testrust.xsl"]
 fn context1_lookupflag_t1_gpos() {
     gpos_test(
         "gpos_context1_lookupflag_f1.otf",
@@ -1420,7 +1417,6 @@ fn context2_simple_t3_gsub() {
 }
 
 #[test]
-#[ignore = "pairpos next glyph layout is not yet implemented"]
 fn context2_simple_t3_gpos() {
     gpos_test(
         "gpos_context2_simple_f2.otf",
@@ -1443,7 +1439,6 @@ fn context2_next_glyph_t1_gsub() {
 }
 
 #[test]
-#[ignore = "next glyph layout is not yet implemented"]
 fn context2_next_glyph_t1_gpos() {
     gpos_test(
         "gpos_context2_next_glyph_f1.otf",
@@ -1576,7 +1571,6 @@ fn context2_lookupflag_t1_gsub() {
 }
 
 #[test]
-#[ignore = "invalid test? Harfbuzz also ignores see NOTE: lookupflag in aots2testrust.xsl"]
 fn context2_lookupflag_t1_gpos() {
     gpos_test(
         "gpos_context2_lookupflag_f1.otf",
@@ -1731,7 +1725,6 @@ fn context3_next_glyph_t1_gsub() {
 }
 
 #[test]
-#[ignore = "next glyph layout is not yet implemented"]
 fn context3_next_glyph_t1_gpos() {
     gpos_test(
         "gpos_context3_next_glyph_f1.otf",
@@ -1820,7 +1813,6 @@ fn context3_lookupflag_t1_gsub() {
 }
 
 #[test]
-#[ignore = "invalid test? Harfbuzz also ignores see NOTE: lookupflag in aots2testrust.xsl"]
 fn context3_lookupflag_t1_gpos() {
     gpos_test(
         "gpos_context3_lookupflag_f1.otf",
@@ -2283,7 +2275,6 @@ fn chaining1_lookupflag_t1_gsub() {
 }
 
 #[test]
-#[ignore = "invalid test? Harfbuzz also ignores see NOTE: lookupflag in aots2testrust.xsl"]
 fn chaining1_lookupflag_t1_gpos() {
     gpos_test(
         "gpos_chaining1_lookupflag_f1.otf",
@@ -2724,7 +2715,6 @@ fn chaining2_lookupflag_t1_gsub() {
 }
 
 #[test]
-#[ignore = "invalid test? Harfbuzz also ignores see NOTE: lookupflag in aots2testrust.xsl"]
 fn chaining2_lookupflag_t1_gpos() {
     gpos_test(
         "gpos_chaining2_lookupflag_f1.otf",
@@ -3121,7 +3111,6 @@ fn chaining3_lookupflag_t1_gsub() {
 }
 
 #[test]
-#[ignore = "invalid test? Harfbuzz also ignores see NOTE: lookupflag in aots2testrust.xsl"]
 fn chaining3_lookupflag_t1_gpos() {
     gpos_test(
         "gpos_chaining3_lookupflag_f1.otf",
@@ -3289,7 +3278,6 @@ fn cmap12_test1() {
 }
 
 #[test]
-#[ignore = "CMAP format 14 not yet implemented"]
 fn cmap14_test1a() {
     cmap_uvs_test(
         "cmap14_font1.otf",
@@ -3299,7 +3287,6 @@ fn cmap14_test1a() {
 }
 
 #[test]
-#[ignore = "CMAP format 14 not yet implemented"]
 fn cmap14_test1b() {
     cmap_uvs_test(
         "cmap14_font1.otf",
@@ -3309,7 +3296,6 @@ fn cmap14_test1b() {
 }
 
 #[test]
-#[ignore = "CMAP format 14 not yet implemented"]
 fn cmap14_test1c() {
     cmap_uvs_test(
         "cmap14_font1.otf",
@@ -495,13 +495,13 @@ mod arabic_tests {
 
             gsub_apply_arabic(
                 &gsub_cache,
-                &gsub_cache.layout_table,
                 font.gdef_table()
                     .expect("Error getting GDEF table")
                     .as_ref()
                     .map(Rc::as_ref),
                 tag::ARAB,
                 lang_tag,
+                &[],
                 &mut raw_glyphs,
             )
             .unwrap();
@@ -13,8 +13,13 @@ use itertools::Itertools;
 use allsorts::binary::read::ReadScope;
 use allsorts::binary::write::{WriteBinary, WriteBuffer};
 use allsorts::cff::{CFFVariant, Charset, Dict, DictDefault, FontDict, Operand, CFF};
+use allsorts::outline::OutlineSink;
 use allsorts::subset::subset;
-use allsorts::tables::{OpenTypeFile, OpenTypeFont};
+use allsorts::tables::vorg::VorgTable;
+use allsorts::tables::{
+    FontTableProvider, HheaTable, HmtxTable, MaxpTable, OpenTypeFile, OpenTypeFont, VheaTable,
+    VmtxTable,
+};
 use allsorts::tag;
 
 use crate::common::read_fixture;
@@ -198,6 +203,35 @@ fn test_subset_cff_cid() {
     .is_ok());
 }
 
+#[test]
+fn test_subset_cff_cid_vertical_metrics() {
+    let buffer = read_fixture("tests/fonts/noto/NotoSansJP-Regular.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    // 6896 has a vertical origin in VORG, 8323 is past the last long vertical metric
+    let glyph_ids = [0, 100, 6896, 8323];
+
+    let subset_buffer = subset(&opentype_file.font_provider(0).unwrap(), &glyph_ids, None).unwrap();
+    let subset_file = ReadScope::new(&subset_buffer)
+        .read::<OpenTypeFile<'_>>()
+        .unwrap();
+    let provider = subset_file.font_provider(0).unwrap();
+
+    let vhea_data = provider.read_table_data(tag::VHEA).unwrap();
+    let vhea = ReadScope::new(&vhea_data).read::<VheaTable>().unwrap();
+    assert_eq!(vhea.num_h_metrics, 4);
+    let vmtx_data = provider.read_table_data(tag::VMTX).unwrap();
+    let vmtx = ReadScope::new(&vmtx_data)
+        .read_dep::<VmtxTable>((4, 4))
+        .unwrap();
+    assert_eq!(vmtx.side_bearing(1, 4).unwrap(), 167);
+    assert_eq!(vmtx.side_bearing(3, 4).unwrap(), 0);
+
+    let vorg_data = provider.read_table_data(tag::VORG).unwrap();
+    let vorg = ReadScope::new(&vorg_data).read::<VorgTable>().unwrap();
+    assert_eq!(vorg.vert_origin_y(1), 880);
+    assert_eq!(vorg.vert_origin_y(2), 860);
+}
+
 #[test]
 fn test_subset_cff_type1() {
     let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
@@ -294,3 +328,89 @@ fn compare_dicts<T: DictDefault + Debug>(actual: &Dict<T>, expected: &Dict<T>) {
         );
     }
 }
+
+struct PathCounter {
+    contours: usize,
+    segments: usize,
+}
+
+impl OutlineSink for PathCounter {
+    fn move_to(&mut self, _x: f32, _y: f32) {}
+
+    fn line_to(&mut self, _x: f32, _y: f32) {
+        self.segments += 1;
+    }
+
+    fn quad_to(&mut self, _x1: f32, _y1: f32, _x: f32, _y: f32) {
+        self.segments += 1;
+    }
+
+    fn curve_to(&mut self, _x1: f32, _y1: f32, _x2: f32, _y2: f32, _x: f32, _y: f32) {
+        self.segments += 1;
+    }
+
+    fn close(&mut self) {
+        self.contours += 1;
+    }
+}
+
+fn check_char_string_widths(path: &str) {
+    let buffer = read_fixture(path);
+    let scope = ReadScope::new(&buffer);
+    let otf = scope.read::<OpenTypeFile>().unwrap();
+    let ttf = match otf.font {
+        OpenTypeFont::Single(ttf) => ttf,
+        OpenTypeFont::Collection(_) => unreachable!(),
+    };
+    let cff_table_data = ttf.read_table(&otf.scope, tag::CFF).unwrap().unwrap();
+    let cff = cff_table_data.read::<CFF>().unwrap();
+    let maxp = ttf
+        .read_table(&otf.scope, tag::MAXP)
+        .unwrap()
+        .unwrap()
+        .read::<MaxpTable>()
+        .unwrap();
+    let hhea = ttf
+        .read_table(&otf.scope, tag::HHEA)
+        .unwrap()
+        .unwrap()
+        .read::<HheaTable>()
+        .unwrap();
+    let hmtx = ttf
+        .read_table(&otf.scope, tag::HMTX)
+        .unwrap()
+        .unwrap()
+        .read_dep::<HmtxTable>((
+            usize::from(maxp.num_glyphs),
+            usize::from(hhea.num_h_metrics),
+        ))
+        .unwrap();
+
+    let mut contours = 0;
+    for glyph_id in 0..maxp.num_glyphs {
+        let mut counter = PathCounter {
+            contours: 0,
+            segments: 0,
+        };
+        let info = cff.visit_char_string(0, glyph_id, &mut counter).unwrap();
+        let advance = hmtx
+            .horizontal_advance(glyph_id, hhea.num_h_metrics)
+            .unwrap();
+        assert_eq!(info.width, Some(f32::from(advance)), "glyph {}", glyph_id);
+        if counter.contours > 0 {
+            assert!(counter.segments > 0);
+        }
+        contours += counter.contours;
+    }
+    assert!(contours > 0);
+}
+
+#[test]
+fn test_visit_char_string() {
+    check_char_string_widths("tests/fonts/opentype/Klei.otf");
+}
+
+#[test]
+fn test_visit_char_string_cid() {
+    check_char_string_widths("tests/fonts/noto/NotoSansJP-Regular.otf");
+}
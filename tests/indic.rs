@@ -82,6 +82,7 @@ fn shape_ttf_indic<'a, T: FontTableProvider>(
             gdef_table.as_ref().map(Rc::as_ref),
             script_tag,
             opt_lang_tag,
+            &[],
             GsubFeatureMask::default(),
             font.num_glyphs(),
             &mut gs,
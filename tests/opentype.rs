@@ -13,6 +13,7 @@ use allsorts::binary::read::ReadScope;
 use allsorts::error::ShapingError;
 use allsorts::font_data_impl::FontDataImpl;
 use allsorts::gsub::{gsub_apply_default, GsubFeatureMask};
+use allsorts::outline::OutlineSink;
 use allsorts::tables::cmap::{Cmap, CmapSubtable, EncodingId, PlatformId};
 use allsorts::tables::glyf::{
     BoundingBox, GlyfRecord, GlyfTable, Glyph, GlyphData, Point, SimpleGlyph, SimpleGlyphFlag,
@@ -238,6 +239,7 @@ fn shape<'a, T: FontTableProvider>(
         gdef_table.as_ref().map(Rc::as_ref),
         script_tag,
         opt_lang_tag,
+        &[],
         GsubFeatureMask::default(),
         font.num_glyphs(),
         &mut glyphs,
@@ -441,3 +443,105 @@ fn test_reverse_chaining_contextual_single_substitution() {
         );
     }
 }
+
+/// Records the extent of all points, including control points, and the number of contours.
+#[derive(Default)]
+struct BoundsSink {
+    contours: usize,
+    /// x_min, x_max, y_min, y_max
+    bounds: Option<(f32, f32, f32, f32)>,
+}
+
+impl BoundsSink {
+    fn add_point(&mut self, x: f32, y: f32) {
+        self.bounds = Some(match self.bounds {
+            Some((x_min, x_max, y_min, y_max)) => {
+                (x_min.min(x), x_max.max(x), y_min.min(y), y_max.max(y))
+            }
+            None => (x, x, y, y),
+        });
+    }
+}
+
+impl OutlineSink for BoundsSink {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.add_point(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.add_point(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.add_point(x1, y1);
+        self.add_point(x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.add_point(x1, y1);
+        self.add_point(x2, y2);
+        self.add_point(x, y);
+    }
+
+    fn close(&mut self) {
+        self.contours += 1;
+    }
+}
+
+fn visit_outline_bounds(path: &str, glyph_id: u16) -> BoundsSink {
+    let font_buffer = read_fixture(path);
+    let opentype_file = ReadScope::new(&font_buffer)
+        .read::<OpenTypeFile<'_>>()
+        .unwrap();
+    let font_table_provider = opentype_file
+        .font_provider(0)
+        .expect("error reading font file");
+    let mut font = FontDataImpl::new(Box::new(font_table_provider))
+        .expect("error reading font data")
+        .expect("missing required font tables");
+
+    let mut sink = BoundsSink::default();
+    font.visit_outline(glyph_id, &mut sink).unwrap();
+    sink
+}
+
+#[test]
+fn test_visit_outline_glyf_composite() {
+    // Glyph 2 is made of four components with five contours in total
+    let bounds = visit_outline_bounds("tests/fonts/opentype/SFNT-TTF-Composite.ttf", 2);
+    assert_eq!(bounds.contours, 5);
+    assert_eq!(bounds.bounds, Some((205., 4514., 0., 1434.)));
+}
+
+#[test]
+fn test_visit_outline_cff() {
+    // Glyph 34 is 'A'
+    let bounds = visit_outline_bounds("tests/fonts/opentype/Klei.otf", 34);
+    assert!(bounds.contours > 0);
+    let (x_min, x_max, y_min, y_max) = bounds.bounds.unwrap();
+    assert!(x_min < x_max && y_min < y_max);
+}
+
+#[test]
+fn test_baseline_fallback() {
+    // Klei.otf doesn't have a BASE table
+    let font_buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+    let opentype_file = ReadScope::new(&font_buffer)
+        .read::<OpenTypeFile<'_>>()
+        .unwrap();
+    let font_table_provider = opentype_file
+        .font_provider(0)
+        .expect("error reading font file");
+    let font = FontDataImpl::new(Box::new(font_table_provider))
+        .expect("error reading font data")
+        .expect("missing required font tables");
+
+    assert!(font.base_table().unwrap().is_none());
+    let baseline = |baseline_tag| font.baseline(tag::LATN, baseline_tag).unwrap();
+    assert_eq!(baseline(tag::ROMN), Some(0));
+    assert_eq!(baseline(tag::IDEO), Some(-232));
+    assert_eq!(baseline(tag::IDTP), Some(768));
+    assert_eq!(baseline(tag::ICFB), Some(-182));
+    assert_eq!(baseline(tag::ICFT), Some(718));
+    assert_eq!(baseline(tag::HANG), Some(730));
+}
@@ -488,13 +488,13 @@ mod syriac_tests {
 
             gsub_apply_syriac(
                 &gsub_cache,
-                &gsub_cache.layout_table,
                 font.gdef_table()
                     .expect("Error getting GDEF table")
                     .as_ref()
                     .map(Rc::as_ref),
                 tag::SYRC,
                 None,
+                &[],
                 &mut raw_glyphs,
             )
             .unwrap();